@@ -1,6 +1,6 @@
 use std::collections::BTreeMap;
 use std::fmt::Write;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use rs_simple_logging::{
     copy::{
@@ -8,8 +8,11 @@ use rs_simple_logging::{
         logger_new_from_proxy,
     },
     proxy::copy::{proxy_new_from_resource_proxy, resource_proxy_new_from_map},
-    serialize::{serializer_new_from_fn, Serialize},
-    write::{level_checker_from_lower_bound, limited_writer_new, log_writer_new_from_fn, LogWrite},
+    serialize::{escape, serializer_new_from_fn, Serialize},
+    write::{
+        level_checker_from_lower_bound, limited_writer_new, log_writer_new_from_fn,
+        rate_limit::token_bucket, LogWrite,
+    },
     Item, Severity,
 };
 
@@ -17,38 +20,28 @@ fn ltsv_write_ignore_err(buf: &mut String, s: String) {
     write!(buf, "{s}").ok();
 }
 
+fn ltsv_write_pair(buf: &mut String, label: &str, val: &str) {
+    ltsv_write_ignore_err(buf, "\t".into());
+    escape::ltsv(label, buf);
+    ltsv_write_ignore_err(buf, ":".into());
+    escape::ltsv(val, buf);
+}
+
 fn ltsv_serializer() -> impl Serialize {
     serializer_new_from_fn(|i: &Item, buf: &mut String| {
         ltsv_write_ignore_err(buf, format!("level:{}", i.severity.as_str()));
         for pair in &i.attributes {
             let (key, val) = pair;
-            ltsv_write_ignore_err(buf, format!("\tattr.{key}:{val}"));
+            ltsv_write_pair(buf, &format!("attr.{key}"), val);
         }
         for pair in &i.resource {
             let (key, val) = pair;
-            ltsv_write_ignore_err(buf, format!("\t{key}:{val}"));
+            ltsv_write_pair(buf, key, val);
         }
-        ltsv_write_ignore_err(buf, format!("\tmsg:{}", i.body));
+        ltsv_write_pair(buf, "msg", &i.body.to_text());
     })
 }
 
-fn rate_limiter(min_duration: Duration) -> impl FnMut(Severity) -> bool {
-    let mut state = BTreeMap::new();
-    move |level: Severity| {
-        let prev: Option<Instant> = state.get(&level).copied();
-        let duration: Option<Duration> =
-            prev.map(|i: Instant| Instant::now().saturating_duration_since(i));
-        let available: bool = duration.map(|d: Duration| min_duration < d).unwrap_or(true);
-        match available {
-            true => {
-                state.insert(level, Instant::now());
-            }
-            false => {}
-        }
-        available
-    }
-}
-
 fn ltsv_writer() -> impl LogWrite {
     let writer = log_writer_new_from_fn(
         |serialized: &str, level: Severity| match level {
@@ -61,7 +54,7 @@ fn ltsv_writer() -> impl LogWrite {
         },
         level_checker_from_lower_bound(Severity::Info),
     );
-    limited_writer_new(writer, rate_limiter(Duration::from_millis(1)))
+    limited_writer_new(writer, token_bucket(100, 1000.0))
 }
 
 fn init_log() {
@@ -78,7 +71,7 @@ fn init_log() {
 
     let logger_with_proxy = logger_new_from_proxy(logger, proxy);
 
-    copy::set_boxed(Box::new(logger_with_proxy));
+    copy::set_boxed(Box::new(logger_with_proxy)).expect("a logger is already set");
 }
 
 static _RESOURCE_KEYS: &[&str] = &["service.name", "host.ip", "host.name"];
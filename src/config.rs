@@ -0,0 +1,350 @@
+//! Builds a logger from a configuration file(TOML, or YAML with the
+//! `config-yaml` feature).
+//!
+//! ```toml
+//! level = "info"                     # The default minimum severity(or off).
+//! format = "json"                    # text(default), logfmt or json
+//!
+//! [targets]                          # Minimum severities per target prefix.
+//! "myapp::db" = "debug"
+//! hyper = "warn"
+//! "myapp::noisy" = "off"
+//!
+//! [resource]                         # Added to the resource of every item.
+//! "service.name" = "api"
+//!
+//! [rate_limit]                       # A token bucket for all writers.
+//! capacity = 100
+//! refill_per_sec = 1000.0
+//!
+//! [[writers]]                        # stderr if no writers are given.
+//! output = "stderr"                  # stderr, stdout, std or a file path
+//! min_level = "warn"
+//!
+//! [[writers]]
+//! output = "/var/log/myapp.log"
+//! rotate = { max_bytes = 10485760, max_files = 5 }
+//!
+//! [[writers]]                        # A strftime template for a period.
+//! output = "/var/log/myapp-%Y%m%d.log"
+//! rotate = { period = "daily", max_age_secs = 604800, compress = true }
+//! ```
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::{
+    copy::{
+        env::{format_serializer, output_writer},
+        logger_new, logger_new_from_proxy, set_arc, set_max_level,
+        target::{parse_filter, parse_level, target_filter_logger_new, TargetLevels},
+        Logger,
+    },
+    proxy::copy::proxy_new_from_fn,
+    serialize::{time::TimeZoneMode, Serialize},
+    write::{
+        fanout_writer_new, limited_writer_new_lock_free, log_writer_route_by_severity,
+        rate_limit::token_bucket,
+        rotate::{
+            rotating_writer_new_with_retention, time_rotating_writer_new_with_retention,
+            RetentionPolicy, RotationPeriod,
+        },
+        LogWrite, SeverityRoute,
+    },
+    Item, Severity,
+};
+
+/// A rotation of a file writer: by size(`max_bytes`) or by time(`period`).
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct RotateConfig {
+    /// The size threshold.
+    #[serde(default)]
+    pub max_bytes: Option<u64>,
+    /// hourly or daily; the output is a strftime template(e.g. `app-%Y%m%d.log`).
+    #[serde(default)]
+    pub period: Option<String>,
+    /// utc(default) or local; the time zone of the template and the periods.
+    #[serde(default)]
+    pub time_zone: Option<String>,
+    /// The number of rotated files to keep(unlimited if None).
+    #[serde(default)]
+    pub max_files: Option<usize>,
+    /// The maximum age of rotated files in seconds(unlimited if None).
+    #[serde(default)]
+    pub max_age_secs: Option<u64>,
+    /// Compresses rotated files(requires the `gzip` feature).
+    #[serde(default)]
+    pub compress: bool,
+}
+
+/// A writer.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct WriterConfig {
+    /// stderr, stdout, std(Warn or higher to stderr) or a file path.
+    pub output: String,
+    /// The lowest severity written(all if None).
+    #[serde(default)]
+    pub min_level: Option<String>,
+    /// Rotates the file(file paths only).
+    #[serde(default)]
+    pub rotate: Option<RotateConfig>,
+}
+
+/// A token bucket shared by the writers(see [`token_bucket`]).
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimitConfig {
+    pub capacity: u32,
+    pub refill_per_sec: f64,
+}
+
+/// A logger configuration.
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct LogConfig {
+    /// The default minimum severity or off(default: info).
+    #[serde(default)]
+    pub level: Option<String>,
+    /// Minimum severities(or off) per target prefix.
+    #[serde(default)]
+    pub targets: BTreeMap<String, String>,
+    /// text(default), logfmt or json.
+    #[serde(default)]
+    pub format: Option<String>,
+    /// Resource fields added to every item(existing fields are kept).
+    #[serde(default)]
+    pub resource: BTreeMap<String, String>,
+    #[serde(default)]
+    pub rate_limit: Option<RateLimitConfig>,
+    /// The writers(stderr if empty).
+    #[serde(default)]
+    pub writers: Vec<WriterConfig>,
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+fn level(name: &str) -> io::Result<Severity> {
+    parse_level(name).ok_or_else(|| invalid(format!("invalid log level: {name}")))
+}
+
+/// Parses a severity name or `off`.
+fn filter(name: &str) -> io::Result<Option<Severity>> {
+    parse_filter(name).ok_or_else(|| invalid(format!("invalid log level: {name}")))
+}
+
+impl LogConfig {
+    /// Parses a TOML document.
+    pub fn from_toml_str(document: &str) -> io::Result<Self> {
+        toml::from_str(document).map_err(|e| invalid(e.to_string()))
+    }
+
+    /// Reads and parses a TOML file.
+    pub fn from_toml_file<P>(path: P) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_toml_str(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parses a YAML document(same keys as TOML).
+    #[cfg(feature = "config-yaml")]
+    pub fn from_yaml_str(document: &str) -> io::Result<Self> {
+        serde_yaml_ng::from_str(document).map_err(|e| invalid(e.to_string()))
+    }
+
+    /// Reads and parses a YAML file.
+    #[cfg(feature = "config-yaml")]
+    pub fn from_yaml_file<P>(path: P) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_yaml_str(&std::fs::read_to_string(path)?)
+    }
+
+    /// Gets the minimum severities.
+    pub fn target_levels(&self) -> io::Result<TargetLevels> {
+        let default: Option<Severity> = filter(self.level.as_deref().unwrap_or("info"))?;
+        self.targets
+            .iter()
+            .try_fold(TargetLevels::new(default), |levels, (target, name)| {
+                Ok(levels.with_target(target, filter(name)?))
+            })
+    }
+}
+
+/// Creates a rotating file writer.
+fn rotating_writer(path: &str, r: &RotateConfig) -> io::Result<Box<dyn LogWrite>> {
+    let retention = RetentionPolicy {
+        max_files: r.max_files,
+        max_age: r.max_age_secs.map(Duration::from_secs),
+        compress: r.compress,
+    };
+    let period: Option<RotationPeriod> = match r.period.as_deref().map(str::trim) {
+        None => None,
+        Some(p) if p.eq_ignore_ascii_case("hourly") => Some(RotationPeriod::Hourly),
+        Some(p) if p.eq_ignore_ascii_case("daily") => Some(RotationPeriod::Daily),
+        Some(p) => return Err(invalid(format!("invalid rotation period: {p}"))),
+    };
+    let zone: TimeZoneMode = match r.time_zone.as_deref().map(str::trim) {
+        None => TimeZoneMode::Utc,
+        Some(z) if z.eq_ignore_ascii_case("utc") => TimeZoneMode::Utc,
+        Some(z) if z.eq_ignore_ascii_case("local") => TimeZoneMode::Local,
+        Some(z) => return Err(invalid(format!("invalid time zone: {z}"))),
+    };
+    match (r.max_bytes, period) {
+        (Some(max_bytes), None) => Ok(Box::new(rotating_writer_new_with_retention(
+            path, max_bytes, retention,
+        )?)),
+        (None, Some(period)) => Ok(Box::new(time_rotating_writer_new_with_retention(
+            path, period, zone, retention,
+        )?)),
+        _ => Err(invalid(format!(
+            "rotation of {path} needs either max_bytes or period"
+        ))),
+    }
+}
+
+/// Creates the writer of a writer configuration.
+fn writer(config: &WriterConfig) -> io::Result<Box<dyn LogWrite>> {
+    let w: Box<dyn LogWrite> = match (&config.rotate, config.output.trim()) {
+        (None, output) => output_writer(output)?,
+        (Some(_), "stderr" | "stdout" | "std") => {
+            return Err(invalid(format!("cannot rotate {}", config.output)))
+        }
+        (Some(r), path) => rotating_writer(path, r)?,
+    };
+    match &config.min_level {
+        None => Ok(w),
+        Some(name) => Ok(Box::new(log_writer_route_by_severity(vec![
+            SeverityRoute::new(level(name)?, Severity::Fatal, w),
+        ]))),
+    }
+}
+
+/// Creates a logger from a configuration.
+///
+/// Gets the logger and the lowest severity it accepts. Use it with
+/// [`crate::copy::replace`] to reload a changed configuration.
+pub fn logger_from_config(config: &LogConfig) -> io::Result<(impl Logger, Severity)> {
+    let levels: TargetLevels = config.target_levels()?;
+    let serializer: Box<dyn Serialize> =
+        format_serializer(config.format.as_deref().unwrap_or("text"))?;
+    let mut writers: Vec<Box<dyn LogWrite>> = config
+        .writers
+        .iter()
+        .map(writer)
+        .collect::<io::Result<_>>()?;
+    if writers.is_empty() {
+        writers.push(output_writer("stderr")?);
+    }
+    let writer: Box<dyn LogWrite> = match &config.rate_limit {
+        None => Box::new(fanout_writer_new(writers)),
+        Some(r) => Box::new(limited_writer_new_lock_free(
+            fanout_writer_new(writers),
+            token_bucket(r.capacity, r.refill_per_sec),
+        )),
+    };
+    let resource: BTreeMap<String, String> = config.resource.clone();
+    let proxy = proxy_new_from_fn(move |mut item: Item| {
+        for (key, val) in &resource {
+            item.resource
+                .entry(key.clone())
+                .or_insert_with(|| val.clone());
+        }
+        item
+    });
+    let min: Severity = levels.min_level();
+    let logger = logger_new_from_proxy(logger_new(serializer, writer), proxy);
+    Ok((target_filter_logger_new(logger, levels), min))
+}
+
+/// Sets a logger built from a configuration.
+///
+/// The global maximum level([`crate::copy::set_max_level`]) is set to the
+/// lowest severity the configuration accepts. Fails on invalid values, if a
+/// file cannot be opened or if a logger is already set
+/// ([`io::ErrorKind::AlreadyExists`]).
+pub fn init_from_config(config: &LogConfig) -> io::Result<()> {
+    let (logger, min) = logger_from_config(config)?;
+    set_arc(Arc::new(logger)).map_err(|e| io::Error::new(io::ErrorKind::AlreadyExists, e))?;
+    set_max_level(min);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{writer, LogConfig, RotateConfig, WriterConfig};
+
+    #[test]
+    fn parses_rotations() {
+        let config: LogConfig = LogConfig::from_toml_str(
+            r#"
+            [[writers]]
+            output = "app.log"
+            rotate = { max_bytes = 1024, max_files = 3 }
+
+            [[writers]]
+            output = "app-%Y%m%d%H.log"
+            rotate = { period = "hourly", time_zone = "local", max_age_secs = 86400 }
+            "#,
+        )
+        .unwrap();
+        let size: &RotateConfig = config.writers[0].rotate.as_ref().unwrap();
+        assert_eq!((Some(1024), Some(3)), (size.max_bytes, size.max_files));
+        let time: &RotateConfig = config.writers[1].rotate.as_ref().unwrap();
+        assert_eq!(Some("hourly"), time.period.as_deref());
+        assert_eq!(Some(86400), time.max_age_secs);
+    }
+
+    #[test]
+    fn rejects_invalid_rotations() {
+        let dir = std::env::temp_dir();
+        for rotate in [
+            RotateConfig::default(),
+            RotateConfig {
+                max_bytes: Some(1024),
+                period: Some("daily".into()),
+                ..Default::default()
+            },
+            RotateConfig {
+                period: Some("weekly".into()),
+                ..Default::default()
+            },
+        ] {
+            let config = WriterConfig {
+                output: dir.join("config-test-%Y.log").to_string_lossy().into(),
+                min_level: None,
+                rotate: Some(rotate),
+            };
+            assert!(writer(&config).is_err());
+        }
+    }
+
+    #[cfg(feature = "config-yaml")]
+    #[test]
+    fn parses_yaml() {
+        let config: LogConfig = LogConfig::from_yaml_str(
+            "level: warn\n\
+             targets:\n  hyper: \"off\"\n\
+             writers:\n  - output: stderr\n    min_level: error\n",
+        )
+        .unwrap();
+        assert_eq!(Some("warn"), config.level.as_deref());
+        assert!(config
+            .target_levels()
+            .unwrap()
+            .level_of(Some("hyper"))
+            .is_none());
+        assert_eq!(Some("error"), config.writers[0].min_level.as_deref());
+        assert!(LogConfig::from_yaml_str("unknown: 1").is_err());
+    }
+}
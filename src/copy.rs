@@ -1,15 +1,52 @@
 //! A simple logging api using non-zero copy.
 
-use std::ops::{Deref, DerefMut};
-use std::sync::Mutex;
+use std::cell::RefCell;
+use std::fmt;
+use std::io;
+use std::ops::DerefMut;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, RwLock, Weak};
 use std::time::SystemTime;
 
-use crate::{proxy::copy::Proxy, serialize::Serialize, write::LogWrite, Item, Severity};
+use crate::{
+    proxy::copy::Proxy,
+    serialize::{Serialize, SerializeBytes},
+    write::{on_error_writer_new, stats, LogWrite, LogWriteBytes, TryLogWrite},
+    Item, Severity,
+};
+
+pub mod env;
+pub mod target;
 
 /// A logger.
 pub trait Logger: Sync + Send {
     /// Logs an item.
     fn log(&self, item: Item);
+
+    /// Writes buffered items(no-op by default).
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct ProxyLogger<L, P> {
+    original: L,
+    proxy: P,
+}
+
+impl<L, P> Logger for ProxyLogger<L, P>
+where
+    L: Logger,
+    P: Proxy + Sync + Send,
+{
+    fn log(&self, item: Item) {
+        let neo: Item = self.proxy.get_item(item);
+        self.original.log(neo)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.original.flush()
+    }
 }
 
 /// Creates a logger which converts a log item before logging.
@@ -22,10 +59,56 @@ where
     L: Logger,
     P: Proxy + Sync + Send,
 {
-    logger_new_from_fn(move |old: Item| {
-        let neo: Item = proxy.get_item(old);
-        original.log(neo)
-    })
+    ProxyLogger { original, proxy }
+}
+
+struct LimitedLogger<L, S> {
+    original: L,
+    state: Mutex<S>,
+}
+
+impl<L, S> Logger for LimitedLogger<L, S>
+where
+    L: Logger,
+    S: FnMut(&Item) -> bool + Sync + Send,
+{
+    fn log(&self, item: Item) {
+        match self.state.lock() {
+            Err(_) => {}
+            Ok(mut guard) => {
+                let state: &mut S = guard.deref_mut();
+                let log_available: bool = state(&item);
+                match log_available {
+                    true => self.original.log(item),
+                    false => stats::DROPPED_BY_RATE_LIMIT.add(1),
+                }
+            }
+        }
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.original.flush()
+    }
+}
+
+/// Creates a logger which can ignore a log item.
+///
+/// Unlike [`crate::write::limited_writer_new`], the state function gets the
+/// whole item(e.g. to limit the rate per attribute; see
+/// [`crate::write::rate_limit::keyed_token_bucket`]).
+///
+/// # Arguments
+/// - original: The original logger.
+/// - log_available: Checks if a log item can be accepted or not.
+pub fn limited_logger_new<L, S>(original: L, log_available: S) -> impl Logger
+where
+    L: Logger,
+    S: FnMut(&Item) -> bool + Sync + Send,
+{
+    LimitedLogger {
+        original,
+        state: Mutex::new(log_available),
+    }
 }
 
 struct FnLogger<L> {
@@ -64,6 +147,10 @@ where
         self.serialize.serialize(&item, &mut buf);
         self.write.write(buf.as_str(), item.severity)
     }
+
+    fn flush(&self) -> io::Result<()> {
+        self.write.flush()
+    }
 }
 
 /// Creates a logger which writes a serialized log item.
@@ -79,28 +166,192 @@ where
     WriteSerialized { serialize, write }
 }
 
-static _LOGGER: Mutex<Option<&dyn Logger>> = Mutex::new(None);
+/// Creates a logger which writes a serialized log item and reports write failures.
+///
+/// # Arguments
+/// - serialize: Serializes a log item.
+/// - write: Writes a serialized log item(fallible).
+/// - on_error: Gets write and flush errors.
+pub fn logger_new_with_on_error<S, W, E>(serialize: S, write: W, on_error: E) -> impl Logger
+where
+    S: Serialize,
+    W: TryLogWrite,
+    E: Fn(&io::Error) + Sync + Send,
+{
+    logger_new(serialize, on_error_writer_new(write, on_error))
+}
 
-impl Logger for Option<&dyn Logger> {
+struct WriteSerializedBytes<S, W> {
+    serialize: S,
+    write: W,
+}
+
+impl<S, W> Logger for WriteSerializedBytes<S, W>
+where
+    S: SerializeBytes,
+    W: LogWriteBytes,
+{
     fn log(&self, item: Item) {
-        match self {
-            None => {}
-            Some(l) => l.log(item),
-        }
+        let mut buf: Vec<u8> = Vec::new();
+        self.serialize.serialize(&item, &mut buf);
+        self.write.write(&buf, item.severity)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.write.flush()
     }
 }
 
-impl Logger for Mutex<Option<&dyn Logger>> {
-    fn log(&self, item: Item) {
-        match self.lock() {
-            Err(_) => {}
-            Ok(g) => {
-                let ro: &Option<_> = g.deref();
-                let o: Option<&dyn Logger> = ro.as_deref();
-                o.log(item)
+/// Creates a logger which writes a log item serialized as bytes.
+///
+/// # Arguments
+/// - serialize: Serializes a log item into bytes.
+/// - write: Writes a serialized log item.
+pub fn logger_new_bytes<S, W>(serialize: S, write: W) -> impl Logger
+where
+    S: SerializeBytes,
+    W: LogWriteBytes,
+{
+    WriteSerializedBytes { serialize, write }
+}
+
+/// An error of [`set`] when a logger is already set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetLoggerError(());
+
+impl fmt::Display for SetLoggerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a logger is already set")
+    }
+}
+
+impl std::error::Error for SetLoggerError {}
+
+/// A logger and the generation of the cell it was loaded at.
+type Loaded = (u64, Option<Arc<dyn Logger>>);
+
+/// A weak copy of the logger of a thread and the generation it was copied at.
+type Cached = (u64, Option<Weak<dyn Logger>>);
+
+thread_local! {
+    /// The copy of the global logger used by the current thread.
+    static CACHED: RefCell<Cached> = const { RefCell::new((0, None)) };
+}
+
+/// A cell of the global logger which can be swapped while it is used.
+///
+/// Each thread keeps a weak copy of the `Arc`, so a log call reads the
+/// generation(a relaxed atomic load) and upgrades the copy for the duration of
+/// the call; the lock is taken only by the first call of a thread after a
+/// change. The cell holds the only lasting strong reference, so a removed
+/// logger is dropped once the calls using it return, even if other threads
+/// stay idle. A stale generation only means the previous logger is used a
+/// little longer.
+struct LoggerCell {
+    slot: RwLock<Option<Arc<dyn Logger>>>,
+    /// Incremented by every change of the slot(under the write lock).
+    generation: AtomicU64,
+}
+
+impl LoggerCell {
+    const fn new() -> Self {
+        Self {
+            slot: RwLock::new(None),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    fn load(&self) -> io::Result<Loaded> {
+        match self.slot.read() {
+            Err(_) => Err(io::Error::other("lock poisoned")),
+            Ok(g) => Ok((self.generation.load(Ordering::Relaxed), g.clone())),
+        }
+    }
+
+    /// Updates the copy of a thread if it is older than the generation.
+    fn refresh(&self, cache: &RefCell<Cached>, generation: u64) {
+        let stale: bool = cache.try_borrow().is_ok_and(|c| c.0 < generation);
+        if !stale {
+            return;
+        }
+        if let (Ok((g, fresh)), Ok(mut c)) = (self.load(), cache.try_borrow_mut()) {
+            *c = (g, fresh.as_ref().map(Arc::downgrade));
+        }
+    }
+
+    /// Calls a function with the current logger.
+    fn with<T, F>(&self, f: F) -> io::Result<T>
+    where
+        F: FnOnce(Option<&dyn Logger>) -> T,
+    {
+        let generation: u64 = self.generation.load(Ordering::Relaxed);
+        let cached: Option<Option<Arc<dyn Logger>>> = CACHED
+            .try_with(|cache: &RefCell<Cached>| {
+                self.refresh(cache, generation);
+                let c = cache.try_borrow().ok()?;
+                match (generation <= c.0, &c.1) {
+                    (false, _) => None,
+                    (true, None) => Some(None),
+                    // None if the logger was removed since the generation was read.
+                    (true, Some(w)) => w.upgrade().map(Some),
+                }
+            })
+            .ok()
+            .flatten();
+        match cached {
+            Some(logger) => Ok(f(logger.as_deref())),
+            // The copy is unavailable(e.g. while the thread exits).
+            None => Ok(f(self.load()?.1.as_deref())),
+        }
+    }
+
+    fn set(&self, neo: Arc<dyn Logger>) -> Result<(), SetLoggerError> {
+        match self.slot.write() {
+            Ok(mut g) if g.is_none() => {
+                g.replace(neo);
+                self.generation.fetch_add(1, Ordering::Relaxed);
+                Ok(())
             }
+            _ => Err(SetLoggerError(())),
         }
     }
+
+    fn swap(&self, neo: Option<Arc<dyn Logger>>) -> Option<Arc<dyn Logger>> {
+        let (generation, old) = match self.slot.write() {
+            Err(_) => return None,
+            Ok(mut g) => (
+                self.generation.fetch_add(1, Ordering::Relaxed) + 1,
+                std::mem::replace(&mut *g, neo),
+            ),
+        };
+        // The copy of the current thread is updated at once.
+        CACHED
+            .try_with(|cache: &RefCell<Cached>| self.refresh(cache, generation))
+            .ok();
+        old
+    }
+}
+
+static _LOGGER: LoggerCell = LoggerCell::new();
+
+impl Logger for &'static dyn Logger {
+    fn log(&self, item: Item) {
+        (*self).log(item)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        (*self).flush()
+    }
+}
+
+impl Logger for Arc<dyn Logger> {
+    fn log(&self, item: Item) {
+        self.as_ref().log(item)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.as_ref().flush()
+    }
 }
 
 impl Logger for Box<dyn Logger> {
@@ -108,11 +359,43 @@ impl Logger for Box<dyn Logger> {
         let r = self.as_ref();
         r.log(item)
     }
+
+    fn flush(&self) -> io::Result<()> {
+        self.as_ref().flush()
+    }
+}
+
+/// The lowest severity logged by the `log_*` functions.
+static MAX_LEVEL: AtomicU8 = AtomicU8::new(1);
+
+/// Sets the lowest severity logged by the `log_*` functions(default: Trace).
+///
+/// Less severe items are ignored before the timestamp is taken and before the
+/// logger is called, unlike the level checks of writers which see serialized
+/// items. Items ignored here are not counted by [`crate::write::stats`].
+pub fn set_max_level(level: Severity) {
+    MAX_LEVEL.store(level.into(), Ordering::Relaxed)
+}
+
+/// Gets the lowest severity logged by the `log_*` functions.
+///
+/// Check it to skip building expensive items.
+pub fn max_level() -> Severity {
+    MAX_LEVEL.load(Ordering::Relaxed).into()
 }
 
 fn _log(mut item: Item) {
+    if item.severity < max_level() {
+        return;
+    }
     item.timestamp = SystemTime::now();
-    _LOGGER.log(item)
+    _LOGGER
+        .with(|o: Option<&dyn Logger>| {
+            if let Some(l) = o {
+                l.log(item)
+            }
+        })
+        .ok();
 }
 
 /// Logs an item as a trace-level event.
@@ -151,18 +434,108 @@ pub fn log_fatal(mut item: Item) {
     _log(item)
 }
 
-/// Sets a logger impl.
-pub fn set(neo: &'static dyn Logger) {
-    match _LOGGER.try_lock() {
-        Err(_) => {}
-        Ok(mut g) => {
-            let mo: &mut Option<_> = g.deref_mut();
-            mo.replace(neo);
-        }
+/// Sets a logger impl; fails if a logger is already set(see [`replace`]).
+pub fn set(neo: &'static dyn Logger) -> Result<(), SetLoggerError> {
+    set_arc(Arc::new(neo))
+}
+
+/// Sets a logger impl(boxed); fails if a logger is already set.
+pub fn set_boxed(neo: Box<dyn Logger>) -> Result<(), SetLoggerError> {
+    set_arc(Arc::from(neo))
+}
+
+/// Sets a shared logger impl; fails if a logger is already set.
+pub fn set_arc(neo: Arc<dyn Logger>) -> Result<(), SetLoggerError> {
+    _LOGGER.set(neo)
+}
+
+/// Replaces the logger at runtime and returns the previous one.
+///
+/// Each item is logged by either the previous or the new logger(e.g. to
+/// switch from stderr to a file after daemonizing, or to a network sink with
+/// new credentials). Flush the returned logger to write its buffered items.
+///
+/// The previous logger is dropped when the returned `Arc` is dropped(or when
+/// calls which started before return, if later).
+pub fn replace(neo: Arc<dyn Logger>) -> Option<Arc<dyn Logger>> {
+    _LOGGER.swap(Some(neo))
+}
+
+/// Removes the logger and returns it; items are ignored until a logger is set.
+///
+/// Calls which started before keep using the returned logger until they
+/// return; flush and drop it to shut down its writers(see [`replace`]).
+pub fn take() -> Option<Arc<dyn Logger>> {
+    _LOGGER.swap(None)
+}
+
+/// Removes the logger and flushes it(e.g. between tests or before unloading a plugin).
+///
+/// The logger(with its writers, background threads and connections) is
+/// dropped before this returns unless a call on another thread is using it.
+pub fn reset() -> io::Result<()> {
+    match take() {
+        None => Ok(()),
+        Some(l) => l.flush(),
     }
 }
 
-/// Sets a logger impl(boxed).
-pub fn set_boxed(neo: Box<dyn Logger>) {
-    set(Box::leak(neo))
+/// Flushes the logger set by [`set`], [`set_boxed`] or [`set_arc`].
+///
+/// Call it before exiting so that buffered items are not lost.
+pub fn flush() -> io::Result<()> {
+    match _LOGGER.load()?.1 {
+        None => Ok(()),
+        Some(l) => l.flush(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{mpsc, Arc};
+
+    use super::{log_info, reset, set_arc, Logger};
+    use crate::Item;
+
+    struct DropFlag(Arc<AtomicBool>, Arc<AtomicBool>);
+
+    impl Logger for DropFlag {
+        fn log(&self, _: Item) {
+            self.0.store(true, Ordering::SeqCst)
+        }
+
+        fn flush(&self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Drop for DropFlag {
+        fn drop(&mut self) {
+            self.1.store(true, Ordering::SeqCst)
+        }
+    }
+
+    #[test]
+    fn reset_releases_a_logger_used_by_an_idle_thread() {
+        let logged: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+        let dropped: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+        set_arc(Arc::new(DropFlag(logged.clone(), dropped.clone()))).unwrap();
+
+        let (idle_tx, idle) = mpsc::channel::<()>();
+        let (wake, wake_rx) = mpsc::channel::<()>();
+        let t = std::thread::spawn(move || {
+            log_info(Item::new("once", Default::default()));
+            idle_tx.send(()).unwrap();
+            wake_rx.recv().ok();
+        });
+        idle.recv().unwrap();
+        assert!(logged.load(Ordering::SeqCst));
+
+        reset().unwrap();
+        assert!(dropped.load(Ordering::SeqCst));
+        wake.send(()).unwrap();
+        t.join().unwrap();
+    }
 }
@@ -0,0 +1,109 @@
+//! A one-call initializer configured by environment variables.
+//!
+//! | Variable          | Value                                                          |
+//! |:-----------------:|:--------------------------------------------------------------:|
+//! | SIMPLE_LOG        | Directives(see [`TargetLevels::parse`]; default: info)         |
+//! | RUST_LOG          | Used if SIMPLE_LOG is not set                                  |
+//! | SIMPLE_LOG_OUTPUT | stderr(default), stdout, std(Warn or higher to stderr) or path |
+//! | SIMPLE_LOG_FORMAT | text(default), logfmt or json                                  |
+
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::{
+    copy::{
+        logger_new, set_arc, set_max_level,
+        target::{target_filter_logger_new, TargetLevels},
+        Logger,
+    },
+    serialize::{
+        compact::compact_serializer_new, logfmt::logfmt_serializer_new,
+        logstash::logstash_serializer_new, Serialize,
+    },
+    write::{
+        file::{locking_file_writer_new, write_line},
+        log_writer_new_std_locked_from_fn, try_log_writer_new_from_fn, LogWrite,
+    },
+    Severity,
+};
+
+/// The variable of the directives.
+pub const DIRECTIVES_VAR: &str = "SIMPLE_LOG";
+/// The variable of the directives used if [`DIRECTIVES_VAR`] is not set.
+pub const FALLBACK_DIRECTIVES_VAR: &str = "RUST_LOG";
+/// The variable of the output.
+pub const OUTPUT_VAR: &str = "SIMPLE_LOG_OUTPUT";
+/// The variable of the format.
+pub const FORMAT_VAR: &str = "SIMPLE_LOG_FORMAT";
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+/// Gets a variable; an unset or empty variable is None.
+fn var(name: &str) -> Option<String> {
+    std::env::var(name)
+        .ok()
+        .filter(|v: &String| !v.trim().is_empty())
+}
+
+/// Creates the writer of an output.
+pub(crate) fn output_writer(output: &str) -> io::Result<Box<dyn LogWrite>> {
+    match output.trim() {
+        "stderr" => Ok(Box::new(try_log_writer_new_from_fn(
+            |serialized: &str, _: Severity| write_line(&mut io::stderr().lock(), serialized),
+        ))),
+        "stdout" => Ok(Box::new(try_log_writer_new_from_fn(
+            |serialized: &str, _: Severity| write_line(&mut io::stdout().lock(), serialized),
+        ))),
+        "std" => Ok(Box::new(log_writer_new_std_locked_from_fn(|_| true))),
+        path => Ok(Box::new(locking_file_writer_new(PathBuf::from(path))?)),
+    }
+}
+
+/// Creates the serializer of a format.
+pub(crate) fn format_serializer(format: &str) -> io::Result<Box<dyn Serialize>> {
+    match format.trim().to_ascii_lowercase().as_str() {
+        "text" => Ok(Box::new(compact_serializer_new(false))),
+        "logfmt" => Ok(Box::new(logfmt_serializer_new())),
+        "json" => Ok(Box::new(logstash_serializer_new())),
+        other => Err(invalid(format!("unknown log format: {other}"))),
+    }
+}
+
+/// Creates a logger configured by environment variables(see the module documentation).
+///
+/// Gets the logger and the lowest severity its directives accept.
+pub fn logger_from_env() -> io::Result<(impl Logger, Severity)> {
+    let directives: String = var(DIRECTIVES_VAR)
+        .or_else(|| var(FALLBACK_DIRECTIVES_VAR))
+        .unwrap_or_default();
+    let levels: TargetLevels =
+        TargetLevels::parse(&directives, Severity::Info).map_err(|e| invalid(e.to_string()))?;
+    let writer: Box<dyn LogWrite> =
+        output_writer(&var(OUTPUT_VAR).unwrap_or_else(|| "stderr".into()))?;
+    let serializer: Box<dyn Serialize> =
+        format_serializer(&var(FORMAT_VAR).unwrap_or_else(|| "text".into()))?;
+    let min: Severity = levels.min_level();
+    Ok((
+        target_filter_logger_new(logger_new(serializer, writer), levels),
+        min,
+    ))
+}
+
+/// Sets a logger configured by environment variables(see the module documentation).
+///
+/// The global maximum level([`crate::copy::set_max_level`]) is set to the
+/// lowest severity the directives accept. Items without a target get the
+/// default level. Fails on invalid variables, if the file cannot be opened or
+/// if a logger is already set([`io::ErrorKind::AlreadyExists`]).
+///
+/// e.g. `SIMPLE_LOG=warn,myapp=debug,hyper=off SIMPLE_LOG_FORMAT=json ./myapp`;
+/// `SIMPLE_LOG=off` turns every target off.
+pub fn init_from_env() -> io::Result<()> {
+    let (logger, min) = logger_from_env()?;
+    set_arc(Arc::new(logger)).map_err(|e| io::Error::new(io::ErrorKind::AlreadyExists, e))?;
+    set_max_level(min);
+    Ok(())
+}
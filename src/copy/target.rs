@@ -0,0 +1,255 @@
+//! A logger which applies minimum severities per target.
+
+use std::fmt;
+use std::io;
+
+use crate::{copy::Logger, write::stats, Item, Severity};
+
+/// Minimum severities per target prefix(e.g. `mycrate::db=debug`, `hyper=warn`).
+///
+/// A level of None turns a target off.
+#[derive(Clone)]
+pub struct TargetLevels {
+    default: Option<Severity>,
+    /// Sorted by the length of the prefix(longest first).
+    targets: Vec<(String, Option<Severity>)>,
+}
+
+/// An invalid directive of [`TargetLevels::parse`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectiveError {
+    pub directive: String,
+}
+
+impl fmt::Display for DirectiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid log directive: {}", self.directive)
+    }
+}
+
+impl std::error::Error for DirectiveError {}
+
+/// Parses a severity name(case-insensitive; `warning` is `warn`).
+pub(crate) fn parse_level(name: &str) -> Option<Severity> {
+    match name.trim().to_ascii_lowercase().as_str() {
+        "trace" => Some(Severity::Trace),
+        "debug" => Some(Severity::Debug),
+        "info" => Some(Severity::Info),
+        "warn" | "warning" => Some(Severity::Warn),
+        "error" => Some(Severity::Error),
+        "fatal" => Some(Severity::Fatal),
+        _ => None,
+    }
+}
+
+/// Parses a level filter: a severity name or `off`(Some(None)).
+pub(crate) fn parse_filter(name: &str) -> Option<Option<Severity>> {
+    match name.trim().eq_ignore_ascii_case("off") {
+        true => Some(None),
+        false => parse_level(name).map(Some),
+    }
+}
+
+/// Checks if a target is the prefix or one of its submodules.
+fn matches(target: &str, prefix: &str) -> bool {
+    match target.strip_prefix(prefix) {
+        None => false,
+        Some(rest) => rest.is_empty() || rest.starts_with("::"),
+    }
+}
+
+impl TargetLevels {
+    /// Creates levels which apply `default` to every target(None: off).
+    pub fn new<L>(default: L) -> Self
+    where
+        L: Into<Option<Severity>>,
+    {
+        Self {
+            default: default.into(),
+            targets: vec![],
+        }
+    }
+
+    /// Parses comma separated directives(e.g. `info,mycrate::db=debug,hyper=warn`).
+    ///
+    /// A directive is `level`(the default), `target=level` or `target`(every
+    /// level of the target); the level `off` turns the default or the target
+    /// off(e.g. `off`, `info,hyper=off`). Empty directives are ignored; the
+    /// default is `default` unless a directive sets it.
+    pub fn parse(directives: &str, default: Severity) -> Result<Self, DirectiveError> {
+        let mut levels: Self = Self::new(default);
+        for directive in directives.split(',').map(str::trim) {
+            let invalid = || DirectiveError {
+                directive: directive.into(),
+            };
+            levels = match directive.split_once('=') {
+                _ if directive.is_empty() => levels,
+                Some((target, level)) => {
+                    let level: Option<Severity> = parse_filter(level).ok_or_else(invalid)?;
+                    match target.trim() {
+                        "" => return Err(invalid()),
+                        t => levels.with_target(t, level),
+                    }
+                }
+                None => match parse_filter(directive) {
+                    Some(level) => levels.with_default(level),
+                    None if directive.contains(char::is_whitespace) => return Err(invalid()),
+                    None => levels.with_target(directive, Severity::Trace),
+                },
+            };
+        }
+        Ok(levels)
+    }
+
+    /// Sets the minimum severity of a target and its submodules.
+    ///
+    /// `hyper` matches `hyper` and `hyper::client` but not `hyperx`; the
+    /// longest matching prefix wins. None turns the target off.
+    pub fn with_target<L>(mut self, prefix: &str, level: L) -> Self
+    where
+        L: Into<Option<Severity>>,
+    {
+        self.targets.retain(|(p, _)| p != prefix);
+        self.targets.push((prefix.into(), level.into()));
+        self.targets
+            .sort_by_key(|(p, _)| std::cmp::Reverse(p.len()));
+        self
+    }
+
+    /// Sets the minimum severity of items without a matching target(None: off).
+    pub fn with_default<L>(self, default: L) -> Self
+    where
+        L: Into<Option<Severity>>,
+    {
+        Self {
+            default: default.into(),
+            ..self
+        }
+    }
+
+    /// Gets the minimum severity of a target(None if it is off).
+    pub fn level_of(&self, target: Option<&str>) -> Option<Severity> {
+        let found: Option<Option<Severity>> = target.and_then(|t: &str| {
+            self.targets
+                .iter()
+                .find(|(p, _)| matches(t, p))
+                .map(|(_, level)| *level)
+        });
+        found.unwrap_or(self.default)
+    }
+
+    /// Gets the lowest severity any target accepts(e.g. for [`crate::copy::set_max_level`]).
+    ///
+    /// Gets Fatal if every target is off; the filter still drops those items.
+    pub fn min_level(&self) -> Severity {
+        self.targets
+            .iter()
+            .filter_map(|(_, level)| *level)
+            .chain(self.default)
+            .fold(Severity::Fatal, Severity::min)
+    }
+
+    /// Checks if an item is severe enough for its target.
+    pub fn enabled(&self, item: &Item) -> bool {
+        self.level_of(item.target.as_deref())
+            .is_some_and(|level: Severity| level <= item.severity)
+    }
+}
+
+struct TargetFilterLogger<L> {
+    original: L,
+    levels: TargetLevels,
+}
+
+impl<L> Logger for TargetFilterLogger<L>
+where
+    L: Logger,
+{
+    fn log(&self, item: Item) {
+        match self.levels.enabled(&item) {
+            true => self.original.log(item),
+            false => stats::DROPPED_BY_LEVEL.add(1),
+        }
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.original.flush()
+    }
+}
+
+/// Creates a logger which ignores items less severe than the level of their target.
+///
+/// The target of an item is [`Item::target`]; items without one get the
+/// default level.
+///
+/// # Arguments
+/// - original: The original logger.
+/// - levels: The minimum severities per target.
+pub fn target_filter_logger_new<L>(original: L, levels: TargetLevels) -> impl Logger
+where
+    L: Logger,
+{
+    TargetFilterLogger { original, levels }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DirectiveError, TargetLevels};
+    use crate::Severity;
+
+    fn parse(directives: &str) -> TargetLevels {
+        TargetLevels::parse(directives, Severity::Info).unwrap()
+    }
+
+    #[test]
+    fn off_globally() {
+        let levels: TargetLevels = parse("off");
+        assert!(levels.level_of(None).is_none());
+        assert!(levels.level_of(Some("mycrate")).is_none());
+        assert!(Severity::Fatal == levels.min_level());
+        assert!(parse("OFF,mycrate=debug").level_of(Some("mycrate")) == Some(Severity::Debug));
+    }
+
+    #[test]
+    fn off_per_target() {
+        let levels: TargetLevels = parse("warn,mycrate=off,mycrate::db=trace");
+        assert!(levels.level_of(Some("mycrate")).is_none());
+        assert!(levels.level_of(Some("mycrate::http")).is_none());
+        assert!(levels.level_of(Some("mycrate::db")) == Some(Severity::Trace));
+        assert!(levels.level_of(Some("other")) == Some(Severity::Warn));
+        assert!(Severity::Trace == levels.min_level());
+    }
+
+    #[test]
+    fn bare_level_sets_the_default() {
+        let levels: TargetLevels = parse(" debug ");
+        assert!(levels.level_of(None) == Some(Severity::Debug));
+        assert!(parse("").level_of(Some("any")) == Some(Severity::Info));
+        assert!(parse("warning").level_of(None) == Some(Severity::Warn));
+    }
+
+    #[test]
+    fn target_level_lists() {
+        let levels: TargetLevels = parse("error,hyper=warn,,mycrate::db=debug,mycrate");
+        assert!(levels.level_of(None) == Some(Severity::Error));
+        assert!(levels.level_of(Some("hyper::client")) == Some(Severity::Warn));
+        assert!(levels.level_of(Some("hyperx")) == Some(Severity::Error));
+        assert!(levels.level_of(Some("mycrate::db::pool")) == Some(Severity::Debug));
+        assert!(levels.level_of(Some("mycrate::api")) == Some(Severity::Trace));
+    }
+
+    #[test]
+    fn invalid_directives() {
+        for (directives, invalid) in [
+            ("mycrate=verbose", "mycrate=verbose"),
+            ("info,=debug", "=debug"),
+            ("hyper=", "hyper="),
+            ("my crate", "my crate"),
+        ] {
+            let e: DirectiveError = TargetLevels::parse(directives, Severity::Info)
+                .err()
+                .unwrap();
+            assert_eq!(invalid, e.directive);
+        }
+    }
+}
@@ -1,9 +1,29 @@
-#![forbid(unsafe_code)]
+#![cfg_attr(
+    not(any(
+        all(windows, feature = "windows-eventlog"),
+        all(target_os = "android", feature = "android-log"),
+        feature = "mmap"
+    )),
+    forbid(unsafe_code)
+)]
+// The Windows Event Log, logcat and mmap sinks call system APIs; unsafe code is allowed only there.
+#![cfg_attr(
+    any(
+        all(windows, feature = "windows-eventlog"),
+        all(target_os = "android", feature = "android-log"),
+        feature = "mmap"
+    ),
+    deny(unsafe_code)
+)]
 
+use std::borrow::Cow;
 use std::cmp::Ordering;
 use std::collections::BTreeMap;
+use std::fmt;
 use std::time::SystemTime;
 
+#[cfg(feature = "config")]
+pub mod config;
 pub mod copy;
 pub mod proxy;
 pub mod serialize;
@@ -72,6 +92,99 @@ impl Severity {
             Self::Fatal => "fatal",
         }
     }
+
+    /// Gets the syslog severity(RFC 5424) for this level.
+    pub fn as_syslog(&self) -> u8 {
+        match self {
+            Self::Trace => 7,
+            Self::Debug => 7,
+            Self::Info => 6,
+            Self::Warn => 4,
+            Self::Error => 3,
+            Self::Fatal => 2,
+        }
+    }
+}
+
+/// A log message.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Body {
+    Text(String),
+    /// A structured message(may be nested).
+    Map(BTreeMap<String, Body>),
+    Bytes(Vec<u8>),
+}
+
+impl Body {
+    /// Gets the text of a text body.
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Self::Text(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Gets a textual representation.
+    ///
+    /// | Body  | Text             |
+    /// |:-----:|:----------------:|
+    /// | Text  | as is            |
+    /// | Map   | JSON object      |
+    /// | Bytes | base64           |
+    pub fn to_text(&self) -> Cow<'_, str> {
+        match self {
+            Self::Text(s) => Cow::Borrowed(s),
+            Self::Map(_) => {
+                let mut buf: String = String::new();
+                serialize::json::write_body(self, &mut buf);
+                Cow::Owned(buf)
+            }
+            Self::Bytes(b) => {
+                let mut buf: String = String::new();
+                serialize::base64::write(b, &mut buf);
+                Cow::Owned(buf)
+            }
+        }
+    }
+
+    /// Checks if this body has no content.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Self::Text(s) => s.is_empty(),
+            Self::Map(m) => m.is_empty(),
+            Self::Bytes(b) => b.is_empty(),
+        }
+    }
+}
+
+impl fmt::Display for Body {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_text())
+    }
+}
+
+impl From<&str> for Body {
+    fn from(s: &str) -> Self {
+        Self::Text(s.into())
+    }
+}
+
+impl From<String> for Body {
+    fn from(s: String) -> Self {
+        Self::Text(s)
+    }
+}
+
+impl From<BTreeMap<String, Body>> for Body {
+    fn from(m: BTreeMap<String, Body>) -> Self {
+        Self::Map(m)
+    }
+}
+
+impl From<Vec<u8>> for Body {
+    fn from(b: Vec<u8>) -> Self {
+        Self::Bytes(b)
+    }
 }
 
 /// A log item.
@@ -80,16 +193,22 @@ pub struct Item {
     pub severity: Severity,
 
     /// A log message.
-    pub body: String,
+    pub body: Body,
 
     pub attributes: BTreeMap<String, String>,
     pub resource: BTreeMap<String, String>,
     pub trace_id: Option<String>,
     pub span_id: Option<String>,
+
+    /// The origin of the item(e.g. `module_path!()`) for target filters.
+    pub target: Option<String>,
 }
 
 impl Item {
-    pub fn new(body: &str, attr: BTreeMap<String, String>) -> Self {
+    pub fn new<B>(body: B, attr: BTreeMap<String, String>) -> Self
+    where
+        B: Into<Body>,
+    {
         Self {
             timestamp: SystemTime::now(),
             severity: Severity::Trace,
@@ -98,6 +217,7 @@ impl Item {
             resource: BTreeMap::new(),
             trace_id: None,
             span_id: None,
+            target: None,
         }
     }
 
@@ -111,6 +231,15 @@ impl Item {
             resource,
             trace_id: self.trace_id,
             span_id: self.span_id,
+            target: self.target,
+        }
+    }
+
+    /// Sets the origin of the item(e.g. `module_path!()`).
+    pub fn with_target(self, target: &str) -> Self {
+        Self {
+            target: Some(target.into()),
+            ..self
         }
     }
 }
@@ -1,12 +1,45 @@
 //! A log item serializer.
 
-use crate::Item;
+use crate::{Item, Severity};
+
+pub mod apache;
+pub mod bunyan;
+pub mod cbor;
+pub mod cloud_logging;
+pub mod compact;
+pub mod csv;
+pub mod ecs;
+pub mod escape;
+pub mod fluent;
+pub mod gelf;
+pub mod journald;
+pub mod layout;
+pub mod logfmt;
+pub mod logstash;
+pub mod msgpack;
+pub mod otlp;
+pub mod pattern;
+pub mod pretty;
+pub mod syslog;
+pub mod time;
+
+pub(crate) mod base64;
+pub(crate) mod json;
+pub(crate) mod protobuf;
+pub(crate) mod sha256;
+pub(crate) mod template;
 
 /// Serialize writes a log item into a string.
 pub trait Serialize: Sync + Send {
     fn serialize(&self, item: &Item, buf: &mut String);
 }
 
+impl Serialize for Box<dyn Serialize> {
+    fn serialize(&self, item: &Item, buf: &mut String) {
+        self.as_ref().serialize(item, buf)
+    }
+}
+
 struct FnSer<S> {
     internal: S,
 }
@@ -27,3 +60,72 @@ where
 {
     FnSer { internal }
 }
+
+struct SeverityRouter<L, U> {
+    lb_inclusive: Severity,
+    lower: L,
+    upper: U,
+}
+
+impl<L, U> Serialize for SeverityRouter<L, U>
+where
+    L: Serialize,
+    U: Serialize,
+{
+    fn serialize(&self, item: &Item, buf: &mut String) {
+        match self.lb_inclusive <= item.severity {
+            true => self.upper.serialize(item, buf),
+            false => self.lower.serialize(item, buf),
+        }
+    }
+}
+
+/// Creates a serializer which dispatches to a serializer by the severity of a log item.
+///
+/// Nest routers to use more than two serializers.
+///
+/// # Arguments
+/// - lb_inclusive: The lower bound(inclusive) of severities serialized by `upper`.
+/// - lower: Serializes items below the bound.
+/// - upper: Serializes items at or above the bound.
+pub fn serializer_route_by_severity<L, U>(
+    lb_inclusive: Severity,
+    lower: L,
+    upper: U,
+) -> impl Serialize
+where
+    L: Serialize,
+    U: Serialize,
+{
+    SeverityRouter {
+        lb_inclusive,
+        lower,
+        upper,
+    }
+}
+
+/// SerializeBytes writes a log item into bytes(e.g. binary formats).
+pub trait SerializeBytes: Sync + Send {
+    fn serialize(&self, item: &Item, buf: &mut Vec<u8>);
+}
+
+struct FnSerBytes<S> {
+    internal: S,
+}
+
+impl<S> SerializeBytes for FnSerBytes<S>
+where
+    S: Fn(&Item, &mut Vec<u8>) + Sync + Send,
+{
+    fn serialize(&self, item: &Item, buf: &mut Vec<u8>) {
+        (self.internal)(item, buf)
+    }
+}
+
+/// Creates a bytes serializer which uses a closure to serialize a log item.
+pub fn bytes_serializer_new_from_fn<S>(internal: S) -> impl SerializeBytes
+where
+    S: Fn(&Item, &mut Vec<u8>) + Sync + Send,
+{
+    FnSerBytes { internal }
+}
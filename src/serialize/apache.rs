@@ -0,0 +1,89 @@
+//! An Apache combined log format serializer for access logs.
+
+use crate::serialize::{
+    time::{TimeZoneMode, TimestampFormatter},
+    Serialize,
+};
+use crate::Item;
+
+/// Writes a quoted string(quotes, backslashes and control characters are escaped).
+fn write_quoted(s: &str, buf: &mut String) {
+    buf.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => buf.push_str("\\\""),
+            '\\' => buf.push_str("\\\\"),
+            c if c.is_control() && (c as u32) <= 0xff => {
+                buf.push_str(&format!("\\x{:02x}", c as u32))
+            }
+            c => buf.push(c),
+        }
+    }
+    buf.push('"');
+}
+
+/// Writes a bare field(`-` if missing; spaces and quotes are replaced).
+fn write_field(s: Option<&str>, buf: &mut String) {
+    match s.filter(|s: &&str| !s.is_empty()) {
+        None => buf.push('-'),
+        Some(s) => buf.extend(s.chars().map(|c: char| match c {
+            ' ' | '"' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })),
+    }
+}
+
+struct Combined {
+    timestamp: TimestampFormatter,
+}
+
+impl Serialize for Combined {
+    fn serialize(&self, item: &Item, buf: &mut String) {
+        let attr = |key: &str| item.attributes.get(key).map(|s| s.as_str());
+        write_field(attr("client.ip"), buf);
+        buf.push_str(" - ");
+        write_field(attr("user.name"), buf);
+        buf.push_str(" [");
+        self.timestamp.format(item.timestamp, buf);
+        buf.push_str("] ");
+        let request: String = format!(
+            "{} {} {}",
+            attr("http.method").unwrap_or("-"),
+            attr("http.path").unwrap_or("-"),
+            attr("http.protocol").unwrap_or("HTTP/1.1"),
+        );
+        write_quoted(&request, buf);
+        buf.push(' ');
+        write_field(attr("http.status"), buf);
+        buf.push(' ');
+        write_field(attr("http.response_size"), buf);
+        buf.push(' ');
+        write_quoted(attr("http.referer").unwrap_or("-"), buf);
+        buf.push(' ');
+        write_quoted(attr("user_agent").unwrap_or("-"), buf);
+    }
+}
+
+/// Creates a serializer which writes a log item as an Apache combined log line.
+///
+/// | Field      | Attribute                               |
+/// |:----------:|:---------------------------------------:|
+/// | %h         | `client.ip`                             |
+/// | %u         | `user.name`                             |
+/// | %r         | `http.method` `http.path` `http.protocol`(default `HTTP/1.1`) |
+/// | %>s        | `http.status`                           |
+/// | %b         | `http.response_size`                    |
+/// | Referer    | `http.referer`                          |
+/// | User-agent | `user_agent`                            |
+///
+/// Missing fields are written as `-`.
+///
+/// # Arguments
+/// - zone: The time zone used to render `[%d/%b/%Y:%H:%M:%S %z]`.
+pub fn apache_combined_serializer_new(zone: TimeZoneMode) -> impl Serialize {
+    let timestamp = TimestampFormatter::strftime("%d/%b/%Y:%H:%M:%S %z")
+        .unwrap_or_default()
+        .with_time_zone(zone);
+    Combined { timestamp }
+}
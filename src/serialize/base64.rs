@@ -0,0 +1,44 @@
+//! Base64(RFC 4648, standard alphabet with padding) encoding.
+
+const _ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Writes bytes encoded as base64.
+pub(crate) fn write(bytes: &[u8], buf: &mut String) {
+    for chunk in bytes.chunks(3) {
+        let b: [u8; 3] = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n: u32 = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => buf.push(char::from(_ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize])),
+                false => buf.push('='),
+            }
+        }
+    }
+}
+
+/// Decodes base64(padding optional); None if invalid.
+#[cfg(feature = "encrypt")]
+pub(crate) fn decode(s: &str) -> Option<Vec<u8>> {
+    let s: &str = s.trim_end_matches('=');
+    let mut out: Vec<u8> = Vec::with_capacity(s.len() * 3 / 4);
+    let mut n: u32 = 0;
+    for (i, c) in s.bytes().enumerate() {
+        let v: u8 = _ALPHABET.iter().position(|a| *a == c)? as u8;
+        n = (n << 6) | u32::from(v);
+        if i % 4 == 3 {
+            out.extend_from_slice(&[(n >> 16) as u8, (n >> 8) as u8, n as u8]);
+            n = 0;
+        }
+    }
+    match s.len() % 4 {
+        0 => {}
+        2 => out.push((n >> 4) as u8),
+        3 => out.extend_from_slice(&[(n >> 10) as u8, (n >> 2) as u8]),
+        _ => return None,
+    }
+    Some(out)
+}
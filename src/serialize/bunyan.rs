@@ -0,0 +1,86 @@
+//! A Bunyan record serializer.
+
+use std::collections::BTreeMap;
+
+use crate::serialize::{json::ObjectWriter, time, Serialize};
+use crate::{Item, Severity};
+
+/// Gets the Bunyan level number for a severity.
+pub fn level_number(s: Severity) -> u8 {
+    match s {
+        Severity::Trace => 10,
+        Severity::Debug => 20,
+        Severity::Info => 30,
+        Severity::Warn => 40,
+        Severity::Error => 50,
+        Severity::Fatal => 60,
+    }
+}
+
+static _RESERVED_KEYS: &[&str] = &[
+    "v", "level", "name", "hostname", "pid", "time", "msg", "src", "trace_id", "span_id",
+];
+
+fn non_empty<'a>(m: &'a BTreeMap<String, String>, key: &str) -> Option<&'a str> {
+    m.get(key)
+        .map(|s| s.as_str())
+        .filter(|s: &&str| !s.is_empty())
+}
+
+struct Bunyan {
+    name: String,
+    hostname: String,
+    pid: u32,
+}
+
+impl Serialize for Bunyan {
+    fn serialize(&self, item: &Item, buf: &mut String) {
+        let name: &str = non_empty(&item.resource, "service.name").unwrap_or(&self.name);
+        let hostname: &str = non_empty(&item.resource, "host.name").unwrap_or(&self.hostname);
+        let mut ts: String = String::new();
+        time::write_rfc3339(item.timestamp, 3, &mut ts);
+
+        let mut obj = ObjectWriter::begin(buf);
+        obj.raw("v", "0");
+        obj.raw("level", &level_number(item.severity).to_string());
+        obj.str("name", name);
+        obj.str("hostname", hostname);
+        obj.raw("pid", &self.pid.to_string());
+        obj.str("time", &ts);
+        obj.str("msg", &item.body.to_text());
+        if let Some(trace_id) = &item.trace_id {
+            obj.str("trace_id", trace_id);
+        }
+        if let Some(span_id) = &item.span_id {
+            obj.str("span_id", span_id);
+        }
+        let fields: BTreeMap<&str, &str> = item
+            .resource
+            .iter()
+            .chain(item.attributes.iter())
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .filter(|(key, _)| !_RESERVED_KEYS.contains(key))
+            .collect();
+        for (key, val) in fields {
+            obj.str(key, val);
+        }
+        obj.end()
+    }
+}
+
+/// Creates a serializer which writes a log item as a Bunyan record.
+///
+/// `name` and `hostname` are taken from the `service.name` and `host.name`
+/// resources if available. Other resource fields and attributes are written
+/// as extra fields.
+///
+/// # Arguments
+/// - name: The logger name used when the `service.name` resource is missing.
+/// - hostname: The host name used when the `host.name` resource is missing.
+pub fn bunyan_serializer_new(name: &str, hostname: &str) -> impl Serialize {
+    Bunyan {
+        name: name.into(),
+        hostname: hostname.into(),
+        pid: std::process::id(),
+    }
+}
@@ -0,0 +1,104 @@
+//! A CBOR(RFC 8949) serializer.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::serialize::{time, SerializeBytes};
+use crate::{Body, Item};
+
+fn write_head(major: u8, arg: u64, buf: &mut Vec<u8>) {
+    let m: u8 = major << 5;
+    match arg {
+        0..=23 => buf.push(m | arg as u8),
+        24..=0xff => {
+            buf.push(m | 24);
+            buf.push(arg as u8)
+        }
+        0x100..=0xffff => {
+            buf.push(m | 25);
+            buf.extend_from_slice(&(arg as u16).to_be_bytes())
+        }
+        0x1_0000..=0xffff_ffff => {
+            buf.push(m | 26);
+            buf.extend_from_slice(&(arg as u32).to_be_bytes())
+        }
+        _ => {
+            buf.push(m | 27);
+            buf.extend_from_slice(&arg.to_be_bytes())
+        }
+    }
+}
+
+fn write_str(s: &str, buf: &mut Vec<u8>) {
+    write_head(3, s.len() as u64, buf);
+    buf.extend_from_slice(s.as_bytes())
+}
+
+fn write_body(body: &Body, buf: &mut Vec<u8>) {
+    match body {
+        Body::Text(s) => write_str(s, buf),
+        Body::Map(m) => {
+            write_head(5, m.len() as u64, buf);
+            for (key, val) in m {
+                write_str(key, buf);
+                write_body(val, buf);
+            }
+        }
+        Body::Bytes(b) => {
+            write_head(2, b.len() as u64, buf);
+            buf.extend_from_slice(b)
+        }
+    }
+}
+
+fn write_str_map(m: &BTreeMap<String, String>, buf: &mut Vec<u8>) {
+    write_head(5, m.len() as u64, buf);
+    for (key, val) in m {
+        write_str(key, buf);
+        write_str(val, buf);
+    }
+}
+
+fn write_opt_str(o: &Option<String>, buf: &mut Vec<u8>) {
+    match o {
+        None => buf.push(0xf6),
+        Some(s) => write_str(s, buf),
+    }
+}
+
+/// Writes a timestamp as an epoch-based date/time(tag 1, float64 seconds).
+fn write_timestamp(d: Duration, buf: &mut Vec<u8>) {
+    write_head(6, 1, buf);
+    buf.push(0xfb);
+    buf.extend_from_slice(&d.as_secs_f64().to_be_bytes())
+}
+
+struct Cbor {}
+
+impl SerializeBytes for Cbor {
+    fn serialize(&self, item: &Item, buf: &mut Vec<u8>) {
+        write_head(5, 7, buf);
+        write_str("timestamp", buf);
+        write_timestamp(time::since_epoch(item.timestamp), buf);
+        write_str("severity", buf);
+        write_str(item.severity.as_str(), buf);
+        write_str("body", buf);
+        write_body(&item.body, buf);
+        write_str("attributes", buf);
+        write_str_map(&item.attributes, buf);
+        write_str("resource", buf);
+        write_str_map(&item.resource, buf);
+        write_str("trace_id", buf);
+        write_opt_str(&item.trace_id, buf);
+        write_str("span_id", buf);
+        write_opt_str(&item.span_id, buf);
+    }
+}
+
+/// Creates a serializer which writes a log item as a CBOR map.
+///
+/// The map has the same keys as the MessagePack serializer; the timestamp is
+/// written as an epoch-based date/time(tag 1, float64 seconds).
+pub fn cbor_serializer_new() -> impl SerializeBytes {
+    Cbor {}
+}
@@ -0,0 +1,74 @@
+//! A Google Cloud Logging structured JSON serializer.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::serialize::{json, json::ObjectWriter, time, Serialize};
+use crate::{Item, Severity};
+
+/// Gets the Cloud Logging `LogSeverity` name for a severity.
+pub fn severity_name(s: Severity) -> &'static str {
+    match s {
+        Severity::Trace => "DEBUG",
+        Severity::Debug => "DEBUG",
+        Severity::Info => "INFO",
+        Severity::Warn => "WARNING",
+        Severity::Error => "ERROR",
+        Severity::Fatal => "CRITICAL",
+    }
+}
+
+struct CloudLogging {
+    project_id: String,
+}
+
+impl Serialize for CloudLogging {
+    fn serialize(&self, item: &Item, buf: &mut String) {
+        let d: Duration = time::since_epoch(item.timestamp);
+        let mut obj = ObjectWriter::begin(buf);
+        obj.str("severity", severity_name(item.severity));
+        json::write_body(&item.body, obj.key("message"));
+        obj.raw(
+            "timestamp",
+            &format!(
+                r#"{{"seconds":{},"nanos":{}}}"#,
+                d.as_secs(),
+                d.subsec_nanos()
+            ),
+        );
+        if let Some(trace_id) = &item.trace_id {
+            let trace: String = format!("projects/{}/traces/{trace_id}", self.project_id);
+            obj.str("logging.googleapis.com/trace", &trace);
+        }
+        if let Some(span_id) = &item.span_id {
+            obj.str("logging.googleapis.com/spanId", span_id);
+        }
+        let labels: BTreeMap<&str, &str> = item
+            .resource
+            .iter()
+            .chain(item.attributes.iter())
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        if !labels.is_empty() {
+            let mut lobj = ObjectWriter::begin(obj.key("logging.googleapis.com/labels"));
+            for (key, val) in labels {
+                lobj.str(key, val);
+            }
+            lobj.end();
+        }
+        obj.end()
+    }
+}
+
+/// Creates a serializer which writes a log item as a Cloud Logging structured JSON.
+///
+/// Resource fields and attributes are written as labels(attributes win on
+/// conflicts). A structured body is written as a nested `message` object.
+///
+/// # Arguments
+/// - project_id: The project id used to build the trace resource name.
+pub fn cloud_logging_serializer_new(project_id: &str) -> impl Serialize {
+    CloudLogging {
+        project_id: project_id.into(),
+    }
+}
@@ -0,0 +1,102 @@
+//! A compact single-line serializer for dense terminal output.
+
+use crate::serialize::{
+    layout::{Field, Layout},
+    time::TimestampFormatter,
+    Serialize,
+};
+use crate::Item;
+
+fn write_pair(key: &str, val: &str, buf: &mut String) {
+    buf.push_str(key);
+    buf.push('=');
+    buf.push_str(val);
+}
+
+fn write_pairs<'a, I>(pairs: I, buf: &mut String)
+where
+    I: Iterator<Item = (&'a String, &'a String)>,
+{
+    for (i, (key, val)) in pairs.enumerate() {
+        if 0 < i {
+            buf.push(' ');
+        }
+        write_pair(key, val, buf);
+    }
+}
+
+struct Compact {
+    layout: Layout,
+}
+
+impl Serialize for Compact {
+    fn serialize(&self, item: &Item, buf: &mut String) {
+        for (i, field) in self.layout.fields().iter().enumerate() {
+            let start: usize = buf.len();
+            if 0 < i {
+                buf.push(' ');
+            }
+            let before: usize = buf.len();
+            match field {
+                Field::Timestamp => self.layout.timestamp().format(item.timestamp, buf),
+                Field::Severity => {
+                    buf.push_str(&format!("{:<5}", item.severity.as_str().to_uppercase()))
+                }
+                Field::Body => self.layout.multiline().write(&item.body.to_text(), buf),
+                Field::Attributes => write_pairs(item.attributes.iter(), buf),
+                Field::Resource => write_pairs(item.resource.iter(), buf),
+                Field::TraceId => {
+                    if let Some(trace_id) = &item.trace_id {
+                        write_pair("trace_id", trace_id, buf);
+                    }
+                }
+                Field::SpanId => {
+                    if let Some(span_id) = &item.span_id {
+                        write_pair("span_id", span_id, buf);
+                    }
+                }
+            }
+            if buf.len() == before {
+                buf.truncate(start);
+            }
+        }
+    }
+}
+
+fn default_layout(with_resource: bool, timestamp: TimestampFormatter) -> Layout {
+    let builder = Layout::builder()
+        .fields(&[
+            Field::Timestamp,
+            Field::Severity,
+            Field::Body,
+            Field::Attributes,
+        ])
+        .timestamp(timestamp);
+    let builder = match with_resource {
+        true => builder.field(Field::Resource),
+        false => builder,
+    };
+    builder.fields(&[Field::TraceId, Field::SpanId]).build()
+}
+
+/// Creates a serializer which writes a log item as `HH:MM:SS LEVEL body key=val ...`.
+///
+/// # Arguments
+/// - with_resource: Writes resource fields if true(they rarely change in a process).
+pub fn compact_serializer_new(with_resource: bool) -> impl Serialize {
+    let timestamp = TimestampFormatter::strftime("%H:%M:%S").unwrap_or_default();
+    compact_serializer_new_with_timestamp(with_resource, timestamp)
+}
+
+/// Creates a compact serializer which uses the timestamp formatter.
+pub fn compact_serializer_new_with_timestamp(
+    with_resource: bool,
+    timestamp: TimestampFormatter,
+) -> impl Serialize {
+    compact_serializer_new_with_layout(default_layout(with_resource, timestamp))
+}
+
+/// Creates a compact serializer which emits fields in the order of the layout.
+pub fn compact_serializer_new_with_layout(layout: Layout) -> impl Serialize {
+    Compact { layout }
+}
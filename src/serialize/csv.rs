@@ -0,0 +1,257 @@
+//! A CSV/TSV serializer with configurable columns.
+
+use crate::serialize::{layout::MultilinePolicy, time::TimestampFormatter, Serialize};
+use crate::Item;
+
+/// A column of a CSV/TSV line.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Column {
+    Timestamp,
+    Severity,
+    Body,
+    /// An attribute value(empty if missing).
+    Attribute(String),
+    /// A resource value(empty if missing).
+    Resource(String),
+    TraceId,
+    SpanId,
+}
+
+fn write_cell(val: &str, delimiter: char, buf: &mut String) {
+    let quote: bool = val
+        .chars()
+        .any(|c: char| c == delimiter || c == '"' || c == '\r' || c == '\n');
+    match quote {
+        false => buf.push_str(val),
+        true => {
+            buf.push('"');
+            buf.push_str(&val.replace('"', "\"\""));
+            buf.push('"');
+        }
+    }
+}
+
+struct Delimited {
+    columns: Vec<Column>,
+    delimiter: char,
+    timestamp: TimestampFormatter,
+    /// Writes the body(None: kept as is and quoted).
+    multiline: Option<MultilinePolicy>,
+}
+
+impl Serialize for Delimited {
+    fn serialize(&self, item: &Item, buf: &mut String) {
+        let mut ts: String = String::new();
+        let text = item.body.to_text();
+        let mut body: String = String::new();
+        let body: &str = match &self.multiline {
+            None => &text,
+            Some(policy) => {
+                policy.write(&text, &mut body);
+                &body
+            }
+        };
+        for (i, col) in self.columns.iter().enumerate() {
+            if 0 < i {
+                buf.push(self.delimiter);
+            }
+            let val: &str = match col {
+                Column::Timestamp => {
+                    ts.clear();
+                    self.timestamp.format(item.timestamp, &mut ts);
+                    &ts
+                }
+                Column::Severity => item.severity.as_str(),
+                Column::Body => body,
+                Column::Attribute(key) => {
+                    item.attributes.get(key).map(|s| s.as_str()).unwrap_or("")
+                }
+                Column::Resource(key) => item.resource.get(key).map(|s| s.as_str()).unwrap_or(""),
+                Column::TraceId => item.trace_id.as_deref().unwrap_or(""),
+                Column::SpanId => item.span_id.as_deref().unwrap_or(""),
+            };
+            write_cell(val, self.delimiter, buf);
+        }
+    }
+}
+
+/// Creates a serializer which writes a log item as a delimited line(RFC 4180 quoting).
+///
+/// Cells with delimiters, quotes or line breaks(including a multi-line body)
+/// are quoted, so a line is one record of a standard CSV reader.
+///
+/// # Arguments
+/// - columns: The ordered columns to write.
+/// - delimiter: The cell delimiter(e.g. `,` or `\t`).
+pub fn delimited_serializer_new(columns: Vec<Column>, delimiter: char) -> impl Serialize {
+    delimited_serializer_new_with_timestamp(columns, delimiter, TimestampFormatter::default())
+}
+
+/// Creates a delimited serializer which uses the timestamp formatter for [`Column::Timestamp`].
+///
+/// # Arguments
+/// - columns: The ordered columns to write.
+/// - delimiter: The cell delimiter(e.g. `,` or `\t`).
+/// - timestamp: Formats the timestamp column.
+pub fn delimited_serializer_new_with_timestamp(
+    columns: Vec<Column>,
+    delimiter: char,
+    timestamp: TimestampFormatter,
+) -> impl Serialize {
+    Delimited {
+        columns,
+        delimiter,
+        timestamp,
+        multiline: None,
+    }
+}
+
+/// Creates a delimited serializer which writes the body by the multi-line policy.
+///
+/// Keeps each record on one physical line(e.g. for line-oriented tools) at the
+/// cost of RFC 4180 compatibility: [`MultilinePolicy::Escape`] also doubles
+/// backslashes. A body cell with line breaks left by the policy
+/// ([`MultilinePolicy::Indent`]) is quoted.
+///
+/// # Arguments
+/// - columns: The ordered columns to write.
+/// - delimiter: The cell delimiter(e.g. `,` or `\t`).
+/// - timestamp: Formats the timestamp column.
+/// - multiline: Writes the body column.
+pub fn delimited_serializer_new_with_multiline(
+    columns: Vec<Column>,
+    delimiter: char,
+    timestamp: TimestampFormatter,
+    multiline: MultilinePolicy,
+) -> impl Serialize {
+    Delimited {
+        columns,
+        delimiter,
+        timestamp,
+        multiline: Some(multiline),
+    }
+}
+
+/// Creates a serializer which writes a log item as a CSV line.
+pub fn csv_serializer_new(columns: Vec<Column>) -> impl Serialize {
+    delimited_serializer_new(columns, ',')
+}
+
+/// Creates a serializer which writes a log item as a TSV line.
+pub fn tsv_serializer_new(columns: Vec<Column>) -> impl Serialize {
+    delimited_serializer_new(columns, '\t')
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{csv_serializer_new, delimited_serializer_new_with_multiline, Column};
+    use crate::serialize::{layout::MultilinePolicy, time::TimestampFormatter, Serialize};
+    use crate::Item;
+
+    fn line<S>(serializer: S, body: &str) -> String
+    where
+        S: Serialize,
+    {
+        let mut buf: String = String::new();
+        serializer.serialize(&Item::new(body, BTreeMap::new()), &mut buf);
+        buf
+    }
+
+    /// Splits a line into RFC 4180 cells.
+    fn cells(line: &str, delimiter: char) -> Vec<String> {
+        let mut out: Vec<String> = vec![String::new()];
+        let mut quoted: bool = false;
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (quoted, c) {
+                (true, '"') if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    out.last_mut().unwrap().push('"');
+                }
+                (_, '"') => quoted = !quoted,
+                (false, c) if c == delimiter => out.push(String::new()),
+                (_, c) => out.last_mut().unwrap().push(c),
+            }
+        }
+        out
+    }
+
+    /// Reverses [`MultilinePolicy::Escape`].
+    fn unescape(s: &str) -> String {
+        s.replace("\\\\", "\u{0}")
+            .replace("\\n", "\n")
+            .replace("\\r", "\r")
+            .replace('\u{0}', "\\")
+    }
+
+    /// Splits lines into RFC 4180 records; quoted cells may hold line breaks.
+    fn records(text: &str, delimiter: char) -> Vec<Vec<String>> {
+        let mut out: Vec<Vec<String>> = vec![];
+        let mut record: String = String::new();
+        for l in text.split_inclusive('\n') {
+            record.push_str(l);
+            if record.matches('"').count().is_multiple_of(2) {
+                let r: &str = record.strip_suffix('\n').unwrap_or(&record);
+                out.push(cells(r.strip_suffix('\r').unwrap_or(r), delimiter));
+                record.clear();
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn default_is_rfc4180() {
+        let bodies = ["plain", "C:\\new", "a\nb", "x,\"y\"\r\nz", "\\n"];
+        let mut text: String = String::new();
+        for body in bodies {
+            text.push_str(&line(
+                csv_serializer_new(vec![Column::Severity, Column::Body]),
+                body,
+            ));
+            text.push('\n');
+        }
+        assert!(text.starts_with("trace,plain\ntrace,C:\\new\ntrace,\"a\nb\"\n"));
+        let got: Vec<Vec<String>> = records(&text, ',');
+        let expected: Vec<Vec<String>> = bodies
+            .iter()
+            .map(|b| vec!["trace".to_string(), b.to_string()])
+            .collect();
+        assert_eq!(expected, got);
+    }
+
+    #[test]
+    fn escaped_body_round_trips() {
+        for body in ["a\nb", "C:\\new\r\nline", "x,\"y\"\n\\n"] {
+            let l: String = line(
+                delimited_serializer_new_with_multiline(
+                    vec![Column::Severity, Column::Body],
+                    ',',
+                    TimestampFormatter::default(),
+                    MultilinePolicy::Escape,
+                ),
+                body,
+            );
+            assert!(!l.contains(['\n', '\r']), "{l:?}");
+            let c: Vec<String> = cells(&l, ',');
+            assert_eq!(2, c.len());
+            assert_eq!("trace", c[0]);
+            assert_eq!(body, unescape(&c[1]));
+        }
+    }
+
+    #[test]
+    fn space_and_indent() {
+        let new = |policy: MultilinePolicy| {
+            delimited_serializer_new_with_multiline(
+                vec![Column::Body],
+                '\t',
+                TimestampFormatter::default(),
+                policy,
+            )
+        };
+        assert_eq!("a b", line(new(MultilinePolicy::Space), "a\nb"));
+        assert_eq!("\"a\n    b\"", line(new(MultilinePolicy::Indent), "a\nb"));
+    }
+}
@@ -0,0 +1,61 @@
+//! An Elastic Common Schema(ECS) serializer.
+
+use crate::serialize::{json::ObjectWriter, time, Serialize};
+use crate::Item;
+
+/// The ECS version written to `ecs.version`.
+pub const ECS_VERSION: &str = "8.11.0";
+
+static _RESERVED_KEYS: &[&str] = &[
+    "@timestamp",
+    "log.level",
+    "message",
+    "ecs.version",
+    "trace.id",
+    "span.id",
+    "labels",
+];
+
+struct Ecs {}
+
+impl Serialize for Ecs {
+    fn serialize(&self, item: &Item, buf: &mut String) {
+        let mut ts: String = String::new();
+        time::write_rfc3339(item.timestamp, 3, &mut ts);
+        let mut obj = ObjectWriter::begin(buf);
+        obj.str("@timestamp", &ts);
+        obj.str("log.level", item.severity.as_str());
+        obj.str("message", &item.body.to_text());
+        obj.str("ecs.version", ECS_VERSION);
+        if let Some(trace_id) = &item.trace_id {
+            obj.str("trace.id", trace_id);
+        }
+        if let Some(span_id) = &item.span_id {
+            obj.str("span.id", span_id);
+        }
+        let resource = item
+            .resource
+            .iter()
+            .filter(|(key, _)| !_RESERVED_KEYS.contains(&key.as_str()));
+        for (key, val) in resource {
+            obj.str(key, val);
+        }
+        if !item.attributes.is_empty() {
+            let mut labels = ObjectWriter::begin(obj.key("labels"));
+            for (key, val) in &item.attributes {
+                // Label keys can not contain dots.
+                labels.str(&key.replace('.', "_"), val);
+            }
+            labels.end();
+        }
+        obj.end()
+    }
+}
+
+/// Creates a serializer which writes a log item as an ECS JSON document.
+///
+/// Resource fields are written as dotted top-level fields(e.g. `service.name`,
+/// `host.name`) and attributes are written under `labels`.
+pub fn ecs_serializer_new() -> impl Serialize {
+    Ecs {}
+}
@@ -0,0 +1,42 @@
+//! Escaping helpers for serializer authors.
+
+/// Writes a string escaped as the contents of a JSON string(without quotes).
+pub fn json(s: &str, buf: &mut String) {
+    for c in s.chars() {
+        match c {
+            '"' => buf.push_str("\\\""),
+            '\\' => buf.push_str("\\\\"),
+            '\n' => buf.push_str("\\n"),
+            '\r' => buf.push_str("\\r"),
+            '\t' => buf.push_str("\\t"),
+            c if (c as u32) < 0x20 => buf.push_str(&format!("\\u{:04x}", c as u32)),
+            c => buf.push(c),
+        }
+    }
+}
+
+/// Writes a string escaped as a LTSV label or value(tabs and line breaks are escaped).
+pub fn ltsv(s: &str, buf: &mut String) {
+    for c in s.chars() {
+        match c {
+            '\t' => buf.push_str("\\t"),
+            '\n' => buf.push_str("\\n"),
+            '\r' => buf.push_str("\\r"),
+            c => buf.push(c),
+        }
+    }
+}
+
+/// Writes a string replacing control characters(e.g. ANSI escape sequences)
+/// with `\xNN`/`\u{NNNN}` escapes.
+pub fn control(s: &str, buf: &mut String) {
+    for c in s.chars() {
+        match c {
+            c if c.is_control() && (c as u32) <= 0xff => {
+                buf.push_str(&format!("\\x{:02x}", c as u32))
+            }
+            c if c.is_control() => buf.push_str(&format!("\\u{{{:04x}}}", c as u32)),
+            c => buf.push(c),
+        }
+    }
+}
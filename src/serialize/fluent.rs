@@ -0,0 +1,61 @@
+//! A Fluentd Forward protocol(message mode) serializer.
+
+use std::time::Duration;
+
+use crate::serialize::msgpack::{write_body, write_map_len, write_str, write_str_map};
+use crate::serialize::template::Template;
+use crate::serialize::{time, SerializeBytes};
+use crate::Item;
+
+/// Writes an EventTime(extension type 0: seconds and nanoseconds).
+fn write_event_time(d: Duration, buf: &mut Vec<u8>) {
+    buf.push(0xd7);
+    buf.push(0x00);
+    buf.extend_from_slice(&(d.as_secs() as u32).to_be_bytes());
+    buf.extend_from_slice(&d.subsec_nanos().to_be_bytes());
+}
+
+struct Fluent {
+    tag: Template,
+}
+
+impl SerializeBytes for Fluent {
+    fn serialize(&self, item: &Item, buf: &mut Vec<u8>) {
+        buf.push(0x93);
+        write_str(&self.tag.render(&item.resource), buf);
+        write_event_time(time::since_epoch(item.timestamp), buf);
+
+        let ids = [("trace_id", &item.trace_id), ("span_id", &item.span_id)];
+        let len: usize = 4 + ids.iter().filter(|(_, id)| id.is_some()).count();
+        write_map_len(len, buf);
+        write_str("message", buf);
+        write_body(&item.body, buf);
+        write_str("severity", buf);
+        write_str(item.severity.as_str(), buf);
+        write_str("attributes", buf);
+        write_str_map(&item.attributes, buf);
+        write_str("resource", buf);
+        write_str_map(&item.resource, buf);
+        for (key, id) in ids {
+            if let Some(id) = id {
+                write_str(key, buf);
+                write_str(id, buf);
+            }
+        }
+    }
+}
+
+/// Creates a serializer which writes a log item as a Forward protocol message.
+///
+/// The message is `[tag, time, record]`; the time is an EventTime and the
+/// record is a map with `message`, `severity`, `attributes`, `resource` and
+/// the `trace_id`/`span_id` if any.
+///
+/// # Arguments
+/// - tag: The tag template; `{key}` is replaced with the resource value
+///   (`unknown` if missing), e.g. `"app.{service.name}"`.
+pub fn fluent_serializer_new(tag: &str) -> impl SerializeBytes {
+    Fluent {
+        tag: Template::parse(tag),
+    }
+}
@@ -0,0 +1,140 @@
+//! A GELF(Graylog Extended Log Format) 1.1 serializer.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::serialize::{json::ObjectWriter, time, Serialize};
+use crate::Item;
+
+fn write_field_name(key: &str, buf: &mut String) {
+    buf.push('_');
+    for c in key.chars() {
+        match c {
+            c if c.is_ascii_alphanumeric() => buf.push(c),
+            '_' | '.' | '-' => buf.push(c),
+            _ => buf.push('_'),
+        }
+    }
+    // "_id" is reserved by the spec.
+    if buf == "_id" {
+        buf.insert(0, '_');
+    }
+}
+
+/// Adds an additional field; replaces a field of the same(sanitized) name.
+fn additional_field<'a>(fields: &mut BTreeMap<String, &'a str>, key: &str, val: &'a str) {
+    let mut name: String = String::new();
+    write_field_name(key, &mut name);
+    fields.insert(name, val);
+}
+
+struct Gelf {
+    host: String,
+}
+
+impl Serialize for Gelf {
+    fn serialize(&self, item: &Item, buf: &mut String) {
+        let host: &str = item
+            .resource
+            .get("host.name")
+            .map(|s| s.as_str())
+            .filter(|s: &&str| !s.is_empty())
+            .unwrap_or(&self.host);
+        let d: Duration = time::since_epoch(item.timestamp);
+        let mut obj = ObjectWriter::begin(buf);
+        obj.str("version", "1.1");
+        obj.str("host", host);
+        obj.str("short_message", &item.body.to_text());
+        obj.raw(
+            "timestamp",
+            &format!("{}.{:03}", d.as_secs(), d.subsec_millis()),
+        );
+        obj.raw("level", &item.severity.as_syslog().to_string());
+        // Later sources take precedence, so each name is written once.
+        let mut fields: BTreeMap<String, &str> = BTreeMap::new();
+        for (key, val) in &item.resource {
+            additional_field(&mut fields, key, val);
+        }
+        for (key, val) in &item.attributes {
+            additional_field(&mut fields, key, val);
+        }
+        if let Some(trace_id) = &item.trace_id {
+            additional_field(&mut fields, "trace_id", trace_id);
+        }
+        if let Some(span_id) = &item.span_id {
+            additional_field(&mut fields, "span_id", span_id);
+        }
+        for (name, val) in &fields {
+            obj.str(name, val);
+        }
+        obj.end()
+    }
+}
+
+/// Creates a serializer which writes a log item as a GELF 1.1 JSON message.
+///
+/// Resource fields, attributes and trace/span ids are written as additional
+/// fields(`_`-prefixed, sorted by name). If names collide(also after invalid
+/// characters are replaced), the trace/span ids win over attributes and
+/// attributes win over resource fields.
+///
+/// # Arguments
+/// - host: The host name used when the `host.name` resource is missing.
+pub fn gelf_serializer_new(host: &str) -> impl Serialize {
+    Gelf { host: host.into() }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::time::{Duration, SystemTime};
+
+    use super::gelf_serializer_new;
+    use crate::serialize::Serialize;
+    use crate::{Item, Severity};
+
+    fn message(item: &Item) -> String {
+        let mut buf: String = String::new();
+        gelf_serializer_new("fallback").serialize(item, &mut buf);
+        buf
+    }
+
+    fn item(body: &str) -> Item {
+        let mut item: Item = Item::new(body, BTreeMap::new());
+        item.timestamp = SystemTime::UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+        item.severity = Severity::Error;
+        item
+    }
+
+    #[test]
+    fn required_fields() {
+        assert_eq!(
+            concat!(
+                r#"{"version":"1.1","host":"fallback","short_message":"hi","#,
+                r#""timestamp":1700000000.123,"level":3}"#,
+            ),
+            message(&item("hi"))
+        );
+    }
+
+    #[test]
+    fn colliding_fields_are_written_once() {
+        let mut i: Item = item("hi");
+        i.resource.insert("host.name".into(), "web".into());
+        i.resource.insert("region".into(), "eu".into());
+        i.attributes.insert("host.name".into(), "attr".into());
+        i.attributes.insert("trace_id".into(), "attr".into());
+        i.attributes.insert("a b".into(), "1".into());
+        i.attributes.insert("a_b".into(), "2".into());
+        i.attributes.insert("id".into(), "3".into());
+        i.trace_id = Some("t1".into());
+        assert_eq!(
+            concat!(
+                r#"{"version":"1.1","host":"web","short_message":"hi","#,
+                r#""timestamp":1700000000.123,"level":3,"#,
+                r#""__id":"3","_a_b":"2","_host.name":"attr","_region":"eu","_trace_id":"t1"}"#,
+            ),
+            message(&i)
+        );
+    }
+}
@@ -0,0 +1,77 @@
+//! A serializer for the systemd journal native protocol.
+
+use crate::serialize::SerializeBytes;
+use crate::Item;
+
+/// Writes a journal field name(upper case `A-Z0-9_`, not starting with `_` or a digit).
+fn write_name(name: &str, buf: &mut Vec<u8>) {
+    let start: usize = buf.len();
+    let sanitized = name
+        .trim_start_matches(|c: char| c == '_' || c.is_ascii_digit())
+        .chars()
+        .map(|c: char| match c {
+            'a'..='z' => c.to_ascii_uppercase() as u8,
+            'A'..='Z' | '0'..='9' | '_' => c as u8,
+            _ => b'_',
+        })
+        .take(64);
+    buf.extend(sanitized);
+    if buf.len() == start {
+        buf.extend_from_slice(b"FIELD");
+    }
+}
+
+/// Writes a field(`NAME=value\n`, or the length-prefixed form for multi-line values).
+fn write_field(name: &str, val: &[u8], buf: &mut Vec<u8>) {
+    write_name(name, buf);
+    match val.contains(&b'\n') {
+        false => {
+            buf.push(b'=');
+            buf.extend_from_slice(val);
+        }
+        true => {
+            buf.push(b'\n');
+            buf.extend_from_slice(&(val.len() as u64).to_le_bytes());
+            buf.extend_from_slice(val);
+        }
+    }
+    buf.push(b'\n');
+}
+
+struct Journald {
+    identifier: String,
+}
+
+impl SerializeBytes for Journald {
+    fn serialize(&self, item: &Item, buf: &mut Vec<u8>) {
+        write_field("MESSAGE", item.body.to_text().as_bytes(), buf);
+        let priority: String = item.severity.as_syslog().to_string();
+        write_field("PRIORITY", priority.as_bytes(), buf);
+        if !self.identifier.is_empty() {
+            write_field("SYSLOG_IDENTIFIER", self.identifier.as_bytes(), buf);
+        }
+        for (key, val) in item.resource.iter().chain(item.attributes.iter()) {
+            write_field(key, val.as_bytes(), buf);
+        }
+        if let Some(id) = &item.trace_id {
+            write_field("TRACE_ID", id.as_bytes(), buf);
+        }
+        if let Some(id) = &item.span_id {
+            write_field("SPAN_ID", id.as_bytes(), buf);
+        }
+    }
+}
+
+/// Creates a serializer which writes a log item as a journal entry.
+///
+/// The body becomes `MESSAGE` and the severity becomes `PRIORITY`(syslog
+/// levels). Resource fields and attributes become upper case fields(e.g.
+/// `service.name` -> `SERVICE_NAME`), followed by `TRACE_ID` and `SPAN_ID`.
+///
+/// # Arguments
+/// - identifier: The `SYSLOG_IDENTIFIER` field(omitted if empty).
+pub fn journald_serializer_new(identifier: &str) -> impl SerializeBytes {
+    Journald {
+        identifier: identifier.into(),
+    }
+}
@@ -0,0 +1,71 @@
+//! JSON writing helpers for serializers.
+
+use crate::serialize::{base64, escape};
+use crate::Body;
+
+/// Writes a quoted JSON string.
+pub(crate) fn write_str(s: &str, buf: &mut String) {
+    buf.push('"');
+    escape::json(s, buf);
+    buf.push('"');
+}
+
+/// Writes a body as a JSON value(string, object or base64 string).
+pub(crate) fn write_body(body: &Body, buf: &mut String) {
+    match body {
+        Body::Text(s) => write_str(s, buf),
+        Body::Map(m) => {
+            let mut obj = ObjectWriter::begin(buf);
+            for (key, val) in m {
+                write_body(val, obj.key(key));
+            }
+            obj.end()
+        }
+        Body::Bytes(b) => {
+            buf.push('"');
+            base64::write(b, buf);
+            buf.push('"');
+        }
+    }
+}
+
+/// Writes a JSON object member by member.
+pub(crate) struct ObjectWriter<'a> {
+    buf: &'a mut String,
+    empty: bool,
+}
+
+impl<'a> ObjectWriter<'a> {
+    /// Starts an object.
+    pub(crate) fn begin(buf: &'a mut String) -> Self {
+        buf.push('{');
+        Self { buf, empty: true }
+    }
+
+    /// Writes a key and returns the buffer to write its value.
+    pub(crate) fn key(&mut self, key: &str) -> &mut String {
+        if !self.empty {
+            self.buf.push(',');
+        }
+        self.empty = false;
+        write_str(key, self.buf);
+        self.buf.push(':');
+        self.buf
+    }
+
+    /// Writes a string member.
+    pub(crate) fn str(&mut self, key: &str, val: &str) {
+        let buf: &mut String = self.key(key);
+        write_str(val, buf)
+    }
+
+    /// Writes a member whose value is an already serialized JSON value.
+    pub(crate) fn raw(&mut self, key: &str, val: &str) {
+        self.key(key).push_str(val)
+    }
+
+    /// Finishes the object.
+    pub(crate) fn end(self) {
+        self.buf.push('}')
+    }
+}
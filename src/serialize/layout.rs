@@ -0,0 +1,181 @@
+//! Field ordering and inclusion for built-in serializers.
+
+use crate::serialize::time::TimestampFormatter;
+
+/// A log item field.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Field {
+    Timestamp,
+    Severity,
+    Body,
+    /// All attributes.
+    Attributes,
+    /// All resource fields.
+    Resource,
+    TraceId,
+    SpanId,
+}
+
+/// How line-oriented serializers write a body containing line breaks.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum MultilinePolicy {
+    /// Writes line breaks as `\n`/`\r` and backslashes as `\\`.
+    #[default]
+    Escape,
+    /// Replaces line breaks with a space.
+    Space,
+    /// Keeps line breaks and indents continuation lines(4 spaces).
+    Indent,
+}
+
+impl MultilinePolicy {
+    /// Writes a string using this policy.
+    pub fn write(&self, s: &str, buf: &mut String) {
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (self, c) {
+                (Self::Escape, '\\') => buf.push_str("\\\\"),
+                (Self::Escape, '\n') => buf.push_str("\\n"),
+                (Self::Escape, '\r') => buf.push_str("\\r"),
+                (Self::Space, '\r') => {
+                    if chars.peek() != Some(&'\n') {
+                        buf.push(' ')
+                    }
+                }
+                (Self::Space, '\n') => buf.push(' '),
+                (Self::Indent, '\r') => {}
+                (Self::Indent, '\n') => buf.push_str("\n    "),
+                (_, c) => buf.push(c),
+            }
+        }
+    }
+}
+
+/// Fields to emit(in order), how to format the timestamp and how to write a
+/// multi-line body.
+#[derive(Clone, Debug)]
+pub struct Layout {
+    fields: Vec<Field>,
+    timestamp: TimestampFormatter,
+    multiline: MultilinePolicy,
+}
+
+impl Layout {
+    /// Creates a builder without any fields.
+    pub fn builder() -> LayoutBuilder {
+        LayoutBuilder {
+            fields: vec![],
+            timestamp: TimestampFormatter::default(),
+            multiline: MultilinePolicy::default(),
+        }
+    }
+
+    /// Gets the fields to emit.
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// Gets the timestamp formatter.
+    pub fn timestamp(&self) -> &TimestampFormatter {
+        &self.timestamp
+    }
+
+    /// Gets the multi-line body policy.
+    pub fn multiline(&self) -> MultilinePolicy {
+        self.multiline
+    }
+}
+
+/// Builds a [`Layout`].
+#[derive(Clone, Debug)]
+pub struct LayoutBuilder {
+    fields: Vec<Field>,
+    timestamp: TimestampFormatter,
+    multiline: MultilinePolicy,
+}
+
+impl LayoutBuilder {
+    /// Appends a field.
+    pub fn field(mut self, f: Field) -> Self {
+        self.fields.push(f);
+        self
+    }
+
+    /// Appends fields.
+    pub fn fields(mut self, f: &[Field]) -> Self {
+        self.fields.extend_from_slice(f);
+        self
+    }
+
+    /// Sets the timestamp formatter(RFC3339 with milliseconds by default).
+    pub fn timestamp(mut self, t: TimestampFormatter) -> Self {
+        self.timestamp = t;
+        self
+    }
+
+    /// Sets the multi-line body policy([`MultilinePolicy::Escape`] by default).
+    pub fn multiline(mut self, m: MultilinePolicy) -> Self {
+        self.multiline = m;
+        self
+    }
+
+    pub fn build(self) -> Layout {
+        Layout {
+            fields: self.fields,
+            timestamp: self.timestamp,
+            multiline: self.multiline,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MultilinePolicy;
+
+    /// Reverses [`MultilinePolicy::Escape`].
+    fn unescape(s: &str) -> String {
+        let mut out: String = String::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            match (c, c == '\\') {
+                (_, true) => match chars.next() {
+                    Some('n') => out.push('\n'),
+                    Some('r') => out.push('\r'),
+                    Some(e) => out.push(e),
+                    None => out.push('\\'),
+                },
+                (c, false) => out.push(c),
+            }
+        }
+        out
+    }
+
+    fn write(policy: MultilinePolicy, s: &str) -> String {
+        let mut buf: String = String::new();
+        policy.write(s, &mut buf);
+        buf
+    }
+
+    #[test]
+    fn escape_round_trips() {
+        for body in ["a\nb", "C:\\new", "x\\ny", "\r\n\\", "\\\\n", "plain"] {
+            let escaped: String = write(MultilinePolicy::Escape, body);
+            assert!(!escaped.contains(['\n', '\r']), "{escaped:?}");
+            assert_eq!(body, unescape(&escaped));
+        }
+        assert_eq!(
+            "C:\\\\new\\nline",
+            write(MultilinePolicy::Escape, "C:\\new\nline")
+        );
+    }
+
+    #[test]
+    fn space_and_indent() {
+        assert_eq!("a b c d", write(MultilinePolicy::Space, "a\nb\r\nc\rd"));
+        assert_eq!(
+            "a\n    b\n    c",
+            write(MultilinePolicy::Indent, "a\nb\r\nc")
+        );
+        assert_eq!("C:\\new", write(MultilinePolicy::Space, "C:\\new"));
+    }
+}
@@ -0,0 +1,219 @@
+//! A logfmt serializer.
+
+use crate::serialize::{
+    layout::{Field, Layout, MultilinePolicy},
+    time::TimestampFormatter,
+    Serialize,
+};
+use crate::Item;
+
+fn write_key(key: &str, buf: &mut String) {
+    match key.is_empty() {
+        true => buf.push('_'),
+        false => {
+            for c in key.chars() {
+                match c {
+                    '=' | '"' | ' ' => buf.push('_'),
+                    c if c.is_control() => buf.push('_'),
+                    c => buf.push(c),
+                }
+            }
+        }
+    }
+}
+
+fn needs_quote(val: &str) -> bool {
+    val.is_empty()
+        || val
+            .chars()
+            .any(|c: char| c == ' ' || c == '=' || c == '"' || c == '\\' || c.is_control())
+}
+
+/// Writes a value; `indent` keeps line breaks in the quotes and indents continuation lines.
+fn write_val(val: &str, indent: bool, buf: &mut String) {
+    match needs_quote(val) {
+        false => buf.push_str(val),
+        true => {
+            buf.push('"');
+            for c in val.chars() {
+                match c {
+                    '"' => buf.push_str("\\\""),
+                    '\\' => buf.push_str("\\\\"),
+                    '\n' if indent => buf.push_str("\n    "),
+                    '\r' if indent => {}
+                    '\n' => buf.push_str("\\n"),
+                    '\r' => buf.push_str("\\r"),
+                    '\t' => buf.push_str("\\t"),
+                    c if c.is_control() => buf.push_str(&format!("\\u{:04x}", c as u32)),
+                    c => buf.push(c),
+                }
+            }
+            buf.push('"');
+        }
+    }
+}
+
+fn write_pair_with(key: &str, val: &str, indent: bool, buf: &mut String) {
+    if !buf.is_empty() {
+        buf.push(' ');
+    }
+    write_key(key, buf);
+    buf.push('=');
+    write_val(val, indent, buf);
+}
+
+fn write_pair(key: &str, val: &str, buf: &mut String) {
+    write_pair_with(key, val, false, buf)
+}
+
+struct Logfmt {
+    layout: Layout,
+}
+
+impl Serialize for Logfmt {
+    fn serialize(&self, item: &Item, buf: &mut String) {
+        let mut ts: String = String::new();
+        for field in self.layout.fields() {
+            match field {
+                Field::Timestamp => {
+                    ts.clear();
+                    self.layout.timestamp().format(item.timestamp, &mut ts);
+                    write_pair("time", &ts, buf)
+                }
+                Field::Severity => write_pair("level", item.severity.as_str(), buf),
+                Field::Body => match self.layout.multiline() {
+                    // Quoted values escape line breaks and backslashes.
+                    MultilinePolicy::Escape => write_pair("msg", &item.body.to_text(), buf),
+                    MultilinePolicy::Space => {
+                        let mut body: String = String::new();
+                        MultilinePolicy::Space.write(&item.body.to_text(), &mut body);
+                        write_pair("msg", &body, buf)
+                    }
+                    MultilinePolicy::Indent => {
+                        write_pair_with("msg", &item.body.to_text(), true, buf)
+                    }
+                },
+                Field::Attributes => {
+                    for (key, val) in &item.attributes {
+                        write_pair(key, val, buf);
+                    }
+                }
+                Field::Resource => {
+                    for (key, val) in &item.resource {
+                        write_pair(key, val, buf);
+                    }
+                }
+                Field::TraceId => {
+                    if let Some(trace_id) = &item.trace_id {
+                        write_pair("trace_id", trace_id, buf);
+                    }
+                }
+                Field::SpanId => {
+                    if let Some(span_id) = &item.span_id {
+                        write_pair("span_id", span_id, buf);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Creates a serializer which writes a log item as logfmt(`key=value` pairs).
+///
+/// The line starts with `time`, `level` and `msg` followed by resource
+/// fields, attributes and the trace/span ids(if any).
+/// Values containing spaces, `=`, quotes or control characters are quoted.
+pub fn logfmt_serializer_new() -> impl Serialize {
+    logfmt_serializer_new_with_timestamp(TimestampFormatter::rfc3339_nanos())
+}
+
+/// Creates a logfmt serializer which uses the timestamp formatter for `time`.
+pub fn logfmt_serializer_new_with_timestamp(timestamp: TimestampFormatter) -> impl Serialize {
+    let layout: Layout = Layout::builder()
+        .fields(&[
+            Field::Timestamp,
+            Field::Severity,
+            Field::Body,
+            Field::Resource,
+            Field::Attributes,
+            Field::TraceId,
+            Field::SpanId,
+        ])
+        .timestamp(timestamp)
+        .build();
+    logfmt_serializer_new_with_layout(layout)
+}
+
+/// Creates a logfmt serializer which emits fields in the order of the layout.
+///
+/// The keys are `time`, `level`, `msg`, `trace_id` and `span_id`;
+/// attributes and resource fields use their own keys.
+/// A body with line breaks is quoted and written by the multi-line policy:
+/// [`MultilinePolicy::Escape`] escapes them, [`MultilinePolicy::Space`]
+/// replaces them with spaces and [`MultilinePolicy::Indent`] keeps them in the
+/// quotes with indented continuation lines.
+pub fn logfmt_serializer_new_with_layout(layout: Layout) -> impl Serialize {
+    Logfmt { layout }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::logfmt_serializer_new_with_layout;
+    use crate::serialize::{
+        layout::{Field, Layout, MultilinePolicy},
+        Serialize,
+    };
+    use crate::Item;
+
+    fn msg(policy: MultilinePolicy, body: &str) -> String {
+        let layout: Layout = Layout::builder()
+            .field(Field::Body)
+            .multiline(policy)
+            .build();
+        let mut buf: String = String::new();
+        logfmt_serializer_new_with_layout(layout)
+            .serialize(&Item::new(body, BTreeMap::new()), &mut buf);
+        buf
+    }
+
+    /// Reads a quoted logfmt value.
+    fn unquote(s: &str) -> String {
+        let mut out: String = String::new();
+        let mut chars = s
+            .strip_prefix('"')
+            .unwrap()
+            .strip_suffix('"')
+            .unwrap()
+            .chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next().unwrap() {
+                    'n' => out.push('\n'),
+                    'r' => out.push('\r'),
+                    't' => out.push('\t'),
+                    e => out.push(e),
+                },
+                c => out.push(c),
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn escape_round_trips() {
+        for body in ["a\nb", "C:\\new\r\nline", "say \"hi\"\n\\"] {
+            let line: String = msg(MultilinePolicy::Escape, body);
+            assert!(!line.contains(['\n', '\r']), "{line:?}");
+            assert_eq!(body, unquote(line.strip_prefix("msg=").unwrap()));
+        }
+    }
+
+    #[test]
+    fn space_and_indent() {
+        assert_eq!("msg=\"a b\"", msg(MultilinePolicy::Space, "a\r\nb"));
+        assert_eq!("msg=\"a\n    b\"", msg(MultilinePolicy::Indent, "a\r\nb"));
+        assert_eq!("msg=single", msg(MultilinePolicy::Indent, "single"));
+    }
+}
@@ -0,0 +1,54 @@
+//! A Logstash JSON event serializer.
+
+use std::collections::BTreeMap;
+
+use crate::serialize::{json, json::ObjectWriter, time, Serialize};
+use crate::Item;
+
+static _RESERVED_KEYS: &[&str] = &[
+    "@timestamp",
+    "@version",
+    "message",
+    "level",
+    "trace_id",
+    "span_id",
+];
+
+struct Logstash {}
+
+impl Serialize for Logstash {
+    fn serialize(&self, item: &Item, buf: &mut String) {
+        let mut ts: String = String::new();
+        time::write_rfc3339(item.timestamp, 3, &mut ts);
+        let mut obj = ObjectWriter::begin(buf);
+        obj.str("@timestamp", &ts);
+        obj.str("@version", "1");
+        json::write_body(&item.body, obj.key("message"));
+        obj.str("level", &item.severity.as_str().to_uppercase());
+        if let Some(trace_id) = &item.trace_id {
+            obj.str("trace_id", trace_id);
+        }
+        if let Some(span_id) = &item.span_id {
+            obj.str("span_id", span_id);
+        }
+        let fields: BTreeMap<&str, &str> = item
+            .resource
+            .iter()
+            .chain(item.attributes.iter())
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .filter(|(key, _)| !_RESERVED_KEYS.contains(key))
+            .collect();
+        for (key, val) in fields {
+            obj.str(key, val);
+        }
+        obj.end()
+    }
+}
+
+/// Creates a serializer which writes a log item as a Logstash JSON event.
+///
+/// Resource fields and attributes are flattened into top-level fields(attributes
+/// win on conflicts). A structured body is written as a nested object.
+pub fn logstash_serializer_new() -> impl Serialize {
+    Logstash {}
+}
@@ -0,0 +1,135 @@
+//! A MessagePack serializer.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::serialize::{time, SerializeBytes};
+use crate::{Body, Item};
+
+pub(crate) fn write_str(s: &str, buf: &mut Vec<u8>) {
+    let len: usize = s.len();
+    match len {
+        0..=31 => buf.push(0xa0 | len as u8),
+        32..=0xff => {
+            buf.push(0xd9);
+            buf.push(len as u8)
+        }
+        0x100..=0xffff => {
+            buf.push(0xda);
+            buf.extend_from_slice(&(len as u16).to_be_bytes())
+        }
+        _ => {
+            buf.push(0xdb);
+            buf.extend_from_slice(&(len as u32).to_be_bytes())
+        }
+    }
+    buf.extend_from_slice(s.as_bytes())
+}
+
+pub(crate) fn write_map_len(len: usize, buf: &mut Vec<u8>) {
+    match len {
+        0..=15 => buf.push(0x80 | len as u8),
+        16..=0xffff => {
+            buf.push(0xde);
+            buf.extend_from_slice(&(len as u16).to_be_bytes())
+        }
+        _ => {
+            buf.push(0xdf);
+            buf.extend_from_slice(&(len as u32).to_be_bytes())
+        }
+    }
+}
+
+fn write_bin(b: &[u8], buf: &mut Vec<u8>) {
+    let len: usize = b.len();
+    match len {
+        0..=0xff => {
+            buf.push(0xc4);
+            buf.push(len as u8)
+        }
+        0x100..=0xffff => {
+            buf.push(0xc5);
+            buf.extend_from_slice(&(len as u16).to_be_bytes())
+        }
+        _ => {
+            buf.push(0xc6);
+            buf.extend_from_slice(&(len as u32).to_be_bytes())
+        }
+    }
+    buf.extend_from_slice(b)
+}
+
+pub(crate) fn write_body(body: &Body, buf: &mut Vec<u8>) {
+    match body {
+        Body::Text(s) => write_str(s, buf),
+        Body::Map(m) => {
+            write_map_len(m.len(), buf);
+            for (key, val) in m {
+                write_str(key, buf);
+                write_body(val, buf);
+            }
+        }
+        Body::Bytes(b) => write_bin(b, buf),
+    }
+}
+
+pub(crate) fn write_str_map(m: &BTreeMap<String, String>, buf: &mut Vec<u8>) {
+    write_map_len(m.len(), buf);
+    for (key, val) in m {
+        write_str(key, buf);
+        write_str(val, buf);
+    }
+}
+
+fn write_opt_str(o: &Option<String>, buf: &mut Vec<u8>) {
+    match o {
+        None => buf.push(0xc0),
+        Some(s) => write_str(s, buf),
+    }
+}
+
+/// Writes a timestamp using the timestamp extension type(-1, timestamp 96).
+fn write_timestamp(d: Duration, buf: &mut Vec<u8>) {
+    buf.push(0xc7);
+    buf.push(12);
+    buf.push(0xff);
+    buf.extend_from_slice(&d.subsec_nanos().to_be_bytes());
+    buf.extend_from_slice(&(d.as_secs() as i64).to_be_bytes());
+}
+
+struct MsgPack {}
+
+impl SerializeBytes for MsgPack {
+    fn serialize(&self, item: &Item, buf: &mut Vec<u8>) {
+        write_map_len(7, buf);
+        write_str("timestamp", buf);
+        write_timestamp(time::since_epoch(item.timestamp), buf);
+        write_str("severity", buf);
+        write_str(item.severity.as_str(), buf);
+        write_str("body", buf);
+        write_body(&item.body, buf);
+        write_str("attributes", buf);
+        write_str_map(&item.attributes, buf);
+        write_str("resource", buf);
+        write_str_map(&item.resource, buf);
+        write_str("trace_id", buf);
+        write_opt_str(&item.trace_id, buf);
+        write_str("span_id", buf);
+        write_opt_str(&item.span_id, buf);
+    }
+}
+
+/// Creates a serializer which writes a log item as a MessagePack map.
+///
+/// | Key          | Value                                  |
+/// |:------------:|:--------------------------------------:|
+/// | timestamp    | timestamp extension type(-1)           |
+/// | severity     | str                                    |
+/// | body         | str, map or bin                        |
+/// | attributes   | map(str, str)                          |
+/// | resource     | map(str, str)                          |
+/// | trace_id     | str or nil                             |
+/// | span_id      | str or nil                             |
+pub fn msgpack_serializer_new() -> impl SerializeBytes {
+    MsgPack {}
+}
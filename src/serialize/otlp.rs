@@ -0,0 +1,217 @@
+//! An OpenTelemetry(OTLP/JSON) log record serializer.
+
+use std::collections::BTreeMap;
+
+use crate::serialize::{base64, json::ObjectWriter, protobuf, time, Serialize};
+use crate::{Body, Item};
+
+fn write_key_values(m: &BTreeMap<String, String>, buf: &mut String) {
+    buf.push('[');
+    for (i, (key, val)) in m.iter().enumerate() {
+        if 0 < i {
+            buf.push(',');
+        }
+        let mut kv = ObjectWriter::begin(buf);
+        kv.str("key", key);
+        let mut value = ObjectWriter::begin(kv.key("value"));
+        value.str("stringValue", val);
+        value.end();
+        kv.end();
+    }
+    buf.push(']');
+}
+
+/// Writes a body as an OTLP/JSON `AnyValue`.
+fn write_any_value(body: &Body, buf: &mut String) {
+    let mut value = ObjectWriter::begin(buf);
+    match body {
+        Body::Text(s) => value.str("stringValue", s),
+        Body::Map(m) => {
+            let mut kvlist = ObjectWriter::begin(value.key("kvlistValue"));
+            let values: &mut String = kvlist.key("values");
+            values.push('[');
+            for (i, (key, val)) in m.iter().enumerate() {
+                if 0 < i {
+                    values.push(',');
+                }
+                let mut kv = ObjectWriter::begin(values);
+                kv.str("key", key);
+                write_any_value(val, kv.key("value"));
+                kv.end();
+            }
+            values.push(']');
+            kvlist.end();
+        }
+        Body::Bytes(b) => {
+            let mut encoded: String = String::new();
+            base64::write(b, &mut encoded);
+            value.str("bytesValue", &encoded)
+        }
+    }
+    value.end()
+}
+
+/// Gets a lower case hex id if the id is a valid hex string of the length.
+fn hex_id(id: &Option<String>, len: usize) -> Option<String> {
+    id.as_deref()
+        .filter(|s: &&str| s.len() == len && s.chars().all(|c: char| c.is_ascii_hexdigit()))
+        .map(|s: &str| s.to_ascii_lowercase())
+}
+
+/// Writes a log item as an OTLP/JSON `LogRecord`.
+pub(crate) fn write_log_record(item: &Item, buf: &mut String) {
+    let nanos: u128 = time::since_epoch(item.timestamp).as_nanos();
+    let severity_number: u8 = item.severity.into();
+    let mut obj = ObjectWriter::begin(buf);
+    obj.str("timeUnixNano", &nanos.to_string());
+    obj.str("observedTimeUnixNano", &nanos.to_string());
+    obj.raw("severityNumber", &severity_number.to_string());
+    obj.str("severityText", &item.severity.as_str().to_uppercase());
+    write_any_value(&item.body, obj.key("body"));
+    write_key_values(&item.attributes, obj.key("attributes"));
+    if let Some(trace_id) = hex_id(&item.trace_id, 32) {
+        obj.str("traceId", &trace_id);
+    }
+    if let Some(span_id) = hex_id(&item.span_id, 16) {
+        obj.str("spanId", &span_id);
+    }
+    obj.end()
+}
+
+/// Writes resource fields as an OTLP/JSON `Resource`.
+pub(crate) fn write_resource(resource: &BTreeMap<String, String>, buf: &mut String) {
+    let mut obj = ObjectWriter::begin(buf);
+    write_key_values(resource, obj.key("attributes"));
+    obj.end()
+}
+
+/// Groups items by their resource(in the order of the first item).
+fn by_resource(items: &[Item]) -> Vec<(&BTreeMap<String, String>, Vec<&Item>)> {
+    let mut groups: Vec<(&BTreeMap<String, String>, Vec<&Item>)> = Vec::new();
+    for item in items {
+        match groups.iter_mut().find(|(r, _)| **r == item.resource) {
+            Some((_, group)) => group.push(item),
+            None => groups.push((&item.resource, vec![item])),
+        }
+    }
+    groups
+}
+
+/// Writes items as an OTLP/JSON `ExportLogsServiceRequest`(one `ResourceLogs` per resource).
+pub(crate) fn write_export_request(items: &[Item], buf: &mut String) {
+    buf.push_str(r#"{"resourceLogs":["#);
+    for (i, (resource, group)) in by_resource(items).into_iter().enumerate() {
+        if 0 < i {
+            buf.push(',');
+        }
+        buf.push_str(r#"{"resource":"#);
+        write_resource(resource, buf);
+        buf.push_str(r#","scopeLogs":[{"scope":{},"logRecords":["#);
+        for (j, item) in group.into_iter().enumerate() {
+            if 0 < j {
+                buf.push(',');
+            }
+            write_log_record(item, buf);
+        }
+        buf.push_str("]}]}");
+    }
+    buf.push_str("]}");
+}
+
+/// Decodes a hex id(already validated by [`hex_id`]).
+fn hex_bytes(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .filter_map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+fn write_key_values_proto(field: u32, m: &BTreeMap<String, String>, buf: &mut Vec<u8>) {
+    for (key, val) in m {
+        protobuf::write_message(field, buf, |kv: &mut Vec<u8>| {
+            protobuf::write_str(1, key, kv);
+            protobuf::write_message(2, kv, |value: &mut Vec<u8>| {
+                protobuf::write_bytes(1, val.as_bytes(), value)
+            });
+        });
+    }
+}
+
+/// Writes a body as a protobuf `AnyValue`.
+fn write_any_value_proto(body: &Body, buf: &mut Vec<u8>) {
+    match body {
+        Body::Text(s) => protobuf::write_bytes(1, s.as_bytes(), buf),
+        Body::Map(m) => protobuf::write_message(6, buf, |kvlist: &mut Vec<u8>| {
+            for (key, val) in m {
+                protobuf::write_message(1, kvlist, |kv: &mut Vec<u8>| {
+                    protobuf::write_str(1, key, kv);
+                    protobuf::write_message(2, kv, |value: &mut Vec<u8>| {
+                        write_any_value_proto(val, value)
+                    });
+                });
+            }
+        }),
+        Body::Bytes(b) => protobuf::write_bytes(7, b, buf),
+    }
+}
+
+/// Writes a log item as a protobuf `LogRecord`.
+fn write_log_record_proto(item: &Item, buf: &mut Vec<u8>) {
+    let nanos: u64 = time::since_epoch(item.timestamp).as_nanos() as u64;
+    let severity_number: u8 = item.severity.into();
+    protobuf::write_fixed64(1, nanos, buf);
+    protobuf::write_uint(2, severity_number.into(), buf);
+    protobuf::write_str(3, &item.severity.as_str().to_uppercase(), buf);
+    protobuf::write_message(5, buf, |value: &mut Vec<u8>| {
+        write_any_value_proto(&item.body, value)
+    });
+    write_key_values_proto(6, &item.attributes, buf);
+    if let Some(trace_id) = hex_id(&item.trace_id, 32) {
+        protobuf::write_bytes(9, &hex_bytes(&trace_id), buf);
+    }
+    if let Some(span_id) = hex_id(&item.span_id, 16) {
+        protobuf::write_bytes(10, &hex_bytes(&span_id), buf);
+    }
+    protobuf::write_fixed64(11, nanos, buf);
+}
+
+/// Writes items as a protobuf `ExportLogsServiceRequest`(one `ResourceLogs` per resource).
+pub(crate) fn write_export_request_proto(items: &[Item], buf: &mut Vec<u8>) {
+    for (resource, group) in by_resource(items) {
+        protobuf::write_message(1, buf, |resource_logs: &mut Vec<u8>| {
+            protobuf::write_message(1, resource_logs, |r: &mut Vec<u8>| {
+                write_key_values_proto(1, resource, r)
+            });
+            protobuf::write_message(2, resource_logs, |scope_logs: &mut Vec<u8>| {
+                protobuf::write_message(1, scope_logs, |_| {});
+                for item in group {
+                    protobuf::write_message(2, scope_logs, |record: &mut Vec<u8>| {
+                        write_log_record_proto(item, record)
+                    });
+                }
+            });
+        });
+    }
+}
+
+struct Otlp {}
+
+impl Serialize for Otlp {
+    fn serialize(&self, item: &Item, buf: &mut String) {
+        buf.push_str(r#"{"resourceLogs":[{"resource":"#);
+        write_resource(&item.resource, buf);
+        buf.push_str(r#","scopeLogs":[{"scope":{},"logRecords":["#);
+        write_log_record(item, buf);
+        buf.push_str("]}]}]}");
+    }
+}
+
+/// Creates a serializer which writes a log item as an OTLP/JSON `ExportLogsServiceRequest`.
+///
+/// The resource fields are written as the resource attributes and the item
+/// is written as a single `LogRecord`(severity number/text, body as an
+/// `AnyValue`, attributes as a KeyValue list and hex trace/span ids).
+/// Trace/span ids which are not hex strings of 16/8 bytes are omitted.
+pub fn otlp_serializer_new() -> impl Serialize {
+    Otlp {}
+}
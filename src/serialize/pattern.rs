@@ -0,0 +1,190 @@
+//! A pattern-layout(log4j-style) serializer.
+
+use std::fmt;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use crate::serialize::{layout::MultilinePolicy, time, time::TimeToken, Serialize};
+use crate::Item;
+
+/// An invalid pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternError {
+    /// The byte offset of the invalid conversion.
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid pattern at {}: {}", self.position, self.message)
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+enum Token {
+    Literal(String),
+    Timestamp(Option<Vec<TimeToken>>),
+    Level,
+    LevelUpper,
+    Body,
+    Attribute(String),
+    Attributes,
+    Resource(String),
+    Resources,
+    TraceId,
+    SpanId,
+}
+
+fn read_arg(
+    chars: &mut Peekable<CharIndices>,
+    position: usize,
+) -> Result<Option<String>, PatternError> {
+    match chars.peek() {
+        Some((_, '{')) => {}
+        _ => return Ok(None),
+    }
+    chars.next();
+    let mut arg: String = String::new();
+    for (_, c) in chars.by_ref() {
+        match c {
+            '}' => return Ok(Some(arg)),
+            c => arg.push(c),
+        }
+    }
+    Err(PatternError {
+        position,
+        message: "unclosed '{'".into(),
+    })
+}
+
+fn compile(pattern: &str) -> Result<Vec<Token>, PatternError> {
+    let mut tokens: Vec<Token> = vec![];
+    let mut lit: String = String::new();
+    let mut chars = pattern.char_indices().peekable();
+    while let Some((position, c)) = chars.next() {
+        if c != '%' {
+            lit.push(c);
+            continue;
+        }
+        let err = |message: &str| PatternError {
+            position,
+            message: message.into(),
+        };
+        let token: Token = match chars.next().map(|(_, c)| c) {
+            Some('%') => {
+                lit.push('%');
+                continue;
+            }
+            Some('d') => match read_arg(&mut chars, position)? {
+                None => Token::Timestamp(None),
+                Some(fmt) => {
+                    let compiled = time::compile_strftime(&fmt).map_err(|e: PatternError| {
+                        err(&format!("invalid timestamp format: {}", e.message))
+                    })?;
+                    Token::Timestamp(Some(compiled))
+                }
+            },
+            Some('l') => Token::Level,
+            Some('L') => Token::LevelUpper,
+            Some('m') => Token::Body,
+            Some('a') => read_arg(&mut chars, position)?
+                .map(Token::Attribute)
+                .unwrap_or(Token::Attributes),
+            Some('r') => read_arg(&mut chars, position)?
+                .map(Token::Resource)
+                .unwrap_or(Token::Resources),
+            Some('t') => Token::TraceId,
+            Some('s') => Token::SpanId,
+            Some(other) => return Err(err(&format!("unknown conversion: %{other}"))),
+            None => return Err(err("incomplete conversion")),
+        };
+        if !lit.is_empty() {
+            tokens.push(Token::Literal(std::mem::take(&mut lit)));
+        }
+        tokens.push(token);
+    }
+    if !lit.is_empty() {
+        tokens.push(Token::Literal(lit));
+    }
+    Ok(tokens)
+}
+
+fn write_pairs<'a, I>(pairs: I, buf: &mut String)
+where
+    I: Iterator<Item = (&'a String, &'a String)>,
+{
+    for (i, (key, val)) in pairs.enumerate() {
+        if 0 < i {
+            buf.push(' ');
+        }
+        buf.push_str(key);
+        buf.push('=');
+        buf.push_str(val);
+    }
+}
+
+struct Pattern {
+    tokens: Vec<Token>,
+    multiline: MultilinePolicy,
+}
+
+impl Serialize for Pattern {
+    fn serialize(&self, item: &Item, buf: &mut String) {
+        for token in &self.tokens {
+            match token {
+                Token::Literal(s) => buf.push_str(s),
+                Token::Timestamp(None) => time::write_rfc3339(item.timestamp, 3, buf),
+                Token::Timestamp(Some(fmt)) => time::write_strftime(item.timestamp, fmt, buf),
+                Token::Level => buf.push_str(item.severity.as_str()),
+                Token::LevelUpper => buf.push_str(&item.severity.as_str().to_uppercase()),
+                Token::Body => self.multiline.write(&item.body.to_text(), buf),
+                Token::Attribute(key) => {
+                    buf.push_str(item.attributes.get(key).map(|s| s.as_str()).unwrap_or(""))
+                }
+                Token::Attributes => write_pairs(item.attributes.iter(), buf),
+                Token::Resource(key) => {
+                    buf.push_str(item.resource.get(key).map(|s| s.as_str()).unwrap_or(""))
+                }
+                Token::Resources => write_pairs(item.resource.iter(), buf),
+                Token::TraceId => buf.push_str(item.trace_id.as_deref().unwrap_or("")),
+                Token::SpanId => buf.push_str(item.span_id.as_deref().unwrap_or("")),
+            }
+        }
+    }
+}
+
+/// Creates a serializer from a pattern which is compiled once.
+///
+/// | Conversion  | Output                                        |
+/// |:-----------:|:---------------------------------------------:|
+/// | `%d`        | RFC3339 timestamp(UTC, milliseconds)          |
+/// | `%d{fmt}`   | strftime-like timestamp(`%Y %m %b %d %H %M %S %f %3f %6f %9f %s %z %Z`) |
+/// | `%l`        | severity                                      |
+/// | `%L`        | severity(upper case)                          |
+/// | `%m`        | body                                          |
+/// | `%a{key}`   | attribute value(empty if missing)             |
+/// | `%a`        | all attributes as `key=value`                 |
+/// | `%r{key}`   | resource value(empty if missing)              |
+/// | `%r`        | all resource fields as `key=value`            |
+/// | `%t`        | trace id                                      |
+/// | `%s`        | span id                                       |
+/// | `%%`        | `%`                                           |
+///
+/// Line breaks in the body are escaped([`MultilinePolicy::Escape`]).
+///
+/// # Arguments
+/// - pattern: The layout pattern(e.g. `%d{%Y-%m-%d} [%l] %a{request_id} %m`).
+pub fn serializer_new_from_pattern(pattern: &str) -> Result<impl Serialize, PatternError> {
+    serializer_new_from_pattern_with_multiline(pattern, MultilinePolicy::Escape)
+}
+
+/// Creates a serializer from a pattern which writes the body(`%m`) using the multi-line policy.
+pub fn serializer_new_from_pattern_with_multiline(
+    pattern: &str,
+    multiline: MultilinePolicy,
+) -> Result<impl Serialize, PatternError> {
+    let tokens: Vec<Token> = compile(pattern)?;
+    Ok(Pattern { tokens, multiline })
+}
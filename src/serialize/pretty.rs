@@ -0,0 +1,93 @@
+//! A human-readable(development-oriented) serializer.
+
+use crate::serialize::{layout::MultilinePolicy, time::TimestampFormatter, Serialize};
+use crate::{Item, Severity};
+
+const _RESET: &str = "\x1b[0m";
+const _DIM: &str = "\x1b[2m";
+
+/// Gets the ANSI escape sequence used to color a severity.
+pub fn severity_color(s: Severity) -> &'static str {
+    match s {
+        Severity::Trace => "\x1b[35m",
+        Severity::Debug => "\x1b[34m",
+        Severity::Info => "\x1b[32m",
+        Severity::Warn => "\x1b[33m",
+        Severity::Error => "\x1b[31m",
+        Severity::Fatal => "\x1b[1;31m",
+    }
+}
+
+struct Pretty {
+    color: bool,
+    timestamp: TimestampFormatter,
+}
+
+impl Pretty {
+    fn styled(&self, style: &str, s: &str, buf: &mut String) {
+        match self.color {
+            false => buf.push_str(s),
+            true => {
+                buf.push_str(style);
+                buf.push_str(s);
+                buf.push_str(_RESET);
+            }
+        }
+    }
+}
+
+impl Serialize for Pretty {
+    fn serialize(&self, item: &Item, buf: &mut String) {
+        let mut ts: String = String::new();
+        self.timestamp.format(item.timestamp, &mut ts);
+        self.styled(_DIM, &ts, buf);
+        buf.push(' ');
+        let level: String = format!("{:>5}", item.severity.as_str().to_uppercase());
+        self.styled(severity_color(item.severity), &level, buf);
+        buf.push(' ');
+        MultilinePolicy::Indent.write(&item.body.to_text(), buf);
+
+        let ids = [("trace_id", &item.trace_id), ("span_id", &item.span_id)];
+        let ids = ids
+            .iter()
+            .filter_map(|(k, v)| v.as_deref().map(|v: &str| (*k, v)));
+        let fields: Vec<(&str, &str)> = item
+            .attributes
+            .iter()
+            .chain(item.resource.iter())
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .chain(ids)
+            .collect();
+        let width: usize = fields
+            .iter()
+            .map(|(k, _)| k.chars().count())
+            .max()
+            .unwrap_or(0);
+        for (key, val) in fields {
+            buf.push_str("\n    ");
+            self.styled(_DIM, &format!("{key:>width$}:"), buf);
+            buf.push(' ');
+            buf.push_str(val);
+        }
+    }
+}
+
+/// Creates a serializer which writes a log item as aligned, human-readable lines.
+///
+/// The first line contains the timestamp, the severity and the body.
+/// Continuation lines of the body, attributes, resource fields and trace/span
+/// ids follow as an indented block.
+///
+/// # Arguments
+/// - color: Uses ANSI colors(severity coloring and dimmed timestamps) if true.
+pub fn pretty_serializer_new(color: bool) -> impl Serialize {
+    pretty_serializer_new_with_timestamp(color, TimestampFormatter::default())
+}
+
+/// Creates a human-readable serializer which uses the timestamp formatter.
+pub fn pretty_serializer_new_with_timestamp(
+    color: bool,
+    timestamp: TimestampFormatter,
+) -> impl Serialize {
+    Pretty { color, timestamp }
+}
@@ -0,0 +1,112 @@
+//! Protocol Buffers writing helpers for serializers.
+
+/// Writes a base 128 varint.
+pub(crate) fn write_varint(mut v: u64, buf: &mut Vec<u8>) {
+    while 0x80 <= v {
+        buf.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    buf.push(v as u8)
+}
+
+/// Writes a varint field(omitted if zero, like proto3 defaults).
+pub(crate) fn write_uint(field: u32, v: u64, buf: &mut Vec<u8>) {
+    if v != 0 {
+        write_varint(u64::from(field) << 3, buf);
+        write_varint(v, buf)
+    }
+}
+
+/// Writes a fixed 64-bit field(omitted if zero).
+pub(crate) fn write_fixed64(field: u32, v: u64, buf: &mut Vec<u8>) {
+    if v != 0 {
+        write_varint(u64::from(field) << 3 | 1, buf);
+        buf.extend_from_slice(&v.to_le_bytes())
+    }
+}
+
+/// Writes a length-delimited field(bytes, string or an encoded message).
+pub(crate) fn write_bytes(field: u32, b: &[u8], buf: &mut Vec<u8>) {
+    write_varint(u64::from(field) << 3 | 2, buf);
+    write_varint(b.len() as u64, buf);
+    buf.extend_from_slice(b)
+}
+
+/// Writes a string field(omitted if empty).
+pub(crate) fn write_str(field: u32, s: &str, buf: &mut Vec<u8>) {
+    if !s.is_empty() {
+        write_bytes(field, s.as_bytes(), buf)
+    }
+}
+
+/// Writes an embedded message field written by `f`.
+pub(crate) fn write_message<F>(field: u32, buf: &mut Vec<u8>, f: F)
+where
+    F: FnOnce(&mut Vec<u8>),
+{
+    let mut msg: Vec<u8> = Vec::new();
+    f(&mut msg);
+    write_bytes(field, &msg, buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_bytes, write_fixed64, write_message, write_str, write_uint, write_varint};
+
+    #[test]
+    fn varints() {
+        let cases: [(u64, &[u8]); 5] = [
+            (0, &[0x00]),
+            (1, &[0x01]),
+            (150, &[0x96, 0x01]),
+            (300, &[0xac, 0x02]),
+            (
+                u64::MAX,
+                &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01],
+            ),
+        ];
+        for (v, expected) in cases {
+            let mut buf: Vec<u8> = Vec::new();
+            write_varint(v, &mut buf);
+            assert_eq!(expected, buf.as_slice(), "{v}");
+        }
+    }
+
+    /// The examples of the encoding guide(protobuf.dev/programming-guides/encoding).
+    #[test]
+    fn encoding_guide_examples() {
+        let mut buf: Vec<u8> = Vec::new();
+        write_uint(1, 150, &mut buf);
+        assert_eq!(&[0x08, 0x96, 0x01], buf.as_slice());
+
+        buf.clear();
+        write_str(2, "testing", &mut buf);
+        assert_eq!(b"\x12\x07testing", buf.as_slice());
+
+        buf.clear();
+        write_message(3, &mut buf, |m: &mut Vec<u8>| write_uint(1, 150, m));
+        assert_eq!(&[0x1a, 0x03, 0x08, 0x96, 0x01], buf.as_slice());
+    }
+
+    #[test]
+    fn fields() {
+        let mut buf: Vec<u8> = Vec::new();
+        write_fixed64(1, 0x0102_0304_0506_0708, &mut buf);
+        assert_eq!(
+            &[0x09, 0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01],
+            buf.as_slice()
+        );
+
+        // Field numbers from 16 take a 2-byte tag.
+        buf.clear();
+        write_bytes(16, b"", &mut buf);
+        assert_eq!(&[0x82, 0x01, 0x00], buf.as_slice());
+
+        // Defaults are omitted except for bytes.
+        buf.clear();
+        write_uint(1, 0, &mut buf);
+        write_fixed64(2, 0, &mut buf);
+        write_str(3, "", &mut buf);
+        assert!(buf.is_empty());
+    }
+}
@@ -0,0 +1,328 @@
+//! A syslog(RFC 5424) message serializer.
+
+use std::collections::BTreeMap;
+use std::time::SystemTime;
+
+use crate::serialize::{time, time::TimestampFormatter, Serialize};
+use crate::{Item, Severity};
+
+/// A syslog facility.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Facility {
+    Kern,
+    User,
+    Mail,
+    Daemon,
+    Auth,
+    Syslog,
+    Lpr,
+    News,
+    Uucp,
+    Cron,
+    Authpriv,
+    Ftp,
+    Local0,
+    Local1,
+    Local2,
+    Local3,
+    Local4,
+    Local5,
+    Local6,
+    Local7,
+}
+
+impl From<Facility> for u8 {
+    fn from(f: Facility) -> Self {
+        match f {
+            Facility::Kern => 0,
+            Facility::User => 1,
+            Facility::Mail => 2,
+            Facility::Daemon => 3,
+            Facility::Auth => 4,
+            Facility::Syslog => 5,
+            Facility::Lpr => 6,
+            Facility::News => 7,
+            Facility::Uucp => 8,
+            Facility::Cron => 9,
+            Facility::Authpriv => 10,
+            Facility::Ftp => 11,
+            Facility::Local0 => 16,
+            Facility::Local1 => 17,
+            Facility::Local2 => 18,
+            Facility::Local3 => 19,
+            Facility::Local4 => 20,
+            Facility::Local5 => 21,
+            Facility::Local6 => 22,
+            Facility::Local7 => 23,
+        }
+    }
+}
+
+/// Gets the PRI value(facility * 8 + severity).
+pub fn priority(facility: Facility, severity: Severity) -> u16 {
+    u16::from(u8::from(facility)) * 8 + u16::from(severity.as_syslog())
+}
+
+/// Writes a header field(PRINTUSASCII only, `-` if empty).
+fn write_header_field(val: &str, max_len: usize, buf: &mut String) {
+    let printable = val
+        .chars()
+        .map(|c: char| match c {
+            '!'..='~' => c,
+            _ => '_',
+        })
+        .take(max_len);
+    let before: usize = buf.len();
+    buf.extend(printable);
+    if buf.len() == before {
+        buf.push('-');
+    }
+}
+
+/// Writes an SD-NAME(1 to 32 printable characters; `_` if empty).
+fn write_sd_name(name: &str, buf: &mut String) {
+    if name.is_empty() {
+        return buf.push('_');
+    }
+    let sanitized = name
+        .chars()
+        .map(|c: char| match c {
+            '=' | ']' | '"' => '_',
+            '!'..='~' => c,
+            _ => '_',
+        })
+        .take(32);
+    buf.extend(sanitized);
+}
+
+fn write_sd_element<'a, I>(id: &str, enterprise_number: u32, params: I, buf: &mut String)
+where
+    I: Iterator<Item = (&'a str, &'a str)>,
+{
+    buf.push('[');
+    buf.push_str(id);
+    buf.push('@');
+    buf.push_str(&enterprise_number.to_string());
+    for (key, val) in params {
+        buf.push(' ');
+        write_sd_name(key, buf);
+        buf.push_str("=\"");
+        for c in val.chars() {
+            match c {
+                '"' | '\\' | ']' => {
+                    buf.push('\\');
+                    buf.push(c)
+                }
+                c => buf.push(c),
+            }
+        }
+        buf.push('"');
+    }
+    buf.push(']');
+}
+
+fn map_params(m: &BTreeMap<String, String>) -> impl Iterator<Item = (&str, &str)> {
+    m.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+}
+
+/// Writes the RFC 5424 header up to MSGID(`<PRI>1 TIMESTAMP HOST APP PROCID - `).
+pub(crate) fn write_rfc5424_header(
+    pri: u16,
+    timestamp: SystemTime,
+    host: &str,
+    app_name: &str,
+    proc_id: &str,
+    buf: &mut String,
+) {
+    buf.push_str(&format!("<{pri}>1 "));
+    time::write_rfc3339(timestamp, 6, buf);
+    buf.push(' ');
+    write_header_field(host, 255, buf);
+    buf.push(' ');
+    write_header_field(app_name, 48, buf);
+    buf.push(' ');
+    write_header_field(proc_id, 128, buf);
+    buf.push_str(" - ");
+}
+
+/// Writes the RFC 3164 header(`<PRI>Mmm dd hh:mm:ss HOST TAG[PID]: `).
+///
+/// The timestamp must be formatted with `%b %d %H:%M:%S`; the day is space padded here.
+pub(crate) fn write_rfc3164_header(
+    pri: u16,
+    timestamp: SystemTime,
+    stamp: &TimestampFormatter,
+    host: &str,
+    tag: &str,
+    proc_id: &str,
+    buf: &mut String,
+) {
+    buf.push_str(&format!("<{pri}>"));
+    let start: usize = buf.len();
+    stamp.format(timestamp, buf);
+    if buf.as_bytes().get(start + 4) == Some(&b'0') {
+        buf.replace_range(start + 4..start + 5, " ");
+    }
+    buf.push(' ');
+    write_header_field(host, 255, buf);
+    buf.push(' ');
+    let tag = tag
+        .chars()
+        .filter(|c: &char| c.is_ascii_alphanumeric() || *c == '-' || *c == '_' || *c == '.')
+        .take(32);
+    buf.extend(tag);
+    buf.push('[');
+    buf.push_str(proc_id);
+    buf.push_str("]: ");
+}
+
+struct Rfc5424 {
+    facility: Facility,
+    app_name: String,
+    proc_id: String,
+    enterprise_number: u32,
+}
+
+impl Serialize for Rfc5424 {
+    fn serialize(&self, item: &Item, buf: &mut String) {
+        let pri: u16 = priority(self.facility, item.severity);
+        let host: &str = item
+            .resource
+            .get("host.name")
+            .map(|s| s.as_str())
+            .unwrap_or("");
+        write_rfc5424_header(
+            pri,
+            item.timestamp,
+            host,
+            &self.app_name,
+            &self.proc_id,
+            buf,
+        );
+
+        let sd_start: usize = buf.len();
+        if !item.attributes.is_empty() {
+            write_sd_element(
+                "attr",
+                self.enterprise_number,
+                map_params(&item.attributes),
+                buf,
+            );
+        }
+        if !item.resource.is_empty() {
+            write_sd_element(
+                "resource",
+                self.enterprise_number,
+                map_params(&item.resource),
+                buf,
+            );
+        }
+        let ids = [("trace_id", &item.trace_id), ("span_id", &item.span_id)];
+        if ids.iter().any(|(_, id)| id.is_some()) {
+            let params = ids
+                .iter()
+                .filter_map(|(k, id)| id.as_deref().map(|v: &str| (*k, v)));
+            write_sd_element("trace", self.enterprise_number, params, buf);
+        }
+        if buf.len() == sd_start {
+            buf.push('-');
+        }
+
+        if !item.body.is_empty() {
+            buf.push(' ');
+            buf.push_str(&item.body.to_text());
+        }
+    }
+}
+
+/// Creates a serializer which writes a log item as a RFC 5424 syslog message.
+///
+/// The HOSTNAME is taken from the `host.name` resource and the PROCID is the
+/// current process id.
+/// Attributes, resource fields and trace/span ids are written as structured
+/// data elements(`attr@N`, `resource@N` and `trace@N`, where N is the
+/// private enterprise number).
+///
+/// # Arguments
+/// - facility: The facility used to compute the PRI part.
+/// - app_name: The APP-NAME field.
+/// - enterprise_number: The IANA private enterprise number of the
+///   structured-data IDs(not 32473, which is reserved for documentation).
+pub fn rfc5424_serializer_new(
+    facility: Facility,
+    app_name: &str,
+    enterprise_number: u32,
+) -> impl Serialize {
+    Rfc5424 {
+        facility,
+        app_name: app_name.into(),
+        proc_id: std::process::id().to_string(),
+        enterprise_number,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::time::{Duration, SystemTime};
+
+    use super::{rfc5424_serializer_new, Facility, Rfc5424};
+    use crate::serialize::Serialize;
+    use crate::{Item, Severity};
+
+    fn message(item: &Item) -> String {
+        let s = Rfc5424 {
+            facility: Facility::Local0,
+            app_name: "app".into(),
+            proc_id: "42".into(),
+            enterprise_number: 12345,
+        };
+        let mut buf: String = String::new();
+        s.serialize(item, &mut buf);
+        buf
+    }
+
+    fn item(body: &str) -> Item {
+        let mut item: Item = Item::new(body, BTreeMap::new());
+        item.timestamp = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        item.severity = Severity::Warn;
+        item
+    }
+
+    #[test]
+    fn without_structured_data() {
+        assert_eq!(
+            "<132>1 2023-11-14T22:13:20.000000Z - app 42 - - hello",
+            message(&item("hello"))
+        );
+    }
+
+    #[test]
+    fn structured_data() {
+        let mut i: Item = item("hi");
+        i.attributes.insert("k".into(), "a\"b]c\\".into());
+        i.attributes.insert("".into(), "empty".into());
+        i.attributes.insert("x=y z".into(), "1".into());
+        i.resource.insert("host.name".into(), "web 1".into());
+        i.trace_id = Some("t1".into());
+        assert_eq!(
+            concat!(
+                "<132>1 2023-11-14T22:13:20.000000Z web_1 app 42 - ",
+                "[attr@12345 _=\"empty\" k=\"a\\\"b\\]c\\\\\" x_y_z=\"1\"]",
+                "[resource@12345 host.name=\"web 1\"]",
+                "[trace@12345 trace_id=\"t1\"] hi",
+            ),
+            message(&i)
+        );
+    }
+
+    #[test]
+    fn enterprise_number_is_a_parameter() {
+        let mut i: Item = item("");
+        i.attributes.insert("k".into(), "v".into());
+        let mut buf: String = String::new();
+        rfc5424_serializer_new(Facility::User, "app", 99).serialize(&i, &mut buf);
+        assert!(buf.starts_with("<12>1 "), "{buf}");
+        assert!(buf.ends_with(" - [attr@99 k=\"v\"]"), "{buf}");
+    }
+}
@@ -0,0 +1,64 @@
+//! Templates with `{key}` placeholders(e.g. tags and stream names).
+
+use std::collections::BTreeMap;
+
+use crate::Item;
+
+/// A part of a template.
+enum Part {
+    Text(String),
+    Key(String),
+}
+
+/// A parsed template; an unclosed `{` is kept as text.
+pub(crate) struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    /// Splits a template into texts and `{key}` placeholders.
+    pub(crate) fn parse(template: &str) -> Self {
+        let mut parts: Vec<Part> = Vec::new();
+        let mut rest: &str = template;
+        while let Some(start) = rest.find('{') {
+            match rest[start..].find('}') {
+                None => break,
+                Some(len) => {
+                    parts.push(Part::Text(rest[..start].into()));
+                    parts.push(Part::Key(rest[start + 1..start + len].into()));
+                    rest = &rest[start + len + 1..];
+                }
+            }
+        }
+        parts.push(Part::Text(rest.into()));
+        Self { parts }
+    }
+
+    /// Fills the placeholders using a lookup(`unknown` if missing).
+    pub(crate) fn render_with<'a, F>(&self, lookup: F) -> String
+    where
+        F: Fn(&str) -> Option<&'a str>,
+    {
+        let mut out: String = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(s) => out.push_str(s),
+                Part::Key(key) => out.push_str(lookup(key).unwrap_or("unknown")),
+            }
+        }
+        out
+    }
+
+    /// Fills the placeholders with resource values.
+    pub(crate) fn render(&self, resource: &BTreeMap<String, String>) -> String {
+        self.render_with(|key: &str| resource.get(key).map(|s| s.as_str()))
+    }
+
+    /// Fills the placeholders with resource values and `{severity}`.
+    pub(crate) fn render_item(&self, item: &Item) -> String {
+        self.render_with(|key: &str| match key {
+            "severity" => Some(item.severity.as_str()),
+            _ => item.resource.get(key).map(|s| s.as_str()),
+        })
+    }
+}
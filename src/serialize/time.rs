@@ -0,0 +1,361 @@
+//! Timestamp formatters for serializers.
+
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::serialize::pattern::PatternError;
+
+mod tz;
+
+use tz::TimeZone;
+
+/// Gets the duration since the unix epoch(clamped to zero for older timestamps).
+pub(crate) fn since_epoch(t: SystemTime) -> Duration {
+    t.duration_since(UNIX_EPOCH).unwrap_or(Duration::ZERO)
+}
+
+/// Converts days since the unix epoch to (year, month, day).
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z: i64 = days + 719468;
+    let era: i64 = z.div_euclid(146097);
+    let doe: i64 = z.rem_euclid(146097);
+    let yoe: i64 = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy: i64 = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp: i64 = (5 * doy + 2) / 153;
+    let d: u32 = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m: u32 = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y: i64 = yoe + era * 400 + i64::from(m <= 2);
+    (y, m, d)
+}
+
+/// Converts (year, month, day) to days since the unix epoch.
+fn days_from_civil(y: i64, m: u32, d: u32) -> i64 {
+    let y: i64 = y - i64::from(m <= 2);
+    let era: i64 = y.div_euclid(400);
+    let yoe: i64 = y.rem_euclid(400);
+    let mp: i64 = (i64::from(m) + 9) % 12;
+    let doy: i64 = (153 * mp + 2) / 5 + i64::from(d) - 1;
+    let doe: i64 = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// A broken-down local time.
+struct Civil {
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+    nanos: u32,
+    epoch_secs: u64,
+}
+
+impl Civil {
+    /// Breaks down a timestamp using a UTC offset(seconds).
+    fn new(t: SystemTime, offset: i32) -> Self {
+        let d: Duration = since_epoch(t);
+        let secs: u64 = d.as_secs();
+        let local: i64 = secs as i64 + i64::from(offset);
+        let (year, month, day) = civil_from_days(local.div_euclid(86400));
+        let sod: u32 = local.rem_euclid(86400) as u32;
+        Self {
+            year,
+            month,
+            day,
+            hour: sod / 3600,
+            minute: (sod % 3600) / 60,
+            second: sod % 60,
+            nanos: d.subsec_nanos(),
+            epoch_secs: secs,
+        }
+    }
+}
+
+impl From<SystemTime> for Civil {
+    fn from(t: SystemTime) -> Self {
+        Self::new(t, 0)
+    }
+}
+
+/// Writes a UTC offset(e.g. `+09:00` or `+0900`).
+fn write_offset(offset: i32, colon: bool, buf: &mut String) {
+    let sign: char = if offset < 0 { '-' } else { '+' };
+    let abs: u32 = offset.unsigned_abs();
+    let (h, m) = (abs / 3600, (abs % 3600) / 60);
+    match colon {
+        true => buf.push_str(&format!("{sign}{h:02}:{m:02}")),
+        false => buf.push_str(&format!("{sign}{h:02}{m:02}")),
+    }
+}
+
+fn write_frac(nanos: u32, digits: u32, buf: &mut String) {
+    let digits: u32 = digits.min(9);
+    let frac: u32 = nanos / 10u32.pow(9 - digits);
+    buf.push_str(&format!("{frac:0width$}", width = digits as usize));
+}
+
+/// Writes a timestamp as RFC3339(UTC).
+///
+/// # Arguments
+/// - t: The timestamp.
+/// - frac_digits: The number of fractional second digits(0 to 9).
+/// - buf: The output buffer.
+pub(crate) fn write_rfc3339(t: SystemTime, frac_digits: u32, buf: &mut String) {
+    write_rfc3339_offset(t, frac_digits, 0, buf)
+}
+
+/// Writes a timestamp as RFC3339 using a UTC offset(seconds).
+fn write_rfc3339_offset(t: SystemTime, frac_digits: u32, offset: i32, buf: &mut String) {
+    let c: Civil = Civil::new(t, offset);
+    buf.push_str(&format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        c.year, c.month, c.day, c.hour, c.minute, c.second
+    ));
+    if 0 < frac_digits {
+        buf.push('.');
+        write_frac(c.nanos, frac_digits, buf);
+    }
+    match offset {
+        0 => buf.push('Z'),
+        _ => write_offset(offset, true, buf),
+    }
+}
+
+static _MONTH_ABBRS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// A compiled strftime-like directive.
+#[derive(Clone, Debug)]
+pub(crate) enum TimeToken {
+    Literal(String),
+    /// `%Y`
+    Year,
+    /// `%m`
+    Month,
+    /// `%b`(e.g. `Jan`)
+    MonthAbbr,
+    /// `%d`
+    Day,
+    /// `%H`
+    Hour,
+    /// `%M`
+    Minute,
+    /// `%S`
+    Second,
+    /// `%f`(9 digits), `%3f`, `%6f`, `%9f`
+    Frac(u32),
+    /// `%s`
+    EpochSecs,
+    /// `%z`
+    Offset,
+    /// `%Z`
+    Zone,
+}
+
+/// Compiles a strftime-like pattern.
+///
+/// Supported directives: `%Y %m %b %d %H %M %S %f %3f %6f %9f %s %z %Z %%`.
+pub(crate) fn compile_strftime(pattern: &str) -> Result<Vec<TimeToken>, PatternError> {
+    let mut tokens: Vec<TimeToken> = vec![];
+    let mut lit: String = String::new();
+    let mut chars = pattern.char_indices();
+    while let Some((position, c)) = chars.next() {
+        if c != '%' {
+            lit.push(c);
+            continue;
+        }
+        let err = |message: String| PatternError { position, message };
+        let token: TimeToken = match chars.next().map(|(_, c)| c) {
+            Some('%') => {
+                lit.push('%');
+                continue;
+            }
+            Some('Y') => TimeToken::Year,
+            Some('m') => TimeToken::Month,
+            Some('b') => TimeToken::MonthAbbr,
+            Some('d') => TimeToken::Day,
+            Some('H') => TimeToken::Hour,
+            Some('M') => TimeToken::Minute,
+            Some('S') => TimeToken::Second,
+            Some('f') => TimeToken::Frac(9),
+            Some(d @ ('3' | '6' | '9')) => match chars.next().map(|(_, c)| c) {
+                Some('f') => TimeToken::Frac(d.to_digit(10).unwrap_or(9)),
+                _ => return Err(err(format!("invalid directive: %{d}"))),
+            },
+            Some('s') => TimeToken::EpochSecs,
+            Some('z') => TimeToken::Offset,
+            Some('Z') => TimeToken::Zone,
+            Some(other) => return Err(err(format!("unknown directive: %{other}"))),
+            None => return Err(err("incomplete directive".into())),
+        };
+        if !lit.is_empty() {
+            tokens.push(TimeToken::Literal(std::mem::take(&mut lit)));
+        }
+        tokens.push(token);
+    }
+    if !lit.is_empty() {
+        tokens.push(TimeToken::Literal(lit));
+    }
+    Ok(tokens)
+}
+
+/// Writes a timestamp using compiled strftime-like directives(UTC).
+pub(crate) fn write_strftime(t: SystemTime, tokens: &[TimeToken], buf: &mut String) {
+    write_strftime_offset(t, tokens, (0, "UTC"), buf)
+}
+
+/// Writes a timestamp using compiled strftime-like directives and a zone(offset, name).
+fn write_strftime_offset(t: SystemTime, tokens: &[TimeToken], zone: (i32, &str), buf: &mut String) {
+    let (offset, name) = zone;
+    let c: Civil = Civil::new(t, offset);
+    for token in tokens {
+        match token {
+            TimeToken::Literal(s) => buf.push_str(s),
+            TimeToken::Year => buf.push_str(&format!("{:04}", c.year)),
+            TimeToken::Month => buf.push_str(&format!("{:02}", c.month)),
+            TimeToken::MonthAbbr => {
+                let i: usize = (c.month as usize).saturating_sub(1).min(11);
+                buf.push_str(_MONTH_ABBRS[i])
+            }
+            TimeToken::Day => buf.push_str(&format!("{:02}", c.day)),
+            TimeToken::Hour => buf.push_str(&format!("{:02}", c.hour)),
+            TimeToken::Minute => buf.push_str(&format!("{:02}", c.minute)),
+            TimeToken::Second => buf.push_str(&format!("{:02}", c.second)),
+            TimeToken::Frac(digits) => write_frac(c.nanos, *digits, buf),
+            TimeToken::EpochSecs => buf.push_str(&c.epoch_secs.to_string()),
+            TimeToken::Offset => write_offset(offset, false, buf),
+            TimeToken::Zone => buf.push_str(name),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Kind {
+    Rfc3339(u32),
+    EpochSecs,
+    EpochMillis,
+    EpochMicros,
+    EpochNanos,
+    Strftime(Vec<TimeToken>),
+}
+
+/// A time zone used to render timestamps.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum TimeZoneMode {
+    Utc,
+    /// The host time zone(`TZ` or `/etc/localtime`; UTC if unavailable).
+    Local,
+    /// A fixed UTC offset in seconds(east of UTC is positive).
+    FixedOffset(i32),
+}
+
+/// Formats a timestamp(UTC unless a time zone is set).
+#[derive(Clone, Debug)]
+pub struct TimestampFormatter {
+    kind: Kind,
+    zone: Option<Arc<TimeZone>>,
+}
+
+impl TimestampFormatter {
+    /// Creates a RFC3339 formatter(e.g. `2006-01-02T15:04:05.999Z`).
+    ///
+    /// # Arguments
+    /// - frac_digits: The number of fractional second digits(0 to 9).
+    pub fn rfc3339(frac_digits: u32) -> Self {
+        Self::from(Kind::Rfc3339(frac_digits.min(9)))
+    }
+
+    /// Creates a RFC3339 formatter without fractional seconds.
+    pub fn rfc3339_secs() -> Self {
+        Self::rfc3339(0)
+    }
+
+    /// Creates a RFC3339 formatter with milliseconds.
+    pub fn rfc3339_millis() -> Self {
+        Self::rfc3339(3)
+    }
+
+    /// Creates a RFC3339 formatter with nanoseconds.
+    pub fn rfc3339_nanos() -> Self {
+        Self::rfc3339(9)
+    }
+
+    /// Creates a formatter which writes seconds since the unix epoch.
+    pub fn epoch_secs() -> Self {
+        Self::from(Kind::EpochSecs)
+    }
+
+    /// Creates a formatter which writes milliseconds since the unix epoch.
+    pub fn epoch_millis() -> Self {
+        Self::from(Kind::EpochMillis)
+    }
+
+    /// Creates a formatter which writes microseconds since the unix epoch.
+    pub fn epoch_micros() -> Self {
+        Self::from(Kind::EpochMicros)
+    }
+
+    /// Creates a formatter which writes nanoseconds since the unix epoch.
+    pub fn epoch_nanos() -> Self {
+        Self::from(Kind::EpochNanos)
+    }
+
+    /// Creates a formatter from a strftime-like pattern.
+    ///
+    /// Supported directives: `%Y %m %b %d %H %M %S %f %3f %6f %9f %s %z %Z %%`.
+    pub fn strftime(pattern: &str) -> Result<Self, PatternError> {
+        let tokens: Vec<TimeToken> = compile_strftime(pattern)?;
+        Ok(Self::from(Kind::Strftime(tokens)))
+    }
+
+    /// Renders timestamps in a time zone(epoch based formats are not affected).
+    ///
+    /// The local time zone rules are loaded when this method is called.
+    pub fn with_time_zone(self, mode: TimeZoneMode) -> Self {
+        let zone: Option<TimeZone> = match mode {
+            TimeZoneMode::Utc => None,
+            TimeZoneMode::Local => TimeZone::local(),
+            TimeZoneMode::FixedOffset(offset) => {
+                let mut name: String = String::new();
+                write_offset(offset, true, &mut name);
+                Some(TimeZone::fixed(offset, &name))
+            }
+        };
+        Self {
+            kind: self.kind,
+            zone: zone.map(Arc::new),
+        }
+    }
+
+    /// Writes a formatted timestamp.
+    pub fn format(&self, t: SystemTime, buf: &mut String) {
+        let d = || since_epoch(t);
+        let zone = || match &self.zone {
+            None => (0, "UTC"),
+            Some(z) => z.offset_at(d().as_secs() as i64),
+        };
+        match &self.kind {
+            Kind::Rfc3339(digits) => write_rfc3339_offset(t, *digits, zone().0, buf),
+            Kind::EpochSecs => buf.push_str(&d().as_secs().to_string()),
+            Kind::EpochMillis => buf.push_str(&d().as_millis().to_string()),
+            Kind::EpochMicros => buf.push_str(&d().as_micros().to_string()),
+            Kind::EpochNanos => buf.push_str(&d().as_nanos().to_string()),
+            Kind::Strftime(tokens) => write_strftime_offset(t, tokens, zone(), buf),
+        }
+    }
+}
+
+impl From<Kind> for TimestampFormatter {
+    fn from(kind: Kind) -> Self {
+        Self { kind, zone: None }
+    }
+}
+
+impl Default for TimestampFormatter {
+    /// RFC3339 with milliseconds.
+    fn default() -> Self {
+        Self::rfc3339_millis()
+    }
+}
@@ -0,0 +1,490 @@
+//! Time zone rules loaded from TZif files and POSIX TZ strings.
+
+use std::path::Path;
+
+use super::{civil_from_days, days_from_civil};
+
+/// A local time type(UTC offset in seconds and designation).
+#[derive(Clone, Debug)]
+struct LocalTimeType {
+    offset: i32,
+    abbr: String,
+}
+
+/// A date rule of a POSIX TZ string.
+#[derive(Clone, Debug)]
+enum DateRule {
+    /// `Jn`: 1 to 365, February 29 is never counted.
+    Julian1(u32),
+    /// `n`: 0 to 365, February 29 is counted in leap years.
+    Julian0(u32),
+    /// `Mm.w.d`: day d(0 = Sunday) of week w(5 = last) of month m.
+    MonthWeekDay(u32, u32, u32),
+}
+
+/// A transition rule: a date and a local time(seconds, may be negative).
+#[derive(Clone, Debug)]
+struct Rule {
+    date: DateRule,
+    time: i64,
+}
+
+/// A parsed POSIX TZ string(e.g. `EST5EDT,M3.2.0,M11.1.0`).
+#[derive(Clone, Debug)]
+struct PosixTz {
+    std: LocalTimeType,
+    dst: Option<(LocalTimeType, Rule, Rule)>,
+}
+
+/// Time zone rules.
+#[derive(Clone, Debug)]
+pub(crate) struct TimeZone {
+    transitions: Vec<i64>,
+    indices: Vec<u8>,
+    types: Vec<LocalTimeType>,
+    footer: Option<PosixTz>,
+}
+
+fn is_leap(y: i64) -> bool {
+    (y % 4 == 0 && y % 100 != 0) || y % 400 == 0
+}
+
+fn days_in_month(y: i64, m: u32) -> u32 {
+    match m {
+        2 if is_leap(y) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl DateRule {
+    /// Gets the days since the unix epoch of this rule for a year.
+    fn days(&self, y: i64) -> i64 {
+        let jan1: i64 = days_from_civil(y, 1, 1);
+        match *self {
+            Self::Julian1(n) => {
+                let n: i64 = i64::from(n);
+                let skip_leap: i64 = i64::from(is_leap(y) && 60 <= n);
+                jan1 + n - 1 + skip_leap
+            }
+            Self::Julian0(n) => jan1 + i64::from(n),
+            Self::MonthWeekDay(m, w, d) => {
+                let first: i64 = days_from_civil(y, m, 1);
+                let first_wday: i64 = (first + 4).rem_euclid(7);
+                let mut mday: i64 = 1 + (i64::from(d) - first_wday).rem_euclid(7);
+                mday += 7 * (i64::from(w) - 1);
+                while i64::from(days_in_month(y, m)) < mday {
+                    mday -= 7;
+                }
+                first + mday - 1
+            }
+        }
+    }
+}
+
+impl Rule {
+    /// Gets the UTC instant of this rule for a year.
+    fn instant(&self, y: i64, offset: i32) -> i64 {
+        self.date.days(y) * 86400 + self.time - i64::from(offset)
+    }
+}
+
+impl PosixTz {
+    fn local_time_type(&self, secs: i64) -> &LocalTimeType {
+        let Some((dst, start, end)) = &self.dst else {
+            return &self.std;
+        };
+        let (y, _, _) = civil_from_days((secs + i64::from(self.std.offset)).div_euclid(86400));
+        let start_at: i64 = start.instant(y, self.std.offset);
+        let end_at: i64 = end.instant(y, dst.offset);
+        let in_dst: bool = match start_at < end_at {
+            true => start_at <= secs && secs < end_at,
+            false => !(end_at <= secs && secs < start_at),
+        };
+        match in_dst {
+            true => dst,
+            false => &self.std,
+        }
+    }
+}
+
+struct Parser<'a> {
+    s: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.s.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        let found: bool = self.peek() == Some(c);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn name(&mut self) -> Option<String> {
+        let start: usize = self.pos;
+        let name: &[u8] = match self.eat(b'<') {
+            true => {
+                while self.peek()? != b'>' {
+                    self.pos += 1;
+                }
+                self.pos += 1;
+                &self.s[start + 1..self.pos - 1]
+            }
+            false => {
+                while self.peek().is_some_and(|c: u8| c.is_ascii_alphabetic()) {
+                    self.pos += 1;
+                }
+                &self.s[start..self.pos]
+            }
+        };
+        match name.len() < 3 {
+            true => None,
+            false => Some(String::from_utf8_lossy(name).into()),
+        }
+    }
+
+    fn num(&mut self) -> Option<u32> {
+        let start: usize = self.pos;
+        while self.peek().is_some_and(|c: u8| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.s[start..self.pos])
+            .ok()?
+            .parse()
+            .ok()
+    }
+
+    /// Parses `[+-]hh[:mm[:ss]]` as seconds.
+    fn hms(&mut self) -> Option<i64> {
+        let sign: i64 = match self.peek() {
+            Some(b'-') => {
+                self.pos += 1;
+                -1
+            }
+            Some(b'+') => {
+                self.pos += 1;
+                1
+            }
+            _ => 1,
+        };
+        let mut secs: i64 = i64::from(self.num()?) * 3600;
+        if self.eat(b':') {
+            secs += i64::from(self.num()?) * 60;
+            if self.eat(b':') {
+                secs += i64::from(self.num()?);
+            }
+        }
+        Some(sign * secs)
+    }
+
+    fn rule(&mut self) -> Option<Rule> {
+        let date: DateRule = match self.peek()? {
+            b'J' => {
+                self.pos += 1;
+                DateRule::Julian1(self.num()?)
+            }
+            b'M' => {
+                self.pos += 1;
+                let m: u32 = self.num()?;
+                self.eat(b'.').then_some(())?;
+                let w: u32 = self.num()?;
+                self.eat(b'.').then_some(())?;
+                let d: u32 = self.num()?;
+                match (1..=12).contains(&m) && (1..=5).contains(&w) && d <= 6 {
+                    true => DateRule::MonthWeekDay(m, w, d),
+                    false => return None,
+                }
+            }
+            _ => DateRule::Julian0(self.num()?),
+        };
+        let time: i64 = match self.eat(b'/') {
+            true => self.hms()?,
+            false => 7200,
+        };
+        Some(Rule { date, time })
+    }
+}
+
+/// Parses a POSIX TZ string.
+fn parse_posix(s: &str) -> Option<PosixTz> {
+    let mut p = Parser {
+        s: s.as_bytes(),
+        pos: 0,
+    };
+    let std_abbr: String = p.name()?;
+    // POSIX offsets are positive west of Greenwich.
+    let std_offset: i32 = -p.hms()? as i32;
+    let std = LocalTimeType {
+        offset: std_offset,
+        abbr: std_abbr,
+    };
+    if p.peek().is_none() {
+        return Some(PosixTz { std, dst: None });
+    }
+    let dst_abbr: String = p.name()?;
+    let dst_offset: i32 = match p.peek() {
+        Some(b',') | None => std_offset + 3600,
+        _ => -p.hms()? as i32,
+    };
+    let dst = LocalTimeType {
+        offset: dst_offset,
+        abbr: dst_abbr,
+    };
+    let (start, end) = match p.eat(b',') {
+        true => {
+            let start: Rule = p.rule()?;
+            p.eat(b',').then_some(())?;
+            (start, p.rule()?)
+        }
+        false => (
+            Rule {
+                date: DateRule::MonthWeekDay(3, 2, 0),
+                time: 7200,
+            },
+            Rule {
+                date: DateRule::MonthWeekDay(11, 1, 0),
+                time: 7200,
+            },
+        ),
+    };
+    match p.peek() {
+        None => Some(PosixTz {
+            std,
+            dst: Some((dst, start, end)),
+        }),
+        Some(_) => None,
+    }
+}
+
+fn be_i32(b: &[u8]) -> Option<i32> {
+    Some(i32::from_be_bytes(b.get(..4)?.try_into().ok()?))
+}
+
+fn be_i64(b: &[u8]) -> Option<i64> {
+    Some(i64::from_be_bytes(b.get(..8)?.try_into().ok()?))
+}
+
+/// Gets the size of a TZif data block; None if it overflows.
+fn tzif_block_len(counts: [usize; 6], time_size: usize) -> Option<usize> {
+    let [isutcnt, isstdcnt, leapcnt, timecnt, typecnt, charcnt] = counts;
+    [
+        timecnt.checked_mul(time_size + 1)?,
+        typecnt.checked_mul(6)?,
+        charcnt,
+        leapcnt.checked_mul(time_size + 4)?,
+        isstdcnt,
+        isutcnt,
+    ]
+    .into_iter()
+    .try_fold(0usize, usize::checked_add)
+}
+
+/// Parses a TZif(RFC 8536) file.
+///
+/// Gets None for a truncated or corrupt file(e.g. negative or too large counts).
+fn parse_tzif(data: &[u8]) -> Option<TimeZone> {
+    let header = |d: &[u8]| -> Option<[usize; 6]> {
+        (d.get(..4)? == b"TZif").then_some(())?;
+        let mut counts = [0usize; 6];
+        for (i, c) in counts.iter_mut().enumerate() {
+            *c = usize::try_from(be_i32(d.get(20 + i * 4..)?)?).ok()?;
+        }
+        Some(counts)
+    };
+    let v1_counts: [usize; 6] = header(data)?;
+    let version: u8 = *data.get(4)?;
+    let (body, time_size, counts) = match version {
+        0 => (data.get(44..)?, 4, v1_counts),
+        _ => {
+            let v1_len: usize = tzif_block_len(v1_counts, 4)?;
+            let v2: &[u8] = data.get(v1_len.checked_add(44)?..)?;
+            (v2.get(44..)?, 8, header(v2)?)
+        }
+    };
+    // The counts are checked against the data before anything is allocated.
+    if body.len() < tzif_block_len(counts, time_size)? {
+        return None;
+    }
+    let [isutcnt, isstdcnt, leapcnt, timecnt, typecnt, charcnt] = counts;
+    let mut pos: usize = 0;
+    let mut transitions: Vec<i64> = Vec::with_capacity(timecnt);
+    for _ in 0..timecnt {
+        let t: i64 = match time_size {
+            4 => i64::from(be_i32(body.get(pos..)?)?),
+            _ => be_i64(body.get(pos..)?)?,
+        };
+        transitions.push(t);
+        pos += time_size;
+    }
+    let indices: Vec<u8> = body.get(pos..pos + timecnt)?.to_vec();
+    pos += timecnt;
+    let raw_types: &[u8] = body.get(pos..pos + typecnt * 6)?;
+    pos += typecnt * 6;
+    let chars: &[u8] = body.get(pos..pos + charcnt)?;
+    pos += charcnt + leapcnt * (time_size + 4) + isstdcnt + isutcnt;
+    let mut types: Vec<LocalTimeType> = Vec::with_capacity(typecnt);
+    for raw in raw_types.chunks_exact(6) {
+        let idx: usize = usize::from(raw[5]);
+        let abbr: &[u8] = chars.get(idx..).unwrap_or_default();
+        let end: usize = abbr.iter().position(|c: &u8| *c == 0).unwrap_or(abbr.len());
+        types.push(LocalTimeType {
+            offset: be_i32(raw)?,
+            abbr: String::from_utf8_lossy(&abbr[..end]).into(),
+        });
+    }
+    if types.is_empty() || indices.iter().any(|i: &u8| types.len() <= usize::from(*i)) {
+        return None;
+    }
+    let footer: Option<PosixTz> = match version {
+        0 => None,
+        _ => body
+            .get(pos..)
+            .and_then(|f: &[u8]| std::str::from_utf8(f).ok())
+            .map(|f: &str| f.trim_matches('\n'))
+            .filter(|f: &&str| !f.is_empty())
+            .and_then(parse_posix),
+    };
+    Some(TimeZone {
+        transitions,
+        indices,
+        types,
+        footer,
+    })
+}
+
+impl TimeZone {
+    /// Creates a time zone with a fixed offset.
+    pub(crate) fn fixed(offset: i32, abbr: &str) -> Self {
+        Self {
+            transitions: vec![],
+            indices: vec![],
+            types: vec![LocalTimeType {
+                offset,
+                abbr: abbr.into(),
+            }],
+            footer: None,
+        }
+    }
+
+    /// Loads a time zone from a TZif file.
+    pub(crate) fn from_file(path: &Path) -> Option<Self> {
+        let data: Vec<u8> = std::fs::read(path).ok()?;
+        parse_tzif(&data)
+    }
+
+    /// Loads the local time zone(from `TZ` or `/etc/localtime`).
+    pub(crate) fn local() -> Option<Self> {
+        let tz: Option<String> = std::env::var("TZ").ok();
+        let Some(tz) = tz else {
+            return Self::from_file(Path::new("/etc/localtime"));
+        };
+        let name: &str = tz.strip_prefix(':').unwrap_or(&tz);
+        if name.is_empty() {
+            return Some(Self::fixed(0, "UTC"));
+        }
+        if name.starts_with('/') {
+            return Self::from_file(Path::new(name));
+        }
+        let zoneinfo = (!name.contains(".."))
+            .then(|| Self::from_file(&Path::new("/usr/share/zoneinfo").join(name)))
+            .flatten();
+        zoneinfo.or_else(|| {
+            parse_posix(name).map(|footer: PosixTz| Self {
+                transitions: vec![],
+                indices: vec![],
+                types: vec![footer.std.clone()],
+                footer: Some(footer),
+            })
+        })
+    }
+
+    fn local_time_type(&self, secs: i64) -> &LocalTimeType {
+        let n: usize = self.transitions.partition_point(|t: &i64| *t <= secs);
+        match (&self.footer, n) {
+            (Some(footer), n) if n == self.transitions.len() => footer.local_time_type(secs),
+            (_, 0) => &self.types[0],
+            (_, n) => &self.types[usize::from(self.indices[n - 1])],
+        }
+    }
+
+    /// Gets the UTC offset(seconds) and the designation at a unix time.
+    pub(crate) fn offset_at(&self, secs: i64) -> (i32, &str) {
+        let t: &LocalTimeType = self.local_time_type(secs);
+        (t.offset, &t.abbr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_tzif, TimeZone};
+
+    /// Builds a TZif block header with the counts.
+    fn header(version: u8, counts: [i32; 6]) -> Vec<u8> {
+        let mut d: Vec<u8> = b"TZif".to_vec();
+        d.push(version);
+        d.extend_from_slice(&[0; 15]);
+        for c in counts {
+            d.extend_from_slice(&c.to_be_bytes());
+        }
+        d
+    }
+
+    /// A version 1 file: UTC until 2000-01-01, JST(+9) after.
+    fn v1() -> Vec<u8> {
+        let mut d: Vec<u8> = header(0, [0, 0, 0, 1, 2, 8]);
+        d.extend_from_slice(&946_684_800i32.to_be_bytes());
+        d.push(1);
+        d.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+        d.extend_from_slice(&[0, 0, 0x7e, 0x90, 0, 4]);
+        d.extend_from_slice(b"UTC\0JST\0");
+        d
+    }
+
+    #[test]
+    fn parses_a_valid_file() {
+        let tz: TimeZone = parse_tzif(&v1()).unwrap();
+        assert_eq!(vec![946_684_800], tz.transitions);
+        assert_eq!(32400, tz.types[1].offset);
+        assert_eq!("JST", tz.types[1].abbr);
+    }
+
+    #[test]
+    fn rejects_truncated_files() {
+        let data: Vec<u8> = v1();
+        for len in 0..data.len() {
+            assert!(parse_tzif(&data[..len]).is_none(), "length {len}");
+        }
+    }
+
+    #[test]
+    fn rejects_corrupt_counts() {
+        for counts in [
+            [0, 0, 0, -1, 2, 8],
+            [0, 0, 0, 1, i32::MIN, 8],
+            [0, 0, 0, i32::MAX, 2, 8],
+            [i32::MAX, i32::MAX, i32::MAX, i32::MAX, i32::MAX, i32::MAX],
+        ] {
+            let mut data: Vec<u8> = header(0, counts);
+            data.extend_from_slice(&v1()[44..]);
+            assert!(parse_tzif(&data).is_none(), "{counts:?}");
+        }
+        // A version 2 file whose version 1 block is too large to skip.
+        let mut data: Vec<u8> = header(b'2', [i32::MAX; 6]);
+        data.extend_from_slice(&v1());
+        assert!(parse_tzif(&data).is_none());
+    }
+
+    #[test]
+    fn rejects_out_of_range_type_indices() {
+        let mut data: Vec<u8> = v1();
+        data[48] = 2;
+        assert!(parse_tzif(&data).is_none());
+    }
+}
@@ -1,42 +1,238 @@
 //! Log Writer generators.
 
-use std::ops::DerefMut;
-use std::sync::Mutex;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 
 use crate::Severity;
 
+#[cfg(all(target_os = "android", feature = "android-log"))]
+#[allow(unsafe_code)]
+pub mod android_log;
+pub mod batch;
+pub mod buffered;
+pub mod chain;
+pub mod circuit;
+pub mod cloudwatch;
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+pub mod compress;
+pub mod datadog;
+pub mod dead_letter;
+pub mod dedup;
+pub mod elasticsearch;
+#[cfg(feature = "encrypt")]
+pub mod encrypt;
+#[cfg(any(unix, windows))]
+pub mod fd;
+pub mod file;
+pub mod fluent;
+pub mod gelf;
+pub mod http;
+#[cfg(unix)]
+pub mod journald;
+#[cfg(feature = "kafka")]
+pub mod kafka;
+pub mod loki;
+#[cfg(feature = "mmap")]
+#[allow(unsafe_code)]
+pub mod mmap;
+pub mod mqtt;
+pub mod nats;
+pub mod net;
+pub mod nonblocking;
+#[cfg(all(target_vendor = "apple", feature = "os-log"))]
+pub mod os_log;
+pub mod otlp;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+pub mod rate_limit;
+pub mod redis;
+pub mod retry;
+pub mod ring;
+pub mod rotate;
+pub mod sample;
+pub mod sentry;
+pub mod shard;
+pub mod spill;
+pub mod splunk;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod stats;
+pub mod syslog;
+#[cfg(all(target_arch = "wasm32", feature = "wasm-console"))]
+pub mod wasm_console;
+#[cfg(all(windows, feature = "windows-eventlog"))]
+#[allow(unsafe_code)]
+pub mod windows_eventlog;
+
+pub use batch::batching_writer_new;
+#[cfg(unix)]
+pub use fd::fd_writer_new;
+#[cfg(windows)]
+pub use fd::handle_writer_new;
+pub use nonblocking::nonblocking_writer_new;
+
 /// A log writer which may write a serialized log string.
 pub trait LogWrite: Sync + Send {
     fn write(&self, serialized: &str, level: Severity);
+
+    /// Writes buffered lines(no-op by default).
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A log writer which reports write failures.
+pub trait TryLogWrite: LogWrite {
+    fn try_write(&self, serialized: &str, level: Severity) -> io::Result<()>;
+}
+
+struct FnTryWrite<W> {
+    internal: W,
+}
+
+impl<W> TryLogWrite for FnTryWrite<W>
+where
+    W: Fn(&str, Severity) -> io::Result<()> + Sync + Send,
+{
+    fn try_write(&self, serialized: &str, level: Severity) -> io::Result<()> {
+        (self.internal)(serialized, level)
+    }
+}
+
+impl<W> LogWrite for FnTryWrite<W>
+where
+    W: Fn(&str, Severity) -> io::Result<()> + Sync + Send,
+{
+    fn write(&self, serialized: &str, level: Severity) {
+        stats::count_write(self.try_write(serialized, level), serialized, level);
+    }
+}
+
+/// Creates a fallible log writer from a closure.
+pub fn try_log_writer_new_from_fn<W>(internal: W) -> impl TryLogWrite
+where
+    W: Fn(&str, Severity) -> io::Result<()> + Sync + Send,
+{
+    FnTryWrite { internal }
+}
+
+struct OnErrorWrite<W, E> {
+    inner: W,
+    on_error: E,
+}
+
+impl<W, E> TryLogWrite for OnErrorWrite<W, E>
+where
+    W: TryLogWrite,
+    E: Fn(&io::Error) + Sync + Send,
+{
+    fn try_write(&self, serialized: &str, level: Severity) -> io::Result<()> {
+        self.inner
+            .try_write(serialized, level)
+            .inspect_err(|e| (self.on_error)(e))
+    }
+}
+
+impl<W, E> LogWrite for OnErrorWrite<W, E>
+where
+    W: TryLogWrite,
+    E: Fn(&io::Error) + Sync + Send,
+{
+    fn write(&self, serialized: &str, level: Severity) {
+        stats::count_write(self.try_write(serialized, level), serialized, level);
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.inner.flush().inspect_err(|e| (self.on_error)(e))
+    }
+}
+
+/// Creates a log writer which reports write and flush failures to a handler.
+///
+/// The errors are still returned by [`TryLogWrite::try_write`], so the
+/// writer can be used as a primary of [`fallback_writer_new`].
+///
+/// # Arguments
+/// - inner: The fallible log writer.
+/// - on_error: Gets errors(e.g. to count or alert on them).
+pub fn on_error_writer_new<W, E>(inner: W, on_error: E) -> impl TryLogWrite
+where
+    W: TryLogWrite,
+    E: Fn(&io::Error) + Sync + Send,
+{
+    OnErrorWrite { inner, on_error }
+}
+
+struct FallbackWrite<P, S> {
+    primary: P,
+    secondary: S,
+}
+
+impl<P, S> LogWrite for FallbackWrite<P, S>
+where
+    P: TryLogWrite,
+    S: LogWrite,
+{
+    fn write(&self, serialized: &str, level: Severity) {
+        match self.primary.try_write(serialized, level) {
+            Ok(_) => {}
+            Err(_) => self.secondary.write(serialized, level),
+        }
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let primary: io::Result<()> = self.primary.flush();
+        let secondary: io::Result<()> = self.secondary.flush();
+        primary.and(secondary)
+    }
+}
+
+/// Creates a log writer which uses another writer when the primary fails.
+///
+/// Each item is tried on the primary first; an item the primary fails to
+/// write is written by the secondary(e.g. a network sink and a local file).
+///
+/// # Arguments
+/// - primary: The preferred log writer.
+/// - secondary: Writes items the primary failed to write.
+pub fn fallback_writer_new<P, S>(primary: P, secondary: S) -> impl LogWrite
+where
+    P: TryLogWrite,
+    S: LogWrite,
+{
+    FallbackWrite { primary, secondary }
 }
 
 struct LimitedWrite<L, S> {
     writer: L,
-    state: Mutex<S>,
+    log_available: S,
 }
 
 impl<L, S> LogWrite for LimitedWrite<L, S>
 where
     L: LogWrite,
-    S: FnMut(Severity) -> bool + Sync + Send,
+    S: Fn(Severity) -> bool + Sync + Send,
 {
     fn write(&self, serialized: &str, level: Severity) {
-        match self.state.lock() {
-            Err(_) => {}
-            Ok(mut guard) => {
-                let state: &mut S = guard.deref_mut();
-                let log_available: bool = state(level);
-                match log_available {
-                    true => self.writer.write(serialized, level),
-                    false => {}
-                }
-            }
+        let log_available: bool = (self.log_available)(level);
+        match log_available {
+            true => self.writer.write(serialized, level),
+            false => stats::DROPPED_BY_RATE_LIMIT.add(1),
         }
     }
+
+    fn flush(&self) -> io::Result<()> {
+        self.writer.flush()
+    }
 }
 
 /// Creates a log writer which can ignore a log item.
 ///
+/// The state function is called under a lock, so it may keep mutable state.
+/// Use [`limited_writer_new_lock_free`] with a thread-safe state function(e.g.
+/// [`rate_limit::token_bucket`]) so that writing threads do not wait for each other.
+///
 /// # Arguments
 /// - original: The original log writer.
 /// - log_available: Checks if a log item for a severity can be accepted or not.
@@ -44,13 +240,162 @@ pub fn limited_writer_new<L, S>(original: L, log_available: S) -> impl LogWrite
 where
     L: LogWrite,
     S: FnMut(Severity) -> bool + Sync + Send,
+{
+    limited_writer_new_lock_free(original, rate_limit::locked(log_available))
+}
+
+/// Creates a log writer which can ignore a log item without a lock.
+///
+/// The state function is shared by the writing threads without a lock; use
+/// atomics(e.g. [`rate_limit::token_bucket`]) or wrap a function with mutable
+/// state by [`rate_limit::locked`].
+///
+/// # Arguments
+/// - original: The original log writer.
+/// - log_available: Checks if a log item for a severity can be accepted or not.
+pub fn limited_writer_new_lock_free<L, S>(original: L, log_available: S) -> impl LogWrite
+where
+    L: LogWrite,
+    S: Fn(Severity) -> bool + Sync + Send,
 {
     LimitedWrite {
         writer: original,
-        state: Mutex::new(log_available),
+        log_available,
+    }
+}
+
+struct SummarizingLimitedWrite<L, S, F>
+where
+    L: LogWrite,
+    F: Fn(Severity, u64, Duration) -> String + Sync + Send,
+{
+    limited: LimitedWrite<L, S>,
+    summarize: F,
+    interval: Duration,
+    created: Instant,
+    /// The nanoseconds since the creation at the last summary.
+    summarized: AtomicU64,
+    /// The suppressed lines per severity.
+    suppressed: [AtomicU64; 6],
+}
+
+impl<L, S, F> SummarizingLimitedWrite<L, S, F>
+where
+    L: LogWrite,
+    F: Fn(Severity, u64, Duration) -> String + Sync + Send,
+{
+    /// Writes the summaries if the interval elapsed(or if forced).
+    fn write_summaries(&self, force: bool) {
+        let now: u64 = self.created.elapsed().as_nanos() as u64;
+        let last: u64 = self.summarized.load(Ordering::Relaxed);
+        let elapsed: Duration = Duration::from_nanos(now.saturating_sub(last));
+        if !force && elapsed < self.interval {
+            return;
+        }
+        // Only one thread writes the summaries of a period.
+        let won: bool = self
+            .summarized
+            .compare_exchange(last, now, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok();
+        if !won {
+            return;
+        }
+        for (i, count) in self.suppressed.iter().enumerate() {
+            let count: u64 = count.swap(0, Ordering::Relaxed);
+            if 0 < count {
+                let level = Severity::from(4 * i as u8 + 1);
+                let summary: String = (self.summarize)(level, count, elapsed);
+                self.limited.writer.write(&summary, level)
+            }
+        }
+    }
+}
+
+impl<L, S, F> LogWrite for SummarizingLimitedWrite<L, S, F>
+where
+    L: LogWrite,
+    S: Fn(Severity) -> bool + Sync + Send,
+    F: Fn(Severity, u64, Duration) -> String + Sync + Send,
+{
+    fn write(&self, serialized: &str, level: Severity) {
+        self.write_summaries(false);
+        match (self.limited.log_available)(level) {
+            true => self.limited.writer.write(serialized, level),
+            false => {
+                let i: usize = usize::from((u8::from(level) - 1) / 4);
+                self.suppressed[i].fetch_add(1, Ordering::Relaxed);
+                stats::DROPPED_BY_RATE_LIMIT.add(1)
+            }
+        }
+    }
+
+    /// Writes the pending summaries and flushes.
+    fn flush(&self) -> io::Result<()> {
+        self.write_summaries(true);
+        self.limited.writer.flush()
+    }
+}
+
+impl<L, S, F> Drop for SummarizingLimitedWrite<L, S, F>
+where
+    L: LogWrite,
+    F: Fn(Severity, u64, Duration) -> String + Sync + Send,
+{
+    /// Writes the pending summaries.
+    fn drop(&mut self) {
+        self.write_summaries(true)
     }
 }
 
+/// Creates a log writer which can ignore a log item and reports the ignored items.
+///
+/// The ignored lines are counted per severity. When `interval` has elapsed
+/// since the last summaries, the next write(or a flush) writes a summary line
+/// for each severity with ignored lines, with the severity. Summaries are not
+/// limited.
+///
+/// Summaries are lazy: there is no timer, so the summaries of a quiet period
+/// are written by the next write, a flush or when the writer is dropped.
+///
+/// # Arguments
+/// - original: The original log writer.
+/// - log_available: Checks if a log item for a severity can be accepted or not.
+/// - interval: The minimum time between summaries.
+/// - summarize: Creates a summary line from a severity, the number of ignored
+///   lines and the time since the last summaries(e.g. [`suppression_summary`]).
+pub fn limited_writer_new_with_summary<L, S, F>(
+    original: L,
+    log_available: S,
+    interval: Duration,
+    summarize: F,
+) -> impl LogWrite
+where
+    L: LogWrite,
+    S: Fn(Severity) -> bool + Sync + Send,
+    F: Fn(Severity, u64, Duration) -> String + Sync + Send,
+{
+    SummarizingLimitedWrite {
+        limited: LimitedWrite {
+            writer: original,
+            log_available,
+        },
+        summarize,
+        interval,
+        created: Instant::now(),
+        summarized: AtomicU64::new(0),
+        suppressed: Default::default(),
+    }
+}
+
+/// Creates a plain summary line(e.g. `12 warn records suppressed in the last 10 seconds`).
+pub fn suppression_summary(level: Severity, count: u64, elapsed: Duration) -> String {
+    format!(
+        "{count} {} records suppressed in the last {} seconds",
+        level.as_str(),
+        elapsed.as_secs()
+    )
+}
+
 struct FnWrite<W, L> {
     internal: W,
     check_level: L,
@@ -63,8 +408,11 @@ where
 {
     fn write(&self, serialized: &str, level: Severity) {
         match (self.check_level)(level) {
-            false => {}
-            true => (self.internal)(serialized, level),
+            false => stats::DROPPED_BY_LEVEL.add(1),
+            true => {
+                (self.internal)(serialized, level);
+                stats::count_written(serialized, level)
+            }
         }
     }
 }
@@ -85,6 +433,111 @@ where
     }
 }
 
+impl LogWrite for Box<dyn LogWrite> {
+    fn write(&self, serialized: &str, level: Severity) {
+        self.as_ref().write(serialized, level)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.as_ref().flush()
+    }
+}
+
+struct FanoutWrite {
+    writers: Vec<Box<dyn LogWrite>>,
+}
+
+impl LogWrite for FanoutWrite {
+    fn write(&self, serialized: &str, level: Severity) {
+        for w in &self.writers {
+            w.write(serialized, level)
+        }
+    }
+
+    /// Flushes every writer; returns the first error.
+    fn flush(&self) -> io::Result<()> {
+        let mut first: io::Result<()> = Ok(());
+        for w in &self.writers {
+            let r: io::Result<()> = w.flush();
+            first = first.and(r);
+        }
+        first
+    }
+}
+
+/// Creates a log writer which writes a serialized log item to every writer.
+///
+/// # Arguments
+/// - writers: The log writers(written in order).
+pub fn fanout_writer_new(writers: Vec<Box<dyn LogWrite>>) -> impl LogWrite {
+    FanoutWrite { writers }
+}
+
+/// A severity range(inclusive) and the log writer for it.
+pub struct SeverityRoute {
+    lower: u8,
+    upper: u8,
+    writer: Box<dyn LogWrite>,
+}
+
+impl SeverityRoute {
+    /// Creates a route.
+    ///
+    /// # Arguments
+    /// - lower: The lowest severity(inclusive).
+    /// - upper: The highest severity(inclusive).
+    /// - writer: Writes items within the range.
+    pub fn new<W>(lower: Severity, upper: Severity, writer: W) -> Self
+    where
+        W: LogWrite + 'static,
+    {
+        Self {
+            lower: lower.into(),
+            upper: upper.into(),
+            writer: Box::new(writer),
+        }
+    }
+
+    fn matches(&self, level: Severity) -> bool {
+        let u: u8 = level.into();
+        self.lower <= u && u <= self.upper
+    }
+}
+
+struct SeverityRouter {
+    routes: Vec<SeverityRoute>,
+}
+
+impl LogWrite for SeverityRouter {
+    fn write(&self, serialized: &str, level: Severity) {
+        for r in self.routes.iter().filter(|r| r.matches(level)) {
+            r.writer.write(serialized, level)
+        }
+    }
+
+    /// Flushes every writer; returns the first error.
+    fn flush(&self) -> io::Result<()> {
+        let mut first: io::Result<()> = Ok(());
+        for r in &self.routes {
+            let res: io::Result<()> = r.writer.flush();
+            first = first.and(res);
+        }
+        first
+    }
+}
+
+/// Creates a log writer which sends severity ranges to different writers.
+///
+/// Ranges may overlap: an item is written by every matching route(e.g.
+/// Trace..=Info to a file, Warn..=Fatal to stderr and Fatal..=Fatal to a
+/// pager). Items without a matching route are dropped.
+///
+/// # Arguments
+/// - routes: The routes(written in order).
+pub fn log_writer_route_by_severity(routes: Vec<SeverityRoute>) -> impl LogWrite {
+    SeverityRouter { routes }
+}
+
 /// Creates a log writer which may write logs to stdout/stderr.
 ///
 /// # Arguments
@@ -116,6 +569,71 @@ where
     )
 }
 
+/// Writes lines to stdout/stderr with the lock held.
+fn write_std_locked(serialized: &str, level: Severity) -> io::Result<()> {
+    match level {
+        Severity::Trace | Severity::Debug | Severity::Info => {
+            file::write_line(&mut io::stdout().lock(), serialized)
+        }
+        Severity::Warn | Severity::Error | Severity::Fatal => {
+            file::write_line(&mut io::stderr().lock(), serialized)
+        }
+    }
+}
+
+struct StdLockedWrite<L> {
+    check_level: L,
+}
+
+impl<L> TryLogWrite for StdLockedWrite<L>
+where
+    L: Fn(Severity) -> bool + Sync + Send,
+{
+    /// Writes the lines(skipped lines succeed).
+    fn try_write(&self, serialized: &str, level: Severity) -> io::Result<()> {
+        match (self.check_level)(level) {
+            false => Ok(()),
+            true => write_std_locked(serialized, level),
+        }
+    }
+}
+
+impl<L> LogWrite for StdLockedWrite<L>
+where
+    L: Fn(Severity) -> bool + Sync + Send,
+{
+    fn write(&self, serialized: &str, level: Severity) {
+        match (self.check_level)(level) {
+            false => stats::DROPPED_BY_LEVEL.add(1),
+            true => stats::count_write(write_std_locked(serialized, level), serialized, level),
+        }
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        use std::io::Write;
+        io::stdout().flush()?;
+        io::stderr().flush()
+    }
+}
+
+/// Creates a log writer which writes to stdout/stderr with the handle locked once per write.
+///
+/// Uses the same outputs as [`log_writer_new_std_default_from_fn`], but a
+/// write(which may be several lines, e.g. a batch from
+/// [`batching_writer_new`]) is written at once with the stdout/stderr lock
+/// held, so lines of other threads are not interleaved. A newline is added
+/// if missing. Write errors(e.g. a closed pipe) are reported instead of
+/// panicking.
+///
+/// # Arguments
+/// - check_level: Checks a severity: Returns false to skip logging.
+pub fn log_writer_new_std_locked_from_fn<L>(check_level: L) -> impl TryLogWrite
+where
+    L: Fn(Severity) -> bool + Sync + Send,
+{
+    StdLockedWrite { check_level }
+}
+
 /// Creates a severity checker which can be used with a log writer.
 ///
 /// # Arguments
@@ -134,3 +652,165 @@ pub fn level_checker_from_lower_bound(
 pub fn log_writer_new_std_default_from_lower_bound(lb_inclusive: Severity) -> impl LogWrite {
     log_writer_new_std_default_from_fn(level_checker_from_lower_bound(lb_inclusive))
 }
+
+/// A log writer which may write a serialized log item(bytes).
+pub trait LogWriteBytes: Sync + Send {
+    fn write(&self, serialized: &[u8], level: Severity);
+
+    /// Writes buffered bytes(no-op by default).
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct FnWriteBytes<W, L> {
+    internal: W,
+    check_level: L,
+}
+
+impl<W, L> LogWriteBytes for FnWriteBytes<W, L>
+where
+    W: Fn(&[u8], Severity) + Sync + Send,
+    L: Fn(Severity) -> bool + Sync + Send,
+{
+    fn write(&self, serialized: &[u8], level: Severity) {
+        match (self.check_level)(level) {
+            false => {}
+            true => (self.internal)(serialized, level),
+        }
+    }
+}
+
+/// Creates a bytes log writer which may skip logging.
+///
+/// # Arguments
+/// - internal: An internal log writer which will be used for actual logging.
+/// - check_level: Checks a severity: Returns false to skip logging.
+pub fn bytes_log_writer_new_from_fn<W, L>(internal: W, check_level: L) -> impl LogWriteBytes
+where
+    W: Fn(&[u8], Severity) + Sync + Send,
+    L: Fn(Severity) -> bool + Sync + Send,
+{
+    FnWriteBytes {
+        internal,
+        check_level,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use super::{
+        fallback_writer_new, fanout_writer_new, limited_writer_new_with_summary,
+        log_writer_new_from_fn, log_writer_route_by_severity, suppression_summary,
+        try_log_writer_new_from_fn, SeverityRoute,
+    };
+    use crate::{write::LogWrite, Severity};
+
+    type Lines = Arc<Mutex<Vec<String>>>;
+
+    /// Records lines as `{severity} {line}`.
+    fn recorder(lines: &Lines) -> impl LogWrite {
+        let sink: Lines = lines.clone();
+        log_writer_new_from_fn(
+            move |serialized: &str, level: Severity| {
+                sink.lock()
+                    .unwrap()
+                    .push(format!("{} {serialized}", level.as_str()))
+            },
+            |_| true,
+        )
+    }
+
+    #[test]
+    fn summaries_are_written_on_drop() {
+        let lines: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+        let sink: Arc<Mutex<Vec<String>>> = lines.clone();
+        let inner = log_writer_new_from_fn(
+            move |serialized: &str, _: Severity| sink.lock().unwrap().push(serialized.into()),
+            |_| true,
+        );
+        let w = limited_writer_new_with_summary(
+            inner,
+            |level: Severity| Severity::Warn <= level,
+            Duration::from_secs(3600),
+            suppression_summary,
+        );
+        w.write("kept", Severity::Error);
+        w.write("dropped", Severity::Info);
+        w.write("dropped", Severity::Info);
+        assert_eq!(vec!["kept".to_string()], *lines.lock().unwrap());
+        drop(w);
+        let lines: Vec<String> = lines.lock().unwrap().clone();
+        assert_eq!(2, lines.len());
+        assert!(
+            lines[1].starts_with("2 info records suppressed"),
+            "{}",
+            lines[1]
+        );
+    }
+
+    #[test]
+    fn fanout_writes_to_every_writer() {
+        let (a, b): (Lines, Lines) = Default::default();
+        let warn_only = {
+            let sink: Lines = b.clone();
+            log_writer_new_from_fn(
+                move |serialized: &str, _: Severity| sink.lock().unwrap().push(serialized.into()),
+                |level: Severity| Severity::Warn <= level,
+            )
+        };
+        let w = fanout_writer_new(vec![Box::new(recorder(&a)), Box::new(warn_only)]);
+        w.write("x", Severity::Info);
+        w.write("y", Severity::Error);
+        w.flush().unwrap();
+        assert_eq!(vec!["info x", "error y"], *a.lock().unwrap());
+        assert_eq!(vec!["y"], *b.lock().unwrap());
+    }
+
+    #[test]
+    fn routes_write_items_within_their_range() {
+        let (debug, alert): (Lines, Lines) = Default::default();
+        let w = log_writer_route_by_severity(vec![
+            SeverityRoute::new(Severity::Debug, Severity::Warn, recorder(&debug)),
+            SeverityRoute::new(Severity::Warn, Severity::Fatal, recorder(&alert)),
+        ]);
+        w.write("t", Severity::Trace);
+        w.write("i", Severity::Info);
+        w.write("w", Severity::Warn);
+        w.write("f", Severity::Fatal);
+        w.flush().unwrap();
+        assert_eq!(vec!["info i", "warn w"], *debug.lock().unwrap());
+        assert_eq!(vec!["warn w", "fatal f"], *alert.lock().unwrap());
+    }
+
+    #[test]
+    fn fallback_writes_items_the_primary_failed() {
+        let (primary, secondary): (Lines, Lines) = Default::default();
+        let sink: Lines = primary.clone();
+        let flaky =
+            try_log_writer_new_from_fn(move |serialized: &str, _: Severity| {
+                match serialized.starts_with("bad") {
+                    true => Err(io::Error::other("unavailable")),
+                    false => {
+                        sink.lock().unwrap().push(serialized.into());
+                        Ok(())
+                    }
+                }
+            });
+        let w = fallback_writer_new(flaky, recorder(&secondary));
+        w.write("a", Severity::Info);
+        w.write("bad 1", Severity::Warn);
+        w.write("b", Severity::Error);
+        w.write("bad 2", Severity::Error);
+        w.flush().unwrap();
+        assert_eq!(vec!["a", "b"], *primary.lock().unwrap());
+        assert_eq!(
+            vec!["warn bad 1", "error bad 2"],
+            *secondary.lock().unwrap()
+        );
+    }
+}
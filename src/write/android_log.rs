@@ -0,0 +1,65 @@
+//! A log writer for the Android logcat(requires the `android-log` feature).
+
+use std::ffi::{c_int, CString};
+use std::io;
+
+use android_log_sys::{__android_log_write, LogPriority};
+
+use crate::{
+    write::{stats, LogWrite, TryLogWrite},
+    Severity,
+};
+
+/// Converts a string to a C string(NUL characters become spaces).
+fn to_cstring(s: &str) -> CString {
+    CString::new(s.replace('\0', " ")).unwrap_or_default()
+}
+
+/// Maps a severity to a logcat priority(e.g. Trace: verbose, Info: info).
+pub fn priority(level: Severity) -> LogPriority {
+    match level {
+        Severity::Trace => LogPriority::VERBOSE,
+        Severity::Debug => LogPriority::DEBUG,
+        Severity::Info => LogPriority::INFO,
+        Severity::Warn => LogPriority::WARN,
+        Severity::Error => LogPriority::ERROR,
+        Severity::Fatal => LogPriority::FATAL,
+    }
+}
+
+struct LogcatWriter {
+    tag: CString,
+}
+
+impl TryLogWrite for LogcatWriter {
+    fn try_write(&self, serialized: &str, level: Severity) -> io::Result<()> {
+        let text: CString = to_cstring(serialized.trim_end_matches('\n'));
+        // SAFETY: both strings are NUL terminated and outlive the call.
+        let written: c_int = unsafe {
+            __android_log_write(priority(level) as c_int, self.tag.as_ptr(), text.as_ptr())
+        };
+        match written < 0 {
+            true => Err(io::Error::from_raw_os_error(-written)),
+            false => Ok(()),
+        }
+    }
+}
+
+impl LogWrite for LogcatWriter {
+    fn write(&self, serialized: &str, level: Severity) {
+        stats::count_write(self.try_write(serialized, level), serialized, level);
+    }
+}
+
+/// Creates a log writer which writes lines to the logcat(`android` only).
+///
+/// The severity maps to the priority via [`priority`]. Long lines are
+/// truncated by logd(about 4KiB).
+///
+/// # Arguments
+/// - tag: The log tag(e.g. the app name; `adb logcat -s <tag>`).
+pub fn logcat_writer_new(tag: &str) -> impl TryLogWrite {
+    LogcatWriter {
+        tag: to_cstring(tag),
+    }
+}
@@ -0,0 +1,244 @@
+//! A log writer which hands lines to an inner writer in batches.
+
+use std::io;
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::time::{Duration, Instant};
+
+use crate::{write::LogWrite, Severity};
+
+struct BatchState {
+    lines: String,
+    count: usize,
+    /// When the oldest pending line was written.
+    first: Option<Instant>,
+    /// The runs of lines of the same severity(the severity and the end of the run).
+    runs: Vec<(Severity, usize)>,
+    /// Writes the pending lines at once after a line of this severity or higher.
+    flush_level: Option<Severity>,
+}
+
+struct Shared<W> {
+    inner: W,
+    max_items: usize,
+    max_delay: Duration,
+    /// The lines being filled by writers.
+    state: Mutex<BatchState>,
+    /// The lines being written to the inner writer(swapped with the pending lines).
+    writing: Mutex<String>,
+}
+
+impl<W> Shared<W>
+where
+    W: LogWrite,
+{
+    /// Writes the pending lines, one write per run of lines of the same severity.
+    ///
+    /// The pending lines are swapped with the empty buffer and written after
+    /// the state is unlocked, so writers keep filling the other buffer. The
+    /// buffer being written is locked before the state is unlocked to keep
+    /// the batches in order.
+    fn write_pending(&self, mut state: MutexGuard<'_, BatchState>) {
+        if 0 == state.count {
+            return;
+        }
+        let mut writing = match self.writing.lock() {
+            Err(_) => return,
+            Ok(w) => w,
+        };
+        std::mem::swap(&mut state.lines, &mut *writing);
+        let runs: Vec<(Severity, usize)> = std::mem::take(&mut state.runs);
+        state.count = 0;
+        state.first = None;
+        drop(state);
+
+        let mut start: usize = 0;
+        for (level, end) in runs {
+            let run: &str = &writing[start..end];
+            self.inner
+                .write(run.strip_suffix('\n').unwrap_or(run), level);
+            start = end;
+        }
+        writing.clear();
+    }
+
+    /// Writes the pending lines if the oldest one has waited long enough.
+    fn write_expired(&self) {
+        if let Ok(guard) = self.state.lock() {
+            let expired: bool = guard
+                .first
+                .is_some_and(|t: Instant| self.max_delay <= t.elapsed());
+            if expired {
+                self.write_pending(guard);
+            }
+        }
+    }
+}
+
+/// A log writer which hands lines to an inner writer in batches.
+pub struct BatchingWriter<W>
+where
+    W: LogWrite,
+{
+    shared: Arc<Shared<W>>,
+}
+
+impl<W> BatchingWriter<W>
+where
+    W: LogWrite,
+{
+    /// Writes the pending lines and flushes the inner writer after a line of
+    /// this severity or higher(default: Error; None disables it).
+    pub fn with_flush_severity(self, level: Option<Severity>) -> Self {
+        if let Ok(mut guard) = self.shared.state.lock() {
+            guard.flush_level = level;
+        }
+        self
+    }
+}
+
+impl<W> LogWrite for BatchingWriter<W>
+where
+    W: LogWrite,
+{
+    fn write(&self, serialized: &str, level: Severity) {
+        let shared: &Shared<W> = &self.shared;
+        if let Ok(mut guard) = shared.state.lock() {
+            let state: &mut BatchState = &mut guard;
+            state.lines.push_str(serialized);
+            if !serialized.ends_with('\n') {
+                state.lines.push('\n');
+            }
+            state.count += 1;
+            state.first.get_or_insert_with(Instant::now);
+            let end: usize = state.lines.len();
+            match state.runs.last_mut() {
+                Some((l, e)) if *l == level => *e = end,
+                _ => state.runs.push((level, end)),
+            }
+            let expired: bool = state
+                .first
+                .is_some_and(|t: Instant| shared.max_delay <= t.elapsed());
+            let severe: bool = state.flush_level.is_some_and(|f: Severity| f <= level);
+            if shared.max_items <= state.count || expired || severe {
+                shared.write_pending(guard);
+            }
+            if severe {
+                shared.inner.flush().ok();
+            }
+        }
+    }
+
+    /// Writes the pending lines and flushes the inner writer.
+    fn flush(&self) -> io::Result<()> {
+        match self.shared.state.lock() {
+            Err(_) => return Err(io::Error::other("lock poisoned")),
+            Ok(guard) => self.shared.write_pending(guard),
+        }
+        self.shared.inner.flush()
+    }
+}
+
+impl<W> Drop for BatchingWriter<W>
+where
+    W: LogWrite,
+{
+    fn drop(&mut self) {
+        self.flush().ok();
+    }
+}
+
+/// Creates a log writer which writes lines to an inner writer in batches.
+///
+/// Lines are joined with newlines and written at once when `max_items` lines
+/// are pending or the oldest one has waited `max_delay`(checked on writes and
+/// by a background thread while idle). Consecutive lines of the same severity
+/// are written at once with their severity, so a batch of mixed severities
+/// takes several writes and the inner writer sees the severity of each line.
+/// Pending lines are written on flush and when dropped, and at
+/// once after a line of Error or higher(see
+/// [`BatchingWriter::with_flush_severity`]).
+///
+/// # Arguments
+/// - inner: The writer of batches(e.g. a file or network writer).
+/// - max_items: The maximum number of lines in a batch.
+/// - max_delay: The maximum time a line waits.
+pub fn batching_writer_new<W>(
+    inner: W,
+    max_items: usize,
+    max_delay: Duration,
+) -> io::Result<BatchingWriter<W>>
+where
+    W: LogWrite + 'static,
+{
+    let shared: Arc<Shared<W>> = Arc::new(Shared {
+        inner,
+        max_items: max_items.max(1),
+        max_delay,
+        state: Mutex::new(BatchState {
+            lines: String::new(),
+            count: 0,
+            first: None,
+            runs: vec![],
+            flush_level: Some(Severity::Error),
+        }),
+        writing: Mutex::new(String::new()),
+    });
+    let weak: Weak<Shared<W>> = Arc::downgrade(&shared);
+    let tick: Duration = (max_delay / 2).max(Duration::from_millis(1));
+    std::thread::Builder::new()
+        .name("log-batch".into())
+        .spawn(move || {
+            // Stops after the writer is dropped.
+            while let Some(shared) = weak.upgrade() {
+                shared.write_expired();
+                drop(shared);
+                std::thread::sleep(tick);
+            }
+        })?;
+    Ok(BatchingWriter { shared })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use super::batching_writer_new;
+    use crate::{
+        write::{log_writer_new_from_fn, log_writer_route_by_severity, LogWrite, SeverityRoute},
+        Severity,
+    };
+
+    type Lines = Arc<Mutex<Vec<String>>>;
+
+    fn recorder(lines: &Lines) -> impl LogWrite {
+        let sink: Lines = lines.clone();
+        log_writer_new_from_fn(
+            move |serialized: &str, _: Severity| sink.lock().unwrap().push(serialized.into()),
+            |_| true,
+        )
+    }
+
+    #[test]
+    fn mixed_batches_are_routed_by_severity() {
+        let low: Lines = Arc::new(Mutex::new(vec![]));
+        let high: Lines = Arc::new(Mutex::new(vec![]));
+        let router = log_writer_route_by_severity(vec![
+            SeverityRoute::new(Severity::Trace, Severity::Info, recorder(&low)),
+            SeverityRoute::new(Severity::Warn, Severity::Fatal, recorder(&high)),
+        ]);
+        let w = batching_writer_new(router, 100, Duration::from_secs(3600))
+            .unwrap()
+            .with_flush_severity(None);
+        w.write("i1", Severity::Info);
+        w.write("i2\n", Severity::Info);
+        w.write("w1", Severity::Warn);
+        w.write("d1", Severity::Debug);
+        w.write("e1", Severity::Error);
+        assert!(low.lock().unwrap().is_empty());
+        w.flush().unwrap();
+
+        assert_eq!(vec!["i1\ni2", "d1"], *low.lock().unwrap());
+        assert_eq!(vec!["w1", "e1"], *high.lock().unwrap());
+    }
+}
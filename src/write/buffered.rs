@@ -0,0 +1,158 @@
+//! A log writer which buffers lines for an `io::Write` sink.
+
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use crate::{
+    write::{file::write_line, stats, LogWrite, TryLogWrite},
+    Severity,
+};
+
+struct BufferState<W> {
+    inner: W,
+    buf: Vec<u8>,
+    /// The error of the last flush by the timer, reported to the next flush.
+    failed: Option<io::Error>,
+}
+
+impl<W> BufferState<W>
+where
+    W: Write,
+{
+    /// Writes the buffer to the sink and flushes it; the buffer is dropped on failure.
+    fn flush_buffer(&mut self) -> io::Result<()> {
+        let written: io::Result<()> = match self.buf.is_empty() {
+            true => Ok(()),
+            false => self.inner.write_all(&self.buf),
+        };
+        self.buf.clear();
+        written.and_then(|_| self.inner.flush())
+    }
+}
+
+struct Shared<W> {
+    state: Mutex<BufferState<W>>,
+    capacity: usize,
+}
+
+impl<W> Shared<W>
+where
+    W: Write,
+{
+    /// Flushes the buffer from the timer thread.
+    fn flush_timer(&self) {
+        if let Ok(mut guard) = self.state.lock() {
+            if guard.buf.is_empty() {
+                return;
+            }
+            if let Err(e) = guard.flush_buffer() {
+                guard.failed = Some(e);
+            }
+        }
+    }
+}
+
+/// A log writer which buffers lines and writes them on size or time.
+pub struct BufferedWriter<W>
+where
+    W: Write + Send,
+{
+    shared: Arc<Shared<W>>,
+}
+
+impl<W> TryLogWrite for BufferedWriter<W>
+where
+    W: Write + Send,
+{
+    /// Buffers a line; fails if writing the full buffer failed.
+    fn try_write(&self, serialized: &str, _level: Severity) -> io::Result<()> {
+        let mut guard = self
+            .shared
+            .state
+            .lock()
+            .map_err(|_| io::Error::other("lock poisoned"))?;
+        write_line(&mut guard.buf, serialized)?;
+        match self.shared.capacity <= guard.buf.len() {
+            true => guard.flush_buffer(),
+            false => Ok(()),
+        }
+    }
+}
+
+impl<W> LogWrite for BufferedWriter<W>
+where
+    W: Write + Send,
+{
+    fn write(&self, serialized: &str, level: Severity) {
+        stats::count_write(self.try_write(serialized, level), serialized, level);
+    }
+
+    /// Writes the buffer and flushes the sink; also reports a failed timer flush.
+    fn flush(&self) -> io::Result<()> {
+        let mut guard = self
+            .shared
+            .state
+            .lock()
+            .map_err(|_| io::Error::other("lock poisoned"))?;
+        let flushed: io::Result<()> = guard.flush_buffer();
+        match guard.failed.take() {
+            Some(e) => Err(e),
+            None => flushed,
+        }
+    }
+}
+
+impl<W> Drop for BufferedWriter<W>
+where
+    W: Write + Send,
+{
+    fn drop(&mut self) {
+        if let Ok(mut guard) = self.shared.state.lock() {
+            guard.flush_buffer().ok();
+        }
+    }
+}
+
+/// Creates a log writer which buffers lines for an `io::Write` sink.
+///
+/// Lines(with a newline added if missing) are buffered and written at once
+/// when the buffer reaches `capacity` bytes. A background thread also writes
+/// and flushes the buffer every `flush_interval`, so the last lines of an idle
+/// process do not stay in memory. The buffer is written when the writer is
+/// dropped. A buffer which fails to be written is dropped.
+///
+/// # Arguments
+/// - inner: The sink(e.g. a file, a pipe or a socket).
+/// - capacity: The buffer size in bytes which triggers a write.
+/// - flush_interval: The time between writes by the background thread.
+pub fn buffered_writer_new<W>(
+    inner: W,
+    capacity: usize,
+    flush_interval: Duration,
+) -> io::Result<BufferedWriter<W>>
+where
+    W: Write + Send + 'static,
+{
+    let shared: Arc<Shared<W>> = Arc::new(Shared {
+        state: Mutex::new(BufferState {
+            inner,
+            buf: Vec::with_capacity(capacity),
+            failed: None,
+        }),
+        capacity,
+    });
+    let weak: Weak<Shared<W>> = Arc::downgrade(&shared);
+    let tick: Duration = flush_interval.max(Duration::from_millis(1));
+    std::thread::Builder::new()
+        .name("log-buffer".into())
+        .spawn(move || loop {
+            std::thread::sleep(tick);
+            // Stops after the writer is dropped.
+            match weak.upgrade() {
+                None => return,
+                Some(shared) => shared.flush_timer(),
+            }
+        })?;
+    Ok(BufferedWriter { shared })
+}
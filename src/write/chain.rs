@@ -0,0 +1,186 @@
+//! A tamper-evident log writer which chains lines with HMAC-SHA256.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::{
+    serialize::{
+        layout::MultilinePolicy,
+        sha256::{hex, hmac_sha256},
+    },
+    write::{stats, LogWrite, TryLogWrite},
+    Severity,
+};
+
+/// The separator between a record and its MAC.
+const SEPARATOR: &str = " hmac=";
+
+/// Computes the MAC of a record: HMAC-SHA256(key, previous MAC || record).
+fn chain_mac(key: &[u8], previous: &[u8; 32], record: &str) -> [u8; 32] {
+    let mut data: Vec<u8> = Vec::with_capacity(32 + record.len());
+    data.extend_from_slice(previous);
+    data.extend_from_slice(record.as_bytes());
+    hmac_sha256(key, &data)
+}
+
+struct ChainWriter<W> {
+    inner: W,
+    key: Vec<u8>,
+    /// The MAC of the last line.
+    previous: Mutex<[u8; 32]>,
+}
+
+impl<W> TryLogWrite for ChainWriter<W>
+where
+    W: TryLogWrite,
+{
+    /// Writes a chained line; the chain advances only if the inner writer succeeds.
+    fn try_write(&self, serialized: &str, level: Severity) -> io::Result<()> {
+        // Line breaks are escaped so that a record stays a single line.
+        let mut record: String = String::with_capacity(serialized.len());
+        MultilinePolicy::Escape.write(serialized.trim_end_matches('\n'), &mut record);
+        // The lock keeps the order of the lines and the chain the same.
+        let mut previous = self
+            .previous
+            .lock()
+            .map_err(|_| io::Error::other("lock poisoned"))?;
+        let mac: [u8; 32] = chain_mac(&self.key, &previous, &record);
+        self.inner
+            .try_write(&format!("{record}{SEPARATOR}{}", hex(&mac)), level)?;
+        *previous = mac;
+        Ok(())
+    }
+}
+
+impl<W> LogWrite for ChainWriter<W>
+where
+    W: TryLogWrite,
+{
+    fn write(&self, serialized: &str, level: Severity) {
+        stats::count_write(self.try_write(serialized, level), serialized, level);
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Creates a log writer which appends a MAC chained to the previous line to each line.
+///
+/// A line becomes `{record} hmac={mac}`, where `mac` is the hex of
+/// HMAC-SHA256(key, MAC of the previous line || record). Modifying, inserting,
+/// removing or reordering lines breaks the chain, which
+/// [`verify_hmac_chain_file`] detects; removing lines at the end does not, so
+/// keep the last MAC elsewhere when that matters. Line breaks in a record are
+/// escaped([`MultilinePolicy::Escape`]) before the MAC is computed. A line the
+/// inner writer fails does not advance the chain, so the next line still
+/// follows the last written one.
+///
+/// # Arguments
+/// - inner: The fallible writer of chained lines(e.g. a file writer).
+/// - key: The secret key.
+/// - previous: The MAC of the last line to continue a chain(e.g. from
+///   [`verify_hmac_chain_file`]); None starts a new chain(from 32 zero bytes).
+pub fn hmac_chain_writer_new<W>(
+    inner: W,
+    key: &[u8],
+    previous: Option<[u8; 32]>,
+) -> impl TryLogWrite
+where
+    W: TryLogWrite,
+{
+    ChainWriter {
+        inner,
+        key: key.to_vec(),
+        previous: Mutex::new(previous.unwrap_or([0; 32])),
+    }
+}
+
+/// Verifies the chain of a file written by [`hmac_chain_writer_new`].
+///
+/// Gets the MAC of the last line(`previous` for an empty file) to continue the
+/// chain, or an [`io::ErrorKind::InvalidData`] error with the first broken line
+/// number(1-based).
+///
+/// # Arguments
+/// - path: The log file.
+/// - key: The secret key.
+/// - previous: The MAC preceding the first line(None for a new chain).
+pub fn verify_hmac_chain_file<P>(
+    path: P,
+    key: &[u8],
+    previous: Option<[u8; 32]>,
+) -> io::Result<Option<[u8; 32]>>
+where
+    P: AsRef<Path>,
+{
+    let reader = BufReader::new(File::open(path)?);
+    let mut last: Option<[u8; 32]> = previous;
+    for (i, line) in reader.lines().enumerate() {
+        let line: String = line?;
+        let broken = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("hmac chain broken at line {}", i + 1),
+            )
+        };
+        let (record, mac) = line.rsplit_once(SEPARATOR).ok_or_else(broken)?;
+        let expected: [u8; 32] = chain_mac(key, &last.unwrap_or([0; 32]), record);
+        if hex(&expected) != mac {
+            return Err(broken());
+        }
+        last = Some(expected);
+    }
+    Ok(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use super::{hmac_chain_writer_new, verify_hmac_chain_file};
+    use crate::{
+        write::{try_log_writer_new_from_fn, TryLogWrite},
+        Severity,
+    };
+
+    #[test]
+    fn failed_and_multiline_records_keep_the_chain_verifiable() {
+        let lines: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+        let failing: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+        let (sink, fail) = (lines.clone(), failing.clone());
+        let inner = try_log_writer_new_from_fn(move |serialized: &str, _: Severity| {
+            match fail.load(Ordering::Relaxed) {
+                true => Err(io::Error::other("sink down")),
+                false => {
+                    sink.lock().unwrap().push(serialized.into());
+                    Ok(())
+                }
+            }
+        });
+        let w = hmac_chain_writer_new(inner, b"key", None);
+        w.try_write("first", Severity::Info).unwrap();
+        failing.store(true, Ordering::Relaxed);
+        assert!(w.try_write("lost", Severity::Info).is_err());
+        failing.store(false, Ordering::Relaxed);
+        w.try_write("multi\nline\r\n", Severity::Info).unwrap();
+
+        let lines: Vec<String> = lines.lock().unwrap().clone();
+        assert_eq!(2, lines.len());
+        assert!(lines[1].starts_with("multi\\nline\\r hmac="));
+
+        let path = std::env::temp_dir().join(format!("chain-test-{}.log", std::process::id()));
+        let mut file = std::fs::File::create(&path).unwrap();
+        for line in &lines {
+            writeln!(file, "{line}").unwrap();
+        }
+        drop(file);
+        let verified = verify_hmac_chain_file(&path, b"key", None);
+        std::fs::remove_file(&path).ok();
+        assert!(verified.unwrap().is_some());
+    }
+}
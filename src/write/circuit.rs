@@ -0,0 +1,118 @@
+//! A log writer which stops calling a failing writer for a while.
+
+use std::io;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::{
+    write::{stats, LogWrite, TryLogWrite},
+    Severity,
+};
+
+struct BreakerState {
+    failures: u32,
+    opened: Option<Instant>,
+}
+
+struct CircuitBreakerWrite<W> {
+    inner: W,
+    threshold: u32,
+    cool_down: Duration,
+    state: Mutex<BreakerState>,
+}
+
+impl<W> CircuitBreakerWrite<W> {
+    /// Checks if a write can be attempted(closed, or open long enough to probe).
+    fn allows(&self) -> bool {
+        match self.state.lock() {
+            Err(_) => true,
+            Ok(mut guard) => match guard.opened {
+                None => true,
+                Some(t) => match self.cool_down <= t.elapsed() {
+                    false => false,
+                    true => {
+                        // Lets one probe through; others wait for its result.
+                        guard.opened = Some(Instant::now());
+                        true
+                    }
+                },
+            },
+        }
+    }
+
+    fn record(&self, ok: bool) {
+        if let Ok(mut guard) = self.state.lock() {
+            match ok {
+                true => {
+                    guard.failures = 0;
+                    guard.opened = None;
+                }
+                false => {
+                    guard.failures = guard.failures.saturating_add(1);
+                    if self.threshold <= guard.failures {
+                        guard.opened = Some(Instant::now());
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<W> TryLogWrite for CircuitBreakerWrite<W>
+where
+    W: TryLogWrite,
+{
+    fn try_write(&self, serialized: &str, level: Severity) -> io::Result<()> {
+        if !self.allows() {
+            return Err(io::Error::other("circuit open"));
+        }
+        let res: io::Result<()> = self.inner.try_write(serialized, level);
+        self.record(res.is_ok());
+        res
+    }
+}
+
+impl<W> LogWrite for CircuitBreakerWrite<W>
+where
+    W: TryLogWrite,
+{
+    fn write(&self, serialized: &str, level: Severity) {
+        stats::count_write(self.try_write(serialized, level), serialized, level);
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Creates a log writer which skips a writer after consecutive failures.
+///
+/// After `failure_threshold` consecutive failures the circuit opens: items
+/// fail immediately without calling the inner writer. Once `cool_down` has
+/// elapsed one item is let through as a probe; a success closes the circuit
+/// and a failure keeps it open for another cool-down. Items rejected while
+/// open are dropped, or diverted when used with
+/// [`fallback_writer_new`](crate::write::fallback_writer_new).
+///
+/// # Arguments
+/// - inner: The fallible log writer(e.g. a remote sink).
+/// - failure_threshold: The number of consecutive failures which opens the circuit.
+/// - cool_down: The time to wait before probing the writer again.
+pub fn circuit_breaker_writer_new<W>(
+    inner: W,
+    failure_threshold: u32,
+    cool_down: Duration,
+) -> impl TryLogWrite
+where
+    W: TryLogWrite,
+{
+    CircuitBreakerWrite {
+        inner,
+        threshold: failure_threshold.max(1),
+        cool_down,
+        state: Mutex::new(BreakerState {
+            failures: 0,
+            opened: None,
+        }),
+    }
+}
@@ -0,0 +1,518 @@
+//! A logger which sends items to Amazon CloudWatch Logs.
+
+use std::collections::{BTreeMap, HashSet};
+use std::io;
+use std::time::{Duration, SystemTime};
+
+#[cfg(feature = "tls")]
+use crate::write::net::tls::TlsConfig;
+use crate::{
+    copy::Logger,
+    serialize::{
+        json::ObjectWriter,
+        sha256::{hex, hmac_sha256, sha256},
+        template::Template,
+        time, Serialize,
+    },
+    write::{
+        http::{Batcher, Deliver, HttpClient, Response},
+        net::truncate_str,
+        retry::{jitter, RetryPolicy},
+        stats,
+    },
+    Item, Severity,
+};
+
+/// The maximum number of events in a `PutLogEvents` request.
+pub const MAX_BATCH_EVENTS: usize = 10_000;
+
+/// The maximum size of a `PutLogEvents` request(messages plus 26 bytes per event).
+pub const MAX_BATCH_BYTES: usize = 1_048_576;
+
+/// The maximum size of a message(longer messages are truncated).
+pub const MAX_MESSAGE_BYTES: usize = 262_144 - EVENT_OVERHEAD;
+
+const EVENT_OVERHEAD: usize = 26;
+
+/// The maximum time span of the events in a request.
+const MAX_SPAN_MILLIS: u64 = 24 * 60 * 60 * 1000;
+
+const CONTENT_TYPE: &str = "application/x-amz-json-1.1";
+
+/// AWS credentials used to sign requests(Signature Version 4).
+#[derive(Clone)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// The token of temporary credentials.
+    pub session_token: Option<String>,
+}
+
+impl AwsCredentials {
+    /// Reads `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and `AWS_SESSION_TOKEN`.
+    pub fn from_env() -> io::Result<Self> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        match (var("AWS_ACCESS_KEY_ID"), var("AWS_SECRET_ACCESS_KEY")) {
+            (Some(access_key_id), Some(secret_access_key)) => Ok(Self {
+                access_key_id,
+                secret_access_key,
+                session_token: var("AWS_SESSION_TOKEN"),
+            }),
+            _ => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "AWS_ACCESS_KEY_ID or AWS_SECRET_ACCESS_KEY not set",
+            )),
+        }
+    }
+}
+
+/// A log event with its stream.
+struct Event {
+    stream: String,
+    millis: u64,
+    message: String,
+}
+
+/// Gets the `__type` of an error response(e.g. `ResourceNotFoundException`).
+fn error_type(r: &Response) -> String {
+    let body = String::from_utf8_lossy(&r.body);
+    let Some((_, rest)) = body.split_once("\"__type\"") else {
+        return String::new();
+    };
+    let name: &str = rest
+        .trim_start_matches([':', ' ', '"'])
+        .split('"')
+        .next()
+        .unwrap_or("");
+    name.rsplit('#').next().unwrap_or(name).into()
+}
+
+/// Splits the sorted events of a stream into requests within the API limits.
+fn split_batches<'a>(events: &[&'a Event]) -> Vec<Vec<&'a Event>> {
+    let mut batches: Vec<Vec<&Event>> = vec![];
+    let mut batch: Vec<&Event> = vec![];
+    let mut bytes: usize = 0;
+    for event in events {
+        let size: usize = event.message.len() + EVENT_OVERHEAD;
+        let full: bool = match batch.first() {
+            None => false,
+            Some(first) => {
+                MAX_BATCH_EVENTS <= batch.len()
+                    || MAX_BATCH_BYTES < bytes + size
+                    || MAX_SPAN_MILLIS < event.millis - first.millis
+            }
+        };
+        if full {
+            batches.push(std::mem::take(&mut batch));
+            bytes = 0;
+        }
+        batch.push(event);
+        bytes += size;
+    }
+    if !batch.is_empty() {
+        batches.push(batch);
+    }
+    batches
+}
+
+/// Derives the SigV4 signing key of a day(`YYYYMMDD`), a region and a service.
+fn signing_key(secret: &str, date: &str, region: &str, service: &str) -> [u8; 32] {
+    let secret: String = format!("AWS4{secret}");
+    [region, service, "aws4_request"]
+        .iter()
+        .fold(hmac_sha256(secret.as_bytes(), date.as_bytes()), |k, s| {
+            hmac_sha256(&k, s.as_bytes())
+        })
+}
+
+/// Calls the CloudWatch Logs API(`Logs_20140328`) with signed requests.
+struct LogsApi {
+    client: HttpClient,
+    region: String,
+    group: String,
+    credentials: AwsCredentials,
+    retry: RetryPolicy,
+    /// The streams created(or found) by this logger.
+    created: HashSet<String>,
+}
+
+impl LogsApi {
+    /// Gets the headers of a signed request(SigV4).
+    fn sign(&self, action: &str, body: &[u8], now: SystemTime) -> Vec<(&'static str, String)> {
+        let mut stamp: String = String::new();
+        time::write_rfc3339(now, 0, &mut stamp);
+        let amz_date: String = stamp.chars().filter(|c| !matches!(c, '-' | ':')).collect();
+        let date: &str = &amz_date[..8];
+
+        let mut headers: Vec<(&'static str, String)> = vec![
+            ("X-Amz-Date", amz_date.clone()),
+            ("X-Amz-Target", format!("Logs_20140328.{action}")),
+        ];
+        if let Some(token) = &self.credentials.session_token {
+            headers.push(("X-Amz-Security-Token", token.clone()));
+        }
+        let mut canonical: Vec<(String, String)> = vec![
+            ("content-type".into(), CONTENT_TYPE.into()),
+            ("host".into(), self.client.authority().into()),
+        ];
+        canonical.extend(
+            headers
+                .iter()
+                .map(|(name, val)| (name.to_ascii_lowercase(), val.clone())),
+        );
+        canonical.sort();
+        let signed: String = canonical
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(";");
+        let mut request: String = format!("POST\n{}\n\n", self.client.path());
+        for (name, val) in &canonical {
+            request.push_str(&format!("{name}:{val}\n"));
+        }
+        request.push_str(&format!("\n{signed}\n{}", hex(&sha256(body))));
+
+        let scope: String = format!("{date}/{}/logs/aws4_request", self.region);
+        let to_sign: String = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            hex(&sha256(request.as_bytes()))
+        );
+        let key: [u8; 32] = signing_key(
+            &self.credentials.secret_access_key,
+            date,
+            &self.region,
+            "logs",
+        );
+        let signature: String = hex(&hmac_sha256(&key, to_sign.as_bytes()));
+        headers.push((
+            "Authorization",
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed}, Signature={signature}",
+                self.credentials.access_key_id
+            ),
+        ));
+        headers
+    }
+
+    /// Calls an action, retrying connection errors, throttling, 429 and 5xx.
+    ///
+    /// Other error responses are returned to be inspected.
+    fn call(&self, action: &str, body: &str) -> io::Result<Response> {
+        let mut failures: u32 = 0;
+        loop {
+            let headers = self.sign(action, body.as_bytes(), SystemTime::now());
+            let headers: Vec<(&str, &str)> = headers
+                .iter()
+                .map(|(name, val)| (*name, val.as_str()))
+                .collect();
+            let e: io::Error =
+                match self
+                    .client
+                    .post_with_headers(CONTENT_TYPE, None, &headers, body.as_bytes())
+                {
+                    Ok(r) if r.status < 300 => return Ok(r),
+                    Ok(r) if r.retryable() || error_type(&r) == "ThrottlingException" => r.error(),
+                    Ok(r) => return Ok(r),
+                    Err(e) => e,
+                };
+            failures += 1;
+            if self.retry.max_attempts <= failures {
+                return Err(e);
+            }
+            std::thread::sleep(jitter(self.retry.backoff(failures)));
+        }
+    }
+
+    /// Calls an action which creates a resource; an existing one is fine.
+    fn create(&self, action: &str, stream: Option<&str>) -> io::Result<Response> {
+        let mut body: String = String::new();
+        let mut obj = ObjectWriter::begin(&mut body);
+        obj.str("logGroupName", &self.group);
+        if let Some(stream) = stream {
+            obj.str("logStreamName", stream);
+        }
+        obj.end();
+        let r: Response = self.call(action, &body)?;
+        match r.status < 300 || error_type(&r) == "ResourceAlreadyExistsException" {
+            true => Ok(Response {
+                status: 200,
+                body: vec![],
+            }),
+            false => Ok(r),
+        }
+    }
+
+    /// Creates a stream(and the group if missing).
+    fn create_stream(&mut self, stream: &str) -> io::Result<()> {
+        let mut r: Response = self.create("CreateLogStream", Some(stream))?;
+        if error_type(&r) == "ResourceNotFoundException" {
+            let g: Response = self.create("CreateLogGroup", None)?;
+            if 300 <= g.status {
+                return Err(g.error());
+            }
+            r = self.create("CreateLogStream", Some(stream))?;
+        }
+        if 300 <= r.status {
+            return Err(r.error());
+        }
+        self.created.insert(stream.into());
+        Ok(())
+    }
+
+    fn put(&mut self, stream: &str, events: &[&Event]) -> io::Result<()> {
+        let mut body: String = String::new();
+        let mut obj = ObjectWriter::begin(&mut body);
+        obj.str("logGroupName", &self.group);
+        obj.str("logStreamName", stream);
+        let list: &mut String = obj.key("logEvents");
+        list.push('[');
+        for (i, event) in events.iter().enumerate() {
+            if 0 < i {
+                list.push(',');
+            }
+            let mut e = ObjectWriter::begin(list);
+            e.raw("timestamp", &event.millis.to_string());
+            e.str("message", &event.message);
+            e.end();
+        }
+        list.push(']');
+        obj.end();
+
+        if !self.created.contains(stream) {
+            self.create_stream(stream)?;
+        }
+        let mut r: Response = self.call("PutLogEvents", &body)?;
+        if error_type(&r) == "ResourceNotFoundException" {
+            // The stream(or the group) was deleted after it was created.
+            self.created.remove(stream);
+            self.create_stream(stream)?;
+            r = self.call("PutLogEvents", &body)?;
+        }
+        if 300 <= r.status {
+            return Err(r.error());
+        }
+        let body = String::from_utf8_lossy(&r.body);
+        match body.contains("rejectedLogEventsInfo") {
+            true => Err(io::Error::other(format!(
+                "rejected log events: {}",
+                truncate_str(&body, 256)
+            ))),
+            false => Ok(()),
+        }
+    }
+}
+
+impl Deliver<Event> for LogsApi {
+    /// Sends the events of each stream in time order; reports the last failure.
+    fn deliver(&mut self, batch: &[Event]) -> io::Result<()> {
+        let mut streams: BTreeMap<&str, Vec<&Event>> = BTreeMap::new();
+        for event in batch {
+            streams.entry(&event.stream).or_default().push(event);
+        }
+        let mut result: io::Result<()> = Ok(());
+        for (stream, mut events) in streams {
+            events.sort_by_key(|e: &&Event| e.millis);
+            for events in split_batches(&events) {
+                if let Err(e) = self.put(stream, &events) {
+                    result = Err(e);
+                }
+            }
+        }
+        result
+    }
+}
+
+/// A logger which sends serialized items to log streams of a log group.
+pub struct CloudWatchLogger<S> {
+    serialize: S,
+    stream: Template,
+    batcher: Batcher<Event, LogsApi>,
+}
+
+impl<S> CloudWatchLogger<S> {
+    /// Sends requests to another endpoint(e.g. a VPC endpoint or a local emulator).
+    pub fn with_endpoint(self, endpoint: &str) -> io::Result<Self> {
+        let client: HttpClient = HttpClient::new(endpoint, &[])?;
+        self.batcher.configure(|api: &mut LogsApi| {
+            api.client = client;
+            Ok(())
+        })?;
+        Ok(self)
+    }
+
+    /// Sets the retries of a failed request(connection errors, throttling, 429 and 5xx).
+    pub fn with_retry_policy(self, policy: RetryPolicy) -> Self {
+        self.batcher
+            .configure(|api: &mut LogsApi| {
+                api.retry = policy;
+                Ok(())
+            })
+            .ok();
+        self
+    }
+
+    /// Sets the maximum number of queued items(default: 8 batches).
+    pub fn with_max_pending(self, max_pending: usize) -> Self {
+        self.batcher.set_max_pending(max_pending);
+        self
+    }
+
+    /// Sends the queued items at once after an item of this severity or higher
+    /// is logged(default: Error; None disables it).
+    pub fn with_flush_severity(self, level: Option<Severity>) -> Self {
+        self.batcher.set_flush_severity(level);
+        self
+    }
+
+    /// Sets the trusted roots and the client certificate for `https` endpoints.
+    #[cfg(feature = "tls")]
+    pub fn with_tls_config(self, config: TlsConfig) -> io::Result<Self> {
+        self.batcher
+            .configure(|api: &mut LogsApi| api.client.set_tls_config(config))?;
+        Ok(self)
+    }
+}
+
+impl<S> Logger for CloudWatchLogger<S>
+where
+    S: Serialize,
+{
+    fn log(&self, item: Item) {
+        let level: Severity = item.severity;
+        let mut message: String = String::new();
+        self.serialize.serialize(&item, &mut message);
+        let message: &str = truncate_str(message.trim_end_matches('\n'), MAX_MESSAGE_BYTES);
+        // `:` and `*` are not allowed in stream names.
+        let stream: String = self.stream.render(&item.resource).replace([':', '*'], "_");
+        let event = Event {
+            stream: truncate_str(&stream, 512).into(),
+            millis: time::since_epoch(item.timestamp).as_millis() as u64,
+            message: message.into(),
+        };
+        stats::count_queued(self.batcher.push(event, level));
+    }
+
+    /// Sends the queued items; fails if a batch was dropped since the last flush.
+    fn flush(&self) -> io::Result<()> {
+        self.batcher.flush()
+    }
+}
+
+/// Creates a logger which sends items to CloudWatch Logs with `PutLogEvents`.
+///
+/// The stream of an item is named by a template filled with its resource
+/// fields. The group and the streams are created when first used. The events
+/// of each stream are sorted by timestamp and split into requests within the
+/// API limits([`MAX_BATCH_EVENTS`], [`MAX_BATCH_BYTES`] and 24 hours);
+/// sequence tokens are not used. Requests are signed with `credentials` and
+/// sent like [`http_batch_writer_new`](crate::write::http::http_batch_writer_new).
+///
+/// The endpoint is `https://logs.{region}.amazonaws.com`, which requires the
+/// `tls` feature(see [`CloudWatchLogger::with_endpoint`]).
+///
+/// # Arguments
+/// - region: The AWS region(e.g. `"us-east-1"`).
+/// - credentials: Signs the requests(e.g. [`AwsCredentials::from_env`]).
+/// - log_group: The log group name.
+/// - stream: The stream name template; `{key}` is replaced with the resource
+///   value(`unknown` if missing), e.g. `"{service.name}/{host.name}"`.
+/// - serialize: Serializes the message of an event.
+/// - batch_size: The maximum number of items sent at a time.
+/// - flush_interval: The maximum time an item waits before being sent.
+pub fn cloudwatch_logger_new<S>(
+    region: &str,
+    credentials: AwsCredentials,
+    log_group: &str,
+    stream: &str,
+    serialize: S,
+    batch_size: usize,
+    flush_interval: Duration,
+) -> io::Result<CloudWatchLogger<S>>
+where
+    S: Serialize,
+{
+    let url: String = format!("https://logs.{region}.amazonaws.com/");
+    let api = LogsApi {
+        client: HttpClient::new(&url, &[])?,
+        region: region.into(),
+        group: log_group.into(),
+        credentials,
+        retry: RetryPolicy::default(),
+        created: HashSet::new(),
+    };
+    Ok(CloudWatchLogger {
+        serialize,
+        stream: Template::parse(stream),
+        batcher: Batcher::new(api, batch_size.min(MAX_BATCH_EVENTS), flush_interval)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::time::{Duration, SystemTime};
+
+    use super::{signing_key, AwsCredentials, LogsApi};
+    use crate::{
+        serialize::sha256::hex,
+        write::{http::HttpClient, retry::RetryPolicy},
+    };
+
+    const SECRET: &str = "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY";
+
+    #[test]
+    fn signing_key_of_the_aws_docs() {
+        let key: [u8; 32] = signing_key(SECRET, "20120215", "us-east-1", "iam");
+        assert_eq!(
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d",
+            hex(&key)
+        );
+    }
+
+    #[test]
+    fn signed_headers() {
+        let mut api = LogsApi {
+            client: HttpClient::new("http://logs.us-east-1.amazonaws.com/", &[]).unwrap(),
+            region: "us-east-1".into(),
+            group: "g".into(),
+            credentials: AwsCredentials {
+                access_key_id: "AKIDEXAMPLE".into(),
+                secret_access_key: SECRET.into(),
+                session_token: None,
+            },
+            retry: RetryPolicy::default(),
+            created: HashSet::new(),
+        };
+        assert_eq!("logs.us-east-1.amazonaws.com", api.client.authority());
+        let now: SystemTime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_440_938_160);
+        let body: &[u8] = br#"{"logGroupName":"g"}"#;
+
+        let headers = api.sign("PutLogEvents", body, now);
+        let expected: Vec<(&str, String)> = vec![
+            ("X-Amz-Date", "20150830T123600Z".into()),
+            ("X-Amz-Target", "Logs_20140328.PutLogEvents".into()),
+            (
+                "Authorization",
+                concat!(
+                    "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/logs/aws4_request, ",
+                    "SignedHeaders=content-type;host;x-amz-date;x-amz-target, ",
+                    "Signature=4a4571849a17a27a04fa6de0d1b4e99c895ce716412251a1dc73ac52d3d95ce7",
+                )
+                .into(),
+            ),
+        ];
+        assert_eq!(expected, headers);
+
+        api.credentials.session_token = Some("tok".into());
+        let headers = api.sign("PutLogEvents", body, now);
+        assert_eq!(("X-Amz-Security-Token", "tok".into()), headers[2]);
+        assert_eq!(
+            concat!(
+                "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/logs/aws4_request, ",
+                "SignedHeaders=content-type;host;x-amz-date;x-amz-security-token;x-amz-target, ",
+                "Signature=248966757685e0c6045dbf2f5b10670df540d551345dd3d9b5d7f1187c8697ea",
+            ),
+            headers[3].1
+        );
+    }
+}
@@ -0,0 +1,218 @@
+//! A log writer which compresses lines into a byte sink(requires the `gzip` or `zstd` feature).
+
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::{
+    write::{file::write_line, LogWrite, LogWriteBytes},
+    Severity,
+};
+
+/// A compression format.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum CompressionFormat {
+    /// gzip with a level(0-9).
+    #[cfg(feature = "gzip")]
+    Gzip(u32),
+    /// zstd with a level(1-22).
+    #[cfg(feature = "zstd")]
+    Zstd(i32),
+}
+
+enum Encoder {
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::write::GzEncoder<Vec<u8>>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::stream::write::Encoder<'static, Vec<u8>>),
+}
+
+impl Encoder {
+    fn new(format: CompressionFormat) -> io::Result<Self> {
+        match format {
+            #[cfg(feature = "gzip")]
+            CompressionFormat::Gzip(level) => Ok(Self::Gzip(flate2::write::GzEncoder::new(
+                vec![],
+                flate2::Compression::new(level.min(9)),
+            ))),
+            #[cfg(feature = "zstd")]
+            CompressionFormat::Zstd(level) => {
+                zstd::stream::write::Encoder::new(vec![], level).map(Self::Zstd)
+            }
+        }
+    }
+
+    fn writer(&mut self) -> &mut dyn Write {
+        match self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip(e) => e,
+            #[cfg(feature = "zstd")]
+            Self::Zstd(e) => e,
+        }
+    }
+
+    /// Gets the compressed bytes written so far.
+    fn output(&mut self) -> &mut Vec<u8> {
+        match self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip(e) => e.get_mut(),
+            #[cfg(feature = "zstd")]
+            Self::Zstd(e) => e.get_mut(),
+        }
+    }
+
+    /// Ends the stream(the gzip trailer or the zstd frame epilogue).
+    fn finish(self) -> io::Result<Vec<u8>> {
+        match self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip(e) => e.finish(),
+            #[cfg(feature = "zstd")]
+            Self::Zstd(e) => e.finish(),
+        }
+    }
+}
+
+struct CompressState {
+    encoder: Option<Encoder>,
+    last_flush: Instant,
+    /// The highest severity since the compressed bytes were last handed over.
+    level: Severity,
+}
+
+/// A log writer which compresses lines into one stream handed to a byte sink.
+pub struct CompressingWriter<B>
+where
+    B: LogWriteBytes,
+{
+    inner: B,
+    flush_interval: Duration,
+    flush_level: Option<Severity>,
+    state: Mutex<CompressState>,
+}
+
+impl<B> CompressingWriter<B>
+where
+    B: LogWriteBytes,
+{
+    /// Flushes the compressor and the sink after a line of this severity or
+    /// higher(default: Error; None disables it).
+    pub fn with_flush_severity(mut self, level: Option<Severity>) -> Self {
+        self.flush_level = level;
+        self
+    }
+
+    /// Hands the compressed bytes to the inner sink(after a sync flush if requested).
+    fn drain(&self, state: &mut CompressState, sync: bool) -> io::Result<()> {
+        let encoder: &mut Encoder = match &mut state.encoder {
+            None => return Ok(()),
+            Some(e) => e,
+        };
+        if sync {
+            encoder.writer().flush()?;
+            state.last_flush = Instant::now();
+        }
+        let output: &mut Vec<u8> = encoder.output();
+        if !output.is_empty() {
+            self.inner.write(output, state.level);
+            output.clear();
+            state.level = Severity::Trace;
+        }
+        Ok(())
+    }
+
+    fn write_locked(
+        &self,
+        state: &mut CompressState,
+        serialized: &str,
+        level: Severity,
+    ) -> io::Result<()> {
+        if let Some(e) = &mut state.encoder {
+            write_line(&mut e.writer(), serialized)?;
+        }
+        if u8::from(state.level) < u8::from(level) {
+            state.level = level;
+        }
+        let severe: bool = self.flush_level.is_some_and(|f: Severity| f <= level);
+        let due: bool = severe || self.flush_interval <= state.last_flush.elapsed();
+        self.drain(state, due)?;
+        match severe {
+            true => self.inner.flush(),
+            false => Ok(()),
+        }
+    }
+}
+
+impl<B> LogWrite for CompressingWriter<B>
+where
+    B: LogWriteBytes,
+{
+    fn write(&self, serialized: &str, level: Severity) {
+        if let Ok(mut guard) = self.state.lock() {
+            self.write_locked(&mut guard, serialized, level).ok();
+        }
+    }
+
+    /// Flushes the compressor(a sync flush) and the inner sink.
+    fn flush(&self) -> io::Result<()> {
+        match self.state.lock() {
+            Err(_) => Err(io::Error::other("lock poisoned")),
+            Ok(mut guard) => {
+                self.drain(&mut guard, true)?;
+                self.inner.flush()
+            }
+        }
+    }
+}
+
+impl<B> Drop for CompressingWriter<B>
+where
+    B: LogWriteBytes,
+{
+    /// Ends the stream so that the output is a complete gzip/zstd stream.
+    fn drop(&mut self) {
+        let state: &mut CompressState = match self.state.get_mut() {
+            Err(_) => return,
+            Ok(s) => s,
+        };
+        if let Some(Ok(rest)) = state.encoder.take().map(Encoder::finish) {
+            if !rest.is_empty() {
+                self.inner.write(&rest, state.level);
+            }
+            self.inner.flush().ok();
+        }
+    }
+}
+
+/// Creates a log writer which compresses lines into one stream handed to a byte sink.
+///
+/// The compressed bytes are handed to the sink as the compressor emits them.
+/// A sync flush is done after a line when the interval has elapsed since the
+/// last one(and on [`LogWrite::flush`]), so a reader of the stream(e.g.
+/// `zcat` on a growing file) can decode every line written before it. Lines of
+/// Error or higher are flushed at once(see
+/// [`CompressingWriter::with_flush_severity`]). The stream is ended when the
+/// writer is dropped.
+///
+/// # Arguments
+/// - inner: The sink of compressed bytes(e.g. a file or a socket).
+/// - format: The compression format.
+/// - flush_interval: The maximum time between sync flushes while lines are written.
+pub fn compressing_writer_new<B>(
+    inner: B,
+    format: CompressionFormat,
+    flush_interval: Duration,
+) -> io::Result<CompressingWriter<B>>
+where
+    B: LogWriteBytes,
+{
+    Ok(CompressingWriter {
+        inner,
+        flush_interval,
+        flush_level: Some(Severity::Error),
+        state: Mutex::new(CompressState {
+            encoder: Some(Encoder::new(format)?),
+            last_flush: Instant::now(),
+            level: Severity::Trace,
+        }),
+    })
+}
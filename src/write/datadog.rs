@@ -0,0 +1,198 @@
+//! A logger which sends items to the Datadog logs intake API(v2).
+
+use std::io;
+use std::time::Duration;
+
+#[cfg(feature = "tls")]
+use crate::write::net::tls::TlsConfig;
+use crate::{
+    copy::Logger,
+    serialize::{json::ObjectWriter, time},
+    write::{
+        http::{BatchFormat, Batcher, HttpClient, Target},
+        retry::RetryPolicy,
+        stats,
+        syslog::hostname,
+    },
+    Item, Severity,
+};
+
+/// The maximum number of logs in a request accepted by the intake API.
+pub const MAX_BATCH_SIZE: usize = 1000;
+
+static _RESERVED_KEYS: &[&str] = &[
+    "message",
+    "status",
+    "service",
+    "hostname",
+    "ddsource",
+    "ddtags",
+    "timestamp",
+];
+
+/// Maps a severity to a Datadog status.
+fn status(level: Severity) -> &'static str {
+    match level {
+        Severity::Trace | Severity::Debug => "debug",
+        Severity::Info => "info",
+        Severity::Warn => "warning",
+        Severity::Error => "error",
+        Severity::Fatal => "critical",
+    }
+}
+
+/// A logger which batches items as Datadog log events.
+pub struct DatadogLogger {
+    source: String,
+    host: String,
+    tags: Vec<String>,
+    batcher: Batcher<String>,
+}
+
+impl DatadogLogger {
+    /// Sets the `ddsource` field(default: `rust`).
+    pub fn with_source(mut self, source: &str) -> Self {
+        self.source = source.into();
+        self
+    }
+
+    /// Adds a tag to every event(e.g. `env:prod`).
+    pub fn with_tag(mut self, tag: &str) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// Compresses batches with gzip(requires the `gzip` feature).
+    #[cfg(feature = "gzip")]
+    pub fn with_gzip(self) -> Self {
+        self.batcher
+            .configure(|t: &mut Target<String>| {
+                t.content_encoding = Some("gzip");
+                t.encode = crate::write::http::gzip_encode(BatchFormat::JsonArray.encode());
+                Ok(())
+            })
+            .ok();
+        self
+    }
+
+    /// Sets the retries of a failed batch(connection errors, 429 and 5xx).
+    pub fn with_retry_policy(self, policy: RetryPolicy) -> Self {
+        self.batcher
+            .configure(|t: &mut Target<String>| {
+                t.retry = policy;
+                Ok(())
+            })
+            .ok();
+        self
+    }
+
+    /// Sets the maximum number of queued items(default: 8 batches).
+    pub fn with_max_pending(self, max_pending: usize) -> Self {
+        self.batcher.set_max_pending(max_pending);
+        self
+    }
+
+    /// Sends the queued items at once after an item of this severity or higher
+    /// is logged(default: Error; None disables it).
+    pub fn with_flush_severity(self, level: Option<Severity>) -> Self {
+        self.batcher.set_flush_severity(level);
+        self
+    }
+
+    /// Sets the trusted roots and the client certificate for `https` endpoints.
+    #[cfg(feature = "tls")]
+    pub fn with_tls_config(self, config: TlsConfig) -> io::Result<Self> {
+        self.batcher
+            .configure(|t: &mut Target<String>| t.client.set_tls_config(config))?;
+        Ok(self)
+    }
+}
+
+impl Logger for DatadogLogger {
+    fn log(&self, item: Item) {
+        let level: Severity = item.severity;
+        let mut tags: Vec<String> = self.tags.clone();
+        let mut service: Option<&str> = None;
+        let mut host: &str = &self.host;
+        for (key, val) in &item.resource {
+            match key.as_str() {
+                "service.name" => service = Some(val),
+                "host.name" if !val.is_empty() => host = val,
+                _ => tags.push(format!("{key}:{val}")),
+            }
+        }
+
+        let mut event: String = String::new();
+        let mut obj = ObjectWriter::begin(&mut event);
+        obj.str("message", &item.body.to_text());
+        obj.str("status", status(item.severity));
+        obj.str("ddsource", &self.source);
+        obj.str("hostname", host);
+        if let Some(service) = service {
+            obj.str("service", service);
+        }
+        if !tags.is_empty() {
+            obj.str("ddtags", &tags.join(","));
+        }
+        let millis: u128 = time::since_epoch(item.timestamp).as_millis();
+        obj.raw("timestamp", &millis.to_string());
+        let attributes = item
+            .attributes
+            .iter()
+            .filter(|(key, _)| !_RESERVED_KEYS.contains(&key.as_str()));
+        for (key, val) in attributes {
+            obj.str(key, val);
+        }
+        if let Some(trace_id) = &item.trace_id {
+            obj.str("trace_id", trace_id);
+        }
+        if let Some(span_id) = &item.span_id {
+            obj.str("span_id", span_id);
+        }
+        obj.end();
+        stats::count_queued(self.batcher.push(event, level));
+    }
+
+    /// Sends the queued items; fails if a batch was dropped since the last flush.
+    fn flush(&self) -> io::Result<()> {
+        self.batcher.flush()
+    }
+}
+
+/// Creates a logger which sends items to the Datadog logs intake in batches.
+///
+/// The body becomes `message` and the severity becomes `status`. The
+/// `service.name` and `host.name` resource fields become `service` and
+/// `hostname`; other resource fields become `ddtags`(`key:value`).
+/// Attributes and trace/span ids are added to the event. Batches(at most
+/// [`MAX_BATCH_SIZE`] items) are sent with the `DD-API-KEY` header like
+/// [`http_batch_writer_new`](crate::write::http::http_batch_writer_new).
+///
+/// # Arguments
+/// - endpoint: The intake URL(e.g. `"https://http-intake.logs.datadoghq.com"`); `/api/v2/logs` is appended.
+/// - api_key: The Datadog API key.
+/// - batch_size: The maximum number of items in a request.
+/// - flush_interval: The maximum time an item waits before being sent.
+pub fn datadog_logger_new(
+    endpoint: &str,
+    api_key: &str,
+    batch_size: usize,
+    flush_interval: Duration,
+) -> io::Result<DatadogLogger> {
+    let url: String = format!("{}/api/v2/logs", endpoint.trim_end_matches('/'));
+    let format: BatchFormat = BatchFormat::JsonArray;
+    let target: Target<String> = Target {
+        client: HttpClient::new(&url, &[("DD-API-KEY", api_key)])?,
+        content_type: format.content_type().into(),
+        content_encoding: None,
+        encode: format.encode(),
+        retry: RetryPolicy::default(),
+        check: None,
+    };
+    Ok(DatadogLogger {
+        source: "rust".into(),
+        host: hostname(),
+        tags: vec![],
+        batcher: Batcher::new(target, batch_size.min(MAX_BATCH_SIZE), flush_interval)?,
+    })
+}
@@ -0,0 +1,250 @@
+//! A log writer which keeps undeliverable records in a dead-letter file.
+//!
+//! Each line of the file is a JSON object:
+//! `{"time":"2026-01-02T03:04:05.678Z","severity":17,"error":"...","record":"..."}`
+//! where `severity` is the number of the [`Severity`] and `record` is the
+//! serialized record.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::{
+    serialize::{json::ObjectWriter, time::write_rfc3339},
+    write::{stats, LogWrite, TryLogWrite},
+    Severity,
+};
+
+/// Formats a dead letter as a line.
+fn dead_letter_line(serialized: &str, level: Severity, error: &io::Error) -> String {
+    let mut line: String = String::with_capacity(serialized.len() + 96);
+    let mut obj = ObjectWriter::begin(&mut line);
+    let time: &mut String = obj.key("time");
+    time.push('"');
+    write_rfc3339(SystemTime::now(), 3, time);
+    time.push('"');
+    obj.raw("severity", &u8::from(level).to_string());
+    obj.str("error", &error.to_string());
+    obj.str("record", serialized);
+    obj.end();
+    line.push('\n');
+    line
+}
+
+/// Reads the 4 hex digits of a `\u` escape; gets the code unit and the rest.
+fn read_hex4(s: &str) -> Option<(u32, &str)> {
+    let hex: &str = s.get(..4)?;
+    Some((u32::from_str_radix(hex, 16).ok()?, &s[4..]))
+}
+
+/// Reads a JSON string after the opening quote; gets the string and the rest.
+fn read_json_str(s: &str) -> Option<(String, &str)> {
+    let mut out: String = String::new();
+    let mut rest: &str = s;
+    loop {
+        let i: usize = rest.find(['"', '\\'])?;
+        out.push_str(&rest[..i]);
+        if rest[i..].starts_with('"') {
+            return Some((out, &rest[i + 1..]));
+        }
+        let escape: char = rest[i + 1..].chars().next()?;
+        rest = &rest[i + 1 + escape.len_utf8()..];
+        let c: char = match escape {
+            'b' => '\u{8}',
+            'f' => '\u{c}',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'u' => {
+                let (high, after) = read_hex4(rest)?;
+                rest = after;
+                let code: u32 = match (0xd800..0xdc00).contains(&high) {
+                    true => {
+                        let (low, after) = read_hex4(rest.strip_prefix("\\u")?)?;
+                        rest = after;
+                        match (0xdc00..0xe000).contains(&low) {
+                            true => 0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00),
+                            false => return None,
+                        }
+                    }
+                    false => high,
+                };
+                char::from_u32(code)?
+            }
+            other => other,
+        };
+        out.push(c);
+    }
+}
+
+/// Parses a line of a dead-letter file; gets the severity and the record.
+fn parse_dead_letter(line: &str) -> Option<(Severity, String)> {
+    let mut rest: &str = line.trim().strip_prefix('{')?;
+    let mut level: Option<u8> = None;
+    let mut record: Option<String> = None;
+    loop {
+        let (key, after) = read_json_str(rest.trim_start().strip_prefix('"')?)?;
+        rest = after.trim_start().strip_prefix(':')?.trim_start();
+        match rest.strip_prefix('"') {
+            Some(s) => {
+                let (val, after) = read_json_str(s)?;
+                rest = after;
+                if key == "record" {
+                    record = Some(val);
+                }
+            }
+            None => {
+                let end: usize = rest.find([',', '}']).unwrap_or(rest.len());
+                if key == "severity" {
+                    level = rest[..end].trim().parse().ok();
+                }
+                rest = &rest[end..];
+            }
+        }
+        rest = rest.trim_start();
+        match rest.chars().next()? {
+            ',' => rest = &rest[1..],
+            '}' => return Some((level?.into(), record?)),
+            _ => return None,
+        }
+    }
+}
+
+/// Writes the records of a dead-letter file until the writer fails.
+///
+/// The records which were not written are kept in the file; broken lines are
+/// dropped. Gets the number of written records.
+fn reingest_file<W>(file: &mut File, writer: &W) -> io::Result<u64>
+where
+    W: TryLogWrite,
+{
+    file.seek(SeekFrom::Start(0))?;
+    let mut reader = BufReader::new(file.try_clone()?);
+    let mut written: u64 = 0;
+    let mut kept: Vec<u8> = Vec::new();
+    let mut line: String = String::new();
+    loop {
+        line.clear();
+        if 0 == reader.read_line(&mut line)? {
+            break;
+        }
+        if !kept.is_empty() {
+            kept.extend_from_slice(line.as_bytes());
+            continue;
+        }
+        if let Some((level, record)) = parse_dead_letter(&line) {
+            match writer.try_write(&record, level) {
+                Ok(_) => written += 1,
+                Err(_) => kept.extend_from_slice(line.as_bytes()),
+            }
+        }
+    }
+    file.set_len(0)?;
+    file.write_all(&kept)?;
+    Ok(written)
+}
+
+fn open_dead_letter(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(path)
+}
+
+/// A log writer which appends records the inner writer fails to a dead-letter file.
+pub struct DeadLetterWriter<W> {
+    inner: W,
+    file: Mutex<File>,
+}
+
+impl<W> DeadLetterWriter<W>
+where
+    W: TryLogWrite,
+{
+    /// Writes the dead letters to the inner writer(e.g. once the sink is healthy again).
+    ///
+    /// Stops at the first record the inner writer fails and keeps it and the
+    /// following records in the file. Gets the number of written records.
+    pub fn reingest(&self) -> io::Result<u64> {
+        match self.file.lock() {
+            Err(_) => Err(io::Error::other("lock poisoned")),
+            Ok(mut guard) => reingest_file(&mut guard, &self.inner),
+        }
+    }
+}
+
+impl<W> TryLogWrite for DeadLetterWriter<W>
+where
+    W: TryLogWrite,
+{
+    /// Fails only if the record could not be appended to the dead-letter file either.
+    fn try_write(&self, serialized: &str, level: Severity) -> io::Result<()> {
+        let e: io::Error = match self.inner.try_write(serialized, level) {
+            Ok(_) => return Ok(()),
+            Err(e) => e,
+        };
+        let line: String = dead_letter_line(serialized, level, &e);
+        match self.file.lock() {
+            Err(_) => Err(io::Error::other("lock poisoned")),
+            Ok(mut guard) => guard.write_all(line.as_bytes()),
+        }
+    }
+}
+
+impl<W> LogWrite for DeadLetterWriter<W>
+where
+    W: TryLogWrite,
+{
+    fn write(&self, serialized: &str, level: Severity) {
+        stats::count_write(self.try_write(serialized, level), serialized, level);
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Creates a log writer which appends records the inner writer fails to a dead-letter file.
+///
+/// Put it outside of the retries and fallbacks of a network sink(e.g.
+/// [`crate::write::retry::retry_writer_new`]): a record still failing is
+/// appended to the file as NDJSON with the error(see the module
+/// documentation), so it can be inspected and re-ingested later by
+/// [`DeadLetterWriter::reingest`] or [`reingest_dead_letter_file`]. Unlike
+/// [`crate::write::spill::spill_writer_new`], later records are not held back.
+///
+/// # Arguments
+/// - inner: The fallible log writer.
+/// - path: The dead-letter file(created if missing).
+pub fn dead_letter_writer_new<W, P>(inner: W, path: P) -> io::Result<DeadLetterWriter<W>>
+where
+    W: TryLogWrite,
+    P: AsRef<Path>,
+{
+    Ok(DeadLetterWriter {
+        inner,
+        file: Mutex::new(open_dead_letter(path.as_ref())?),
+    })
+}
+
+/// Writes the records of a dead-letter file to a writer.
+///
+/// Stops at the first record the writer fails and keeps it and the following
+/// records in the file; broken lines are dropped. Do not use it for a file a
+/// [`DeadLetterWriter`] of another process is appending to. Gets the number of
+/// written records.
+///
+/// # Arguments
+/// - path: The dead-letter file(e.g. left by a previous process).
+/// - writer: Gets the records(e.g. the sink which is healthy again).
+pub fn reingest_dead_letter_file<P, W>(path: P, writer: &W) -> io::Result<u64>
+where
+    P: AsRef<Path>,
+    W: TryLogWrite,
+{
+    let mut file: File = open_dead_letter(path.as_ref())?;
+    reingest_file(&mut file, writer)
+}
@@ -0,0 +1,208 @@
+//! A logger which replaces repeated items with summaries.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::{copy::Logger, Body, Item, Severity};
+
+/// The last logged item.
+struct Last {
+    severity: Severity,
+    body: Body,
+    resource: BTreeMap<String, String>,
+    target: Option<String>,
+    /// When the item was logged.
+    logged: Instant,
+    /// The suppressed repeats since.
+    repeats: u64,
+}
+
+impl Last {
+    fn repeated_by(&self, item: &Item) -> bool {
+        self.severity == item.severity && self.body == item.body
+    }
+
+    /// Creates the summary of the suppressed repeats(if any) and resets them.
+    fn take_summary(&mut self) -> Option<Item> {
+        let repeats: u64 = std::mem::take(&mut self.repeats);
+        if 0 == repeats {
+            return None;
+        }
+        let mut summary = Item::new(
+            format!("last message repeated {repeats} times"),
+            BTreeMap::new(),
+        );
+        summary.severity = self.severity;
+        summary.resource = self.resource.clone();
+        summary.target = self.target.clone();
+        Some(summary)
+    }
+}
+
+struct DedupLogger<L>
+where
+    L: Logger,
+{
+    inner: L,
+    window: Duration,
+    last: Mutex<Option<Last>>,
+}
+
+impl<L> DedupLogger<L>
+where
+    L: Logger,
+{
+    fn log_summary(&self) {
+        if let Ok(mut guard) = self.last.lock() {
+            if let Some(summary) = guard.as_mut().and_then(Last::take_summary) {
+                self.inner.log(summary)
+            }
+        }
+    }
+}
+
+impl<L> Logger for DedupLogger<L>
+where
+    L: Logger,
+{
+    fn log(&self, item: Item) {
+        let mut guard = match self.last.lock() {
+            Ok(guard) => guard,
+            Err(_) => return self.inner.log(item),
+        };
+        let now: Instant = Instant::now();
+        if let Some(last) = guard.as_mut() {
+            let recent: bool = now.saturating_duration_since(last.logged) < self.window;
+            if recent && last.repeated_by(&item) {
+                last.repeats += 1;
+                return;
+            }
+            if let Some(summary) = last.take_summary() {
+                self.inner.log(summary)
+            }
+        }
+        *guard = Some(Last {
+            severity: item.severity,
+            body: item.body.clone(),
+            resource: item.resource.clone(),
+            target: item.target.clone(),
+            logged: now,
+            repeats: 0,
+        });
+        self.inner.log(item)
+    }
+
+    /// Logs the summary of the pending repeats(if any) and flushes.
+    fn flush(&self) -> io::Result<()> {
+        self.log_summary();
+        self.inner.flush()
+    }
+}
+
+impl<L> Drop for DedupLogger<L>
+where
+    L: Logger,
+{
+    fn drop(&mut self) {
+        self.log_summary()
+    }
+}
+
+/// Creates a logger which suppresses consecutive identical items.
+///
+/// An item with the same severity and body as the previous one is dropped if
+/// it arrives within `window` after the previous logged copy. The count of
+/// dropped items is logged as `last message repeated N times`(with the
+/// severity and the resource of the repeated item) before the next different
+/// item, the next copy after the window, a flush, or the drop of the logger.
+///
+/// # Arguments
+/// - inner: The logger of the kept items and the summaries.
+/// - window: The time after a logged item during which its copies are dropped.
+pub fn dedup_logger_new<L>(inner: L, window: Duration) -> impl Logger
+where
+    L: Logger,
+{
+    DedupLogger {
+        inner,
+        window,
+        last: Mutex::new(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::io;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use super::dedup_logger_new;
+    use crate::{copy::Logger, Item, Severity};
+
+    type Bodies = Arc<Mutex<Vec<String>>>;
+
+    /// Records the severities and bodies of logged items.
+    struct Recorder(Bodies);
+
+    impl Logger for Recorder {
+        fn log(&self, item: Item) {
+            let line: String = format!("{} {}", item.severity.as_str(), item.body.to_text());
+            self.0.lock().unwrap().push(line)
+        }
+
+        fn flush(&self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn item(body: &str, level: Severity) -> Item {
+        let mut item: Item = Item::new(body, BTreeMap::new());
+        item.severity = level;
+        item
+    }
+
+    #[test]
+    fn repeats_are_summarized_before_the_next_item() {
+        let bodies: Bodies = Arc::new(Mutex::new(vec![]));
+        let l = dedup_logger_new(Recorder(bodies.clone()), Duration::from_secs(3600));
+        for _ in 0..3 {
+            l.log(item("a", Severity::Info));
+        }
+        // Another severity is another item.
+        l.log(item("a", Severity::Warn));
+        l.log(item("b", Severity::Warn));
+        l.log(item("b", Severity::Warn));
+        l.flush().unwrap();
+        l.flush().unwrap();
+        l.log(item("b", Severity::Warn));
+        drop(l);
+        assert_eq!(
+            vec![
+                "info a",
+                "info last message repeated 2 times",
+                "warn a",
+                "warn b",
+                "warn last message repeated 1 times",
+                "warn last message repeated 1 times",
+            ],
+            *bodies.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn copies_after_the_window_are_logged() {
+        let bodies: Bodies = Arc::new(Mutex::new(vec![]));
+        let l = dedup_logger_new(Recorder(bodies.clone()), Duration::from_millis(50));
+        l.log(item("a", Severity::Info));
+        l.log(item("a", Severity::Info));
+        std::thread::sleep(Duration::from_millis(60));
+        l.log(item("a", Severity::Info));
+        assert_eq!(
+            vec!["info a", "info last message repeated 1 times", "info a"],
+            *bodies.lock().unwrap()
+        );
+    }
+}
@@ -0,0 +1,175 @@
+//! A logger which indexes items via the Elasticsearch/OpenSearch bulk API.
+
+use std::io;
+use std::time::Duration;
+
+#[cfg(feature = "tls")]
+use crate::write::net::tls::TlsConfig;
+use crate::{
+    copy::Logger,
+    serialize::{
+        base64, json,
+        time::{compile_strftime, write_strftime, TimeToken},
+        Serialize,
+    },
+    write::{
+        http::{Batcher, HttpClient, Response, Target},
+        net::truncate_str,
+        retry::RetryPolicy,
+        stats,
+    },
+    Item, Severity,
+};
+
+/// A document and its index.
+struct Document {
+    index: String,
+    source: String,
+}
+
+fn encode_bulk(batch: &[Document], buf: &mut Vec<u8>) {
+    let mut s: String = String::new();
+    for doc in batch {
+        s.push_str("{\"create\":{\"_index\":");
+        json::write_str(&doc.index, &mut s);
+        s.push_str("}}\n");
+        s.push_str(&doc.source);
+        s.push('\n');
+    }
+    buf.extend_from_slice(s.as_bytes())
+}
+
+/// Fails if the bulk response reports rejected documents(`"errors":true`).
+fn check_bulk(r: &Response) -> io::Result<()> {
+    let body = String::from_utf8_lossy(&r.body);
+    match body.contains("\"errors\":true") {
+        false => Ok(()),
+        true => Err(io::Error::other(format!(
+            "bulk request rejected documents: {}",
+            truncate_str(&body, 256)
+        ))),
+    }
+}
+
+/// A logger which sends items as bulk `create` actions.
+pub struct ElasticsearchLogger<S> {
+    serialize: S,
+    index: Vec<TimeToken>,
+    batcher: Batcher<Document>,
+}
+
+impl<S> ElasticsearchLogger<S> {
+    fn add_header(self, name: &str, val: &str) -> io::Result<Self> {
+        self.batcher
+            .configure(|t: &mut Target<Document>| t.client.add_header(name, val))?;
+        Ok(self)
+    }
+
+    /// Authenticates with a user name and a password.
+    pub fn with_basic_auth(self, user: &str, password: &str) -> io::Result<Self> {
+        let mut credentials: String = String::from("Basic ");
+        base64::write(format!("{user}:{password}").as_bytes(), &mut credentials);
+        self.add_header("Authorization", &credentials)
+    }
+
+    /// Authenticates with an API key(the base64 encoded `id:api_key`).
+    pub fn with_api_key(self, key: &str) -> io::Result<Self> {
+        self.add_header("Authorization", &format!("ApiKey {key}"))
+    }
+
+    /// Sets the retries of a failed batch(connection errors, 429 and 5xx).
+    pub fn with_retry_policy(self, policy: RetryPolicy) -> Self {
+        self.batcher
+            .configure(|t: &mut Target<Document>| {
+                t.retry = policy;
+                Ok(())
+            })
+            .ok();
+        self
+    }
+
+    /// Sets the maximum number of queued items(default: 8 batches).
+    pub fn with_max_pending(self, max_pending: usize) -> Self {
+        self.batcher.set_max_pending(max_pending);
+        self
+    }
+
+    /// Sends the queued items at once after an item of this severity or higher
+    /// is logged(default: Error; None disables it).
+    pub fn with_flush_severity(self, level: Option<Severity>) -> Self {
+        self.batcher.set_flush_severity(level);
+        self
+    }
+
+    /// Sets the trusted roots and the client certificate for `https` endpoints.
+    #[cfg(feature = "tls")]
+    pub fn with_tls_config(self, config: TlsConfig) -> io::Result<Self> {
+        self.batcher
+            .configure(|t: &mut Target<Document>| t.client.set_tls_config(config))?;
+        Ok(self)
+    }
+}
+
+impl<S> Logger for ElasticsearchLogger<S>
+where
+    S: Serialize,
+{
+    fn log(&self, item: Item) {
+        let level: Severity = item.severity;
+        let mut index: String = String::new();
+        write_strftime(item.timestamp, &self.index, &mut index);
+        let mut source: String = String::new();
+        self.serialize.serialize(&item, &mut source);
+        let len: usize = source.trim_end_matches('\n').len();
+        source.truncate(len);
+        stats::count_queued(self.batcher.push(Document { index, source }, level));
+    }
+
+    /// Sends the queued items; fails if a batch was dropped or partially rejected.
+    fn flush(&self) -> io::Result<()> {
+        self.batcher.flush()
+    }
+}
+
+/// Creates a logger which indexes items in batches via the `_bulk` API.
+///
+/// Each item is serialized as a JSON document(e.g. by
+/// [`ecs_serializer_new`](crate::serialize::ecs::ecs_serializer_new)) and
+/// sent as a `create` action, which works for both indices and data streams.
+/// The index name is formatted from the item timestamp(UTC). A response with
+/// `"errors":true` is reported by the next flush. Batches are sent like
+/// [`http_batch_writer_new`](crate::write::http::http_batch_writer_new).
+///
+/// # Arguments
+/// - endpoint: The cluster URL(e.g. `"http://127.0.0.1:9200"`).
+/// - index: The index name pattern(e.g. `"logs-%Y.%m.%d"`).
+/// - serialize: Serializes a document.
+/// - batch_size: The maximum number of items in a request.
+/// - flush_interval: The maximum time an item waits before being sent.
+pub fn elasticsearch_logger_new<S>(
+    endpoint: &str,
+    index: &str,
+    serialize: S,
+    batch_size: usize,
+    flush_interval: Duration,
+) -> io::Result<ElasticsearchLogger<S>>
+where
+    S: Serialize,
+{
+    let index: Vec<TimeToken> =
+        compile_strftime(index).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let url: String = format!("{}/_bulk", endpoint.trim_end_matches('/'));
+    let target: Target<Document> = Target {
+        client: HttpClient::new(&url, &[])?,
+        content_type: "application/x-ndjson".into(),
+        content_encoding: None,
+        encode: Box::new(encode_bulk),
+        retry: RetryPolicy::default(),
+        check: Some(check_bulk),
+    };
+    Ok(ElasticsearchLogger {
+        serialize,
+        index,
+        batcher: Batcher::new(target, batch_size, flush_interval)?,
+    })
+}
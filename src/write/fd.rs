@@ -0,0 +1,67 @@
+//! Log writers for inherited file descriptors and handles.
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+use crate::{
+    write::{stats, LogWrite, TryLogWrite},
+    Severity,
+};
+
+/// An unbuffered writer; each line is a single write when possible.
+struct DescriptorWriter {
+    file: Mutex<File>,
+}
+
+impl TryLogWrite for DescriptorWriter {
+    fn try_write(&self, serialized: &str, _level: Severity) -> io::Result<()> {
+        let mut line: String = String::with_capacity(serialized.len() + 1);
+        line.push_str(serialized);
+        if !line.ends_with('\n') {
+            line.push('\n');
+        }
+        match self.file.lock() {
+            Err(_) => Err(io::Error::other("lock poisoned")),
+            Ok(mut guard) => guard.write_all(line.as_bytes()),
+        }
+    }
+}
+
+impl LogWrite for DescriptorWriter {
+    fn write(&self, serialized: &str, level: Severity) {
+        stats::count_write(self.try_write(serialized, level), serialized, level);
+    }
+}
+
+/// Creates an unbuffered log writer which writes lines to an inherited file descriptor.
+///
+/// The descriptor is opened again through `/dev/fd`(in append mode), so it is
+/// not closed by the writer. Pipes, terminals and files are supported; on
+/// Linux sockets cannot be opened this way. Lines up to `PIPE_BUF` bytes are
+/// written atomically to pipes.
+///
+/// # Arguments
+/// - fd: An open descriptor(e.g. 3 passed by a supervisor).
+#[cfg(unix)]
+pub fn fd_writer_new(fd: std::os::fd::RawFd) -> io::Result<impl TryLogWrite> {
+    let file: File = File::options().append(true).open(format!("/dev/fd/{fd}"))?;
+    Ok(DescriptorWriter {
+        file: Mutex::new(file),
+    })
+}
+
+/// Creates an unbuffered log writer which writes lines to an inherited handle.
+///
+/// An inherited raw handle can be claimed with
+/// [`std::os::windows::io::FromRawHandle::from_raw_handle`]; the handle is
+/// closed when the writer is dropped.
+///
+/// # Arguments
+/// - handle: An open handle(e.g. a pipe passed by a supervisor).
+#[cfg(windows)]
+pub fn handle_writer_new(handle: std::os::windows::io::OwnedHandle) -> impl TryLogWrite {
+    DescriptorWriter {
+        file: Mutex::new(File::from(handle)),
+    }
+}
@@ -0,0 +1,417 @@
+//! File log writers.
+
+use std::fs::{File, Metadata, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::{
+    write::{stats, LogWrite, TryLogWrite},
+    Severity,
+};
+
+/// Writes a serialized log item with a trailing newline(if missing).
+pub(crate) fn write_line<W>(w: &mut W, serialized: &str) -> io::Result<()>
+where
+    W: Write,
+{
+    w.write_all(serialized.as_bytes())?;
+    match serialized.ends_with('\n') {
+        true => Ok(()),
+        false => w.write_all(b"\n"),
+    }
+}
+
+/// A durability policy: when buffered lines are flushed and synced to the disk.
+#[derive(PartialEq, Eq, Clone, Copy, Default)]
+pub enum SyncPolicy {
+    /// Leaves it to the OS(default).
+    #[default]
+    Never,
+    /// Syncs after every line.
+    EveryWrite,
+    /// Syncs after every n lines.
+    EveryWrites(u32),
+    /// Syncs after a line when the interval has elapsed since the last sync.
+    Interval(Duration),
+    /// Syncs after a line with a severity higher than or equal to this(e.g. Error).
+    OnSeverity(Severity),
+}
+
+/// Applies a [`SyncPolicy`] to a buffered file.
+pub(crate) struct Syncer {
+    policy: SyncPolicy,
+    writes: u32,
+    last: Instant,
+}
+
+impl Syncer {
+    pub(crate) fn new(policy: SyncPolicy) -> Self {
+        Self {
+            policy,
+            writes: 0,
+            last: Instant::now(),
+        }
+    }
+
+    /// Syncs the file if the policy requires it after a line.
+    pub(crate) fn wrote(&mut self, file: &mut BufWriter<File>, level: Severity) -> io::Result<()> {
+        let due: bool = match self.policy {
+            SyncPolicy::Never => false,
+            SyncPolicy::EveryWrite => true,
+            SyncPolicy::EveryWrites(n) => {
+                self.writes += 1;
+                n <= self.writes
+            }
+            SyncPolicy::Interval(i) => i <= self.last.elapsed(),
+            SyncPolicy::OnSeverity(lb) => u8::from(lb) <= u8::from(level),
+        };
+        match due {
+            true => self.sync(file),
+            false => Ok(()),
+        }
+    }
+
+    /// Flushes the file before it gets closed(synced unless the policy is Never).
+    pub(crate) fn closing(&mut self, file: &mut BufWriter<File>) -> io::Result<()> {
+        match self.policy {
+            SyncPolicy::Never => file.flush(),
+            _ => self.sync(file),
+        }
+    }
+
+    fn sync(&mut self, file: &mut BufWriter<File>) -> io::Result<()> {
+        file.flush()?;
+        file.get_ref().sync_data()?;
+        self.writes = 0;
+        self.last = Instant::now();
+        Ok(())
+    }
+}
+
+struct FileState {
+    file: BufWriter<File>,
+    syncer: Syncer,
+}
+
+/// A buffered log writer which appends lines to a file.
+pub struct FileWriter {
+    internal: Mutex<FileState>,
+}
+
+impl FileWriter {
+    /// Sets the durability policy(default: [`SyncPolicy::Never`]).
+    pub fn with_sync_policy(self, policy: SyncPolicy) -> Self {
+        let mut state: FileState = self
+            .internal
+            .into_inner()
+            .unwrap_or_else(|e| e.into_inner());
+        state.syncer = Syncer::new(policy);
+        Self {
+            internal: Mutex::new(state),
+        }
+    }
+}
+
+impl TryLogWrite for FileWriter {
+    fn try_write(&self, serialized: &str, level: Severity) -> io::Result<()> {
+        match self.internal.lock() {
+            Err(_) => Err(io::Error::other("lock poisoned")),
+            Ok(mut guard) => {
+                let state: &mut FileState = &mut guard;
+                write_line(&mut state.file, serialized)?;
+                state.syncer.wrote(&mut state.file, level)
+            }
+        }
+    }
+}
+
+impl LogWrite for FileWriter {
+    fn write(&self, serialized: &str, level: Severity) {
+        stats::count_write(self.try_write(serialized, level), serialized, level);
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        match self.internal.lock() {
+            Err(_) => Err(io::Error::other("lock poisoned")),
+            Ok(mut guard) => guard.file.flush(),
+        }
+    }
+}
+
+/// Opens a file for appending(creates it if missing).
+pub(crate) fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// Creates a buffered log writer which appends lines to a file.
+///
+/// Buffered lines are written when the buffer is full, on [`LogWrite::flush`]
+/// or when the writer is dropped.
+///
+/// # Arguments
+/// - path: The log file(created if missing).
+pub fn file_writer_new<P>(path: P) -> io::Result<FileWriter>
+where
+    P: AsRef<Path>,
+{
+    let f: File = open_append(path.as_ref())?;
+    Ok(FileWriter {
+        internal: Mutex::new(FileState {
+            file: BufWriter::new(f),
+            syncer: Syncer::new(SyncPolicy::Never),
+        }),
+    })
+}
+
+/// Checks if two metadata refer to the same file.
+#[cfg(unix)]
+fn same_file(open: &Metadata, current: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    open.dev() == current.dev() && open.ino() == current.ino()
+}
+
+/// Checks if two metadata refer to the same file(a shorter file is a new one).
+#[cfg(not(unix))]
+fn same_file(open: &Metadata, current: &Metadata) -> bool {
+    open.len() <= current.len()
+}
+
+struct ReopenState {
+    file: BufWriter<File>,
+    syncer: Syncer,
+    checked: Instant,
+}
+
+/// A buffered log writer which reopens its path when the file is moved away.
+pub struct ReopeningFileWriter {
+    path: PathBuf,
+    interval: Duration,
+    state: Mutex<ReopenState>,
+}
+
+impl ReopeningFileWriter {
+    fn reopen_locked(&self, state: &mut ReopenState) -> io::Result<()> {
+        state.syncer.closing(&mut state.file)?;
+        state.file = BufWriter::new(open_append(&self.path)?);
+        Ok(())
+    }
+
+    fn replaced(&self, state: &ReopenState) -> io::Result<bool> {
+        match std::fs::metadata(&self.path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(true),
+            Err(e) => Err(e),
+            Ok(current) => Ok(!same_file(&state.file.get_ref().metadata()?, &current)),
+        }
+    }
+
+    fn write_locked(
+        &self,
+        state: &mut ReopenState,
+        serialized: &str,
+        level: Severity,
+    ) -> io::Result<()> {
+        if self.interval <= state.checked.elapsed() {
+            state.checked = Instant::now();
+            if self.replaced(state)? {
+                self.reopen_locked(state)?;
+            }
+        }
+        write_line(&mut state.file, serialized)?;
+        state.syncer.wrote(&mut state.file, level)
+    }
+
+    /// Sets the durability policy(default: [`SyncPolicy::Never`]).
+    pub fn with_sync_policy(self, policy: SyncPolicy) -> Self {
+        let mut state: ReopenState = self.state.into_inner().unwrap_or_else(|e| e.into_inner());
+        state.syncer = Syncer::new(policy);
+        Self {
+            state: Mutex::new(state),
+            ..self
+        }
+    }
+
+    /// Flushes buffered lines and reopens the path(e.g. from a logrotate hook).
+    pub fn reopen(&self) -> io::Result<()> {
+        match self.state.lock() {
+            Err(_) => Err(io::Error::other("lock poisoned")),
+            Ok(mut guard) => self.reopen_locked(&mut guard),
+        }
+    }
+}
+
+impl TryLogWrite for ReopeningFileWriter {
+    fn try_write(&self, serialized: &str, level: Severity) -> io::Result<()> {
+        match self.state.lock() {
+            Err(_) => Err(io::Error::other("lock poisoned")),
+            Ok(mut guard) => self.write_locked(&mut guard, serialized, level),
+        }
+    }
+}
+
+impl LogWrite for ReopeningFileWriter {
+    fn write(&self, serialized: &str, level: Severity) {
+        stats::count_write(self.try_write(serialized, level), serialized, level);
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        match self.state.lock() {
+            Err(_) => Err(io::Error::other("lock poisoned")),
+            Ok(mut guard) => guard.file.flush(),
+        }
+    }
+}
+
+/// Creates a buffered log writer which follows renames by external tools(e.g. logrotate).
+///
+/// At most once per `check_interval`, the path is compared with the open file
+/// (device and inode on unix); when it was moved or deleted, buffered lines go
+/// to the old file and the path is reopened. [`ReopeningFileWriter::reopen`]
+/// forces it.
+///
+/// # Arguments
+/// - path: The log file(created if missing).
+/// - check_interval: The minimum interval between checks(zero: every write).
+pub fn reopening_file_writer_new<P>(
+    path: P,
+    check_interval: Duration,
+) -> io::Result<ReopeningFileWriter>
+where
+    P: AsRef<Path>,
+{
+    let path: PathBuf = path.as_ref().into();
+    let f: File = open_append(&path)?;
+    Ok(ReopeningFileWriter {
+        path,
+        interval: check_interval,
+        state: Mutex::new(ReopenState {
+            file: BufWriter::new(f),
+            syncer: Syncer::new(SyncPolicy::Never),
+            checked: Instant::now(),
+        }),
+    })
+}
+
+struct AtomicLineWriter {
+    file: File,
+}
+
+impl TryLogWrite for AtomicLineWriter {
+    fn try_write(&self, serialized: &str, _level: Severity) -> io::Result<()> {
+        let record: &str = serialized.trim_end_matches('\n');
+        if record.contains('\n') {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "a record must be a single line",
+            ));
+        }
+        let mut line: Vec<u8> = Vec::with_capacity(record.len() + 1);
+        line.extend_from_slice(record.as_bytes());
+        line.push(b'\n');
+        let written: usize = (&self.file).write(&line)?;
+        match written == line.len() {
+            true => Ok(()),
+            false => {
+                // Completes the line so that the next line starts on its own.
+                (&self.file).write_all(&line[written..])?;
+                Err(io::Error::new(
+                    io::ErrorKind::Interrupted,
+                    "a line was written by more than one write",
+                ))
+            }
+        }
+    }
+}
+
+impl LogWrite for AtomicLineWriter {
+    fn write(&self, serialized: &str, level: Severity) {
+        stats::count_write(self.try_write(serialized, level), serialized, level);
+    }
+
+    /// Syncs the written lines to the disk(lines are not buffered).
+    fn flush(&self) -> io::Result<()> {
+        self.file.sync_data()
+    }
+}
+
+/// Creates an unbuffered log writer which appends each line to a file with a single `write`.
+///
+/// The file is opened in append mode and each record is written with its
+/// newline at once without a lock, so lines of threads and processes
+/// appending to the same local file do not interleave(NFS is not supported).
+/// Records with newlines are rejected, so each line is one record(e.g. NDJSON
+/// with the JSON serializer; see [`locking_file_writer_new`] for other
+/// records). A short write(e.g. disk full) is completed by
+/// another write and reported as an error.
+///
+/// # Arguments
+/// - path: The log file(created if missing).
+pub fn ndjson_writer_new<P>(path: P) -> io::Result<impl TryLogWrite>
+where
+    P: AsRef<Path>,
+{
+    Ok(AtomicLineWriter {
+        file: open_append(path.as_ref())?,
+    })
+}
+
+struct LockingFileWriter {
+    /// The lock of the threads(an advisory lock is shared by the threads of a process).
+    file: Mutex<File>,
+}
+
+impl LockingFileWriter {
+    fn write_locked(file: &mut File, line: &[u8]) -> io::Result<()> {
+        file.lock()?;
+        let written: io::Result<()> = file.write_all(line);
+        let unlocked: io::Result<()> = file.unlock();
+        written.and(unlocked)
+    }
+}
+
+impl TryLogWrite for LockingFileWriter {
+    fn try_write(&self, serialized: &str, _level: Severity) -> io::Result<()> {
+        let mut line: Vec<u8> = Vec::with_capacity(serialized.len() + 1);
+        write_line(&mut line, serialized)?;
+        match self.file.lock() {
+            Err(_) => Err(io::Error::other("lock poisoned")),
+            Ok(mut guard) => Self::write_locked(&mut guard, &line),
+        }
+    }
+}
+
+impl LogWrite for LockingFileWriter {
+    fn write(&self, serialized: &str, level: Severity) {
+        stats::count_write(self.try_write(serialized, level), serialized, level);
+    }
+
+    /// Syncs the written lines to the disk(lines are not buffered).
+    fn flush(&self) -> io::Result<()> {
+        match self.file.lock() {
+            Err(_) => Err(io::Error::other("lock poisoned")),
+            Ok(guard) => guard.sync_data(),
+        }
+    }
+}
+
+/// Creates an unbuffered log writer which appends each record under an advisory file lock.
+///
+/// Each record(with a newline added if missing) is appended while holding an
+/// exclusive lock of the file(`flock` on unix, `LockFileEx` on windows), so
+/// records of processes using this writer for the same file never interleave,
+/// even multi-line records or records too large for a single `write`. The
+/// lock is advisory: processes appending without it(e.g.
+/// [`ndjson_writer_new`] or `echo >>`) are not excluded. Writes wait while
+/// another process holds the lock.
+///
+/// # Arguments
+/// - path: The log file(created if missing).
+pub fn locking_file_writer_new<P>(path: P) -> io::Result<impl TryLogWrite>
+where
+    P: AsRef<Path>,
+{
+    Ok(LockingFileWriter {
+        file: Mutex::new(open_append(path.as_ref())?),
+    })
+}
@@ -0,0 +1,158 @@
+//! A log writer for the Fluentd Forward protocol.
+
+use std::borrow::Cow;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::serialize::{base64, msgpack::write_str};
+use crate::{
+    write::{
+        net::{tcp_connect, DEFAULT_TIMEOUT},
+        LogWriteBytes,
+    },
+    Severity,
+};
+
+/// A log writer which sends Forward protocol messages over TCP.
+pub struct FluentWriter {
+    addrs: Vec<SocketAddr>,
+    ack: Option<Duration>,
+    stream: Mutex<Option<TcpStream>>,
+    chunks: AtomicU64,
+}
+
+impl FluentWriter {
+    /// Waits for the ack of each message(the `require_ack_response` mode).
+    ///
+    /// A message which is not acked in time is sent again once on a new
+    /// connection, so it may be received twice.
+    pub fn with_ack(mut self, timeout: Duration) -> Self {
+        self.ack = Some(timeout);
+        self
+    }
+
+    /// Gets a new random chunk id(base64 of 16 bytes).
+    fn chunk_id(&self) -> String {
+        let n: u64 = self.chunks.fetch_add(1, Ordering::Relaxed);
+        let mut bytes: Vec<u8> = Vec::with_capacity(16);
+        for salt in [0u64, 1] {
+            let mut h = RandomState::new().build_hasher();
+            h.write_u64(n);
+            h.write_u64(salt);
+            bytes.extend_from_slice(&h.finish().to_be_bytes());
+        }
+        let mut id: String = String::new();
+        base64::write(&bytes, &mut id);
+        id
+    }
+
+    fn send_locked(
+        &self,
+        stream: &mut Option<TcpStream>,
+        message: &[u8],
+        ack: Option<(&str, Duration)>,
+    ) -> io::Result<()> {
+        let s: &mut TcpStream = match stream {
+            Some(s) => s,
+            None => stream.insert(tcp_connect(&self.addrs.as_slice(), DEFAULT_TIMEOUT)?),
+        };
+        s.write_all(message)?;
+        match ack {
+            None => Ok(()),
+            Some((chunk, timeout)) => wait_ack(s, chunk, timeout),
+        }
+    }
+
+    /// Sends a message made by [`fluent_serializer_new`](crate::serialize::fluent::fluent_serializer_new).
+    ///
+    /// Connects on the first send and after a failure(retrying once).
+    pub fn send(&self, message: &[u8]) -> io::Result<()> {
+        let chunk: Option<String> = self.ack.map(|_| self.chunk_id());
+        let message: Cow<[u8]> = match &chunk {
+            None => Cow::Borrowed(message),
+            Some(chunk) => Cow::Owned(with_chunk_option(message, chunk)?),
+        };
+        let ack: Option<(&str, Duration)> = chunk.as_deref().zip(self.ack);
+        let mut guard = self
+            .stream
+            .lock()
+            .map_err(|_| io::Error::other("lock poisoned"))?;
+        let first: io::Result<()> = self.send_locked(&mut guard, &message, ack);
+        if first.is_ok() {
+            return first;
+        }
+        *guard = None;
+        let second: io::Result<()> = self.send_locked(&mut guard, &message, ack);
+        if second.is_err() {
+            *guard = None;
+        }
+        second
+    }
+}
+
+/// Appends the option map(`{"chunk": id}`) to a `[tag, time, record]` message.
+fn with_chunk_option(message: &[u8], chunk: &str) -> io::Result<Vec<u8>> {
+    match message.first() {
+        Some(0x93) => {
+            let mut buf: Vec<u8> = Vec::with_capacity(message.len() + 40);
+            buf.push(0x94);
+            buf.extend_from_slice(&message[1..]);
+            buf.push(0x81);
+            write_str("chunk", &mut buf);
+            write_str(chunk, &mut buf);
+            Ok(buf)
+        }
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "not a forward protocol message",
+        )),
+    }
+}
+
+/// Reads the ack response(`{"ack": id}`).
+fn wait_ack(s: &mut TcpStream, chunk: &str, timeout: Duration) -> io::Result<()> {
+    let mut expected: Vec<u8> = vec![0x81];
+    write_str("ack", &mut expected);
+    write_str(chunk, &mut expected);
+    let mut response: Vec<u8> = vec![0; expected.len()];
+    s.set_read_timeout(Some(timeout))?;
+    s.read_exact(&mut response)?;
+    match response == expected {
+        true => Ok(()),
+        false => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unexpected ack response",
+        )),
+    }
+}
+
+impl LogWriteBytes for FluentWriter {
+    fn write(&self, serialized: &[u8], _level: Severity) {
+        self.send(serialized).ok();
+    }
+}
+
+/// Creates a log writer which sends messages to Fluentd/Fluent Bit(`in_forward`).
+///
+/// Use it with [`fluent_serializer_new`](crate::serialize::fluent::fluent_serializer_new)
+/// and [`logger_new_bytes`](crate::copy::logger_new_bytes). The address is
+/// resolved once; sends block while connecting(up to [`DEFAULT_TIMEOUT`]).
+///
+/// # Arguments
+/// - addr: The address of the forward input(e.g. `"127.0.0.1:24224"`).
+pub fn fluent_writer_new<A>(addr: A) -> io::Result<FluentWriter>
+where
+    A: ToSocketAddrs,
+{
+    Ok(FluentWriter {
+        addrs: addr.to_socket_addrs()?.collect(),
+        ack: None,
+        stream: Mutex::new(None),
+        chunks: AtomicU64::new(0),
+    })
+}
@@ -0,0 +1,148 @@
+//! A log writer which sends GELF messages over UDP(with chunking).
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{
+    write::{net::udp_connect, stats, LogWrite, TryLogWrite},
+    Severity,
+};
+
+/// The default maximum datagram size(fits a WAN path MTU).
+pub const DEFAULT_CHUNK_SIZE: usize = 1420;
+
+const CHUNK_MAGIC: [u8; 2] = [0x1e, 0x0f];
+const CHUNK_HEADER_LEN: usize = 12;
+const MAX_CHUNKS: usize = 128;
+
+/// The compression of GELF payloads(requires the `gzip` feature).
+#[cfg(feature = "gzip")]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum GelfCompression {
+    Zlib,
+    Gzip,
+}
+
+#[cfg(feature = "gzip")]
+impl GelfCompression {
+    fn compress(self, payload: &[u8]) -> io::Result<Vec<u8>> {
+        use flate2::{
+            write::{GzEncoder, ZlibEncoder},
+            Compression,
+        };
+        use std::io::Write;
+
+        match self {
+            Self::Zlib => {
+                let mut enc = ZlibEncoder::new(Vec::new(), Compression::default());
+                enc.write_all(payload)?;
+                enc.finish()
+            }
+            Self::Gzip => {
+                let mut enc = GzEncoder::new(Vec::new(), Compression::default());
+                enc.write_all(payload)?;
+                enc.finish()
+            }
+        }
+    }
+}
+
+/// A log writer which sends each GELF message as datagrams.
+pub struct GelfUdpWriter {
+    socket: UdpSocket,
+    chunk_size: usize,
+    #[cfg(feature = "gzip")]
+    compression: Option<GelfCompression>,
+    messages: AtomicU64,
+}
+
+impl GelfUdpWriter {
+    /// Sets the maximum datagram size(e.g. 8192 on a LAN); larger messages are chunked.
+    pub fn with_chunk_size(mut self, size: usize) -> Self {
+        self.chunk_size = size.max(CHUNK_HEADER_LEN + 1);
+        self
+    }
+
+    /// Compresses messages before chunking(no compression by default).
+    #[cfg(feature = "gzip")]
+    pub fn with_compression(mut self, compression: GelfCompression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Gets a new random message id.
+    fn message_id(&self) -> [u8; 8] {
+        let mut h = RandomState::new().build_hasher();
+        h.write_u64(self.messages.fetch_add(1, Ordering::Relaxed));
+        h.finish().to_be_bytes()
+    }
+
+    /// Sends a payload as one datagram or as up to 128 chunks.
+    pub fn send(&self, payload: &[u8]) -> io::Result<()> {
+        if payload.len() <= self.chunk_size {
+            return self.socket.send(payload).map(|_| ());
+        }
+        let data_len: usize = self.chunk_size - CHUNK_HEADER_LEN;
+        let count: usize = payload.len().div_ceil(data_len);
+        if MAX_CHUNKS < count {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "message larger than 128 GELF chunks",
+            ));
+        }
+        let id: [u8; 8] = self.message_id();
+        let mut chunk: Vec<u8> = Vec::with_capacity(self.chunk_size);
+        for (seq, data) in payload.chunks(data_len).enumerate() {
+            chunk.clear();
+            chunk.extend_from_slice(&CHUNK_MAGIC);
+            chunk.extend_from_slice(&id);
+            chunk.push(seq as u8);
+            chunk.push(count as u8);
+            chunk.extend_from_slice(data);
+            self.socket.send(&chunk)?;
+        }
+        Ok(())
+    }
+}
+
+impl TryLogWrite for GelfUdpWriter {
+    fn try_write(&self, serialized: &str, _level: Severity) -> io::Result<()> {
+        let payload: &[u8] = serialized.trim_end_matches('\n').as_bytes();
+        #[cfg(feature = "gzip")]
+        if let Some(c) = self.compression {
+            return self.send(&c.compress(payload)?);
+        }
+        self.send(payload)
+    }
+}
+
+impl LogWrite for GelfUdpWriter {
+    fn write(&self, serialized: &str, level: Severity) {
+        stats::count_write(self.try_write(serialized, level), serialized, level);
+    }
+}
+
+/// Creates a log writer which sends GELF messages to Graylog over UDP.
+///
+/// Use it with [`gelf_serializer_new`](crate::serialize::gelf::gelf_serializer_new).
+/// Messages larger than the chunk size([`DEFAULT_CHUNK_SIZE`]) are split
+/// using the GELF chunking protocol; messages needing more than 128 chunks
+/// are rejected.
+///
+/// # Arguments
+/// - addr: The address of the GELF UDP input(e.g. `"graylog:12201"`).
+pub fn gelf_udp_writer_new<A>(addr: A) -> io::Result<GelfUdpWriter>
+where
+    A: ToSocketAddrs,
+{
+    Ok(GelfUdpWriter {
+        socket: udp_connect(addr)?,
+        chunk_size: DEFAULT_CHUNK_SIZE,
+        #[cfg(feature = "gzip")]
+        compression: None,
+        messages: AtomicU64::new(0),
+    })
+}
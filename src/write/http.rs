@@ -0,0 +1,789 @@
+//! A log writer which ships batches of records over HTTP.
+
+use std::io::{self, Read, Write};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+pub(crate) mod snappy;
+
+#[cfg(feature = "tls")]
+use crate::write::net::tls::{tls_connect, TlsConfig};
+use crate::{
+    write::{
+        net::{tcp_connect, truncate_str, DEFAULT_TIMEOUT},
+        nonblocking::{queue_full, OverflowPolicy},
+        retry::{jitter, RetryPolicy},
+        stats, LogWrite, TryLogWrite,
+    },
+    Severity,
+};
+
+fn invalid_input(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+/// A parsed `http://` or `https://` URL.
+struct Endpoint {
+    tls: bool,
+    host: String,
+    port: u16,
+    /// The `Host` header value.
+    authority: String,
+    path: String,
+}
+
+impl Endpoint {
+    fn parse(url: &str) -> io::Result<Self> {
+        let invalid = || invalid_input(format!("invalid endpoint: {url}"));
+        let (tls, rest) = match url.split_once("://") {
+            Some(("http", rest)) => (false, rest),
+            Some(("https", rest)) => (true, rest),
+            _ => return Err(invalid()),
+        };
+        let (authority, path) = match rest.find('/') {
+            None => (rest, "/"),
+            Some(i) => (&rest[..i], &rest[i..]),
+        };
+        let authority: &str = authority.rsplit('@').next().unwrap_or(authority);
+        let (host, port) = match authority.rfind(':') {
+            Some(i) if !authority[i..].contains(']') => {
+                let port: u16 = authority[i + 1..].parse().map_err(|_| invalid())?;
+                (&authority[..i], port)
+            }
+            _ => (authority, if tls { 443 } else { 80 }),
+        };
+        let host: &str = host.trim_start_matches('[').trim_end_matches(']');
+        if host.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self {
+            tls,
+            host: host.into(),
+            port,
+            authority: authority.into(),
+            path: path.into(),
+        })
+    }
+}
+
+/// A response of a request.
+pub(crate) struct Response {
+    pub(crate) status: u16,
+    pub(crate) body: Vec<u8>,
+}
+
+/// Decodes a `Transfer-Encoding: chunked` body(a broken tail is dropped).
+fn decode_chunked(mut raw: &[u8]) -> Vec<u8> {
+    let mut body: Vec<u8> = Vec::new();
+    while let Some(eol) = raw.windows(2).position(|w| w == b"\r\n") {
+        let line: &str = std::str::from_utf8(&raw[..eol]).unwrap_or("");
+        let size: &str = line.split(';').next().unwrap_or("").trim();
+        let size: usize = match usize::from_str_radix(size, 16) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        let data: &[u8] = &raw[eol + 2..];
+        let size: usize = size.min(data.len());
+        body.extend_from_slice(&data[..size]);
+        raw = data.get(size + 2..).unwrap_or(&[]);
+    }
+    body
+}
+
+impl Response {
+    fn parse(raw: &[u8]) -> io::Result<Self> {
+        let broken = || io::Error::new(io::ErrorKind::InvalidData, "broken http response");
+        let end: usize = raw
+            .windows(4)
+            .position(|w| w == b"\r\n\r\n")
+            .ok_or_else(broken)?;
+        let head: &str = std::str::from_utf8(&raw[..end]).map_err(|_| broken())?;
+        let status: u16 = head
+            .split(' ')
+            .nth(1)
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(broken)?;
+        let chunked: bool = head.lines().any(|l: &str| {
+            let l: String = l.to_ascii_lowercase();
+            l.starts_with("transfer-encoding:") && l.contains("chunked")
+        });
+        let body: &[u8] = &raw[end + 4..];
+        let body: Vec<u8> = match chunked {
+            true => decode_chunked(body),
+            false => body.to_vec(),
+        };
+        Ok(Self { status, body })
+    }
+
+    /// Checks if a failed request may succeed later(429 and 5xx).
+    pub(crate) fn retryable(&self) -> bool {
+        self.status == 429 || (500..600).contains(&self.status)
+    }
+
+    /// Gets an error for a non-2xx response(including the start of the body).
+    pub(crate) fn error(&self) -> io::Error {
+        let body = String::from_utf8_lossy(&self.body);
+        let body: &str = truncate_str(&body, 256);
+        io::Error::other(format!("http status {}: {body}", self.status))
+    }
+}
+
+trait Stream: Read + Write {}
+
+impl<S> Stream for S where S: Read + Write {}
+
+/// A minimal HTTP/1.1 client which sends each request on a new connection.
+pub(crate) struct HttpClient {
+    endpoint: Endpoint,
+    headers: Vec<(String, String)>,
+    #[cfg(feature = "tls")]
+    tls: Option<Arc<rustls::ClientConfig>>,
+}
+
+impl HttpClient {
+    pub(crate) fn new(endpoint: &str, headers: &[(&str, &str)]) -> io::Result<Self> {
+        let endpoint: Endpoint = Endpoint::parse(endpoint)?;
+        #[cfg(not(feature = "tls"))]
+        if endpoint.tls {
+            return Err(invalid_input("https requires the tls feature".into()));
+        }
+        #[cfg(feature = "tls")]
+        let tls = match endpoint.tls {
+            true => Some(TlsConfig::default().build()?),
+            false => None,
+        };
+        let mut client = Self {
+            endpoint,
+            headers: vec![],
+            #[cfg(feature = "tls")]
+            tls,
+        };
+        for (name, val) in headers {
+            client.add_header(name, val)?;
+        }
+        Ok(client)
+    }
+
+    /// Adds a request header(rejecting line breaks).
+    pub(crate) fn add_header(&mut self, name: &str, val: &str) -> io::Result<()> {
+        let broken = |s: &str| s.contains(['\r', '\n']);
+        if name.is_empty() || name.contains(':') || broken(name) || broken(val) {
+            return Err(invalid_input(format!("invalid header: {name}")));
+        }
+        self.headers.push((name.into(), val.into()));
+        Ok(())
+    }
+
+    #[cfg(feature = "tls")]
+    pub(crate) fn set_tls_config(&mut self, config: TlsConfig) -> io::Result<()> {
+        self.tls = Some(config.build()?);
+        Ok(())
+    }
+
+    fn connect(&self) -> io::Result<Box<dyn Stream>> {
+        let addr = (self.endpoint.host.as_str(), self.endpoint.port);
+        match self.endpoint.tls {
+            false => {
+                let s = tcp_connect(&addr, DEFAULT_TIMEOUT)?;
+                s.set_read_timeout(Some(DEFAULT_TIMEOUT))?;
+                Ok(Box::new(s))
+            }
+            #[cfg(feature = "tls")]
+            true => {
+                let config = self
+                    .tls
+                    .as_ref()
+                    .ok_or_else(|| invalid_input("no tls config".into()))?;
+                let name = rustls_pki_types::ServerName::try_from(self.endpoint.host.clone())
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+                Ok(Box::new(tls_connect(&addr, config, &name)?))
+            }
+            #[cfg(not(feature = "tls"))]
+            true => Err(invalid_input("https requires the tls feature".into())),
+        }
+    }
+
+    /// The `Host` header value.
+    pub(crate) fn authority(&self) -> &str {
+        &self.endpoint.authority
+    }
+
+    /// The request path(e.g. `/`).
+    pub(crate) fn path(&self) -> &str {
+        &self.endpoint.path
+    }
+
+    /// Sends a POST request and reads the whole response.
+    pub(crate) fn post(
+        &self,
+        content_type: &str,
+        content_encoding: Option<&str>,
+        body: &[u8],
+    ) -> io::Result<Response> {
+        self.post_with_headers(content_type, content_encoding, &[], body)
+    }
+
+    /// Sends a POST request with per-request headers(e.g. a signature).
+    pub(crate) fn post_with_headers(
+        &self,
+        content_type: &str,
+        content_encoding: Option<&str>,
+        extra: &[(&str, &str)],
+        body: &[u8],
+    ) -> io::Result<Response> {
+        let mut head: String = format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: rs-simple-logging\r\n\
+             Content-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            self.endpoint.path,
+            self.endpoint.authority,
+            body.len(),
+        );
+        if let Some(encoding) = content_encoding {
+            head.push_str(&format!("Content-Encoding: {encoding}\r\n"));
+        }
+        let extra = extra.iter().map(|(name, val)| (*name, *val));
+        let headers = self
+            .headers
+            .iter()
+            .map(|(name, val)| (name.as_str(), val.as_str()));
+        for (name, val) in headers.chain(extra) {
+            head.push_str(&format!("{name}: {val}\r\n"));
+        }
+        head.push_str("\r\n");
+
+        let mut s: Box<dyn Stream> = self.connect()?;
+        s.write_all(head.as_bytes())?;
+        s.write_all(body)?;
+        s.flush()?;
+        let mut raw: Vec<u8> = Vec::new();
+        match s.read_to_end(&mut raw) {
+            Ok(_) => {}
+            // A TLS peer may close without close_notify after the response.
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof && !raw.is_empty() => {}
+            Err(e) => return Err(e),
+        }
+        Response::parse(&raw)
+    }
+}
+
+/// Encodes a batch of records into a request body.
+pub(crate) type Encode<R> = Box<dyn Fn(&[R], &mut Vec<u8>) + Sync + Send>;
+
+/// Wraps an encoder to gzip the body(`Content-Encoding: gzip`).
+#[cfg(feature = "gzip")]
+pub(crate) fn gzip_encode<R>(inner: Encode<R>) -> Encode<R>
+where
+    R: 'static,
+{
+    use flate2::{write::GzEncoder, Compression};
+
+    Box::new(move |batch: &[R], buf: &mut Vec<u8>| {
+        let mut raw: Vec<u8> = Vec::new();
+        inner(batch, &mut raw);
+        let mut enc = GzEncoder::new(buf, Compression::default());
+        // Writing to a Vec does not fail.
+        enc.write_all(&raw).ok();
+        enc.finish().ok();
+    })
+}
+
+/// Where and how batches are sent.
+pub(crate) struct Target<R> {
+    pub(crate) client: HttpClient,
+    pub(crate) content_type: String,
+    pub(crate) content_encoding: Option<&'static str>,
+    pub(crate) encode: Encode<R>,
+    pub(crate) retry: RetryPolicy,
+    /// Checks a 2xx response(e.g. partial failures reported in the body).
+    pub(crate) check: Option<fn(&Response) -> io::Result<()>>,
+}
+
+/// Sends a batch of records(from the background thread of a [`Batcher`]).
+pub(crate) trait Deliver<R>: Send {
+    fn deliver(&mut self, batch: &[R]) -> io::Result<()>;
+}
+
+impl<R> Deliver<R> for Target<R>
+where
+    R: 'static,
+{
+    /// Sends a batch, retrying connection errors, 429 and 5xx responses.
+    fn deliver(&mut self, batch: &[R]) -> io::Result<()> {
+        let mut body: Vec<u8> = Vec::new();
+        (self.encode)(batch, &mut body);
+        let mut failures: u32 = 0;
+        loop {
+            let (e, retryable) =
+                match self
+                    .client
+                    .post(&self.content_type, self.content_encoding, &body)
+                {
+                    Ok(r) if r.status < 300 => return self.check.map(|c| c(&r)).unwrap_or(Ok(())),
+                    Ok(r) => (r.error(), r.retryable()),
+                    Err(e) => (e, true),
+                };
+            failures += 1;
+            if !retryable || self.retry.max_attempts <= failures {
+                return Err(e);
+            }
+            std::thread::sleep(jitter(self.retry.backoff(failures)));
+        }
+    }
+}
+
+/// The waiters of a flush.
+type Flushes = Vec<Sender<io::Result<()>>>;
+
+struct BatchState<R> {
+    records: Vec<R>,
+    /// When the oldest pending record was queued.
+    oldest: Option<Instant>,
+    max_pending: usize,
+    overflow: OverflowPolicy,
+    /// Sends the pending records at once after a record of this severity or higher.
+    flush_level: Option<Severity>,
+    /// Set until the pending records are sent after such a record.
+    urgent: bool,
+    flushes: Flushes,
+    closed: bool,
+}
+
+struct Shared<R, T> {
+    state: Mutex<BatchState<R>>,
+    wake: Condvar,
+    not_full: Condvar,
+    target: Mutex<T>,
+    batch_size: usize,
+    interval: Duration,
+}
+
+impl<R, T> Shared<R, T> {
+    /// Waits for a full batch, the interval, a flush or close; None if closed and drained.
+    fn next(&self) -> Option<(Vec<R>, Flushes)> {
+        let mut guard = self.state.lock().ok()?;
+        loop {
+            let due: bool = self.batch_size <= guard.records.len()
+                || guard.closed
+                || guard.urgent
+                || !guard.flushes.is_empty();
+            if due {
+                break;
+            }
+            guard = match guard.oldest {
+                None => self.wake.wait(guard).ok()?,
+                Some(t) => match self.interval.checked_sub(t.elapsed()) {
+                    None => break,
+                    Some(d) => self.wake.wait_timeout(guard, d).ok()?.0,
+                },
+            };
+        }
+        let len: usize = guard.records.len().min(self.batch_size);
+        let batch: Vec<R> = guard.records.drain(..len).collect();
+        stats::queue_depth("batch", -(len as i64));
+        self.not_full.notify_all();
+        if guard.records.is_empty() {
+            guard.oldest = None;
+            guard.urgent = false;
+        }
+        let flushes: Flushes = match guard.records.is_empty() {
+            true => std::mem::take(&mut guard.flushes),
+            false => vec![],
+        };
+        match guard.closed && batch.is_empty() && flushes.is_empty() {
+            true => None,
+            false => Some((batch, flushes)),
+        }
+    }
+}
+
+/// The interval at which a flush checks that the background thread is alive.
+const FLUSH_POLL: Duration = Duration::from_millis(100);
+
+/// Queues records and sends them in batches from a background thread.
+pub(crate) struct Batcher<R, T = Target<R>> {
+    shared: Arc<Shared<R, T>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl<R, T> Batcher<R, T>
+where
+    R: Send + 'static,
+    T: Deliver<R> + 'static,
+{
+    pub(crate) fn new(target: T, batch_size: usize, flush_interval: Duration) -> io::Result<Self> {
+        let batch_size: usize = batch_size.max(1);
+        let shared: Arc<Shared<R, T>> = Arc::new(Shared {
+            state: Mutex::new(BatchState {
+                records: Vec::with_capacity(batch_size),
+                oldest: None,
+                max_pending: batch_size.saturating_mul(8),
+                overflow: OverflowPolicy::DropNewest,
+                flush_level: Some(Severity::Error),
+                urgent: false,
+                flushes: vec![],
+                closed: false,
+            }),
+            wake: Condvar::new(),
+            not_full: Condvar::new(),
+            target: Mutex::new(target),
+            batch_size,
+            interval: flush_interval,
+        });
+        let worker: Arc<Shared<R, T>> = shared.clone();
+        let handle: JoinHandle<()> =
+            std::thread::Builder::new()
+                .name("log-http".into())
+                .spawn(move || {
+                    // The error of the last failed batch, reported to the next flush.
+                    let mut failed: Option<io::Error> = None;
+                    while let Some((batch, flushes)) = worker.next() {
+                        if !batch.is_empty() {
+                            let sent: io::Result<()> = match worker.target.lock() {
+                                Err(_) => Err(io::Error::other("lock poisoned")),
+                                Ok(mut target) => {
+                                    stats::timed("log_batch_delivery_seconds", &[], || {
+                                        target.deliver(&batch)
+                                    })
+                                }
+                            };
+                            let count: u64 = batch.len() as u64;
+                            match sent {
+                                Ok(()) => stats::WRITTEN.add(count),
+                                Err(e) => {
+                                    stats::WRITE_ERRORS.add(count);
+                                    failed = Some(e);
+                                }
+                            }
+                        }
+                        if !flushes.is_empty() {
+                            let failed: Option<io::Error> = failed.take();
+                            for f in flushes {
+                                let r: io::Result<()> = match &failed {
+                                    None => Ok(()),
+                                    Some(e) => Err(io::Error::new(e.kind(), e.to_string())),
+                                };
+                                f.send(r).ok();
+                            }
+                        }
+                    }
+                })?;
+        Ok(Self {
+            shared,
+            handle: Some(handle),
+        })
+    }
+}
+
+impl<R, T> Batcher<R, T> {
+    /// Changes where and how batches are sent.
+    pub(crate) fn configure<F>(&self, f: F) -> io::Result<()>
+    where
+        F: FnOnce(&mut T) -> io::Result<()>,
+    {
+        match self.shared.target.lock() {
+            Err(_) => Err(io::Error::other("lock poisoned")),
+            Ok(mut target) => f(&mut target),
+        }
+    }
+
+    pub(crate) fn set_max_pending(&self, max_pending: usize) {
+        if let Ok(mut guard) = self.shared.state.lock() {
+            guard.max_pending = max_pending.max(1);
+        }
+    }
+
+    pub(crate) fn set_overflow_policy(&self, policy: OverflowPolicy) {
+        if let Ok(mut guard) = self.shared.state.lock() {
+            guard.overflow = policy;
+        }
+    }
+
+    pub(crate) fn set_flush_severity(&self, level: Option<Severity>) {
+        if let Ok(mut guard) = self.shared.state.lock() {
+            guard.flush_level = level;
+        }
+    }
+
+    /// Queues a record; fails if too many records are pending(see [`OverflowPolicy`]).
+    ///
+    /// A record of the flush severity or higher is sent without waiting for
+    /// a full batch or the interval.
+    pub(crate) fn push(&self, record: R, level: Severity) -> io::Result<()> {
+        let poisoned = |_| io::Error::other("lock poisoned");
+        let mut guard = self.shared.state.lock().map_err(poisoned)?;
+        let deadline: Option<Instant> = match guard.overflow {
+            OverflowPolicy::BlockWithTimeout(t) => Instant::now().checked_add(t),
+            _ => None,
+        };
+        while guard.max_pending <= guard.records.len() {
+            match guard.overflow {
+                // The records have no severity.
+                OverflowPolicy::DropNewest | OverflowPolicy::DropLowestSeverity => {
+                    return Err(queue_full())
+                }
+                OverflowPolicy::Block => {
+                    guard = self.shared.not_full.wait(guard).map_err(poisoned)?
+                }
+                OverflowPolicy::BlockWithTimeout(_) => {
+                    let left: Option<Duration> = match deadline {
+                        None => None,
+                        Some(d) => match d.checked_duration_since(Instant::now()) {
+                            Some(left) if !left.is_zero() => Some(left),
+                            _ => return Err(queue_full()),
+                        },
+                    };
+                    guard = match left {
+                        None => self.shared.not_full.wait(guard).map_err(poisoned)?,
+                        Some(left) => {
+                            self.shared
+                                .not_full
+                                .wait_timeout(guard, left)
+                                .map_err(|_| io::Error::other("lock poisoned"))?
+                                .0
+                        }
+                    };
+                }
+                OverflowPolicy::DropOldest => {
+                    guard.records.remove(0);
+                    stats::DROPPED_BY_FULL_QUEUE.add(1);
+                    stats::queue_depth("batch", -1);
+                }
+            }
+        }
+        guard.records.push(record);
+        stats::queue_depth("batch", 1);
+        let len: usize = guard.records.len();
+        if 1 == len {
+            guard.oldest = Some(Instant::now());
+        }
+        let urgent: bool = guard.flush_level.is_some_and(|f: Severity| f <= level);
+        guard.urgent |= urgent;
+        if 1 == len || self.shared.batch_size == len || urgent {
+            self.shared.wake.notify_one();
+        }
+        Ok(())
+    }
+
+    /// Sends the pending records and waits; fails if a batch failed since the last flush.
+    ///
+    /// Fails with [`io::ErrorKind::BrokenPipe`] if the background thread has stopped.
+    pub(crate) fn flush(&self) -> io::Result<()> {
+        let stopped = || io::Error::new(io::ErrorKind::BrokenPipe, "log writer thread stopped");
+        let (tx, rx) = mpsc::channel();
+        match self.shared.state.lock() {
+            Err(_) => return Err(io::Error::other("lock poisoned")),
+            Ok(mut guard) => guard.flushes.push(tx),
+        }
+        self.shared.wake.notify_one();
+        loop {
+            match rx.recv_timeout(FLUSH_POLL) {
+                Ok(r) => return r,
+                Err(RecvTimeoutError::Disconnected) => return Err(stopped()),
+                Err(RecvTimeoutError::Timeout) => {
+                    // A panicked thread never answers; the request stays queued.
+                    let alive: bool = self.handle.as_ref().is_some_and(|h| !h.is_finished());
+                    if !alive {
+                        return Err(stopped());
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<R, T> Drop for Batcher<R, T> {
+    fn drop(&mut self) {
+        if let Ok(mut guard) = self.shared.state.lock() {
+            guard.closed = true;
+        }
+        self.shared.wake.notify_one();
+        if let Some(h) = self.handle.take() {
+            h.join().ok();
+        }
+    }
+}
+
+/// How a batch of records is written into a request body.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum BatchFormat {
+    /// One record per line(`application/x-ndjson`, default).
+    #[default]
+    Ndjson,
+    /// A JSON array of records(`application/json`); records must be JSON values.
+    JsonArray,
+}
+
+impl BatchFormat {
+    pub(crate) fn content_type(self) -> &'static str {
+        match self {
+            Self::Ndjson => "application/x-ndjson",
+            Self::JsonArray => "application/json",
+        }
+    }
+
+    pub(crate) fn encode(self) -> Encode<String> {
+        match self {
+            Self::Ndjson => Box::new(|batch: &[String], buf: &mut Vec<u8>| {
+                for record in batch {
+                    buf.extend_from_slice(record.as_bytes());
+                    buf.push(b'\n');
+                }
+            }),
+            Self::JsonArray => Box::new(|batch: &[String], buf: &mut Vec<u8>| {
+                buf.push(b'[');
+                for (i, record) in batch.iter().enumerate() {
+                    if 0 < i {
+                        buf.push(b',');
+                    }
+                    buf.extend_from_slice(record.as_bytes());
+                }
+                buf.push(b']');
+            }),
+        }
+    }
+}
+
+/// A log writer which POSTs batches of serialized records from a background thread.
+pub struct HttpBatchWriter {
+    batcher: Batcher<String>,
+}
+
+impl HttpBatchWriter {
+    /// Sets the body format(default: [`BatchFormat::Ndjson`]).
+    pub fn with_format(self, format: BatchFormat) -> Self {
+        self.batcher
+            .configure(|t: &mut Target<String>| {
+                t.content_type = format.content_type().into();
+                t.encode = format.encode();
+                Ok(())
+            })
+            .ok();
+        self
+    }
+
+    /// Sets the retries of a failed batch(connection errors, 429 and 5xx).
+    pub fn with_retry_policy(self, policy: RetryPolicy) -> Self {
+        self.batcher
+            .configure(|t: &mut Target<String>| {
+                t.retry = policy;
+                Ok(())
+            })
+            .ok();
+        self
+    }
+
+    /// Sets the maximum number of queued records(default: 8 batches).
+    pub fn with_max_pending(self, max_pending: usize) -> Self {
+        self.batcher.set_max_pending(max_pending);
+        self
+    }
+
+    /// Sets the policy used when too many records are pending(default:
+    /// [`OverflowPolicy::DropNewest`]).
+    ///
+    /// [`OverflowPolicy::DropLowestSeverity`] drops the incoming record(the
+    /// queued records have no severity).
+    pub fn with_overflow_policy(self, policy: OverflowPolicy) -> Self {
+        self.batcher.set_overflow_policy(policy);
+        self
+    }
+
+    /// Sends the queued records at once after a record of this severity or
+    /// higher is written(default: Error; None disables it).
+    pub fn with_flush_severity(self, level: Option<Severity>) -> Self {
+        self.batcher.set_flush_severity(level);
+        self
+    }
+
+    /// Sets the trusted roots and the client certificate for `https` endpoints.
+    #[cfg(feature = "tls")]
+    pub fn with_tls_config(self, config: TlsConfig) -> io::Result<Self> {
+        self.batcher
+            .configure(|t: &mut Target<String>| t.client.set_tls_config(config))?;
+        Ok(self)
+    }
+}
+
+impl TryLogWrite for HttpBatchWriter {
+    /// Queues a record; fails if too many records are pending.
+    fn try_write(&self, serialized: &str, level: Severity) -> io::Result<()> {
+        self.batcher
+            .push(serialized.trim_end_matches('\n').into(), level)
+    }
+}
+
+impl LogWrite for HttpBatchWriter {
+    fn write(&self, serialized: &str, level: Severity) {
+        stats::count_queued(self.try_write(serialized, level));
+    }
+
+    /// Sends the queued records; fails if a batch was dropped since the last flush.
+    fn flush(&self) -> io::Result<()> {
+        self.batcher.flush()
+    }
+}
+
+/// Creates a log writer which POSTs serialized records in batches.
+///
+/// Records are queued and sent by a background thread when `batch_size`
+/// records are pending, the oldest one has waited `flush_interval`, or a
+/// record of Error or higher is written(see
+/// [`HttpBatchWriter::with_flush_severity`]). A failed batch is retried with
+/// [`RetryPolicy::default`] and then dropped.
+/// Dropping the writer sends the pending records and waits for the thread.
+///
+/// `https` endpoints require the `tls` feature. Each batch uses a new
+/// connection.
+///
+/// # Arguments
+/// - endpoint: The URL(e.g. `"http://127.0.0.1:8080/logs"`).
+/// - headers: Extra request headers(e.g. `[("Authorization", "Bearer ...")]`).
+/// - batch_size: The maximum number of records in a request.
+/// - flush_interval: The maximum time a record waits before being sent.
+pub fn http_batch_writer_new(
+    endpoint: &str,
+    headers: &[(&str, &str)],
+    batch_size: usize,
+    flush_interval: Duration,
+) -> io::Result<HttpBatchWriter> {
+    let format: BatchFormat = BatchFormat::default();
+    let target: Target<String> = Target {
+        client: HttpClient::new(endpoint, headers)?,
+        content_type: format.content_type().into(),
+        content_encoding: None,
+        encode: format.encode(),
+        retry: RetryPolicy::default(),
+        check: None,
+    };
+    Ok(HttpBatchWriter {
+        batcher: Batcher::new(target, batch_size, flush_interval)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::time::Duration;
+
+    use super::{Batcher, Deliver};
+    use crate::Severity;
+
+    struct Panicking;
+
+    impl Deliver<String> for Panicking {
+        fn deliver(&mut self, _batch: &[String]) -> io::Result<()> {
+            panic!("delivery panicked")
+        }
+    }
+
+    #[test]
+    fn flush_fails_after_the_worker_stopped() {
+        let batcher: Batcher<String, Panicking> =
+            Batcher::new(Panicking, 1, Duration::from_millis(1)).unwrap();
+        batcher.push("record".into(), Severity::Info).unwrap();
+        let e: io::Error = batcher.flush().unwrap_err();
+        assert_eq!(io::ErrorKind::BrokenPipe, e.kind());
+    }
+}
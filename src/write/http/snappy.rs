@@ -0,0 +1,198 @@
+//! A Snappy(block format) compressor for HTTP payloads.
+
+const BLOCK_SIZE: usize = 1 << 16;
+const TABLE_BITS: u32 = 14;
+
+fn write_varint(mut v: usize, buf: &mut Vec<u8>) {
+    while 0x80 <= v {
+        buf.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    buf.push(v as u8)
+}
+
+fn emit_literal(lit: &[u8], buf: &mut Vec<u8>) {
+    if lit.is_empty() {
+        return;
+    }
+    let n: usize = lit.len() - 1;
+    match n {
+        0..=59 => buf.push((n as u8) << 2),
+        60..=0xff => buf.extend_from_slice(&[60 << 2, n as u8]),
+        0x100..=0xffff => {
+            buf.push(61 << 2);
+            buf.extend_from_slice(&(n as u16).to_le_bytes())
+        }
+        _ => {
+            buf.push(63 << 2);
+            buf.extend_from_slice(&(n as u32).to_le_bytes())
+        }
+    }
+    buf.extend_from_slice(lit)
+}
+
+/// Emits copies(2-byte offsets) of up to 64 bytes each.
+fn emit_copy(offset: usize, mut len: usize, buf: &mut Vec<u8>) {
+    let mut copy = |n: usize| {
+        buf.push((((n - 1) as u8) << 2) | 2);
+        buf.extend_from_slice(&(offset as u16).to_le_bytes())
+    };
+    // Keeps the last copy at least 4 bytes long.
+    while 68 <= len {
+        copy(64);
+        len -= 64;
+    }
+    if 64 < len {
+        copy(60);
+        len -= 60;
+    }
+    copy(len)
+}
+
+fn compress_block(block: &[u8], table: &mut [usize], buf: &mut Vec<u8>) {
+    table.fill(0);
+    let mut lit_start: usize = 0;
+    let mut i: usize = 0;
+    while i + 4 <= block.len() {
+        let v: u32 = u32::from_le_bytes([block[i], block[i + 1], block[i + 2], block[i + 3]]);
+        let h: usize = (v.wrapping_mul(0x1e35_a7bd) >> (32 - TABLE_BITS)) as usize;
+        // Positions are stored plus one; zero means empty.
+        let candidate: usize = table[h];
+        table[h] = i + 1;
+        if 0 == candidate || block[candidate - 1..candidate + 3] != block[i..i + 4] {
+            i += 1;
+            continue;
+        }
+        let start: usize = candidate - 1;
+        let mut len: usize = 4;
+        while i + len < block.len() && block[start + len] == block[i + len] {
+            len += 1;
+        }
+        emit_literal(&block[lit_start..i], buf);
+        emit_copy(i - start, len, buf);
+        i += len;
+        lit_start = i;
+    }
+    emit_literal(&block[lit_start..], buf)
+}
+
+/// Compresses bytes in the Snappy block format(as used by the Loki/Prometheus APIs).
+pub(crate) fn compress(input: &[u8]) -> Vec<u8> {
+    let mut buf: Vec<u8> = Vec::with_capacity(input.len() / 2 + 16);
+    write_varint(input.len(), &mut buf);
+    let mut table: Vec<usize> = vec![0; 1 << TABLE_BITS];
+    for block in input.chunks(BLOCK_SIZE) {
+        compress_block(block, &mut table, &mut buf);
+    }
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::compress;
+
+    /// Decodes the Snappy block format(all element types).
+    fn decompress(mut input: &[u8]) -> Vec<u8> {
+        let mut len: usize = 0;
+        let mut shift: u32 = 0;
+        loop {
+            let b: u8 = input[0];
+            input = &input[1..];
+            len |= usize::from(b & 0x7f) << shift;
+            shift += 7;
+            if b < 0x80 {
+                break;
+            }
+        }
+        let mut out: Vec<u8> = Vec::with_capacity(len);
+        let le = |b: &[u8]| {
+            b.iter()
+                .rev()
+                .fold(0, |v: usize, b: &u8| v << 8 | usize::from(*b))
+        };
+        while let [tag, rest @ ..] = input {
+            let (n, offset, rest): (usize, usize, &[u8]) = match tag & 3 {
+                0 => {
+                    let (n, rest) = match tag >> 2 {
+                        t @ 0..=59 => (usize::from(t), rest),
+                        t => {
+                            let k: usize = usize::from(t) - 59;
+                            (le(&rest[..k]), &rest[k..])
+                        }
+                    };
+                    out.extend_from_slice(&rest[..n + 1]);
+                    input = &rest[n + 1..];
+                    continue;
+                }
+                1 => (
+                    usize::from((tag >> 2) & 7) + 4,
+                    usize::from(tag >> 5) << 8 | usize::from(rest[0]),
+                    &rest[1..],
+                ),
+                2 => (usize::from(tag >> 2) + 1, le(&rest[..2]), &rest[2..]),
+                _ => (usize::from(tag >> 2) + 1, le(&rest[..4]), &rest[4..]),
+            };
+            for _ in 0..n {
+                out.push(out[out.len() - offset]);
+            }
+            input = rest;
+        }
+        assert_eq!(len, out.len());
+        out
+    }
+
+    #[test]
+    fn golden_bytes() {
+        assert_eq!(vec![0x00], compress(b""));
+        assert_eq!(vec![0x03, 0x08, b'a', b'b', b'c'], compress(b"abc"));
+        #[rustfmt::skip]
+        let expected: Vec<u8> = vec![
+            0x0c,
+            0x0c, b'a', b'b', b'c', b'd',
+            0x1e, 0x04, 0x00,
+        ];
+        assert_eq!(expected, compress(b"abcdabcdabcd"));
+
+        // A 61-byte literal takes a length byte.
+        let distinct: Vec<u8> = (0..61).collect();
+        let mut expected: Vec<u8> = vec![0x3d, 0xf0, 60];
+        expected.extend_from_slice(&distinct);
+        assert_eq!(expected, compress(&distinct));
+    }
+
+    #[test]
+    fn long_copies_are_split() {
+        // 99 bytes: 64 + 35.
+        let expected: Vec<u8> = vec![0x64, 0x00, b'a', 0xfe, 0x01, 0x00, 0x8a, 0x01, 0x00];
+        assert_eq!(expected, compress(&[b'a'; 100]));
+        // 66 bytes: 60 + 6, not 64 + 2(a copy is at least 4 bytes).
+        let expected: Vec<u8> = vec![0x43, 0x00, b'a', 0xee, 0x01, 0x00, 0x16, 0x01, 0x00];
+        assert_eq!(expected, compress(&[b'a'; 67]));
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut text: Vec<u8> = Vec::new();
+        for i in 0..20_000u32 {
+            text.extend_from_slice(
+                format!("level=info msg=\"request {}\" n={i}\n", i % 7).as_bytes(),
+            );
+        }
+        // Spans several blocks; the varint length takes 3 bytes.
+        assert!(3 << 16 < text.len());
+        let compressed: Vec<u8> = compress(&text);
+        assert!(compressed.len() < text.len() / 4);
+        assert_eq!(text, decompress(&compressed));
+
+        let mut x: u32 = 1;
+        let noise: Vec<u8> = (0..70_000)
+            .map(|_| {
+                x ^= x << 13;
+                x ^= x >> 17;
+                x ^= x << 5;
+                x as u8
+            })
+            .collect();
+        assert_eq!(noise, decompress(&compress(&noise)));
+    }
+}
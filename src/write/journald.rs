@@ -0,0 +1,99 @@
+//! A log writer for the systemd journal.
+
+use std::io;
+use std::os::unix::net::UnixDatagram;
+use std::path::PathBuf;
+
+use crate::{write::LogWriteBytes, Severity};
+
+/// The socket of the journal native protocol.
+pub const JOURNAL_SOCKET: &str = "/run/systemd/journal/socket";
+
+/// A log writer which sends serialized journal entries to journald.
+pub struct JournaldWriter {
+    socket: UnixDatagram,
+    path: PathBuf,
+}
+
+impl JournaldWriter {
+    /// Sends an entry; entries too large for a datagram are passed via a memfd.
+    pub fn send(&self, entry: &[u8]) -> io::Result<()> {
+        match self.socket.send_to(entry, &self.path) {
+            Ok(_) => Ok(()),
+            Err(e) if is_too_large(&e) => self.send_memfd(entry),
+            Err(e) => Err(e),
+        }
+    }
+
+    #[cfg(all(target_os = "linux", feature = "journald-memfd"))]
+    fn send_memfd(&self, entry: &[u8]) -> io::Result<()> {
+        use rustix::fs::{fcntl_add_seals, memfd_create, MemfdFlags, SealFlags};
+        use rustix::net::{
+            sendmsg_addr, SendAncillaryBuffer, SendAncillaryMessage, SendFlags, SocketAddrUnix,
+        };
+        use std::io::Write;
+        use std::mem::MaybeUninit;
+        use std::os::fd::AsFd;
+
+        let fd = memfd_create(
+            "journal-entry",
+            MemfdFlags::CLOEXEC | MemfdFlags::ALLOW_SEALING,
+        )?;
+        let mut f: std::fs::File = fd.into();
+        f.write_all(entry)?;
+        fcntl_add_seals(
+            &f,
+            SealFlags::SHRINK | SealFlags::GROW | SealFlags::WRITE | SealFlags::SEAL,
+        )?;
+        let addr = SocketAddrUnix::new(&self.path)?;
+        let fds = [f.as_fd()];
+        let mut space = [MaybeUninit::uninit(); rustix::cmsg_space!(ScmRights(1))];
+        let mut control = SendAncillaryBuffer::new(&mut space);
+        control.push(SendAncillaryMessage::ScmRights(&fds));
+        sendmsg_addr(&self.socket, &addr, &[], &mut control, SendFlags::empty())?;
+        Ok(())
+    }
+
+    #[cfg(not(all(target_os = "linux", feature = "journald-memfd")))]
+    fn send_memfd(&self, _entry: &[u8]) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "journal entry too large(enable the journald-memfd feature)",
+        ))
+    }
+}
+
+/// Checks if a send failed because of the datagram size(Linux EMSGSIZE or ENOBUFS).
+fn is_too_large(e: &io::Error) -> bool {
+    matches!(e.raw_os_error(), Some(90) | Some(105))
+}
+
+impl LogWriteBytes for JournaldWriter {
+    fn write(&self, serialized: &[u8], _level: Severity) {
+        self.send(serialized).ok();
+    }
+}
+
+/// Creates a log writer which sends entries to journald via [`JOURNAL_SOCKET`].
+///
+/// Use it with [`journald_serializer_new`](crate::serialize::journald::journald_serializer_new)
+/// and [`logger_new_bytes`](crate::copy::logger_new_bytes). Entries larger
+/// than a datagram are sent as a sealed memfd when the `journald-memfd`
+/// feature is enabled(Linux only).
+pub fn journald_writer_new() -> io::Result<JournaldWriter> {
+    journald_writer_new_with_path(JOURNAL_SOCKET)
+}
+
+/// Creates a journald log writer for a socket path(e.g. a container bind mount).
+///
+/// # Arguments
+/// - path: The socket of the journal native protocol.
+pub fn journald_writer_new_with_path<P>(path: P) -> io::Result<JournaldWriter>
+where
+    P: Into<PathBuf>,
+{
+    Ok(JournaldWriter {
+        socket: UnixDatagram::unbound()?,
+        path: path.into(),
+    })
+}
@@ -0,0 +1,362 @@
+//! A logger which produces items to a Kafka topic(requires the `kafka` feature).
+
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::net::TcpStream;
+use std::time::Duration;
+
+mod protocol;
+
+use protocol::{Metadata, Record};
+
+use crate::{
+    copy::Logger,
+    serialize::{time, Serialize},
+    write::{
+        http::{Batcher, Deliver},
+        net::{tcp_connect, DEFAULT_TIMEOUT},
+        retry::{jitter, RetryPolicy},
+        stats,
+    },
+    Item, Severity,
+};
+
+/// The acknowledgements a produce request waits for.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum Acks {
+    /// No response(`acks=0`); failures are not detected.
+    None,
+    /// The leader wrote the records(`acks=1`, default).
+    #[default]
+    Leader,
+    /// All in-sync replicas wrote the records(`acks=all`).
+    All,
+}
+
+impl From<Acks> for i16 {
+    fn from(a: Acks) -> Self {
+        match a {
+            Acks::None => 0,
+            Acks::Leader => 1,
+            Acks::All => -1,
+        }
+    }
+}
+
+/// Gets errors of failed deliveries.
+type OnError = Box<dyn Fn(&io::Error) + Sync + Send>;
+
+/// Produces batches from the background thread.
+struct Producer {
+    bootstrap: Vec<String>,
+    topic: String,
+    acks: Acks,
+    retry: RetryPolicy,
+    on_error: Option<OnError>,
+    metadata: Option<Metadata>,
+    /// Connections by node id.
+    conns: HashMap<i32, TcpStream>,
+    correlation_id: i32,
+    /// The partition of records without a key(changed for each batch).
+    sticky: usize,
+}
+
+fn connect(addr: &str) -> io::Result<TcpStream> {
+    let s: TcpStream = tcp_connect(&addr, DEFAULT_TIMEOUT)?;
+    s.set_read_timeout(Some(DEFAULT_TIMEOUT * 6))?;
+    Ok(s)
+}
+
+impl Producer {
+    fn next_id(&mut self) -> i32 {
+        self.correlation_id = self.correlation_id.wrapping_add(1);
+        self.correlation_id
+    }
+
+    /// Gets the metadata from a bootstrap server(or a known broker).
+    fn refresh(&mut self) -> io::Result<()> {
+        self.conns.clear();
+        let mut addrs: Vec<String> = self.bootstrap.clone();
+        if let Some(m) = self.metadata.take() {
+            addrs.extend(m.brokers.iter().map(|b| format!("{}:{}", b.host, b.port)));
+        }
+        let mut last: io::Error = io::Error::new(io::ErrorKind::NotFound, "no bootstrap server");
+        for addr in addrs {
+            let id: i32 = self.next_id();
+            match connect(&addr).and_then(|mut s| protocol::metadata(&mut s, id, &self.topic)) {
+                Ok(m) => {
+                    self.metadata = Some(m);
+                    return Ok(());
+                }
+                Err(e) => last = e,
+            }
+        }
+        Err(last)
+    }
+
+    fn leader_conn(&mut self, node_id: i32) -> io::Result<&mut TcpStream> {
+        if !self.conns.contains_key(&node_id) {
+            let broker = self
+                .metadata
+                .as_ref()
+                .and_then(|m| m.brokers.iter().find(|b| b.node_id == node_id))
+                .ok_or_else(|| io::Error::other(format!("kafka broker not found: {node_id}")))?;
+            let s: TcpStream = connect(&format!("{}:{}", broker.host, broker.port))?;
+            self.conns.insert(node_id, s);
+        }
+        self.conns
+            .get_mut(&node_id)
+            .ok_or_else(|| io::Error::other("no connection"))
+    }
+
+    /// Picks the partition of a record(murmur2 of the key like Kafka clients).
+    fn partition(&self, record: &Record, count: usize) -> usize {
+        match &record.key {
+            None => self.sticky % count,
+            Some(key) => (protocol::murmur2(key) & 0x7fff_ffff) as usize % count,
+        }
+    }
+
+    /// Sends records once; gets the records to retry and the last error.
+    fn send<'a>(&mut self, records: Vec<&'a Record>) -> (Vec<&'a Record>, Option<io::Error>) {
+        if self.metadata.is_none() {
+            if let Err(e) = self.refresh() {
+                return (records, Some(e));
+            }
+        }
+        let leaders: Vec<i32> = self
+            .metadata
+            .as_ref()
+            .map(|m| m.leaders.clone())
+            .unwrap_or_default();
+        let mut by_leader: BTreeMap<i32, BTreeMap<i32, Vec<&Record>>> = BTreeMap::new();
+        for r in records {
+            let p: usize = self.partition(r, leaders.len());
+            by_leader
+                .entry(leaders[p])
+                .or_default()
+                .entry(p as i32)
+                .or_default()
+                .push(r);
+        }
+
+        let mut failed: Vec<&Record> = vec![];
+        let mut last: Option<io::Error> = None;
+        let mut stale: bool = false;
+        for (leader, partitions) in by_leader {
+            let partitions: Vec<(i32, Vec<&Record>)> = partitions.into_iter().collect();
+            let id: i32 = self.next_id();
+            let (acks, topic) = (i16::from(self.acks), self.topic.clone());
+            let timeout_ms: i32 = DEFAULT_TIMEOUT.as_millis() as i32;
+            let sent = self
+                .leader_conn(leader)
+                .and_then(|s| protocol::produce(s, id, acks, timeout_ms, &topic, &partitions));
+            let codes: Vec<(i32, i16)> = match sent {
+                Ok(codes) => codes,
+                Err(e) => {
+                    self.conns.remove(&leader);
+                    stale = true;
+                    failed.extend(partitions.into_iter().flat_map(|(_, r)| r));
+                    last = Some(e);
+                    continue;
+                }
+            };
+            for (index, records) in partitions {
+                let code: i16 = codes
+                    .iter()
+                    .find(|(i, _)| *i == index)
+                    .map(|(_, c)| *c)
+                    .unwrap_or(-1);
+                if code == 0 {
+                    continue;
+                }
+                let e = io::Error::other(format!("kafka error {code}: partition {index}"));
+                if !protocol::retryable(code) {
+                    self.report(&e);
+                    last = Some(e);
+                    continue;
+                }
+                stale = true;
+                failed.extend(records);
+                last = Some(e);
+            }
+        }
+        if stale {
+            self.metadata = None;
+        }
+        (failed, last)
+    }
+
+    fn report(&self, e: &io::Error) {
+        if let Some(f) = &self.on_error {
+            f(e)
+        }
+    }
+}
+
+impl Deliver<Record> for Producer {
+    /// Produces a batch, retrying connection errors and retryable error codes.
+    fn deliver(&mut self, batch: &[Record]) -> io::Result<()> {
+        self.sticky = self.sticky.wrapping_add(1);
+        let mut pending: Vec<&Record> = batch.iter().collect();
+        let mut failures: u32 = 0;
+        loop {
+            let (failed, e) = self.send(pending);
+            if failed.is_empty() {
+                return e.map(Err).unwrap_or(Ok(()));
+            }
+            failures += 1;
+            if self.retry.max_attempts <= failures {
+                let e: io::Error = e.unwrap_or_else(|| io::Error::other("kafka produce failed"));
+                self.report(&e);
+                return Err(e);
+            }
+            std::thread::sleep(jitter(self.retry.backoff(failures)));
+            pending = failed;
+        }
+    }
+}
+
+/// A logger which produces serialized items to a Kafka topic.
+pub struct KafkaLogger<S> {
+    serialize: S,
+    key: String,
+    batcher: Batcher<Record, Producer>,
+}
+
+impl<S> KafkaLogger<S> {
+    /// Sets the acknowledgements to wait for(default: [`Acks::Leader`]).
+    pub fn with_acks(self, acks: Acks) -> Self {
+        self.batcher
+            .configure(|p: &mut Producer| {
+                p.acks = acks;
+                Ok(())
+            })
+            .ok();
+        self
+    }
+
+    /// Sets the retries of a failed batch(connection errors and retryable error codes).
+    pub fn with_retry_policy(self, policy: RetryPolicy) -> Self {
+        self.batcher
+            .configure(|p: &mut Producer| {
+                p.retry = policy;
+                Ok(())
+            })
+            .ok();
+        self
+    }
+
+    /// Sets the maximum number of queued items(default: 8 batches).
+    pub fn with_max_pending(self, max_pending: usize) -> Self {
+        self.batcher.set_max_pending(max_pending);
+        self
+    }
+
+    /// Sends the queued items at once after an item of this severity or higher
+    /// is logged(default: Error; None disables it).
+    pub fn with_flush_severity(self, level: Option<Severity>) -> Self {
+        self.batcher.set_flush_severity(level);
+        self
+    }
+
+    /// Gets the errors of records which were not delivered(from the background thread).
+    pub fn with_on_error<E>(self, on_error: E) -> Self
+    where
+        E: Fn(&io::Error) + Sync + Send + 'static,
+    {
+        self.batcher
+            .configure(|p: &mut Producer| {
+                p.on_error = Some(Box::new(on_error));
+                Ok(())
+            })
+            .ok();
+        self
+    }
+}
+
+impl<S> Logger for KafkaLogger<S>
+where
+    S: Serialize,
+{
+    fn log(&self, item: Item) {
+        let level: Severity = item.severity;
+        let key: Option<&String> = match self.key.as_str() {
+            "trace_id" => item.trace_id.as_ref(),
+            "span_id" => item.span_id.as_ref(),
+            _ => None,
+        };
+        let key: Option<Vec<u8>> = item
+            .attributes
+            .get(&self.key)
+            .or(key)
+            .map(|k| k.as_bytes().to_vec());
+        let mut value: String = String::new();
+        self.serialize.serialize(&item, &mut value);
+        let value: &str = value.trim_end_matches('\n');
+        let record = Record {
+            key,
+            value: value.as_bytes().to_vec(),
+            millis: time::since_epoch(item.timestamp).as_millis() as i64,
+        };
+        stats::count_queued(self.batcher.push(record, level));
+    }
+
+    /// Sends the queued items; fails if a batch was dropped since the last flush.
+    fn flush(&self) -> io::Result<()> {
+        self.batcher.flush()
+    }
+}
+
+/// Creates a logger which produces serialized items to a topic in batches.
+///
+/// The record key is the attribute named `key_attribute`(or the trace/span
+/// id for `trace_id`/`span_id`); records with the same key go to the same
+/// partition, like the default partitioner of Kafka clients. Records without a
+/// key go to one partition per batch.
+///
+/// Batches are produced from a background thread(Produce v3, uncompressed)
+/// and retried with [`RetryPolicy::default`] after refreshing the metadata.
+/// Records which still fail are passed to [`KafkaLogger::with_on_error`].
+///
+/// # Arguments
+/// - bootstrap: The bootstrap servers(e.g. `["127.0.0.1:9092"]`).
+/// - topic: The topic; it must exist or be created automatically.
+/// - key_attribute: The attribute used as the record key(e.g. `"trace_id"`).
+/// - serialize: Serializes the record value.
+/// - batch_size: The maximum number of items in a request.
+/// - flush_interval: The maximum time an item waits before being sent.
+pub fn kafka_logger_new<S>(
+    bootstrap: &[&str],
+    topic: &str,
+    key_attribute: &str,
+    serialize: S,
+    batch_size: usize,
+    flush_interval: Duration,
+) -> io::Result<KafkaLogger<S>>
+where
+    S: Serialize,
+{
+    if bootstrap.is_empty() || topic.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "no bootstrap server or topic",
+        ));
+    }
+    let producer = Producer {
+        bootstrap: bootstrap.iter().map(|s| s.to_string()).collect(),
+        topic: topic.into(),
+        acks: Acks::default(),
+        retry: RetryPolicy::default(),
+        on_error: None,
+        metadata: None,
+        conns: HashMap::new(),
+        correlation_id: 0,
+        sticky: 0,
+    };
+    Ok(KafkaLogger {
+        serialize,
+        key: key_attribute.into(),
+        batcher: Batcher::new(producer, batch_size, flush_interval)?,
+    })
+}
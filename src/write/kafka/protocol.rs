@@ -0,0 +1,409 @@
+//! The parts of the Kafka protocol used by the producer(Metadata v1, Produce v3).
+
+use std::io::{self, Read, Write};
+
+const API_PRODUCE: i16 = 0;
+const API_METADATA: i16 = 3;
+
+/// An error code which may succeed after refreshing the metadata or waiting.
+pub(crate) fn retryable(code: i16) -> bool {
+    // UNKNOWN_TOPIC_OR_PARTITION, LEADER_NOT_AVAILABLE, NOT_LEADER_OR_FOLLOWER,
+    // REQUEST_TIMED_OUT, NETWORK_EXCEPTION, NOT_ENOUGH_REPLICAS(_AFTER_APPEND).
+    matches!(code, 3 | 5 | 6 | 7 | 13 | 19 | 20)
+}
+
+/// Computes the CRC-32C(Castagnoli) of data.
+fn crc32c(data: &[u8]) -> u32 {
+    let mut crc: u32 = !0;
+    for b in data {
+        crc ^= u32::from(*b);
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ 0x82f6_3b78,
+                _ => crc >> 1,
+            };
+        }
+    }
+    !crc
+}
+
+/// Computes the murmur2 hash used by the default partitioner of Kafka clients.
+pub(crate) fn murmur2(data: &[u8]) -> u32 {
+    const M: u32 = 0x5bd1_e995;
+    let mut h: u32 = 0x9747_b28c ^ (data.len() as u32);
+    let mut chunks = data.chunks_exact(4);
+    for c in &mut chunks {
+        let mut k: u32 = u32::from_le_bytes([c[0], c[1], c[2], c[3]]);
+        k = k.wrapping_mul(M);
+        k ^= k >> 24;
+        k = k.wrapping_mul(M);
+        h = h.wrapping_mul(M) ^ k;
+    }
+    let rest: &[u8] = chunks.remainder();
+    if !rest.is_empty() {
+        for (i, b) in rest.iter().enumerate() {
+            h ^= u32::from(*b) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+    h ^= h >> 13;
+    h = h.wrapping_mul(M);
+    h ^ (h >> 15)
+}
+
+/// Writes a zigzag varint.
+fn write_varint(v: i64, buf: &mut Vec<u8>) {
+    let mut u: u64 = ((v << 1) ^ (v >> 63)) as u64;
+    while 0x80 <= u {
+        buf.push((u as u8) | 0x80);
+        u >>= 7;
+    }
+    buf.push(u as u8);
+}
+
+fn write_string(s: &str, buf: &mut Vec<u8>) {
+    buf.extend_from_slice(&(s.len() as i16).to_be_bytes());
+    buf.extend_from_slice(s.as_bytes());
+}
+
+/// A record to produce.
+pub(crate) struct Record {
+    pub(crate) key: Option<Vec<u8>>,
+    pub(crate) value: Vec<u8>,
+    pub(crate) millis: i64,
+}
+
+/// Writes records as a RecordBatch(magic 2, uncompressed).
+fn write_record_batch(records: &[&Record], buf: &mut Vec<u8>) {
+    let base: i64 = records.iter().map(|r| r.millis).min().unwrap_or(0);
+    let max: i64 = records.iter().map(|r| r.millis).max().unwrap_or(0);
+
+    // From the attributes to the end(covered by the CRC).
+    let mut body: Vec<u8> = Vec::new();
+    body.extend_from_slice(&0i16.to_be_bytes());
+    body.extend_from_slice(&(records.len() as i32 - 1).to_be_bytes());
+    body.extend_from_slice(&base.to_be_bytes());
+    body.extend_from_slice(&max.to_be_bytes());
+    body.extend_from_slice(&(-1i64).to_be_bytes());
+    body.extend_from_slice(&(-1i16).to_be_bytes());
+    body.extend_from_slice(&(-1i32).to_be_bytes());
+    body.extend_from_slice(&(records.len() as i32).to_be_bytes());
+    let mut record: Vec<u8> = Vec::new();
+    for (i, r) in records.iter().enumerate() {
+        record.clear();
+        record.push(0);
+        write_varint(r.millis - base, &mut record);
+        write_varint(i as i64, &mut record);
+        match &r.key {
+            None => write_varint(-1, &mut record),
+            Some(key) => {
+                write_varint(key.len() as i64, &mut record);
+                record.extend_from_slice(key);
+            }
+        }
+        write_varint(r.value.len() as i64, &mut record);
+        record.extend_from_slice(&r.value);
+        write_varint(0, &mut record);
+        write_varint(record.len() as i64, &mut body);
+        body.extend_from_slice(&record);
+    }
+
+    buf.extend_from_slice(&0i64.to_be_bytes());
+    // The partition leader epoch, magic and CRC precede the body.
+    buf.extend_from_slice(&((4 + 1 + 4 + body.len()) as i32).to_be_bytes());
+    buf.extend_from_slice(&(-1i32).to_be_bytes());
+    buf.push(2);
+    buf.extend_from_slice(&crc32c(&body).to_be_bytes());
+    buf.extend_from_slice(&body);
+}
+
+/// Sends a request and reads its response body(after the correlation id).
+pub(crate) fn call<S>(
+    s: &mut S,
+    api_key: i16,
+    api_version: i16,
+    correlation_id: i32,
+    body: &[u8],
+    expect_response: bool,
+) -> io::Result<Vec<u8>>
+where
+    S: Read + Write,
+{
+    let mut req: Vec<u8> = Vec::with_capacity(body.len() + 32);
+    req.extend_from_slice(&[0; 4]);
+    req.extend_from_slice(&api_key.to_be_bytes());
+    req.extend_from_slice(&api_version.to_be_bytes());
+    req.extend_from_slice(&correlation_id.to_be_bytes());
+    write_string("rs-simple-logging", &mut req);
+    req.extend_from_slice(body);
+    let len: [u8; 4] = ((req.len() - 4) as i32).to_be_bytes();
+    req[..4].copy_from_slice(&len);
+    s.write_all(&req)?;
+    if !expect_response {
+        return Ok(vec![]);
+    }
+
+    let mut len: [u8; 4] = [0; 4];
+    s.read_exact(&mut len)?;
+    let len: usize = i32::from_be_bytes(len).max(0) as usize;
+    let mut resp: Vec<u8> = vec![0; len];
+    s.read_exact(&mut resp)?;
+    let mut r = Reader { buf: &resp };
+    match r.i32()? == correlation_id {
+        true => Ok(resp[4..].to_vec()),
+        false => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unexpected correlation id",
+        )),
+    }
+}
+
+/// Reads big-endian fields of a response.
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> io::Result<&[u8]> {
+        if self.buf.len() < n {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "truncated kafka response",
+            ));
+        }
+        let (head, rest) = self.buf.split_at(n);
+        self.buf = rest;
+        Ok(head)
+    }
+
+    fn i16(&mut self) -> io::Result<i16> {
+        let b: &[u8] = self.take(2)?;
+        Ok(i16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn i32(&mut self) -> io::Result<i32> {
+        let b: &[u8] = self.take(4)?;
+        Ok(i32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len: i16 = self.i16()?;
+        let b: &[u8] = self.take(len.max(0) as usize)?;
+        Ok(String::from_utf8_lossy(b).into())
+    }
+
+    /// Reads an array length(a null array is empty).
+    fn len(&mut self) -> io::Result<usize> {
+        Ok(self.i32()?.max(0) as usize)
+    }
+}
+
+/// A broker address.
+pub(crate) struct Broker {
+    pub(crate) node_id: i32,
+    pub(crate) host: String,
+    pub(crate) port: u16,
+}
+
+/// The brokers and the partition leaders of a topic.
+pub(crate) struct Metadata {
+    pub(crate) brokers: Vec<Broker>,
+    /// The leader node of each partition(by index).
+    pub(crate) leaders: Vec<i32>,
+}
+
+/// Gets the metadata of a topic.
+pub(crate) fn metadata<S>(s: &mut S, correlation_id: i32, topic: &str) -> io::Result<Metadata>
+where
+    S: Read + Write,
+{
+    let mut body: Vec<u8> = Vec::new();
+    body.extend_from_slice(&1i32.to_be_bytes());
+    write_string(topic, &mut body);
+    let resp: Vec<u8> = call(s, API_METADATA, 1, correlation_id, &body, true)?;
+
+    let mut r = Reader { buf: &resp };
+    let mut brokers: Vec<Broker> = vec![];
+    for _ in 0..r.len()? {
+        let node_id: i32 = r.i32()?;
+        let host: String = r.string()?;
+        let port: i32 = r.i32()?;
+        r.string()?;
+        brokers.push(Broker {
+            node_id,
+            host,
+            port: port as u16,
+        });
+    }
+    r.i32()?;
+    let mut leaders: Vec<(i32, i32)> = vec![];
+    for _ in 0..r.len()? {
+        let error: i16 = r.i16()?;
+        let name: String = r.string()?;
+        r.take(1)?;
+        if error != 0 && name == topic {
+            return Err(io::Error::other(format!(
+                "kafka error {error}: metadata of {topic}"
+            )));
+        }
+        for _ in 0..r.len()? {
+            r.i16()?;
+            let index: i32 = r.i32()?;
+            let leader: i32 = r.i32()?;
+            for _ in 0..2 {
+                let n: usize = r.len()?;
+                r.take(n * 4)?;
+            }
+            if name == topic {
+                leaders.push((index, leader));
+            }
+        }
+    }
+    if leaders.is_empty() {
+        return Err(io::Error::other(format!("kafka topic not found: {topic}")));
+    }
+    leaders.sort();
+    Ok(Metadata {
+        brokers,
+        leaders: leaders.into_iter().map(|(_, leader)| leader).collect(),
+    })
+}
+
+/// Produces record batches to partitions of a topic; gets the error code of each partition.
+pub(crate) fn produce<S>(
+    s: &mut S,
+    correlation_id: i32,
+    acks: i16,
+    timeout_ms: i32,
+    topic: &str,
+    partitions: &[(i32, Vec<&Record>)],
+) -> io::Result<Vec<(i32, i16)>>
+where
+    S: Read + Write,
+{
+    let mut body: Vec<u8> = Vec::new();
+    body.extend_from_slice(&(-1i16).to_be_bytes());
+    body.extend_from_slice(&acks.to_be_bytes());
+    body.extend_from_slice(&timeout_ms.to_be_bytes());
+    body.extend_from_slice(&1i32.to_be_bytes());
+    write_string(topic, &mut body);
+    body.extend_from_slice(&(partitions.len() as i32).to_be_bytes());
+    let mut batch: Vec<u8> = Vec::new();
+    for (index, records) in partitions {
+        batch.clear();
+        write_record_batch(records, &mut batch);
+        body.extend_from_slice(&index.to_be_bytes());
+        body.extend_from_slice(&(batch.len() as i32).to_be_bytes());
+        body.extend_from_slice(&batch);
+    }
+    let resp: Vec<u8> = call(s, API_PRODUCE, 3, correlation_id, &body, acks != 0)?;
+    if acks == 0 {
+        return Ok(partitions.iter().map(|(index, _)| (*index, 0)).collect());
+    }
+
+    let mut r = Reader { buf: &resp };
+    let mut codes: Vec<(i32, i16)> = vec![];
+    for _ in 0..r.len()? {
+        r.string()?;
+        for _ in 0..r.len()? {
+            let index: i32 = r.i32()?;
+            let error: i16 = r.i16()?;
+            r.take(16)?;
+            codes.push((index, error));
+        }
+    }
+    Ok(codes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Read, Write};
+
+    use super::{crc32c, murmur2, produce, Record};
+
+    /// Keeps the written bytes; reads nothing.
+    struct Recorder(Vec<u8>);
+
+    impl Read for Recorder {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            Ok(0)
+        }
+    }
+
+    impl Write for Recorder {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn crc32c_vectors() {
+        // The check value of CRC-32C and the vectors of RFC 3720 B.4.
+        assert_eq!(0xe306_9283, crc32c(b"123456789"));
+        assert_eq!(0x8a91_36aa, crc32c(&[0; 32]));
+        assert_eq!(0x62a8_ab43, crc32c(&[0xff; 32]));
+        let ascending: Vec<u8> = (0..32).collect();
+        assert_eq!(0x46dd_794e, crc32c(&ascending));
+    }
+
+    #[test]
+    fn murmur2_matches_the_java_client() {
+        // org.apache.kafka.common.utils.Utils.murmur2 of the Java client.
+        let cases: [(&[u8], i32); 6] = [
+            (b"21", -973_932_308),
+            (b"foobar", -790_332_482),
+            (b"a-little-bit-long-string", -985_981_536),
+            (b"a-little-bit-longer-string", -1_486_304_829),
+            (
+                b"lkjh234lh9fiuh90y23oiuhsafujhadof229phr9h19h89h8",
+                -58_897_971,
+            ),
+            (b"abc", 479_470_107),
+        ];
+        for (key, expected) in cases {
+            assert_eq!(expected, murmur2(key) as i32);
+        }
+    }
+
+    #[test]
+    fn produce_request_golden_bytes() {
+        let record = Record {
+            key: None,
+            value: b"v".to_vec(),
+            millis: 1000,
+        };
+        let mut s = Recorder(vec![]);
+        let codes = produce(&mut s, 7, 0, 1000, "logs", &[(0, vec![&record])]).unwrap();
+        assert_eq!(vec![(0, 0)], codes);
+        #[rustfmt::skip]
+        let expected: [u8; 130] = [
+            // The size, Produce v3, the correlation id and the client id.
+            0x00, 0x00, 0x00, 0x7e, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x07, 0x00, 0x11,
+            b'r', b's', b'-', b's', b'i', b'm', b'p', b'l', b'e', b'-', b'l', b'o', b'g', b'g',
+            b'i', b'n', b'g',
+            // No transactional id, acks 0, the timeout and the topic.
+            0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0x03, 0xe8, 0x00, 0x00, 0x00, 0x01, 0x00, 0x04,
+            b'l', b'o', b'g', b's',
+            // Partition 0 and the size of its record batch.
+            0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x45,
+            // The base offset, the batch length, the leader epoch, magic 2 and the CRC-32C.
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x39,
+            0xff, 0xff, 0xff, 0xff, 0x02, 0xae, 0x59, 0xb0, 0x8f,
+            // The attributes, the last offset delta and the timestamps.
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0xe8,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0xe8,
+            // No producer id, epoch or base sequence; 1 record.
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+            0x00, 0x00, 0x00, 0x01,
+            // The record: length 7, attributes, deltas, a null key, value "v", no headers.
+            0x0e, 0x00, 0x00, 0x00, 0x01, 0x02, b'v', 0x00,
+        ];
+        assert_eq!(expected.as_slice(), s.0.as_slice());
+    }
+}
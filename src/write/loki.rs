@@ -0,0 +1,293 @@
+//! A logger which pushes items to Grafana Loki.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::time::Duration;
+
+#[cfg(feature = "tls")]
+use crate::write::net::tls::TlsConfig;
+use crate::{
+    copy::Logger,
+    serialize::{json, json::ObjectWriter, protobuf, time, Serialize},
+    write::{
+        http::{snappy, Batcher, HttpClient, Target},
+        retry::RetryPolicy,
+        stats,
+    },
+    Item, Severity,
+};
+
+/// The payload encoding of the push API.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum LokiEncoding {
+    /// `application/json`(default).
+    #[default]
+    Json,
+    /// Snappy compressed `logproto.PushRequest`(`application/x-protobuf`).
+    Protobuf,
+}
+
+/// Label names and values(sorted by name).
+type Labels = [(String, String)];
+
+/// A log line and the labels of its stream.
+struct Entry {
+    labels: Vec<(String, String)>,
+    time: Duration,
+    line: String,
+}
+
+/// Converts a resource key to a label name(`[a-zA-Z_][a-zA-Z0-9_]*`).
+fn label_name(key: &str) -> String {
+    let mut name: String = key
+        .chars()
+        .map(|c: char| match c.is_ascii_alphanumeric() {
+            true => c,
+            false => '_',
+        })
+        .collect();
+    if name
+        .chars()
+        .next()
+        .map(|c| c.is_ascii_digit())
+        .unwrap_or(true)
+    {
+        name.insert(0, '_');
+    }
+    name
+}
+
+/// Groups entries by their labels(in the order of the first entry).
+fn streams(batch: &[Entry]) -> Vec<(&Labels, Vec<&Entry>)> {
+    let mut index: BTreeMap<&Labels, usize> = BTreeMap::new();
+    let mut streams: Vec<(&Labels, Vec<&Entry>)> = Vec::new();
+    for e in batch {
+        let labels: &Labels = &e.labels;
+        match index.get(labels) {
+            Some(i) => streams[*i].1.push(e),
+            None => {
+                index.insert(labels, streams.len());
+                streams.push((labels, vec![e]));
+            }
+        }
+    }
+    streams
+}
+
+fn encode_json(batch: &[Entry], buf: &mut Vec<u8>) {
+    let mut s: String = String::from("{\"streams\":[");
+    for (i, (labels, entries)) in streams(batch).into_iter().enumerate() {
+        if 0 < i {
+            s.push(',');
+        }
+        let mut stream = ObjectWriter::begin(&mut s);
+        let mut obj = ObjectWriter::begin(stream.key("stream"));
+        for (name, val) in labels {
+            obj.str(name, val);
+        }
+        obj.end();
+        let values: &mut String = stream.key("values");
+        values.push('[');
+        for (j, e) in entries.iter().enumerate() {
+            if 0 < j {
+                values.push(',');
+            }
+            values.push_str(&format!("[\"{}\",", e.time.as_nanos()));
+            json::write_str(&e.line, values);
+            values.push(']');
+        }
+        values.push(']');
+        stream.end();
+    }
+    s.push_str("]}");
+    buf.extend_from_slice(s.as_bytes())
+}
+
+/// Writes labels in the selector form(`{name="value", ...}`).
+fn label_selector(labels: &Labels) -> String {
+    let mut s: String = String::from("{");
+    for (i, (name, val)) in labels.iter().enumerate() {
+        if 0 < i {
+            s.push_str(", ");
+        }
+        s.push_str(name);
+        s.push_str("=\"");
+        for c in val.chars() {
+            match c {
+                '"' | '\\' => {
+                    s.push('\\');
+                    s.push(c)
+                }
+                '\n' => s.push_str("\\n"),
+                c => s.push(c),
+            }
+        }
+        s.push('"');
+    }
+    s.push('}');
+    s
+}
+
+fn encode_protobuf(batch: &[Entry], buf: &mut Vec<u8>) {
+    let mut req: Vec<u8> = Vec::new();
+    for (labels, entries) in streams(batch) {
+        protobuf::write_message(1, &mut req, |stream: &mut Vec<u8>| {
+            protobuf::write_str(1, &label_selector(labels), stream);
+            for e in entries {
+                protobuf::write_message(2, stream, |entry: &mut Vec<u8>| {
+                    protobuf::write_message(1, entry, |ts: &mut Vec<u8>| {
+                        protobuf::write_uint(1, e.time.as_secs(), ts);
+                        protobuf::write_uint(2, e.time.subsec_nanos().into(), ts);
+                    });
+                    protobuf::write_str(2, &e.line, entry);
+                });
+            }
+        });
+    }
+    buf.extend_from_slice(&snappy::compress(&req))
+}
+
+/// A logger which batches items into Loki streams.
+pub struct LokiLogger<S> {
+    serialize: S,
+    /// The resource keys and their label names.
+    labels: Vec<(String, String)>,
+    batcher: Batcher<Entry>,
+}
+
+impl<S> LokiLogger<S> {
+    /// Sets the payload encoding(default: [`LokiEncoding::Json`]).
+    pub fn with_encoding(self, encoding: LokiEncoding) -> Self {
+        self.batcher
+            .configure(|t: &mut Target<Entry>| {
+                match encoding {
+                    LokiEncoding::Json => {
+                        t.content_type = "application/json".into();
+                        t.content_encoding = None;
+                        t.encode = Box::new(encode_json);
+                    }
+                    LokiEncoding::Protobuf => {
+                        t.content_type = "application/x-protobuf".into();
+                        t.content_encoding = Some("snappy");
+                        t.encode = Box::new(encode_protobuf);
+                    }
+                }
+                Ok(())
+            })
+            .ok();
+        self
+    }
+
+    /// Adds a request header(e.g. `X-Scope-OrgID` or `Authorization`).
+    pub fn with_header(self, name: &str, val: &str) -> io::Result<Self> {
+        self.batcher
+            .configure(|t: &mut Target<Entry>| t.client.add_header(name, val))?;
+        Ok(self)
+    }
+
+    /// Sets the retries of a failed batch(connection errors, 429 and 5xx).
+    pub fn with_retry_policy(self, policy: RetryPolicy) -> Self {
+        self.batcher
+            .configure(|t: &mut Target<Entry>| {
+                t.retry = policy;
+                Ok(())
+            })
+            .ok();
+        self
+    }
+
+    /// Sets the maximum number of queued items(default: 8 batches).
+    pub fn with_max_pending(self, max_pending: usize) -> Self {
+        self.batcher.set_max_pending(max_pending);
+        self
+    }
+
+    /// Sends the queued items at once after an item of this severity or higher
+    /// is logged(default: Error; None disables it).
+    pub fn with_flush_severity(self, level: Option<Severity>) -> Self {
+        self.batcher.set_flush_severity(level);
+        self
+    }
+
+    /// Sets the trusted roots and the client certificate for `https` endpoints.
+    #[cfg(feature = "tls")]
+    pub fn with_tls_config(self, config: TlsConfig) -> io::Result<Self> {
+        self.batcher
+            .configure(|t: &mut Target<Entry>| t.client.set_tls_config(config))?;
+        Ok(self)
+    }
+}
+
+impl<S> Logger for LokiLogger<S>
+where
+    S: Serialize,
+{
+    fn log(&self, item: Item) {
+        let level: Severity = item.severity;
+        let labels: Vec<(String, String)> = self
+            .labels
+            .iter()
+            .filter_map(|(key, name)| item.resource.get(key).map(|v| (name.clone(), v.clone())))
+            .collect();
+        let mut line: String = String::new();
+        self.serialize.serialize(&item, &mut line);
+        let len: usize = line.trim_end_matches('\n').len();
+        line.truncate(len);
+        let entry = Entry {
+            labels,
+            time: time::since_epoch(item.timestamp),
+            line,
+        };
+        stats::count_queued(self.batcher.push(entry, level));
+    }
+
+    /// Sends the queued items; fails if a batch was dropped since the last flush.
+    fn flush(&self) -> io::Result<()> {
+        self.batcher.flush()
+    }
+}
+
+/// Creates a logger which pushes items to Loki in batches.
+///
+/// Items are grouped into streams by the labels taken from the `resource`
+/// fields in `labels`(`.` and other characters become `_`, e.g.
+/// `service.name` -> `service_name`); missing fields are omitted. The log
+/// line is the serialized item. Batches are sent like
+/// [`http_batch_writer_new`](crate::write::http::http_batch_writer_new).
+///
+/// # Arguments
+/// - endpoint: The push URL(e.g. `"http://127.0.0.1:3100/loki/api/v1/push"`).
+/// - labels: The resource keys used as stream labels.
+/// - serialize: Serializes the log line(e.g. logfmt).
+/// - batch_size: The maximum number of items in a request.
+/// - flush_interval: The maximum time an item waits before being sent.
+pub fn loki_logger_new<S>(
+    endpoint: &str,
+    labels: &[&str],
+    serialize: S,
+    batch_size: usize,
+    flush_interval: Duration,
+) -> io::Result<LokiLogger<S>>
+where
+    S: Serialize,
+{
+    let target: Target<Entry> = Target {
+        client: HttpClient::new(endpoint, &[])?,
+        content_type: "application/json".into(),
+        content_encoding: None,
+        encode: Box::new(encode_json),
+        retry: RetryPolicy::default(),
+        check: None,
+    };
+    let mut labels: Vec<(String, String)> = labels
+        .iter()
+        .map(|key: &&str| (key.to_string(), label_name(key)))
+        .collect();
+    labels.sort_by(|a, b| a.1.cmp(&b.1));
+    labels.dedup_by(|a, b| a.1 == b.1);
+    Ok(LokiLogger {
+        serialize,
+        labels,
+        batcher: Batcher::new(target, batch_size, flush_interval)?,
+    })
+}
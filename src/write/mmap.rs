@@ -0,0 +1,99 @@
+//! A log writer backed by a memory-mapped file(requires the `mmap` feature).
+
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+
+use memmap2::MmapMut;
+
+use crate::{
+    write::{stats, LogWrite, TryLogWrite},
+    Severity,
+};
+
+struct MmapState {
+    map: MmapMut,
+    /// The offset of the first unused(NUL) byte.
+    cursor: usize,
+}
+
+struct MmapWriter {
+    state: Mutex<MmapState>,
+}
+
+impl TryLogWrite for MmapWriter {
+    fn try_write(&self, serialized: &str, _level: Severity) -> io::Result<()> {
+        let line: &[u8] = serialized.trim_end_matches('\n').as_bytes();
+        let mut guard = self
+            .state
+            .lock()
+            .map_err(|_| io::Error::other("lock poisoned"))?;
+        let state: &mut MmapState = &mut guard;
+        let end: usize = state.cursor + line.len() + 1;
+        if state.map.len() < end {
+            return Err(io::Error::new(io::ErrorKind::StorageFull, "mmap log full"));
+        }
+        let dst: &mut [u8] = &mut state.map[state.cursor..end];
+        for (d, s) in dst.iter_mut().zip(line) {
+            // NUL marks the unused space.
+            *d = match *s {
+                0 => b' ',
+                b => b,
+            };
+        }
+        dst[line.len()] = b'\n';
+        state.cursor = end;
+        Ok(())
+    }
+}
+
+impl LogWrite for MmapWriter {
+    fn write(&self, serialized: &str, level: Severity) {
+        stats::count_write(self.try_write(serialized, level), serialized, level);
+    }
+
+    /// Syncs the written lines to the disk(they survive a crash of the process without this).
+    fn flush(&self) -> io::Result<()> {
+        match self.state.lock() {
+            Err(_) => Err(io::Error::other("lock poisoned")),
+            Ok(guard) => guard.map.flush(),
+        }
+    }
+}
+
+/// Creates a log writer which appends lines to a pre-sized memory-mapped file.
+///
+/// A line is in the page cache as soon as it is written, so the last lines
+/// survive even if the process is killed(e.g. `SIGKILL`); [`LogWrite::flush`]
+/// syncs them to the disk. The unused space is filled with NUL bytes and an
+/// existing file is appended from its first NUL byte(e.g. `tr -d '\0' <
+/// app.mlog` to read it). Writes fail with [`io::ErrorKind::StorageFull`]
+/// when the file is full.
+///
+/// The file must not be truncated by other processes while it is mapped.
+///
+/// # Arguments
+/// - path: The log file(created if missing).
+/// - capacity: The file size in bytes(a larger existing file is kept as is).
+pub fn mmap_writer_new<P>(path: P, capacity: u64) -> io::Result<impl TryLogWrite>
+where
+    P: AsRef<Path>,
+{
+    let file: File = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?;
+    if file.metadata()?.len() < capacity {
+        file.set_len(capacity)?;
+    }
+    // SAFETY: the file is opened read-write and is not resized while mapped
+    // (a concurrent truncation by another process is documented as unsupported).
+    let map: MmapMut = unsafe { MmapMut::map_mut(&file)? };
+    let cursor: usize = map.iter().position(|b| *b == 0).unwrap_or(map.len());
+    Ok(MmapWriter {
+        state: Mutex::new(MmapState { map, cursor }),
+    })
+}
@@ -0,0 +1,457 @@
+//! A logger which publishes items to an MQTT broker(MQTT 3.1.1).
+
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use crate::{
+    copy::Logger,
+    serialize::{template::Template, Serialize},
+    write::{
+        http::{Batcher, Deliver},
+        net::{tcp_connect, DEFAULT_TIMEOUT},
+        retry::{jitter, RetryPolicy},
+        stats,
+    },
+    Item, Severity,
+};
+
+const CONNECT: u8 = 0x10;
+const CONNACK: u8 = 0x20;
+const PUBLISH: u8 = 0x30;
+const PUBACK: u8 = 0x40;
+const PUBREC: u8 = 0x50;
+const PUBREL: u8 = 0x62;
+const PUBCOMP: u8 = 0x70;
+const PINGREQ: u8 = 0xc0;
+const PINGRESP: u8 = 0xd0;
+
+/// The delivery guarantee of a message.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum MqttQos {
+    /// QoS 0: no ack; a `PINGREQ` after each batch detects broken connections.
+    AtMostOnce,
+    /// QoS 1: acked with `PUBACK`(default); a message may be delivered twice.
+    #[default]
+    AtLeastOnce,
+    /// QoS 2: the `PUBREC`/`PUBREL`/`PUBCOMP` handshake; exactly once per
+    /// connection(a batch retried on a new session may be delivered twice).
+    ExactlyOnce,
+}
+
+/// A message to publish.
+struct Message {
+    topic: String,
+    payload: Vec<u8>,
+}
+
+fn write_remaining_length(mut len: usize, buf: &mut Vec<u8>) {
+    loop {
+        let b: u8 = (len % 128) as u8;
+        len /= 128;
+        match len {
+            0 => return buf.push(b),
+            _ => buf.push(b | 0x80),
+        }
+    }
+}
+
+fn write_string(s: &[u8], buf: &mut Vec<u8>) {
+    buf.extend_from_slice(&(s.len() as u16).to_be_bytes());
+    buf.extend_from_slice(s);
+}
+
+/// Writes a packet(the fixed header and the rest).
+fn write_packet(kind: u8, rest: &[u8], buf: &mut Vec<u8>) {
+    buf.push(kind);
+    write_remaining_length(rest.len(), buf);
+    buf.extend_from_slice(rest);
+}
+
+/// Writes a `CONNECT` packet of a clean session without keep alive.
+fn write_connect(client_id: &str, credentials: Option<(&str, &str)>, buf: &mut Vec<u8>) {
+    let mut rest: Vec<u8> = Vec::new();
+    write_string(b"MQTT", &mut rest);
+    rest.push(4);
+    let mut flags: u8 = 0x02;
+    if credentials.is_some() {
+        flags |= 0xc0;
+    }
+    rest.push(flags);
+    rest.extend_from_slice(&0u16.to_be_bytes());
+    write_string(client_id.as_bytes(), &mut rest);
+    if let Some((user, password)) = credentials {
+        write_string(user.as_bytes(), &mut rest);
+        write_string(password.as_bytes(), &mut rest);
+    }
+    write_packet(CONNECT, &rest, buf);
+}
+
+/// Writes a `PUBLISH` packet; the packet identifier is required if 0 < qos.
+fn write_publish(topic: &str, qos: u8, id: Option<u16>, payload: &[u8], buf: &mut Vec<u8>) {
+    let mut rest: Vec<u8> = Vec::with_capacity(2 + topic.len() + 2 + payload.len());
+    write_string(topic.as_bytes(), &mut rest);
+    if let Some(id) = id {
+        rest.extend_from_slice(&id.to_be_bytes());
+    }
+    rest.extend_from_slice(payload);
+    write_packet(PUBLISH | (qos << 1), &rest, buf);
+}
+
+/// Reads a packet; gets the type byte and the rest.
+fn read_packet<R>(r: &mut R) -> io::Result<(u8, Vec<u8>)>
+where
+    R: Read,
+{
+    let mut b: [u8; 1] = [0];
+    r.read_exact(&mut b)?;
+    let kind: u8 = b[0];
+    let mut len: usize = 0;
+    for shift in [0, 7, 14, 21] {
+        r.read_exact(&mut b)?;
+        len |= usize::from(b[0] & 0x7f) << shift;
+        if b[0] < 0x80 {
+            break;
+        }
+    }
+    let mut rest: Vec<u8> = vec![0; len];
+    r.read_exact(&mut rest)?;
+    Ok((kind, rest))
+}
+
+fn packet_id(rest: &[u8]) -> u16 {
+    match rest {
+        [hi, lo, ..] => u16::from_be_bytes([*hi, *lo]),
+        _ => 0,
+    }
+}
+
+/// Publishes batches from the background thread.
+struct Publisher {
+    addrs: Vec<SocketAddr>,
+    client_id: String,
+    credentials: Option<(String, String)>,
+    qos: MqttQos,
+    retry: RetryPolicy,
+    conn: Option<TcpStream>,
+    /// The last packet identifier(never 0).
+    packet_id: u16,
+}
+
+impl Publisher {
+    fn connect(&self) -> io::Result<TcpStream> {
+        let mut s: TcpStream = tcp_connect(&self.addrs.as_slice(), DEFAULT_TIMEOUT)?;
+        s.set_read_timeout(Some(DEFAULT_TIMEOUT))?;
+        let credentials: Option<(&str, &str)> = self
+            .credentials
+            .as_ref()
+            .map(|(u, p)| (u.as_str(), p.as_str()));
+        let mut packet: Vec<u8> = Vec::new();
+        write_connect(&self.client_id, credentials, &mut packet);
+        s.write_all(&packet)?;
+
+        match read_packet(&mut s)? {
+            (CONNACK, rest) if rest.get(1) == Some(&0) => Ok(s),
+            (CONNACK, rest) => Err(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                format!("mqtt connection refused: {}", rest.get(1).unwrap_or(&0)),
+            )),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "mqtt: unexpected packet",
+            )),
+        }
+    }
+
+    fn next_id(&mut self) -> u16 {
+        self.packet_id = self.packet_id.checked_add(1).unwrap_or(1);
+        self.packet_id
+    }
+
+    /// Publishes messages(pipelined) and waits for their acks.
+    fn publish(&mut self, batch: &[Message]) -> io::Result<()> {
+        if self.conn.is_none() {
+            self.conn = Some(self.connect()?);
+        }
+        let qos: u8 = match self.qos {
+            MqttQos::AtMostOnce => 0,
+            MqttQos::AtLeastOnce => 1,
+            MqttQos::ExactlyOnce => 2,
+        };
+        let mut buf: Vec<u8> = Vec::new();
+        let mut pending: Vec<u16> = vec![];
+        for m in batch {
+            let id: Option<u16> = match 0 < qos {
+                true => Some(self.next_id()),
+                false => None,
+            };
+            pending.extend(id);
+            write_publish(&m.topic, qos, id, &m.payload, &mut buf);
+        }
+        if qos == 0 {
+            write_packet(PINGREQ, &[], &mut buf);
+        }
+
+        let s: &mut TcpStream = self
+            .conn
+            .as_mut()
+            .ok_or_else(|| io::Error::other("no connection"))?;
+        s.write_all(&buf)?;
+        let mut waiting_ping: bool = qos == 0;
+        while waiting_ping || !pending.is_empty() {
+            let (kind, rest) = read_packet(s)?;
+            let id: u16 = packet_id(&rest);
+            match kind & 0xf0 {
+                PINGRESP => waiting_ping = false,
+                PUBACK | PUBCOMP => pending.retain(|p: &u16| *p != id),
+                PUBREC => {
+                    let mut rel: Vec<u8> = Vec::new();
+                    write_packet(PUBREL, &id.to_be_bytes(), &mut rel);
+                    s.write_all(&rel)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Deliver<Message> for Publisher {
+    /// Publishes a batch, reconnecting and retrying after failures.
+    fn deliver(&mut self, batch: &[Message]) -> io::Result<()> {
+        let mut failures: u32 = 0;
+        loop {
+            let e: io::Error = match self.publish(batch) {
+                Ok(()) => return Ok(()),
+                Err(e) => e,
+            };
+            self.conn = None;
+            failures += 1;
+            if self.retry.max_attempts <= failures {
+                return Err(e);
+            }
+            std::thread::sleep(jitter(self.retry.backoff(failures)));
+        }
+    }
+}
+
+/// A logger which publishes serialized items to topics built from the items.
+pub struct MqttLogger<S> {
+    serialize: S,
+    topic: Template,
+    batcher: Batcher<Message, Publisher>,
+}
+
+impl<S> MqttLogger<S> {
+    /// Sets the QoS of messages(default: [`MqttQos::AtLeastOnce`]).
+    pub fn with_qos(self, qos: MqttQos) -> Self {
+        self.batcher
+            .configure(|p: &mut Publisher| {
+                p.qos = qos;
+                Ok(())
+            })
+            .ok();
+        self
+    }
+
+    /// Sets the client identifier(default: `rs-simple-logging-<pid>`).
+    pub fn with_client_id(self, client_id: &str) -> Self {
+        self.batcher
+            .configure(|p: &mut Publisher| {
+                p.client_id = client_id.into();
+                p.conn = None;
+                Ok(())
+            })
+            .ok();
+        self
+    }
+
+    /// Authenticates with a user name and a password.
+    pub fn with_credentials(self, user: &str, password: &str) -> Self {
+        self.batcher
+            .configure(|p: &mut Publisher| {
+                p.credentials = Some((user.into(), password.into()));
+                p.conn = None;
+                Ok(())
+            })
+            .ok();
+        self
+    }
+
+    /// Sets the retries of a failed batch(each on a new connection).
+    pub fn with_retry_policy(self, policy: RetryPolicy) -> Self {
+        self.batcher
+            .configure(|p: &mut Publisher| {
+                p.retry = policy;
+                Ok(())
+            })
+            .ok();
+        self
+    }
+
+    /// Sets the maximum number of queued items(default: 8 batches).
+    pub fn with_max_pending(self, max_pending: usize) -> Self {
+        self.batcher.set_max_pending(max_pending);
+        self
+    }
+
+    /// Sends the queued items at once after an item of this severity or higher
+    /// is logged(default: Error; None disables it).
+    pub fn with_flush_severity(self, level: Option<Severity>) -> Self {
+        self.batcher.set_flush_severity(level);
+        self
+    }
+}
+
+impl<S> Logger for MqttLogger<S>
+where
+    S: Serialize,
+{
+    fn log(&self, item: Item) {
+        let level: Severity = item.severity;
+        // Wildcards are not allowed in topic names.
+        let topic: String = self.topic.render_item(&item).replace(['+', '#'], "_");
+        let mut payload: String = String::new();
+        self.serialize.serialize(&item, &mut payload);
+        let payload: &str = payload.trim_end_matches('\n');
+        let message = Message {
+            topic,
+            payload: payload.as_bytes().to_vec(),
+        };
+        stats::count_queued(self.batcher.push(message, level));
+    }
+
+    /// Publishes the queued items; fails if a batch was dropped since the last flush.
+    fn flush(&self) -> io::Result<()> {
+        self.batcher.flush()
+    }
+}
+
+/// Creates a logger which publishes serialized items to an MQTT broker in batches.
+///
+/// The topic of an item is a template filled with its resource fields and
+/// `{severity}`(e.g. `"devices/{host.name}/logs"`; `unknown` if missing).
+/// Batches are published from a background thread over plain TCP with a clean
+/// session. After a failure the connection is made again and the batch is
+/// published again, waiting with [`RetryPolicy::default`].
+///
+/// # Arguments
+/// - addr: The address of the broker(e.g. `"127.0.0.1:1883"`).
+/// - topic: The topic template.
+/// - serialize: Serializes the message payload.
+/// - batch_size: The maximum number of items published at a time.
+/// - flush_interval: The maximum time an item waits before being published.
+pub fn mqtt_logger_new<A, S>(
+    addr: A,
+    topic: &str,
+    serialize: S,
+    batch_size: usize,
+    flush_interval: Duration,
+) -> io::Result<MqttLogger<S>>
+where
+    A: ToSocketAddrs,
+    S: Serialize,
+{
+    let publisher = Publisher {
+        addrs: addr.to_socket_addrs()?.collect(),
+        client_id: format!("rs-simple-logging-{}", std::process::id()),
+        credentials: None,
+        qos: MqttQos::default(),
+        retry: RetryPolicy::default(),
+        conn: None,
+        packet_id: 0,
+    };
+    Ok(MqttLogger {
+        serialize,
+        topic: Template::parse(topic),
+        batcher: Batcher::new(publisher, batch_size, flush_interval)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_packet, write_connect, write_publish, write_remaining_length, PUBREL};
+
+    #[test]
+    fn remaining_length_boundaries() {
+        // MQTT 3.1.1 table 2.4.
+        let cases: [(usize, &[u8]); 8] = [
+            (0, &[0x00]),
+            (127, &[0x7f]),
+            (128, &[0x80, 0x01]),
+            (16_383, &[0xff, 0x7f]),
+            (16_384, &[0x80, 0x80, 0x01]),
+            (2_097_151, &[0xff, 0xff, 0x7f]),
+            (2_097_152, &[0x80, 0x80, 0x80, 0x01]),
+            (268_435_455, &[0xff, 0xff, 0xff, 0x7f]),
+        ];
+        for (len, expected) in cases {
+            let mut buf: Vec<u8> = Vec::new();
+            write_remaining_length(len, &mut buf);
+            assert_eq!(expected, buf.as_slice(), "{len}");
+        }
+    }
+
+    #[test]
+    fn connect_golden_bytes() {
+        let mut buf: Vec<u8> = Vec::new();
+        write_connect("c1", None, &mut buf);
+        #[rustfmt::skip]
+        let expected: &[u8] = &[
+            0x10, 14,
+            0x00, 0x04, b'M', b'Q', b'T', b'T', 4, 0x02, 0x00, 0x00,
+            0x00, 0x02, b'c', b'1',
+        ];
+        assert_eq!(expected, buf.as_slice());
+
+        buf.clear();
+        write_connect("c1", Some(("u", "pw")), &mut buf);
+        #[rustfmt::skip]
+        let expected: &[u8] = &[
+            0x10, 21,
+            0x00, 0x04, b'M', b'Q', b'T', b'T', 4, 0xc2, 0x00, 0x00,
+            0x00, 0x02, b'c', b'1',
+            0x00, 0x01, b'u',
+            0x00, 0x02, b'p', b'w',
+        ];
+        assert_eq!(expected, buf.as_slice());
+    }
+
+    #[test]
+    fn publish_golden_bytes() {
+        let mut buf: Vec<u8> = Vec::new();
+        write_publish("a/b", 0, None, b"hi", &mut buf);
+        assert_eq!(
+            &[0x30, 7, 0x00, 0x03, b'a', b'/', b'b', b'h', b'i'],
+            buf.as_slice()
+        );
+
+        buf.clear();
+        write_publish("a/b", 1, Some(0x0102), b"hi", &mut buf);
+        #[rustfmt::skip]
+        let expected: &[u8] = &[
+            0x32, 9, 0x00, 0x03, b'a', b'/', b'b', 0x01, 0x02, b'h', b'i',
+        ];
+        assert_eq!(expected, buf.as_slice());
+
+        buf.clear();
+        write_publish("t", 2, Some(1), &[b'x'; 200], &mut buf);
+        assert_eq!(&[0x34, 0xcd, 0x01, 0x00, 0x01, b't', 0x00, 0x01], &buf[..8]);
+        assert_eq!(208, buf.len());
+    }
+
+    #[test]
+    fn read_packet_round_trip() {
+        let mut buf: Vec<u8> = Vec::new();
+        write_publish("t", 2, Some(1), &[b'x'; 200], &mut buf);
+        buf.extend_from_slice(&[PUBREL, 2, 0x00, 0x07]);
+        let mut r: &[u8] = &buf;
+        let (kind, rest) = read_packet(&mut r).unwrap();
+        assert_eq!(0x34, kind);
+        assert_eq!(205, rest.len());
+        let (kind, rest) = read_packet(&mut r).unwrap();
+        assert_eq!(PUBREL, kind);
+        assert_eq!(vec![0x00, 0x07], rest);
+        assert!(r.is_empty());
+    }
+}
@@ -0,0 +1,484 @@
+//! A logger which publishes items to NATS subjects(optionally acked by JetStream).
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+use crate::{
+    copy::Logger,
+    serialize::{json::ObjectWriter, template::Template, Serialize},
+    write::{
+        http::{Batcher, Deliver},
+        net::{tcp_connect, truncate_str, DEFAULT_TIMEOUT},
+        retry::{jitter, RetryPolicy},
+        stats,
+    },
+    Item, Severity,
+};
+
+/// A message to publish.
+struct Message {
+    subject: String,
+    payload: Vec<u8>,
+}
+
+/// Gets a number field of the `INFO` JSON(e.g. `max_payload`).
+fn info_number(info: &str, key: &str) -> Option<usize> {
+    let (_, rest) = info.split_once(&format!("\"{key}\":"))?;
+    let digits: String = rest
+        .trim_start()
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok()
+}
+
+fn protocol_error(line: &str) -> io::Error {
+    io::Error::other(format!("nats: {}", truncate_str(line.trim_end(), 256)))
+}
+
+/// A client connection.
+struct Conn {
+    reader: BufReader<TcpStream>,
+    max_payload: usize,
+}
+
+impl Conn {
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        self.reader.get_mut().write_all(data)
+    }
+
+    /// Reads a protocol line(answering `PING`); `-ERR` is an error.
+    fn read_line(&mut self) -> io::Result<String> {
+        loop {
+            let mut line: String = String::new();
+            if 0 == self.reader.read_line(&mut line)? {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            match line.trim_end() {
+                "PING" => self.write(b"PONG\r\n")?,
+                "+OK" | "" => {}
+                l if l.starts_with("-ERR") => return Err(protocol_error(l)),
+                _ => return Ok(line),
+            }
+        }
+    }
+
+    /// Answers the `PING`s received since the last call(without blocking).
+    fn drain(&mut self) -> io::Result<()> {
+        self.reader.get_ref().set_nonblocking(true)?;
+        let mut result: io::Result<()> = Ok(());
+        while result.is_ok() {
+            result = match self.reader.fill_buf() {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => Err(e),
+                Ok([]) => Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(_) => self.read_line().and_then(|l| match l.starts_with("INFO ") {
+                    true => Ok(()),
+                    false => Err(protocol_error(&l)),
+                }),
+            };
+        }
+        self.reader.get_ref().set_nonblocking(false)?;
+        result
+    }
+
+    /// Sends `PING` and waits for `PONG`(the server processed the preceding commands).
+    fn ping(&mut self) -> io::Result<()> {
+        self.write(b"PING\r\n")?;
+        loop {
+            let line: String = self.read_line()?;
+            match line.trim_end() {
+                "PONG" => return Ok(()),
+                l if l.starts_with("INFO ") => {}
+                l => return Err(protocol_error(l)),
+            }
+        }
+    }
+
+    /// Reads a `MSG`/`HMSG` to an inbox; gets the last subject token and the payload.
+    fn read_reply(&mut self) -> io::Result<(String, String)> {
+        let line: String = loop {
+            let line: String = self.read_line()?;
+            if !line.starts_with("INFO ") {
+                break line;
+            }
+        };
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let (subject, len) = match fields.as_slice() {
+            ["MSG", subject, .., len] | ["HMSG", subject, .., _, len] => (*subject, *len),
+            _ => return Err(protocol_error(&line)),
+        };
+        let len: usize = len.parse().map_err(|_| protocol_error(&line))?;
+        let mut payload: Vec<u8> = vec![0; len + 2];
+        self.reader.read_exact(&mut payload)?;
+        payload.truncate(len);
+        let token: &str = subject.rsplit('.').next().unwrap_or("");
+        Ok((token.into(), String::from_utf8_lossy(&payload).into()))
+    }
+}
+
+/// Checks a JetStream ack(`{"stream":..,"seq":..}`) or a `503` no responders status.
+fn check_ack(payload: &str) -> io::Result<()> {
+    match payload.starts_with("NATS/1.0") || payload.contains("\"error\"") {
+        true => Err(io::Error::other(format!(
+            "jetstream: {}",
+            truncate_str(payload.trim_end(), 256)
+        ))),
+        false => Ok(()),
+    }
+}
+
+/// Writes the `CONNECT` command with the credentials.
+fn write_connect(auth: &[(&'static str, String)], buf: &mut Vec<u8>) {
+    let mut options: String = String::new();
+    let mut obj = ObjectWriter::begin(&mut options);
+    obj.raw("verbose", "false");
+    obj.raw("pedantic", "false");
+    obj.str("lang", "rust");
+    obj.str("version", env!("CARGO_PKG_VERSION"));
+    obj.str("name", "rs-simple-logging");
+    obj.raw("protocol", "1");
+    obj.raw("headers", "true");
+    obj.raw("no_responders", "true");
+    for (key, val) in auth {
+        obj.str(key, val);
+    }
+    obj.end();
+    buf.extend_from_slice(b"CONNECT ");
+    buf.extend_from_slice(options.as_bytes());
+    buf.extend_from_slice(b"\r\n");
+}
+
+/// Writes a `PUB` command with an optional reply subject.
+fn write_pub(subject: &str, reply: Option<&str>, payload: &[u8], buf: &mut Vec<u8>) {
+    let head: String = match reply {
+        None => format!("PUB {subject} {}\r\n", payload.len()),
+        Some(reply) => format!("PUB {subject} {reply} {}\r\n", payload.len()),
+    };
+    buf.extend_from_slice(head.as_bytes());
+    buf.extend_from_slice(payload);
+    buf.extend_from_slice(b"\r\n");
+}
+
+/// Publishes batches from the background thread.
+struct Publisher {
+    addrs: Vec<SocketAddr>,
+    /// The credentials in `CONNECT`(e.g. `auth_token`).
+    auth: Vec<(&'static str, String)>,
+    retry: RetryPolicy,
+    /// The reply subject prefix of JetStream acks.
+    inbox: Option<String>,
+    conn: Option<Conn>,
+}
+
+impl Publisher {
+    fn connect(&self) -> io::Result<Conn> {
+        let s: TcpStream = tcp_connect(&self.addrs.as_slice(), DEFAULT_TIMEOUT)?;
+        s.set_read_timeout(Some(DEFAULT_TIMEOUT))?;
+        let mut conn = Conn {
+            reader: BufReader::new(s),
+            max_payload: 1 << 20,
+        };
+        let info: String = conn.read_line()?;
+        if !info.starts_with("INFO ") {
+            return Err(protocol_error(&info));
+        }
+        if info.contains("\"tls_required\":true") {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "nats server requires tls",
+            ));
+        }
+        if let Some(max) = info_number(&info, "max_payload") {
+            conn.max_payload = max;
+        }
+
+        let mut command: Vec<u8> = Vec::new();
+        write_connect(&self.auth, &mut command);
+        conn.write(&command)?;
+        if let Some(inbox) = &self.inbox {
+            conn.write(format!("SUB {inbox}.* 1\r\n").as_bytes())?;
+        }
+        conn.ping()?;
+        Ok(conn)
+    }
+
+    /// Publishes messages once; gets the indices to retry and the last error.
+    fn publish(
+        &mut self,
+        batch: &[Message],
+        indices: Vec<usize>,
+    ) -> (Vec<usize>, Option<io::Error>) {
+        let conn: &mut Conn = match &mut self.conn {
+            Some(c) => c,
+            None => match self.connect() {
+                Ok(c) => self.conn.insert(c),
+                Err(e) => return (indices, Some(e)),
+            },
+        };
+        let mut last: Option<io::Error> = None;
+        let mut buf: Vec<u8> = Vec::new();
+        let mut sent: Vec<usize> = vec![];
+        for i in indices.iter().copied() {
+            let m: &Message = &batch[i];
+            if conn.max_payload < m.payload.len() {
+                last = Some(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "nats: message larger than max_payload",
+                ));
+                continue;
+            }
+            let reply: Option<String> = self.inbox.as_ref().map(|inbox| format!("{inbox}.{i}"));
+            write_pub(&m.subject, reply.as_deref(), &m.payload, &mut buf);
+            sent.push(i);
+        }
+
+        let acked: io::Result<Vec<usize>> = conn.drain().and_then(|_| {
+            conn.write(&buf)?;
+            if self.inbox.is_none() {
+                return conn.ping().map(|_| vec![]);
+            }
+            let deadline: Instant = Instant::now() + DEFAULT_TIMEOUT;
+            let mut pending: Vec<usize> = sent.clone();
+            while !pending.is_empty() && Instant::now() < deadline {
+                let (token, payload) = conn.read_reply()?;
+                let Some(pos) = pending.iter().position(|i| i.to_string() == token) else {
+                    // An ack of a message of an earlier, failed attempt.
+                    continue;
+                };
+                pending.swap_remove(pos);
+                if let Err(e) = check_ack(&payload) {
+                    last = Some(e);
+                }
+            }
+            Ok(pending)
+        });
+        match acked {
+            Ok(pending) if pending.is_empty() => (vec![], last),
+            Ok(pending) => (
+                pending,
+                Some(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "jetstream ack timed out",
+                )),
+            ),
+            Err(e) => {
+                self.conn = None;
+                (sent, Some(e))
+            }
+        }
+    }
+}
+
+impl Deliver<Message> for Publisher {
+    /// Publishes a batch, reconnecting and retrying after connection errors.
+    fn deliver(&mut self, batch: &[Message]) -> io::Result<()> {
+        let mut pending: Vec<usize> = (0..batch.len()).collect();
+        let mut failures: u32 = 0;
+        loop {
+            let (failed, e) = self.publish(batch, pending);
+            if failed.is_empty() {
+                return e.map_or(Ok(()), Err);
+            }
+            failures += 1;
+            if self.retry.max_attempts <= failures {
+                return Err(e.unwrap_or_else(|| io::Error::other("nats publish failed")));
+            }
+            std::thread::sleep(jitter(self.retry.backoff(failures)));
+            pending = failed;
+        }
+    }
+}
+
+/// A logger which publishes serialized items to subjects built from the items.
+pub struct NatsLogger<S> {
+    serialize: S,
+    subject: Template,
+    batcher: Batcher<Message, Publisher>,
+}
+
+impl<S> NatsLogger<S> {
+    /// Waits for the JetStream ack of each message.
+    ///
+    /// The subjects must be captured by a stream; a message without an ack
+    /// is published again, so it may be stored twice.
+    pub fn with_jetstream(self) -> Self {
+        let mut h = RandomState::new().build_hasher();
+        h.write_u64(std::process::id().into());
+        let inbox: String = format!("_INBOX.{:016x}", h.finish());
+        self.batcher
+            .configure(|p: &mut Publisher| {
+                p.inbox = Some(inbox);
+                p.conn = None;
+                Ok(())
+            })
+            .ok();
+        self
+    }
+
+    /// Authenticates with a token.
+    pub fn with_token(self, token: &str) -> Self {
+        self.set_auth(vec![("auth_token", token.into())])
+    }
+
+    /// Authenticates with a user and a password.
+    pub fn with_user_password(self, user: &str, password: &str) -> Self {
+        self.set_auth(vec![("user", user.into()), ("pass", password.into())])
+    }
+
+    fn set_auth(self, auth: Vec<(&'static str, String)>) -> Self {
+        self.batcher
+            .configure(|p: &mut Publisher| {
+                p.auth = auth;
+                p.conn = None;
+                Ok(())
+            })
+            .ok();
+        self
+    }
+
+    /// Sets the retries of a failed batch(connection errors and ack timeouts).
+    pub fn with_retry_policy(self, policy: RetryPolicy) -> Self {
+        self.batcher
+            .configure(|p: &mut Publisher| {
+                p.retry = policy;
+                Ok(())
+            })
+            .ok();
+        self
+    }
+
+    /// Sets the maximum number of queued items(default: 8 batches).
+    pub fn with_max_pending(self, max_pending: usize) -> Self {
+        self.batcher.set_max_pending(max_pending);
+        self
+    }
+
+    /// Sends the queued items at once after an item of this severity or higher
+    /// is logged(default: Error; None disables it).
+    pub fn with_flush_severity(self, level: Option<Severity>) -> Self {
+        self.batcher.set_flush_severity(level);
+        self
+    }
+}
+
+impl<S> Logger for NatsLogger<S>
+where
+    S: Serialize,
+{
+    fn log(&self, item: Item) {
+        let level: Severity = item.severity;
+        // Whitespace and wildcards are not allowed in subjects.
+        let subject: String = self
+            .subject
+            .render_item(&item)
+            .chars()
+            .map(|c: char| match c.is_whitespace() || c == '*' || c == '>' {
+                true => '_',
+                false => c,
+            })
+            .collect();
+        let mut payload: String = String::new();
+        self.serialize.serialize(&item, &mut payload);
+        let payload: &str = payload.trim_end_matches('\n');
+        let message = Message {
+            subject,
+            payload: payload.as_bytes().to_vec(),
+        };
+        stats::count_queued(self.batcher.push(message, level));
+    }
+
+    /// Publishes the queued items; fails if a batch was dropped since the last flush.
+    fn flush(&self) -> io::Result<()> {
+        self.batcher.flush()
+    }
+}
+
+/// Creates a logger which publishes serialized items to NATS in batches.
+///
+/// The subject of an item is a template filled with its resource fields and
+/// `{severity}`(e.g. `"logs.{service.name}.{severity}"`; `unknown` if
+/// missing). Batches are published from a background thread over plain TCP,
+/// followed by a `PING` to detect errors; connection errors are retried with
+/// [`RetryPolicy::default`] on a new connection. See
+/// [`NatsLogger::with_jetstream`] for acked publishing.
+///
+/// # Arguments
+/// - addr: The address of the server(e.g. `"127.0.0.1:4222"`).
+/// - subject: The subject template.
+/// - serialize: Serializes the message payload.
+/// - batch_size: The maximum number of items published at a time.
+/// - flush_interval: The maximum time an item waits before being published.
+pub fn nats_logger_new<A, S>(
+    addr: A,
+    subject: &str,
+    serialize: S,
+    batch_size: usize,
+    flush_interval: Duration,
+) -> io::Result<NatsLogger<S>>
+where
+    A: ToSocketAddrs,
+    S: Serialize,
+{
+    let publisher = Publisher {
+        addrs: addr.to_socket_addrs()?.collect(),
+        auth: vec![],
+        retry: RetryPolicy::default(),
+        inbox: None,
+        conn: None,
+    };
+    Ok(NatsLogger {
+        serialize,
+        subject: Template::parse(subject),
+        batcher: Batcher::new(publisher, batch_size, flush_interval)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_ack, info_number, write_connect, write_pub};
+
+    #[test]
+    fn pub_golden_bytes() {
+        let mut buf: Vec<u8> = Vec::new();
+        write_pub("logs.app", None, b"hello", &mut buf);
+        assert_eq!(b"PUB logs.app 5\r\nhello\r\n", buf.as_slice());
+
+        buf.clear();
+        write_pub("logs.app", Some("_INBOX.00ff.3"), b"", &mut buf);
+        assert_eq!(b"PUB logs.app _INBOX.00ff.3 0\r\n\r\n", buf.as_slice());
+    }
+
+    #[test]
+    fn connect_golden_bytes() {
+        let mut buf: Vec<u8> = Vec::new();
+        write_connect(&[("auth_token", "s\"3".into())], &mut buf);
+        let expected: String = format!(
+            concat!(
+                "CONNECT {{\"verbose\":false,\"pedantic\":false,\"lang\":\"rust\",",
+                "\"version\":\"{}\",\"name\":\"rs-simple-logging\",\"protocol\":1,",
+                "\"headers\":true,\"no_responders\":true,\"auth_token\":\"s\\\"3\"}}\r\n",
+            ),
+            env!("CARGO_PKG_VERSION"),
+        );
+        assert_eq!(expected, String::from_utf8(buf).unwrap());
+    }
+
+    #[test]
+    fn info_numbers() {
+        let info: &str = r#"INFO {"server_id":"x","max_payload": 1048576,"proto":1}"#;
+        assert_eq!(Some(1_048_576), info_number(info, "max_payload"));
+        assert_eq!(Some(1), info_number(info, "proto"));
+        assert_eq!(None, info_number(info, "port"));
+    }
+
+    #[test]
+    fn jetstream_acks() {
+        assert!(check_ack(r#"{"stream":"LOGS","seq":7}"#).is_ok());
+        assert!(check_ack(r#"{"error":{"code":503}}"#).is_err());
+        assert!(check_ack("NATS/1.0 503\r\n\r\n").is_err());
+    }
+}
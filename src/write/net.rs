@@ -0,0 +1,553 @@
+//! Network log writers.
+
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::{
+    write::{
+        retry::{jitter, RetryPolicy},
+        stats, LogWrite, TryLogWrite,
+    },
+    Severity,
+};
+
+#[cfg(feature = "tls")]
+pub mod tls;
+
+/// The default number of records kept while disconnected.
+pub const DEFAULT_MAX_PENDING: usize = 1024;
+
+/// The default timeout for connecting and writing.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+type Connect = Box<dyn Fn() -> io::Result<Box<dyn Write + Send>> + Sync + Send>;
+
+/// Frames a record with a newline delimiter.
+pub(crate) fn frame_newline(serialized: &str, buf: &mut Vec<u8>) {
+    buf.extend_from_slice(serialized.trim_end_matches('\n').as_bytes());
+    buf.push(b'\n');
+}
+
+struct StreamState {
+    stream: Option<Box<dyn Write + Send>>,
+    pending: VecDeque<String>,
+    backoff: RetryPolicy,
+    failures: u32,
+    retry_at: Option<Instant>,
+    /// A background thread is connecting.
+    connecting: bool,
+}
+
+struct StreamShared {
+    connect: Connect,
+    frame: fn(&str, &mut Vec<u8>),
+    state: Mutex<StreamState>,
+}
+
+impl StreamShared {
+    fn disconnected(&self, state: &mut StreamState) {
+        state.stream = None;
+        state.failures = state.failures.saturating_add(1);
+        let backoff: Duration = state.backoff.backoff(state.failures);
+        state.retry_at = Some(Instant::now() + jitter(backoff));
+    }
+
+    fn connected(&self, state: &mut StreamState, stream: Box<dyn Write + Send>) {
+        state.stream = Some(stream);
+        state.failures = 0;
+        state.retry_at = None;
+    }
+
+    /// Checks if the backoff after the last failure has elapsed.
+    fn may_connect(state: &StreamState) -> bool {
+        state.retry_at.is_none_or(|t: Instant| t <= Instant::now())
+    }
+
+    /// Writes pending records to the connected stream.
+    ///
+    /// A record is removed once written in full. A record partially written
+    /// when the connection fails is sent again in full over the next
+    /// connection, so the collector may also get a truncated copy.
+    fn send_pending(&self, state: &mut StreamState) -> io::Result<()> {
+        let mut buf: Vec<u8> = Vec::new();
+        while let Some(line) = state.pending.front() {
+            buf.clear();
+            (self.frame)(line, &mut buf);
+            let written: io::Result<()> = match &mut state.stream {
+                None => Err(io::Error::new(io::ErrorKind::NotConnected, "not connected")),
+                Some(s) => s.write_all(&buf),
+            };
+            match written {
+                Ok(_) => {
+                    state.pending.pop_front();
+                }
+                Err(e) => {
+                    self.disconnected(state);
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Connects on a background thread if the backoff has elapsed.
+    fn reconnect_in_background(self: &Arc<Self>, state: &mut StreamState) {
+        if state.connecting || !Self::may_connect(state) {
+            return;
+        }
+        state.connecting = true;
+        let shared: Arc<Self> = self.clone();
+        let spawned = std::thread::Builder::new()
+            .name("log-reconnect".into())
+            .spawn(move || {
+                let connected = (shared.connect)();
+                if let Ok(mut guard) = shared.state.lock() {
+                    guard.connecting = false;
+                    match connected {
+                        Err(_) => shared.disconnected(&mut guard),
+                        Ok(s) => {
+                            shared.connected(&mut guard, s);
+                            shared.send_pending(&mut guard).ok();
+                        }
+                    }
+                }
+            });
+        if spawned.is_err() {
+            state.connecting = false;
+            self.disconnected(state);
+        }
+    }
+}
+
+/// A log writer which keeps a stream connection and reconnects after failures.
+pub struct StreamWriter {
+    max_pending: usize,
+    shared: Arc<StreamShared>,
+}
+
+impl StreamWriter {
+    pub(crate) fn new(connect: Connect, frame: fn(&str, &mut Vec<u8>)) -> Self {
+        Self {
+            max_pending: DEFAULT_MAX_PENDING,
+            shared: Arc::new(StreamShared {
+                connect,
+                frame,
+                state: Mutex::new(StreamState {
+                    stream: None,
+                    pending: VecDeque::new(),
+                    backoff: RetryPolicy {
+                        max_attempts: u32::MAX,
+                        initial_backoff: Duration::from_millis(100),
+                        max_backoff: Duration::from_secs(30),
+                    },
+                    failures: 0,
+                    retry_at: None,
+                    connecting: false,
+                }),
+            }),
+        }
+    }
+
+    /// Sets the number of records kept while disconnected(default: [`DEFAULT_MAX_PENDING`]).
+    pub fn with_max_pending(mut self, max_pending: usize) -> Self {
+        self.max_pending = max_pending.max(1);
+        self
+    }
+
+    /// Sets the reconnect backoff(default: 100ms doubled up to 30s, with jitter).
+    pub fn with_backoff(self, initial: Duration, max: Duration) -> Self {
+        if let Ok(mut guard) = self.shared.state.lock() {
+            guard.backoff.initial_backoff = initial;
+            guard.backoff.max_backoff = max;
+        }
+        self
+    }
+}
+
+impl TryLogWrite for StreamWriter {
+    /// Sends a record; it is kept for a later attempt while disconnected.
+    ///
+    /// Connecting runs on a background thread, so a write never waits for it.
+    /// Fails only if the record was dropped because too many are pending.
+    fn try_write(&self, serialized: &str, _level: Severity) -> io::Result<()> {
+        let mut guard = self
+            .shared
+            .state
+            .lock()
+            .map_err(|_| io::Error::other("lock poisoned"))?;
+        if self.max_pending <= guard.pending.len() && guard.stream.is_some() {
+            self.shared.send_pending(&mut guard).ok();
+        }
+        if self.max_pending <= guard.pending.len() {
+            self.shared.reconnect_in_background(&mut guard);
+            return Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "too many pending records",
+            ));
+        }
+        guard.pending.push_back(serialized.into());
+        match guard.stream.is_some() {
+            true => {
+                self.shared.send_pending(&mut guard).ok();
+            }
+            false => self.shared.reconnect_in_background(&mut guard),
+        }
+        Ok(())
+    }
+}
+
+impl LogWrite for StreamWriter {
+    fn write(&self, serialized: &str, level: Severity) {
+        stats::count_write(self.try_write(serialized, level), serialized, level);
+    }
+
+    /// Writes pending records and flushes the connection.
+    ///
+    /// Unlike a write, a flush waits for a connection(unless the backoff after
+    /// a failure has not elapsed); other writers are not blocked meanwhile.
+    fn flush(&self) -> io::Result<()> {
+        let shared: &StreamShared = &self.shared;
+        let poisoned = |_| io::Error::other("lock poisoned");
+        let mut guard = shared.state.lock().map_err(poisoned)?;
+        if guard.stream.is_none() {
+            if guard.connecting || !StreamShared::may_connect(&guard) {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotConnected,
+                    "waiting to reconnect",
+                ));
+            }
+            guard.connecting = true;
+            drop(guard);
+            let connected = (shared.connect)();
+            guard = shared.state.lock().map_err(poisoned)?;
+            guard.connecting = false;
+            match connected {
+                Err(e) => {
+                    shared.disconnected(&mut guard);
+                    return Err(e);
+                }
+                Ok(s) => shared.connected(&mut guard, s),
+            }
+        }
+        shared.send_pending(&mut guard)?;
+        match &mut guard.stream {
+            None => Ok(()),
+            Some(s) => s.flush(),
+        }
+    }
+}
+
+/// Connects to the first reachable address.
+pub(crate) fn tcp_connect<A>(addr: &A, timeout: Duration) -> io::Result<TcpStream>
+where
+    A: ToSocketAddrs,
+{
+    let mut last: io::Error = io::Error::new(io::ErrorKind::NotFound, "no address");
+    for a in addr.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&a, timeout) {
+            Err(e) => last = e,
+            Ok(s) => {
+                s.set_write_timeout(Some(timeout))?;
+                s.set_nodelay(true)?;
+                return Ok(s);
+            }
+        }
+    }
+    Err(last)
+}
+
+/// Creates a log writer which sends newline-delimited records over TCP.
+///
+/// The connection is made by a background thread after the first write and
+/// made again after a failure, waiting with exponential backoff between
+/// attempts; writes never wait for a connection. While disconnected, up to
+/// [`DEFAULT_MAX_PENDING`] records are kept and sent after reconnecting.
+/// The address is resolved on each connection attempt.
+///
+/// # Arguments
+/// - addr: The address of the collector(e.g. `"logs.example.com:5170"`).
+pub fn tcp_writer_new<A>(addr: A) -> StreamWriter
+where
+    A: ToSocketAddrs + Sync + Send + 'static,
+{
+    StreamWriter::new(
+        Box::new(move || {
+            let s: TcpStream = tcp_connect(&addr, DEFAULT_TIMEOUT)?;
+            Ok(Box::new(s) as Box<dyn Write + Send>)
+        }),
+        frame_newline,
+    )
+}
+
+/// What to do with a record larger than the maximum datagram size.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum OversizePolicy {
+    /// Sends the first bytes(cut at a char boundary).
+    Truncate,
+    /// Drops the record.
+    Drop,
+}
+
+/// Cuts a string at a char boundary so that it fits in `max` bytes.
+pub(crate) fn truncate_str(s: &str, max: usize) -> &str {
+    match s.len() <= max {
+        true => s,
+        false => {
+            let end: usize = (0..=max)
+                .rev()
+                .find(|i| s.is_char_boundary(*i))
+                .unwrap_or(0);
+            &s[..end]
+        }
+    }
+}
+
+/// Removes a trailing newline and applies the size limit.
+fn fit_datagram(serialized: &str, max_size: Option<(usize, OversizePolicy)>) -> io::Result<&str> {
+    let line: &str = serialized.trim_end_matches('\n');
+    match max_size {
+        None => Ok(line),
+        Some((max, _)) if line.len() <= max => Ok(line),
+        Some((max, OversizePolicy::Truncate)) => Ok(truncate_str(line, max)),
+        Some((_, OversizePolicy::Drop)) => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "record larger than the datagram limit",
+        )),
+    }
+}
+
+/// A log writer which sends each record as a datagram.
+pub struct UdpWriter {
+    socket: UdpSocket,
+    max_size: Option<(usize, OversizePolicy)>,
+}
+
+impl UdpWriter {
+    /// Limits the datagram size(default: unlimited; the OS may still reject large datagrams).
+    pub fn with_max_datagram_size(mut self, max: usize, policy: OversizePolicy) -> Self {
+        self.max_size = Some((max, policy));
+        self
+    }
+}
+
+impl TryLogWrite for UdpWriter {
+    fn try_write(&self, serialized: &str, _level: Severity) -> io::Result<()> {
+        let line: &str = fit_datagram(serialized, self.max_size)?;
+        self.socket.send(line.as_bytes()).map(|_| ())
+    }
+}
+
+impl LogWrite for UdpWriter {
+    fn write(&self, serialized: &str, level: Severity) {
+        stats::count_write(self.try_write(serialized, level), serialized, level);
+    }
+}
+
+/// Creates a UDP socket connected to a remote address.
+pub(crate) fn udp_connect<A>(addr: A) -> io::Result<UdpSocket>
+where
+    A: ToSocketAddrs,
+{
+    let remote: SocketAddr = addr
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address"))?;
+    let local: SocketAddr = match remote {
+        SocketAddr::V4(_) => (std::net::Ipv4Addr::UNSPECIFIED, 0).into(),
+        SocketAddr::V6(_) => (std::net::Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+    let socket: UdpSocket = UdpSocket::bind(local)?;
+    socket.connect(remote)?;
+    Ok(socket)
+}
+
+/// Creates a log writer which sends each record as a UDP datagram(fire and forget).
+///
+/// The address is resolved once. A trailing newline is not sent.
+///
+/// # Arguments
+/// - addr: The address of the collector(e.g. `"127.0.0.1:5140"`).
+pub fn udp_writer_new<A>(addr: A) -> io::Result<UdpWriter>
+where
+    A: ToSocketAddrs,
+{
+    Ok(UdpWriter {
+        socket: udp_connect(addr)?,
+        max_size: None,
+    })
+}
+
+/// Creates a log writer which sends newline-delimited records over a unix stream socket.
+///
+/// Reconnects like [`tcp_writer_new`](e.g. after `EPIPE` when the collector restarts).
+///
+/// # Arguments
+/// - path: The socket path of the collector.
+#[cfg(unix)]
+pub fn unix_stream_writer_new<P>(path: P) -> StreamWriter
+where
+    P: AsRef<std::path::Path> + Sync + Send + 'static,
+{
+    use std::os::unix::net::UnixStream;
+    StreamWriter::new(
+        Box::new(move || {
+            let s: UnixStream = UnixStream::connect(&path)?;
+            s.set_write_timeout(Some(DEFAULT_TIMEOUT))?;
+            Ok(Box::new(s) as Box<dyn Write + Send>)
+        }),
+        frame_newline,
+    )
+}
+
+/// A log writer which sends each record as a unix datagram.
+#[cfg(unix)]
+pub struct UnixDatagramWriter {
+    path: std::path::PathBuf,
+    socket: Mutex<Option<std::os::unix::net::UnixDatagram>>,
+    max_size: Option<(usize, OversizePolicy)>,
+}
+
+#[cfg(unix)]
+impl UnixDatagramWriter {
+    /// Limits the datagram size(default: unlimited).
+    pub fn with_max_datagram_size(mut self, max: usize, policy: OversizePolicy) -> Self {
+        self.max_size = Some((max, policy));
+        self
+    }
+
+    fn connect(&self) -> io::Result<std::os::unix::net::UnixDatagram> {
+        let s = std::os::unix::net::UnixDatagram::unbound()?;
+        s.connect(&self.path)?;
+        s.set_write_timeout(Some(DEFAULT_TIMEOUT))?;
+        Ok(s)
+    }
+
+    /// Sends a datagram; reconnects once if the socket was replaced.
+    pub(crate) fn send(&self, datagram: &[u8]) -> io::Result<()> {
+        let mut guard = self
+            .socket
+            .lock()
+            .map_err(|_| io::Error::other("lock poisoned"))?;
+        if let Some(s) = guard.as_ref() {
+            match s.send(datagram) {
+                Ok(_) => return Ok(()),
+                Err(_) => *guard = None,
+            }
+        }
+        let s = self.connect()?;
+        s.send(datagram)?;
+        *guard = Some(s);
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+impl TryLogWrite for UnixDatagramWriter {
+    fn try_write(&self, serialized: &str, _level: Severity) -> io::Result<()> {
+        let line: &str = fit_datagram(serialized, self.max_size)?;
+        self.send(line.as_bytes())
+    }
+}
+
+#[cfg(unix)]
+impl LogWrite for UnixDatagramWriter {
+    fn write(&self, serialized: &str, level: Severity) {
+        stats::count_write(self.try_write(serialized, level), serialized, level);
+    }
+}
+
+/// Creates a log writer which sends each record as a unix datagram(e.g. to `/dev/log`).
+///
+/// The socket is connected on the first write and connected again when a
+/// send fails(e.g. the collector was restarted and the path was recreated).
+///
+/// # Arguments
+/// - path: The socket path of the collector.
+#[cfg(unix)]
+pub fn unix_datagram_writer_new<P>(path: P) -> UnixDatagramWriter
+where
+    P: AsRef<std::path::Path>,
+{
+    UnixDatagramWriter {
+        path: path.as_ref().into(),
+        socket: Mutex::new(None),
+        max_size: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    use super::{frame_newline, StreamWriter};
+    use crate::{
+        write::{LogWrite, TryLogWrite},
+        Severity,
+    };
+
+    struct Sink(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Sink {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn writes_do_not_wait_for_a_slow_connection() {
+        let sent: Arc<Mutex<Vec<u8>>> = Arc::new(Mutex::new(vec![]));
+        let sink: Arc<Mutex<Vec<u8>>> = sent.clone();
+        let w = StreamWriter::new(
+            Box::new(move || {
+                std::thread::sleep(Duration::from_millis(300));
+                Ok(Box::new(Sink(sink.clone())) as Box<dyn Write + Send>)
+            }),
+            frame_newline,
+        );
+        let started = Instant::now();
+        w.try_write("first", Severity::Info).unwrap();
+        w.try_write("second", Severity::Info).unwrap();
+        assert!(started.elapsed() < Duration::from_millis(200));
+        assert!(sent.lock().unwrap().is_empty());
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while sent.lock().unwrap().is_empty() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        w.try_write("third", Severity::Info).unwrap();
+        w.flush().unwrap();
+        assert_eq!(b"first\nsecond\nthird\n", sent.lock().unwrap().as_slice());
+    }
+
+    #[test]
+    fn failed_connections_back_off() {
+        let attempts: Arc<Mutex<u32>> = Arc::new(Mutex::new(0));
+        let counted: Arc<Mutex<u32>> = attempts.clone();
+        let w = StreamWriter::new(
+            Box::new(move || {
+                *counted.lock().unwrap() += 1;
+                Err(io::Error::new(io::ErrorKind::ConnectionRefused, "refused"))
+            }),
+            frame_newline,
+        )
+        .with_backoff(Duration::from_secs(60), Duration::from_secs(60))
+        .with_max_pending(2);
+        assert!(w.flush().is_err());
+        w.try_write("a", Severity::Info).unwrap();
+        w.try_write("b", Severity::Info).unwrap();
+        let e: io::Error = w.try_write("c", Severity::Info).unwrap_err();
+        assert_eq!(io::ErrorKind::WouldBlock, e.kind());
+        assert_eq!(io::ErrorKind::NotConnected, w.flush().unwrap_err().kind());
+        assert_eq!(1, *attempts.lock().unwrap());
+    }
+}
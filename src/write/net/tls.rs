@@ -0,0 +1,120 @@
+//! TLS for network log writers(requires the `tls` feature).
+
+use std::io::{self, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+
+use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+use rustls_pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer, ServerName};
+
+use crate::write::net::{frame_newline, tcp_connect, StreamWriter, DEFAULT_TIMEOUT};
+
+fn invalid<E>(e: E) -> io::Error
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    io::Error::new(io::ErrorKind::InvalidInput, e)
+}
+
+/// TLS client settings: trusted roots and an optional client certificate.
+pub struct TlsConfig {
+    roots: RootCertStore,
+    client: Option<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)>,
+}
+
+impl Default for TlsConfig {
+    /// Trusts the Mozilla root certificates(webpki-roots).
+    fn default() -> Self {
+        Self {
+            roots: RootCertStore {
+                roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+            },
+            client: None,
+        }
+    }
+}
+
+impl TlsConfig {
+    /// Creates settings which trust no root certificate.
+    pub fn empty() -> Self {
+        Self {
+            roots: RootCertStore::empty(),
+            client: None,
+        }
+    }
+
+    /// Trusts the certificates in a PEM file content(e.g. a private CA).
+    pub fn with_root_certificates_pem(mut self, pem: &[u8]) -> io::Result<Self> {
+        for cert in CertificateDer::pem_slice_iter(pem) {
+            self.roots.add(cert.map_err(invalid)?).map_err(invalid)?;
+        }
+        Ok(self)
+    }
+
+    /// Presents a client certificate chain and its private key(PEM).
+    pub fn with_client_certificate_pem(mut self, chain: &[u8], key: &[u8]) -> io::Result<Self> {
+        let chain: Vec<CertificateDer<'static>> = CertificateDer::pem_slice_iter(chain)
+            .collect::<Result<_, _>>()
+            .map_err(invalid)?;
+        let key: PrivateKeyDer<'static> = PrivateKeyDer::from_pem_slice(key).map_err(invalid)?;
+        self.client = Some((chain, key));
+        Ok(self)
+    }
+
+    pub(crate) fn build(self) -> io::Result<Arc<ClientConfig>> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let builder = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .map_err(invalid)?
+            .with_root_certificates(self.roots);
+        let config: ClientConfig = match self.client {
+            None => builder.with_no_client_auth(),
+            Some((chain, key)) => builder.with_client_auth_cert(chain, key).map_err(invalid)?,
+        };
+        Ok(Arc::new(config))
+    }
+}
+
+/// Connects and completes the handshake(so that certificate errors fail the connection).
+pub(crate) fn tls_connect<A>(
+    addr: &A,
+    config: &Arc<ClientConfig>,
+    name: &ServerName<'static>,
+) -> io::Result<StreamOwned<ClientConnection, TcpStream>>
+where
+    A: ToSocketAddrs,
+{
+    let mut tcp: TcpStream = tcp_connect(addr, DEFAULT_TIMEOUT)?;
+    tcp.set_read_timeout(Some(DEFAULT_TIMEOUT))?;
+    let mut conn: ClientConnection =
+        ClientConnection::new(config.clone(), name.clone()).map_err(io::Error::other)?;
+    while conn.is_handshaking() {
+        conn.complete_io(&mut tcp)?;
+    }
+    Ok(StreamOwned::new(conn, tcp))
+}
+
+/// Creates a log writer which sends newline-delimited records over TLS.
+///
+/// The server certificate is validated against the roots in `config` and
+/// `server_name`. Reconnects like [`tcp_writer_new`](crate::write::net::tcp_writer_new).
+///
+/// # Arguments
+/// - addr: The address of the collector(e.g. `"logs.example.com:6514"`).
+/// - server_name: The name in the server certificate(e.g. `"logs.example.com"`).
+/// - config: The trusted roots and the client certificate.
+pub fn tls_writer_new<A>(addr: A, server_name: &str, config: TlsConfig) -> io::Result<StreamWriter>
+where
+    A: ToSocketAddrs + Sync + Send + 'static,
+{
+    let name: ServerName<'static> =
+        ServerName::try_from(server_name.to_string()).map_err(invalid)?;
+    let config: Arc<ClientConfig> = config.build()?;
+    Ok(StreamWriter::new(
+        Box::new(move || {
+            let s = tls_connect(&addr, &config, &name)?;
+            Ok(Box::new(s) as Box<dyn Write + Send>)
+        }),
+        frame_newline,
+    ))
+}
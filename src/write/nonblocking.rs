@@ -0,0 +1,448 @@
+//! A log writer which hands serialized lines to a background thread.
+
+use std::collections::VecDeque;
+use std::io;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::{
+    write::{stats, LogWrite, TryLogWrite},
+    Severity,
+};
+
+/// What to do when the queue of a background writer is full.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum OverflowPolicy {
+    /// Waits until the worker takes a line.
+    Block,
+    /// Waits until the worker takes a line; drops the incoming line on timeout.
+    BlockWithTimeout(Duration),
+    /// Drops the incoming line(default).
+    #[default]
+    DropNewest,
+    /// Drops the oldest queued line.
+    DropOldest,
+    /// Drops the oldest queued line of the lowest severity if it is lower than
+    /// the incoming line's; otherwise drops the incoming line.
+    DropLowestSeverity,
+}
+
+/// The error of a line dropped because of a full queue.
+///
+/// The drop is counted by the writer which gets the error(see `stats::count_write`).
+pub(crate) fn queue_full() -> io::Error {
+    io::Error::new(io::ErrorKind::WouldBlock, "log queue full")
+}
+
+/// The time [`WorkerGuard`] waits for the queue to be drained when dropped.
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct QueueState {
+    lines: VecDeque<(String, Severity)>,
+    flushes: Vec<Sender<io::Result<()>>>,
+    closed: bool,
+    done: bool,
+    /// The worker stopped by a panic of the inner writer.
+    panicked: bool,
+}
+
+enum Job {
+    Line(String, Severity),
+    /// Flushes the inner writer after the queue got empty.
+    Flush(Vec<Sender<io::Result<()>>>),
+}
+
+struct Queue {
+    state: Mutex<QueueState>,
+    not_empty: Condvar,
+    not_full: Condvar,
+    drained: Condvar,
+    capacity: usize,
+}
+
+impl Queue {
+    fn push(&self, line: String, level: Severity, policy: OverflowPolicy) -> io::Result<()> {
+        let poisoned = |_| io::Error::other("lock poisoned");
+        let mut guard = self.state.lock().map_err(poisoned)?;
+        let deadline: Option<Instant> = match policy {
+            OverflowPolicy::BlockWithTimeout(t) => Instant::now().checked_add(t),
+            _ => None,
+        };
+        while !guard.closed && self.capacity <= guard.lines.len() {
+            match policy {
+                OverflowPolicy::DropNewest => return Err(queue_full()),
+                OverflowPolicy::Block => guard = self.not_full.wait(guard).map_err(poisoned)?,
+                OverflowPolicy::BlockWithTimeout(_) => {
+                    let left: Option<Duration> = match deadline {
+                        // Too far to represent: waits without a timeout.
+                        None => None,
+                        Some(d) => match d.checked_duration_since(Instant::now()) {
+                            Some(left) if !left.is_zero() => Some(left),
+                            _ => return Err(queue_full()),
+                        },
+                    };
+                    guard = match left {
+                        None => self.not_full.wait(guard).map_err(poisoned)?,
+                        Some(left) => {
+                            self.not_full
+                                .wait_timeout(guard, left)
+                                .map_err(|_| io::Error::other("lock poisoned"))?
+                                .0
+                        }
+                    };
+                }
+                OverflowPolicy::DropOldest => {
+                    guard.lines.pop_front();
+                    stats::DROPPED_BY_FULL_QUEUE.add(1);
+                    stats::queue_depth("nonblocking", -1);
+                }
+                OverflowPolicy::DropLowestSeverity => {
+                    let lowest: Option<usize> = guard
+                        .lines
+                        .iter()
+                        .enumerate()
+                        .min_by_key(|(i, (_, l))| (*l, *i))
+                        .filter(|(_, (_, l))| *l < level)
+                        .map(|(i, _)| i);
+                    match lowest {
+                        None => return Err(queue_full()),
+                        Some(i) => {
+                            guard.lines.remove(i);
+                            stats::DROPPED_BY_FULL_QUEUE.add(1);
+                            stats::queue_depth("nonblocking", -1);
+                        }
+                    }
+                }
+            }
+        }
+        if guard.closed {
+            return Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "log writer thread stopped",
+            ));
+        }
+        guard.lines.push_back((line, level));
+        stats::queue_depth("nonblocking", 1);
+        self.not_empty.notify_one();
+        Ok(())
+    }
+
+    /// Requests a flush; the receiver gets the result of the inner writer.
+    fn request_flush(&self) -> Option<Receiver<io::Result<()>>> {
+        let mut guard = self.state.lock().ok()?;
+        if guard.done {
+            return None;
+        }
+        let (tx, rx) = mpsc::channel();
+        guard.flushes.push(tx);
+        self.not_empty.notify_one();
+        Some(rx)
+    }
+
+    /// Takes the next job; None after the queue is closed and drained.
+    fn pop(&self) -> Option<Job> {
+        let mut guard = self.state.lock().ok()?;
+        loop {
+            if let Some((line, level)) = guard.lines.pop_front() {
+                stats::queue_depth("nonblocking", -1);
+                self.not_full.notify_one();
+                return Some(Job::Line(line, level));
+            }
+            if !guard.flushes.is_empty() {
+                return Some(Job::Flush(std::mem::take(&mut guard.flushes)));
+            }
+            if guard.closed {
+                return None;
+            }
+            guard = self.not_empty.wait(guard).ok()?;
+        }
+    }
+
+    fn close(&self) {
+        if let Ok(mut guard) = self.state.lock() {
+            guard.closed = true;
+        }
+        self.not_empty.notify_all();
+        self.not_full.notify_all();
+    }
+
+    fn finish(&self, panicked: bool) {
+        if let Ok(mut guard) = self.state.lock() {
+            guard.done = true;
+            guard.panicked = panicked;
+            guard.flushes.clear();
+        }
+        self.drained.notify_all();
+    }
+
+    /// The result of a flush after the worker stopped.
+    fn stopped(&self) -> io::Result<()> {
+        match self.state.lock().map(|s| s.panicked) {
+            Ok(false) => Ok(()),
+            _ => Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "log writer thread panicked",
+            )),
+        }
+    }
+
+    /// Waits until the worker has written every line; false on timeout.
+    fn wait_drained(&self, timeout: Duration) -> bool {
+        match self.state.lock() {
+            Err(_) => false,
+            Ok(guard) => self
+                .drained
+                .wait_timeout_while(guard, timeout, |s: &mut QueueState| !s.done)
+                .map(|(s, _)| s.done)
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// Stops the worker of a background writer, writing the queued lines first.
+///
+/// Keep it alive(e.g. in `main`) while logging; dropping it shuts the worker
+/// down with [`SHUTDOWN_TIMEOUT`]. Lines written after shutdown are dropped.
+#[must_use = "dropping the guard stops the background writer"]
+pub struct WorkerGuard {
+    queue: Arc<Queue>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl WorkerGuard {
+    /// Closes the queue and waits for the worker to write the queued lines.
+    ///
+    /// Returns false if the worker is still busy after the timeout.
+    pub fn shutdown(mut self, timeout: Duration) -> bool {
+        self.shutdown_by_ref(timeout)
+    }
+
+    fn shutdown_by_ref(&mut self, timeout: Duration) -> bool {
+        self.queue.close();
+        let drained: bool = self.queue.wait_drained(timeout);
+        if drained {
+            if let Some(h) = self.handle.take() {
+                h.join().ok();
+            }
+        }
+        drained
+    }
+}
+
+impl Drop for WorkerGuard {
+    fn drop(&mut self) {
+        if self.handle.is_some() {
+            self.shutdown_by_ref(SHUTDOWN_TIMEOUT);
+        }
+    }
+}
+
+/// A log writer which queues lines for a background thread.
+pub struct NonBlockingWriter {
+    queue: Arc<Queue>,
+    policy: OverflowPolicy,
+}
+
+impl NonBlockingWriter {
+    /// Sets the policy used when the queue is full(default: [`OverflowPolicy::DropNewest`]).
+    pub fn with_overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+}
+
+impl TryLogWrite for NonBlockingWriter {
+    /// Queues a line; fails if the queue is full(unless blocking) or closed.
+    fn try_write(&self, serialized: &str, level: Severity) -> io::Result<()> {
+        self.queue.push(serialized.into(), level, self.policy)
+    }
+}
+
+impl LogWrite for NonBlockingWriter {
+    fn write(&self, serialized: &str, level: Severity) {
+        stats::count_queued(self.try_write(serialized, level));
+    }
+
+    /// Waits until the worker has written the queued lines and flushed the inner writer.
+    fn flush(&self) -> io::Result<()> {
+        match self.queue.request_flush() {
+            None => self.queue.stopped(),
+            // The worker has stopped after writing and dropping the inner writer.
+            Some(rx) => rx.recv().unwrap_or_else(|_| self.queue.stopped()),
+        }
+    }
+}
+
+impl Drop for NonBlockingWriter {
+    fn drop(&mut self) {
+        self.queue.close()
+    }
+}
+
+/// Closes the queue and wakes the waiters when the worker exits, even by a panic.
+struct WorkerExit(Arc<Queue>);
+
+impl Drop for WorkerExit {
+    fn drop(&mut self) {
+        self.0.close();
+        self.0.finish(std::thread::panicking());
+    }
+}
+
+/// Creates a log writer which writes lines on a dedicated thread.
+///
+/// Lines are pushed into a bounded queue which is drained by the thread in
+/// order. The thread writes the remaining lines and exits when the writer or
+/// the returned guard is dropped; the guard waits for it. If the inner writer
+/// panics, the thread stops: later writes fail and flushes return an error.
+///
+/// # Arguments
+/// - inner: The log writer used by the thread.
+/// - capacity: The maximum number of queued lines.
+pub fn nonblocking_writer_new<W>(
+    inner: W,
+    capacity: usize,
+) -> io::Result<(NonBlockingWriter, WorkerGuard)>
+where
+    W: LogWrite + 'static,
+{
+    let queue: Arc<Queue> = Arc::new(Queue {
+        state: Mutex::new(QueueState {
+            lines: VecDeque::with_capacity(capacity),
+            flushes: vec![],
+            closed: false,
+            done: false,
+            panicked: false,
+        }),
+        not_empty: Condvar::new(),
+        not_full: Condvar::new(),
+        drained: Condvar::new(),
+        capacity: capacity.max(1),
+    });
+    let worker: Arc<Queue> = queue.clone();
+    let handle: JoinHandle<()> = std::thread::Builder::new()
+        .name("log-writer".into())
+        .spawn(move || {
+            let exit = WorkerExit(worker.clone());
+            // Dropped before `exit` on unwind.
+            let inner: W = inner;
+            while let Some(job) = worker.pop() {
+                match job {
+                    Job::Line(line, level) => inner.write(&line, level),
+                    Job::Flush(waiters) => {
+                        let flushed: io::Result<()> =
+                            stats::timed("log_flush_seconds", &[("writer", "nonblocking")], || {
+                                inner.flush()
+                            });
+                        for w in waiters {
+                            let r: io::Result<()> = match &flushed {
+                                Ok(()) => Ok(()),
+                                Err(e) => Err(io::Error::new(e.kind(), e.to_string())),
+                            };
+                            w.send(r).ok();
+                        }
+                    }
+                }
+            }
+            drop(inner);
+            drop(exit);
+        })?;
+    let guard = WorkerGuard {
+        queue: queue.clone(),
+        handle: Some(handle),
+    };
+    let writer = NonBlockingWriter {
+        queue,
+        policy: OverflowPolicy::default(),
+    };
+    Ok((writer, guard))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::{self, Sender};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use super::{nonblocking_writer_new, OverflowPolicy};
+    use crate::{
+        write::{
+            log_writer_new_from_fn, on_error_writer_new,
+            retry::{retry_writer_new, RetryPolicy},
+            stats, LogWrite, TryLogWrite,
+        },
+        Severity,
+    };
+
+    #[test]
+    fn full_queue_drop_is_counted_once_by_wrappers() {
+        let gate: Arc<Mutex<()>> = Arc::new(Mutex::new(()));
+        let held = gate.lock().unwrap();
+        let blocked: Arc<Mutex<()>> = gate.clone();
+        let inner = log_writer_new_from_fn(
+            move |_: &str, _: Severity| {
+                blocked.lock().ok();
+            },
+            |_| true,
+        );
+        let (queue, guard) = nonblocking_writer_new(inner, 1).unwrap();
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(1),
+        };
+        let w = retry_writer_new(on_error_writer_new(queue, |_| {}), policy);
+        // The worker blocks on the first line; the queue gets full.
+        while w.try_write("fill", Severity::Info).is_ok() {}
+
+        let before: u64 = stats::snapshot().dropped_by_full_queue;
+        w.write("dropped", Severity::Info);
+        let after: u64 = stats::snapshot().dropped_by_full_queue;
+        assert_eq!(before + 1, after);
+
+        drop(held);
+        drop(w);
+        drop(guard);
+    }
+
+    #[test]
+    fn waiters_are_released_when_the_inner_writer_panics() {
+        let gate: Arc<Mutex<()>> = Arc::new(Mutex::new(()));
+        let held = gate.lock().unwrap();
+        let blocked: Arc<Mutex<()>> = gate.clone();
+        let (entered_tx, entered) = mpsc::channel();
+        let entered_tx: Mutex<Sender<()>> = Mutex::new(entered_tx);
+        let inner = log_writer_new_from_fn(
+            move |_: &str, _: Severity| {
+                entered_tx.lock().unwrap().send(()).ok();
+                let _held = blocked.lock();
+                panic!("inner writer failed");
+            },
+            |_| true,
+        );
+        let (queue, guard) = nonblocking_writer_new(inner, 1).unwrap();
+        let w = Arc::new(queue.with_overflow_policy(OverflowPolicy::Block));
+        w.try_write("taken", Severity::Info).unwrap();
+        entered.recv().unwrap();
+        w.try_write("queued", Severity::Info).unwrap();
+
+        let (done_tx, done) = mpsc::channel();
+        let writer = w.clone();
+        std::thread::spawn(move || {
+            // Waits for a free slot which never comes.
+            done_tx
+                .send(writer.try_write("blocked", Severity::Info))
+                .ok();
+        });
+        std::thread::sleep(Duration::from_millis(50));
+        drop(held);
+
+        let blocked: std::io::Result<()> = done.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(blocked.is_err());
+        assert!(w.flush().is_err());
+        assert!(w.try_write("late", Severity::Info).is_err());
+        drop(guard);
+    }
+}
@@ -0,0 +1,50 @@
+//! A log writer for the Apple unified logging system(requires the `os-log` feature).
+
+use oslog::{Level, OsLog};
+
+use crate::{write::LogWrite, Severity};
+
+/// Maps a severity to a log type.
+///
+/// | Severity     | Log type |
+/// |:------------:|:--------:|
+/// | Trace, Debug | debug    |
+/// | Info         | info     |
+/// | Warn         | default  |
+/// | Error        | error    |
+/// | Fatal        | fault    |
+fn log_type(level: Severity) -> Level {
+    match level {
+        Severity::Trace | Severity::Debug => Level::Debug,
+        Severity::Info => Level::Info,
+        Severity::Warn => Level::Default,
+        Severity::Error => Level::Error,
+        Severity::Fatal => Level::Fault,
+    }
+}
+
+struct OsLogWriter {
+    log: OsLog,
+}
+
+impl LogWrite for OsLogWriter {
+    fn write(&self, serialized: &str, level: Severity) {
+        self.log
+            .with_level(log_type(level), serialized.trim_end_matches('\n'))
+    }
+}
+
+/// Creates a log writer which sends lines to the unified logging system(macOS/iOS only).
+///
+/// Lines are logged as public strings, so they are not redacted in
+/// Console.app or `log stream`. Debug and info messages are kept in memory
+/// only unless the subsystem is configured otherwise(e.g. `log config`).
+///
+/// # Arguments
+/// - subsystem: The subsystem(e.g. `com.example.daemon`).
+/// - category: The category within the subsystem(e.g. `network`).
+pub fn os_log_writer_new(subsystem: &str, category: &str) -> impl LogWrite {
+    OsLogWriter {
+        log: OsLog::new(subsystem, category),
+    }
+}
@@ -0,0 +1,169 @@
+//! A logger which exports items to an OpenTelemetry collector over OTLP/HTTP.
+
+use std::io;
+use std::time::Duration;
+
+#[cfg(feature = "otlp-grpc")]
+pub mod grpc;
+
+#[cfg(feature = "tls")]
+use crate::write::net::tls::TlsConfig;
+use crate::{
+    copy::Logger,
+    serialize::otlp::{write_export_request, write_export_request_proto},
+    write::{
+        http::{Batcher, Encode, HttpClient, Target},
+        retry::RetryPolicy,
+        stats,
+    },
+    Item, Severity,
+};
+
+/// The payload encoding of OTLP/HTTP.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum OtlpEncoding {
+    /// `application/x-protobuf`(default).
+    #[default]
+    Protobuf,
+    /// `application/json`.
+    Json,
+}
+
+fn encode_json(batch: &[Item], buf: &mut Vec<u8>) {
+    let mut s: String = String::new();
+    write_export_request(batch, &mut s);
+    buf.extend_from_slice(s.as_bytes())
+}
+
+/// A logger which batches items into `ExportLogsServiceRequest`s.
+pub struct OtlpHttpLogger {
+    batcher: Batcher<Item>,
+    encoding: OtlpEncoding,
+    #[cfg(feature = "gzip")]
+    gzip: bool,
+}
+
+impl OtlpHttpLogger {
+    /// Updates the content type and the encoder of requests.
+    fn apply_encoding(self) -> Self {
+        let (content_type, encode): (&str, Encode<Item>) = match self.encoding {
+            OtlpEncoding::Protobuf => (
+                "application/x-protobuf",
+                Box::new(write_export_request_proto),
+            ),
+            OtlpEncoding::Json => ("application/json", Box::new(encode_json)),
+        };
+        #[cfg(feature = "gzip")]
+        let (content_encoding, encode) = match self.gzip {
+            true => (Some("gzip"), crate::write::http::gzip_encode(encode)),
+            false => (None, encode),
+        };
+        #[cfg(not(feature = "gzip"))]
+        let content_encoding: Option<&'static str> = None;
+        self.batcher
+            .configure(|t: &mut Target<Item>| {
+                t.content_type = content_type.into();
+                t.content_encoding = content_encoding;
+                t.encode = encode;
+                Ok(())
+            })
+            .ok();
+        self
+    }
+
+    /// Sets the payload encoding(default: [`OtlpEncoding::Protobuf`]).
+    pub fn with_encoding(mut self, encoding: OtlpEncoding) -> Self {
+        self.encoding = encoding;
+        self.apply_encoding()
+    }
+
+    /// Compresses requests with gzip(requires the `gzip` feature).
+    #[cfg(feature = "gzip")]
+    pub fn with_gzip(mut self) -> Self {
+        self.gzip = true;
+        self.apply_encoding()
+    }
+
+    /// Adds a request header(e.g. an API key of a vendor endpoint).
+    pub fn with_header(self, name: &str, val: &str) -> io::Result<Self> {
+        self.batcher
+            .configure(|t: &mut Target<Item>| t.client.add_header(name, val))?;
+        Ok(self)
+    }
+
+    /// Sets the retries of a failed batch(connection errors, 429 and 5xx).
+    pub fn with_retry_policy(self, policy: RetryPolicy) -> Self {
+        self.batcher
+            .configure(|t: &mut Target<Item>| {
+                t.retry = policy;
+                Ok(())
+            })
+            .ok();
+        self
+    }
+
+    /// Sets the maximum number of queued items(default: 8 batches).
+    pub fn with_max_pending(self, max_pending: usize) -> Self {
+        self.batcher.set_max_pending(max_pending);
+        self
+    }
+
+    /// Sends the queued items at once after an item of this severity or higher
+    /// is logged(default: Error; None disables it).
+    pub fn with_flush_severity(self, level: Option<Severity>) -> Self {
+        self.batcher.set_flush_severity(level);
+        self
+    }
+
+    /// Sets the trusted roots and the client certificate for `https` endpoints.
+    #[cfg(feature = "tls")]
+    pub fn with_tls_config(self, config: TlsConfig) -> io::Result<Self> {
+        self.batcher
+            .configure(|t: &mut Target<Item>| t.client.set_tls_config(config))?;
+        Ok(self)
+    }
+}
+
+impl Logger for OtlpHttpLogger {
+    fn log(&self, item: Item) {
+        let level: Severity = item.severity;
+        stats::count_queued(self.batcher.push(item, level));
+    }
+
+    /// Sends the queued items; fails if a batch was dropped since the last flush.
+    fn flush(&self) -> io::Result<()> {
+        self.batcher.flush()
+    }
+}
+
+/// Creates a logger which exports items to an OTLP/HTTP collector in batches.
+///
+/// Items are mapped like [`otlp_serializer_new`](crate::serialize::otlp::otlp_serializer_new)
+/// and grouped into one `ResourceLogs` per distinct resource. Batches are
+/// sent like [`http_batch_writer_new`](crate::write::http::http_batch_writer_new).
+///
+/// # Arguments
+/// - endpoint: The collector URL(e.g. `"http://127.0.0.1:4318"`); `/v1/logs` is appended.
+/// - batch_size: The maximum number of items in a request.
+/// - flush_interval: The maximum time an item waits before being sent.
+pub fn otlp_http_logger_new(
+    endpoint: &str,
+    batch_size: usize,
+    flush_interval: Duration,
+) -> io::Result<OtlpHttpLogger> {
+    let url: String = format!("{}/v1/logs", endpoint.trim_end_matches('/'));
+    let target: Target<Item> = Target {
+        client: HttpClient::new(&url, &[])?,
+        content_type: "application/x-protobuf".into(),
+        content_encoding: None,
+        encode: Box::new(write_export_request_proto),
+        retry: RetryPolicy::default(),
+        check: None,
+    };
+    Ok(OtlpHttpLogger {
+        batcher: Batcher::new(target, batch_size, flush_interval)?,
+        encoding: OtlpEncoding::default(),
+        #[cfg(feature = "gzip")]
+        gzip: false,
+    })
+}
@@ -0,0 +1,313 @@
+//! A logger which exports items to an OpenTelemetry collector over OTLP/gRPC(requires the `otlp-grpc` feature).
+
+use std::io;
+use std::time::Duration;
+
+use bytes::{Buf, BufMut};
+use http::uri::PathAndQuery;
+use tokio::runtime::Runtime;
+use tonic::codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder};
+use tonic::metadata::{AsciiMetadataKey, AsciiMetadataValue, MetadataMap};
+use tonic::transport::{Channel, Endpoint};
+use tonic::{Code, Request, Status};
+
+use crate::{
+    copy::Logger,
+    serialize::otlp::write_export_request_proto,
+    write::{
+        http::{Batcher, Deliver},
+        net::DEFAULT_TIMEOUT,
+        retry::{jitter, RetryPolicy},
+        stats,
+    },
+    Item, Severity,
+};
+
+const EXPORT: &str = "/opentelemetry.proto.collector.logs.v1.LogsService/Export";
+
+fn invalid_input(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+/// Sends pre-encoded messages and ignores the content of responses.
+struct RawCodec;
+
+impl Encoder for RawCodec {
+    type Item = Vec<u8>;
+    type Error = Status;
+
+    fn encode(&mut self, item: Vec<u8>, dst: &mut EncodeBuf<'_>) -> Result<(), Status> {
+        dst.put_slice(&item);
+        Ok(())
+    }
+}
+
+impl Decoder for RawCodec {
+    type Item = ();
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<()>, Status> {
+        src.advance(src.remaining());
+        Ok(Some(()))
+    }
+}
+
+impl Codec for RawCodec {
+    type Encode = Vec<u8>;
+    type Decode = ();
+    type Encoder = Self;
+    type Decoder = Self;
+
+    fn encoder(&mut self) -> Self {
+        Self
+    }
+
+    fn decoder(&mut self) -> Self {
+        Self
+    }
+}
+
+/// Checks if a failed export may succeed later(the retryable codes of OTLP).
+fn retryable(code: Code) -> bool {
+    matches!(
+        code,
+        Code::Cancelled
+            | Code::DeadlineExceeded
+            | Code::Aborted
+            | Code::OutOfRange
+            | Code::Unavailable
+            | Code::DataLoss
+    )
+}
+
+/// Calls `LogsService/Export` on a runtime owned by the background thread.
+struct Exporter {
+    runtime: Runtime,
+    endpoint: Endpoint,
+    #[cfg(feature = "tls")]
+    tls: Option<tonic::transport::ClientTlsConfig>,
+    /// Created on the first export(and after the settings change).
+    channel: Option<Channel>,
+    metadata: MetadataMap,
+    retry: RetryPolicy,
+    #[cfg(feature = "gzip")]
+    gzip: bool,
+}
+
+impl Exporter {
+    fn channel(&mut self) -> io::Result<Channel> {
+        if let Some(c) = &self.channel {
+            return Ok(c.clone());
+        }
+        let endpoint: Endpoint = self.endpoint.clone();
+        #[cfg(feature = "tls")]
+        let endpoint: Endpoint = match &self.tls {
+            None => endpoint,
+            Some(tls) => endpoint
+                .tls_config(tls.clone())
+                .map_err(|e| invalid_input(e.to_string()))?,
+        };
+        let _guard = self.runtime.enter();
+        let channel: Channel = endpoint.connect_lazy();
+        self.channel = Some(channel.clone());
+        Ok(channel)
+    }
+
+    fn export(&mut self, body: &[u8]) -> Result<(), Status> {
+        let channel: Channel = self
+            .channel()
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let mut client = tonic::client::Grpc::new(channel);
+        #[cfg(feature = "gzip")]
+        if self.gzip {
+            client = client.send_compressed(tonic::codec::CompressionEncoding::Gzip);
+        }
+        let mut request: Request<Vec<u8>> = Request::new(body.to_vec());
+        *request.metadata_mut() = self.metadata.clone();
+        self.runtime.block_on(async move {
+            client
+                .ready()
+                .await
+                .map_err(|e| Status::unavailable(e.to_string()))?;
+            client
+                .unary(request, PathAndQuery::from_static(EXPORT), RawCodec)
+                .await
+                .map(|_| ())
+        })
+    }
+}
+
+impl Deliver<Item> for Exporter {
+    /// Exports a batch, retrying the retryable status codes.
+    fn deliver(&mut self, batch: &[Item]) -> io::Result<()> {
+        let mut body: Vec<u8> = Vec::new();
+        write_export_request_proto(batch, &mut body);
+        let mut failures: u32 = 0;
+        loop {
+            let status: Status = match self.export(&body) {
+                Ok(()) => return Ok(()),
+                Err(s) => s,
+            };
+            failures += 1;
+            if !retryable(status.code()) || self.retry.max_attempts <= failures {
+                return Err(io::Error::other(format!(
+                    "grpc status {:?}: {}",
+                    status.code(),
+                    status.message()
+                )));
+            }
+            std::thread::sleep(jitter(self.retry.backoff(failures)));
+        }
+    }
+}
+
+/// A logger which batches items into `ExportLogsServiceRequest`s.
+pub struct OtlpGrpcLogger {
+    batcher: Batcher<Item, Exporter>,
+}
+
+impl OtlpGrpcLogger {
+    /// Adds request metadata(e.g. an API key of a vendor endpoint).
+    pub fn with_header(self, name: &str, val: &str) -> io::Result<Self> {
+        let invalid = || invalid_input(format!("invalid header: {name}"));
+        let key: AsciiMetadataKey = name.parse().map_err(|_| invalid())?;
+        let val: AsciiMetadataValue = val.parse().map_err(|_| invalid())?;
+        self.batcher.configure(|e: &mut Exporter| {
+            e.metadata.append(key, val);
+            Ok(())
+        })?;
+        Ok(self)
+    }
+
+    /// Compresses requests with gzip(requires the `gzip` feature).
+    #[cfg(feature = "gzip")]
+    pub fn with_gzip(self) -> Self {
+        self.batcher
+            .configure(|e: &mut Exporter| {
+                e.gzip = true;
+                Ok(())
+            })
+            .ok();
+        self
+    }
+
+    /// Sets the retries of a failed batch(the retryable status codes of OTLP).
+    pub fn with_retry_policy(self, policy: RetryPolicy) -> Self {
+        self.batcher
+            .configure(|e: &mut Exporter| {
+                e.retry = policy;
+                Ok(())
+            })
+            .ok();
+        self
+    }
+
+    /// Sets the maximum number of queued items(default: 8 batches).
+    pub fn with_max_pending(self, max_pending: usize) -> Self {
+        self.batcher.set_max_pending(max_pending);
+        self
+    }
+
+    /// Sends the queued items at once after an item of this severity or higher
+    /// is logged(default: Error; None disables it).
+    pub fn with_flush_severity(self, level: Option<Severity>) -> Self {
+        self.batcher.set_flush_severity(level);
+        self
+    }
+
+    /// Trusts the certificates in a PEM file content for `https` endpoints(e.g. a private CA).
+    #[cfg(feature = "tls")]
+    pub fn with_root_certificates_pem(self, pem: &[u8]) -> io::Result<Self> {
+        let cert = tonic::transport::Certificate::from_pem(pem);
+        self.batcher
+            .configure(|e: &mut Exporter| match e.tls.take() {
+                None => Err(invalid_input("not an https endpoint".into())),
+                Some(tls) => {
+                    e.tls = Some(tls.ca_certificate(cert));
+                    e.channel = None;
+                    Ok(())
+                }
+            })?;
+        Ok(self)
+    }
+
+    /// Presents a client certificate chain and its private key(PEM) to `https` endpoints.
+    #[cfg(feature = "tls")]
+    pub fn with_client_certificate_pem(self, chain: &[u8], key: &[u8]) -> io::Result<Self> {
+        let identity = tonic::transport::Identity::from_pem(chain, key);
+        self.batcher
+            .configure(|e: &mut Exporter| match e.tls.take() {
+                None => Err(invalid_input("not an https endpoint".into())),
+                Some(tls) => {
+                    e.tls = Some(tls.identity(identity));
+                    e.channel = None;
+                    Ok(())
+                }
+            })?;
+        Ok(self)
+    }
+}
+
+impl Logger for OtlpGrpcLogger {
+    fn log(&self, item: Item) {
+        let level: Severity = item.severity;
+        stats::count_queued(self.batcher.push(item, level));
+    }
+
+    /// Sends the queued items; fails if a batch was dropped since the last flush.
+    fn flush(&self) -> io::Result<()> {
+        self.batcher.flush()
+    }
+}
+
+/// Creates a logger which exports items to an OTLP/gRPC collector in batches.
+///
+/// Items are mapped and batched like [`otlp_http_logger_new`](crate::write::otlp::otlp_http_logger_new)
+/// and sent with `LogsService/Export`. A failed batch is retried with
+/// [`RetryPolicy::default`] if the status code is retryable(e.g. `UNAVAILABLE`).
+/// The connection is opened on the first export and reused.
+///
+/// `https` endpoints require the `tls` feature and trust the Mozilla root
+/// certificates(webpki-roots).
+///
+/// # Arguments
+/// - endpoint: The collector URL(e.g. `"http://127.0.0.1:4317"`).
+/// - batch_size: The maximum number of items in a request.
+/// - flush_interval: The maximum time an item waits before being sent.
+pub fn otlp_grpc_logger_new(
+    endpoint: &str,
+    batch_size: usize,
+    flush_interval: Duration,
+) -> io::Result<OtlpGrpcLogger> {
+    let invalid = || invalid_input(format!("invalid endpoint: {endpoint}"));
+    let tls: bool = match endpoint.split_once("://") {
+        Some(("http", _)) => false,
+        Some(("https", _)) => true,
+        _ => return Err(invalid()),
+    };
+    #[cfg(not(feature = "tls"))]
+    if tls {
+        return Err(invalid_input("https requires the tls feature".into()));
+    }
+    let endpoint: Endpoint = Endpoint::from_shared(endpoint.to_string())
+        .map_err(|_| invalid())?
+        .connect_timeout(DEFAULT_TIMEOUT)
+        .timeout(DEFAULT_TIMEOUT);
+    let runtime: Runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let exporter = Exporter {
+        runtime,
+        endpoint,
+        #[cfg(feature = "tls")]
+        tls: tls.then(|| tonic::transport::ClientTlsConfig::new().with_webpki_roots()),
+        channel: None,
+        metadata: MetadataMap::new(),
+        retry: RetryPolicy::default(),
+        #[cfg(feature = "gzip")]
+        gzip: false,
+    };
+    Ok(OtlpGrpcLogger {
+        batcher: Batcher::new(exporter, batch_size, flush_interval)?,
+    })
+}
@@ -0,0 +1,219 @@
+//! A logger which archives items as Parquet files(requires the `parquet` feature).
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use arrow_array::{
+    builder::{MapBuilder, StringBuilder, TimestampMicrosecondBuilder},
+    ArrayRef, RecordBatch,
+};
+use arrow_schema::{Field, Schema};
+use parquet::{arrow::ArrowWriter, basic::Compression, file::properties::WriterProperties};
+
+use crate::{
+    copy::Logger,
+    serialize::time::{since_epoch, TimeZoneMode, TimestampFormatter},
+    write::{
+        http::{Batcher, Deliver},
+        stats,
+    },
+    Item, Severity,
+};
+
+/// Converts an error of the arrow/parquet crates.
+fn other<E>(e: E) -> io::Error
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    io::Error::other(e)
+}
+
+fn append_map(b: &mut MapBuilder<StringBuilder, StringBuilder>, map: &BTreeMap<String, String>) {
+    for (key, val) in map {
+        b.keys().append_value(key);
+        b.values().append_value(val);
+    }
+    // Fails only if the keys and the values differ in length.
+    b.append(true).ok();
+}
+
+/// Converts items to a record batch.
+fn record_batch(items: &[&Item]) -> io::Result<RecordBatch> {
+    let mut timestamp = TimestampMicrosecondBuilder::new().with_timezone("UTC");
+    let mut severity = StringBuilder::new();
+    let mut body = StringBuilder::new();
+    let mut attributes = MapBuilder::new(None, StringBuilder::new(), StringBuilder::new());
+    let mut resource = MapBuilder::new(None, StringBuilder::new(), StringBuilder::new());
+    let mut trace_id = StringBuilder::new();
+    let mut span_id = StringBuilder::new();
+    for item in items {
+        timestamp.append_value(since_epoch(item.timestamp).as_micros() as i64);
+        severity.append_value(item.severity.as_str());
+        body.append_value(item.body.to_text());
+        append_map(&mut attributes, &item.attributes);
+        append_map(&mut resource, &item.resource);
+        trace_id.append_option(item.trace_id.as_ref());
+        span_id.append_option(item.span_id.as_ref());
+    }
+    let columns: [(&str, ArrayRef, bool); 7] = [
+        ("timestamp", Arc::new(timestamp.finish()), false),
+        ("severity", Arc::new(severity.finish()), false),
+        ("body", Arc::new(body.finish()), false),
+        ("attributes", Arc::new(attributes.finish()), false),
+        ("resource", Arc::new(resource.finish()), false),
+        ("trace_id", Arc::new(trace_id.finish()), true),
+        ("span_id", Arc::new(span_id.finish()), true),
+    ];
+    let fields: Vec<Field> = columns
+        .iter()
+        .map(|(name, a, nullable)| Field::new(*name, a.data_type().clone(), *nullable))
+        .collect();
+    let arrays: Vec<ArrayRef> = columns.into_iter().map(|(_, a, _)| a).collect();
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays).map_err(other)
+}
+
+/// Writes batches from the background thread.
+struct Archiver {
+    partition: TimestampFormatter,
+    compression: Compression,
+    /// Distinguishes files written in the same microsecond.
+    seq: u64,
+}
+
+impl Archiver {
+    /// Writes a file atomically(via a temporary file).
+    fn write_file(&mut self, dir: &Path, items: &[&Item]) -> io::Result<()> {
+        std::fs::create_dir_all(dir)?;
+        let now: u128 = since_epoch(SystemTime::now()).as_micros();
+        self.seq = self.seq.wrapping_add(1);
+        let name: String = format!("part-{now}-{}-{}", std::process::id(), self.seq);
+        let tmp: PathBuf = dir.join(format!(".{name}.tmp"));
+
+        let batch: RecordBatch = record_batch(items)?;
+        let props = WriterProperties::builder()
+            .set_compression(self.compression)
+            .build();
+        let f: File = File::create(&tmp)?;
+        let mut w = ArrowWriter::try_new(f, batch.schema(), Some(props)).map_err(other)?;
+        w.write(&batch).map_err(other)?;
+        let f: File = w.into_inner().map_err(other)?;
+        f.sync_all()?;
+        std::fs::rename(&tmp, dir.join(format!("{name}.parquet")))
+    }
+}
+
+impl Deliver<Item> for Archiver {
+    /// Writes a file(a row group) for each partition of a batch.
+    fn deliver(&mut self, batch: &[Item]) -> io::Result<()> {
+        let mut partitions: BTreeMap<String, Vec<&Item>> = BTreeMap::new();
+        for item in batch {
+            let mut dir: String = String::new();
+            self.partition.format(item.timestamp, &mut dir);
+            partitions.entry(dir).or_default().push(item);
+        }
+        let mut last: io::Result<()> = Ok(());
+        for (dir, items) in partitions {
+            if let Err(e) = self.write_file(Path::new(&dir), &items) {
+                last = Err(e);
+            }
+        }
+        last
+    }
+}
+
+/// A logger which archives items as Parquet files partitioned by time.
+pub struct ParquetLogger {
+    batcher: Batcher<Item, Archiver>,
+}
+
+impl ParquetLogger {
+    /// Sets the time zone of the partition directories(default: UTC).
+    pub fn with_time_zone(self, zone: TimeZoneMode) -> Self {
+        self.batcher
+            .configure(|a: &mut Archiver| {
+                a.partition = a.partition.clone().with_time_zone(zone);
+                Ok(())
+            })
+            .ok();
+        self
+    }
+
+    /// Sets the compression of the files(default: snappy).
+    pub fn with_compression(self, compression: Compression) -> Self {
+        self.batcher
+            .configure(|a: &mut Archiver| {
+                a.compression = compression;
+                Ok(())
+            })
+            .ok();
+        self
+    }
+
+    /// Sets the maximum number of queued items(default: 8 batches).
+    pub fn with_max_pending(self, max_pending: usize) -> Self {
+        self.batcher.set_max_pending(max_pending);
+        self
+    }
+
+    /// Sends the queued items at once after an item of this severity or higher
+    /// is logged(default: Error; None disables it).
+    pub fn with_flush_severity(self, level: Option<Severity>) -> Self {
+        self.batcher.set_flush_severity(level);
+        self
+    }
+}
+
+impl Logger for ParquetLogger {
+    fn log(&self, item: Item) {
+        let level: Severity = item.severity;
+        stats::count_queued(self.batcher.push(item, level));
+    }
+
+    /// Writes the queued items; fails if a batch was dropped since the last flush.
+    fn flush(&self) -> io::Result<()> {
+        self.batcher.flush()
+    }
+}
+
+/// Creates a logger which archives items as Parquet files partitioned by time.
+///
+/// Items are buffered and written by a background thread when `batch_size`
+/// items are queued or `flush_interval` elapses. Each batch is converted to an
+/// Arrow record batch and written as a new file(`part-*.parquet`) into the
+/// directory rendered from the timestamps of its items, so a batch spanning
+/// partitions becomes several files. Files appear atomically.
+///
+/// | Column     | Type                   |
+/// |:----------:|:----------------------:|
+/// | timestamp  | timestamp(us, UTC)     |
+/// | severity   | string                 |
+/// | body       | string                 |
+/// | attributes | map(string, string)    |
+/// | resource   | map(string, string)    |
+/// | trace_id   | string(nullable)       |
+/// | span_id    | string(nullable)       |
+///
+/// # Arguments
+/// - partition: The strftime-like directory template(e.g. `logs/dt=%Y-%m-%d/hour=%H`).
+/// - batch_size: The maximum number of rows in a batch.
+/// - flush_interval: The maximum time an item waits before being written.
+pub fn parquet_logger_new(
+    partition: &str,
+    batch_size: usize,
+    flush_interval: Duration,
+) -> io::Result<ParquetLogger> {
+    let partition = TimestampFormatter::strftime(partition)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let archiver = Archiver {
+        partition,
+        compression: Compression::SNAPPY,
+        seq: 0,
+    };
+    Ok(ParquetLogger {
+        batcher: Batcher::new(archiver, batch_size, flush_interval)?,
+    })
+}
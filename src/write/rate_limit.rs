@@ -0,0 +1,168 @@
+//! State functions for [`crate::write::limited_writer_new`],
+//! [`crate::write::limited_writer_new_lock_free`] and
+//! [`crate::copy::limited_logger_new`].
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::{Item, Severity};
+
+/// A bucket of tokens refilled over time.
+struct Bucket {
+    capacity: f64,
+    refill_per_sec: f64,
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl Bucket {
+    fn new(capacity: u32, refill_per_sec: f64) -> Self {
+        Self {
+            capacity: capacity.into(),
+            refill_per_sec: refill_per_sec.max(0.0),
+            tokens: capacity.into(),
+            refilled: Instant::now(),
+        }
+    }
+
+    /// Checks if the bucket would be full(same as a new one).
+    fn is_full(&self, now: Instant) -> bool {
+        let elapsed: Duration = now.saturating_duration_since(self.refilled);
+        self.capacity <= self.tokens + elapsed.as_secs_f64() * self.refill_per_sec
+    }
+
+    /// Takes a token if available.
+    fn take(&mut self, now: Instant) -> bool {
+        let elapsed: Duration = now.saturating_duration_since(self.refilled);
+        self.refilled = now;
+        let refill: f64 = elapsed.as_secs_f64() * self.refill_per_sec;
+        self.tokens = (self.tokens + refill).min(self.capacity);
+        match 1.0 <= self.tokens {
+            true => {
+                self.tokens -= 1.0;
+                true
+            }
+            false => false,
+        }
+    }
+}
+
+/// A lock-free token bucket(the generic cell rate algorithm).
+///
+/// Instead of tokens it keeps the time at which the bucket would be full
+/// again, as nanoseconds since the creation.
+struct AtomicBucket {
+    created: Instant,
+    full_at: AtomicU64,
+    /// The nanoseconds to refill a token.
+    interval: u64,
+    /// How far ahead of now `full_at` may be.
+    tolerance: u64,
+    capacity: u32,
+}
+
+impl AtomicBucket {
+    fn new(capacity: u32, refill_per_sec: f64) -> Self {
+        // Keeps capacity * interval representable.
+        let max_interval: u64 = u64::MAX / (u64::from(capacity) + 1);
+        let interval: u64 = match 0.0 < refill_per_sec {
+            true => (1e9 / refill_per_sec).clamp(1.0, max_interval as f64) as u64,
+            false => max_interval,
+        };
+        Self {
+            created: Instant::now(),
+            full_at: AtomicU64::new(0),
+            interval,
+            tolerance: interval * u64::from(capacity.saturating_sub(1)),
+            capacity,
+        }
+    }
+
+    /// Takes a token if available.
+    fn take(&self) -> bool {
+        if 0 == self.capacity {
+            return false;
+        }
+        let now: u64 = self.created.elapsed().as_nanos() as u64;
+        self.full_at
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |full_at: u64| {
+                let full_at: u64 = full_at.max(now);
+                match full_at - now <= self.tolerance {
+                    true => Some(full_at.saturating_add(self.interval)),
+                    false => None,
+                }
+            })
+            .is_ok()
+    }
+}
+
+/// Creates a token bucket state function.
+///
+/// Each item takes a token; an item is rejected if the bucket is empty. The
+/// bucket starts full and gets `refill_per_sec` tokens per second up to
+/// `capacity`, so bursts of `capacity` items are accepted after quiet periods
+/// while the long-term rate is limited to `refill_per_sec`.
+///
+/// The state is a single atomic, so threads never wait for each other.
+///
+/// # Arguments
+/// - capacity: The maximum number of tokens(the burst size).
+/// - refill_per_sec: The tokens added per second.
+pub fn token_bucket(capacity: u32, refill_per_sec: f64) -> impl Fn(Severity) -> bool + Sync + Send {
+    let bucket = AtomicBucket::new(capacity, refill_per_sec);
+    move |_: Severity| bucket.take()
+}
+
+/// Converts a state function with mutable state(e.g. a closure updating a map).
+///
+/// The function is called under a lock, so it serializes the callers.
+pub fn locked<S>(state: S) -> impl Fn(Severity) -> bool + Sync + Send
+where
+    S: FnMut(Severity) -> bool + Send,
+{
+    let state: Mutex<S> = Mutex::new(state);
+    move |level: Severity| match state.lock() {
+        Ok(mut guard) => (guard)(level),
+        Err(_) => false,
+    }
+}
+
+/// The number of keys which triggers the first removal of full buckets.
+const MIN_PRUNE_AT: usize = 1024;
+
+/// Creates a token bucket state function with a bucket per key.
+///
+/// Each item takes a token from the bucket of its key(e.g. an attribute or a
+/// hash of the body), so a noisy key cannot use up the tokens of the others.
+/// Buckets which have been refilled are removed when the number of keys grows.
+///
+/// # Arguments
+/// - capacity: The maximum number of tokens of a key(the burst size).
+/// - refill_per_sec: The tokens added per second to each bucket.
+/// - key: Gets the key of an item.
+pub fn keyed_token_bucket<K, F>(
+    capacity: u32,
+    refill_per_sec: f64,
+    key: F,
+) -> impl FnMut(&Item) -> bool
+where
+    K: Eq + Hash,
+    F: Fn(&Item) -> K,
+{
+    let mut buckets: HashMap<K, Bucket> = HashMap::new();
+    let mut prune_at: usize = MIN_PRUNE_AT;
+    move |item: &Item| {
+        let now: Instant = Instant::now();
+        if prune_at <= buckets.len() {
+            buckets.retain(|_, b: &mut Bucket| !b.is_full(now));
+            prune_at = MIN_PRUNE_AT.max(2 * buckets.len());
+        }
+        buckets
+            .entry(key(item))
+            .or_insert_with(|| Bucket::new(capacity, refill_per_sec))
+            .take(now)
+    }
+}
@@ -0,0 +1,336 @@
+//! A logger which appends items to a Redis stream(`XADD`).
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use crate::{
+    copy::Logger,
+    serialize::time,
+    write::{
+        http::{Batcher, Deliver},
+        net::{tcp_connect, truncate_str, DEFAULT_TIMEOUT},
+        retry::{jitter, RetryPolicy},
+        stats,
+    },
+    Item, Severity,
+};
+
+/// Writes a command as a RESP array of bulk strings.
+fn write_command<S>(args: &[S], buf: &mut Vec<u8>)
+where
+    S: AsRef<[u8]>,
+{
+    buf.extend_from_slice(format!("*{}\r\n", args.len()).as_bytes());
+    for a in args {
+        let a: &[u8] = a.as_ref();
+        buf.extend_from_slice(format!("${}\r\n", a.len()).as_bytes());
+        buf.extend_from_slice(a);
+        buf.extend_from_slice(b"\r\n");
+    }
+}
+
+/// A reply; nested values are skipped.
+enum Reply {
+    Ok,
+    Error(String),
+}
+
+/// Reads a reply(an error reply is not an io error).
+fn read_reply<R>(r: &mut R) -> io::Result<Reply>
+where
+    R: BufRead,
+{
+    let mut line: String = String::new();
+    if 0 == r.read_line(&mut line)? {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    let line: &str = line.trim_end();
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "redis: invalid reply");
+    let (kind, rest) = line.split_at_checked(1).ok_or_else(invalid)?;
+    match kind {
+        "+" | ":" => Ok(Reply::Ok),
+        "-" => Ok(Reply::Error(truncate_str(rest, 256).into())),
+        "$" => {
+            let len: i64 = rest.parse().map_err(|_| invalid())?;
+            if 0 <= len {
+                let mut data: Vec<u8> = vec![0; len as usize + 2];
+                r.read_exact(&mut data)?;
+            }
+            Ok(Reply::Ok)
+        }
+        "*" => {
+            let len: i64 = rest.parse().map_err(|_| invalid())?;
+            for _ in 0..len.max(0) {
+                read_reply(r)?;
+            }
+            Ok(Reply::Ok)
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// Sends batches from the background thread.
+struct Sender {
+    addrs: Vec<SocketAddr>,
+    key: String,
+    /// The `MAXLEN` arguments(e.g. `["MAXLEN", "~", "1000"]`).
+    trim: Vec<String>,
+    /// The `AUTH` arguments.
+    auth: Vec<String>,
+    retry: RetryPolicy,
+    conn: Option<BufReader<TcpStream>>,
+}
+
+impl Sender {
+    fn connect(&self) -> io::Result<BufReader<TcpStream>> {
+        let s: TcpStream = tcp_connect(&self.addrs.as_slice(), DEFAULT_TIMEOUT)?;
+        s.set_read_timeout(Some(DEFAULT_TIMEOUT))?;
+        let mut conn: BufReader<TcpStream> = BufReader::new(s);
+        if self.auth.is_empty() {
+            return Ok(conn);
+        }
+        let mut cmd: Vec<u8> = Vec::new();
+        let mut args: Vec<&str> = vec!["AUTH"];
+        args.extend(self.auth.iter().map(|s| s.as_str()));
+        write_command(&args, &mut cmd);
+        conn.get_mut().write_all(&cmd)?;
+        match read_reply(&mut conn)? {
+            Reply::Ok => Ok(conn),
+            Reply::Error(e) => Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("redis: {e}"),
+            )),
+        }
+    }
+
+    /// Sends the commands(pipelined) and reads their replies.
+    fn send(&mut self, commands: &[u8], count: usize) -> io::Result<Option<String>> {
+        if self.conn.is_none() {
+            self.conn = Some(self.connect()?);
+        }
+        let conn: &mut BufReader<TcpStream> = self
+            .conn
+            .as_mut()
+            .ok_or_else(|| io::Error::other("no connection"))?;
+        conn.get_mut().write_all(commands)?;
+        let mut error: Option<String> = None;
+        for _ in 0..count {
+            if let Reply::Error(e) = read_reply(conn)? {
+                error = Some(e);
+            }
+        }
+        Ok(error)
+    }
+}
+
+impl Deliver<Vec<String>> for Sender {
+    /// Appends a batch, reconnecting and retrying after connection errors.
+    ///
+    /// Error replies(e.g. `WRONGTYPE`) fail the batch without retrying.
+    fn deliver(&mut self, batch: &[Vec<String>]) -> io::Result<()> {
+        let mut commands: Vec<u8> = Vec::new();
+        let mut args: Vec<&str> = Vec::new();
+        for fields in batch {
+            args.clear();
+            args.extend(["XADD", self.key.as_str()]);
+            args.extend(self.trim.iter().map(|s| s.as_str()));
+            args.push("*");
+            args.extend(fields.iter().map(|s| s.as_str()));
+            write_command(&args, &mut commands);
+        }
+        let mut failures: u32 = 0;
+        loop {
+            let e: io::Error = match self.send(&commands, batch.len()) {
+                Ok(None) => return Ok(()),
+                Ok(Some(e)) => return Err(io::Error::other(format!("redis: {e}"))),
+                Err(e) => e,
+            };
+            self.conn = None;
+            failures += 1;
+            if self.retry.max_attempts <= failures {
+                return Err(e);
+            }
+            std::thread::sleep(jitter(self.retry.backoff(failures)));
+        }
+    }
+}
+
+/// A logger which appends items to a Redis stream.
+pub struct RedisStreamLogger {
+    batcher: Batcher<Vec<String>, Sender>,
+}
+
+impl RedisStreamLogger {
+    /// Trims the stream to about `max_len` entries(`MAXLEN ~`) on each append.
+    ///
+    /// # Arguments
+    /// - max_len: The maximum number of entries to keep.
+    /// - exact: Trims exactly(`MAXLEN =`); slower than the approximate trimming.
+    pub fn with_max_len(self, max_len: u64, exact: bool) -> Self {
+        let op: &str = match exact {
+            true => "=",
+            false => "~",
+        };
+        self.batcher
+            .configure(|s: &mut Sender| {
+                s.trim = vec!["MAXLEN".into(), op.into(), max_len.to_string()];
+                Ok(())
+            })
+            .ok();
+        self
+    }
+
+    /// Authenticates with a password(`requirepass`).
+    pub fn with_password(self, password: &str) -> Self {
+        self.batcher
+            .configure(|s: &mut Sender| {
+                s.auth = vec![password.into()];
+                s.conn = None;
+                Ok(())
+            })
+            .ok();
+        self
+    }
+
+    /// Authenticates with a user name and a password(ACL, Redis 6 or later).
+    pub fn with_user_password(self, user: &str, password: &str) -> Self {
+        self.batcher
+            .configure(|s: &mut Sender| {
+                s.auth = vec![user.into(), password.into()];
+                s.conn = None;
+                Ok(())
+            })
+            .ok();
+        self
+    }
+
+    /// Sets the retries of a batch after connection errors.
+    pub fn with_retry_policy(self, policy: RetryPolicy) -> Self {
+        self.batcher
+            .configure(|s: &mut Sender| {
+                s.retry = policy;
+                Ok(())
+            })
+            .ok();
+        self
+    }
+
+    /// Sets the maximum number of queued items(default: 8 batches).
+    pub fn with_max_pending(self, max_pending: usize) -> Self {
+        self.batcher.set_max_pending(max_pending);
+        self
+    }
+
+    /// Sends the queued items at once after an item of this severity or higher
+    /// is logged(default: Error; None disables it).
+    pub fn with_flush_severity(self, level: Option<Severity>) -> Self {
+        self.batcher.set_flush_severity(level);
+        self
+    }
+}
+
+impl Logger for RedisStreamLogger {
+    fn log(&self, item: Item) {
+        let level: Severity = item.severity;
+        let mut timestamp: String = String::new();
+        time::write_rfc3339(item.timestamp, 3, &mut timestamp);
+        let mut fields: Vec<String> = vec![
+            "timestamp".into(),
+            timestamp,
+            "severity".into(),
+            item.severity.as_str().into(),
+            "body".into(),
+            item.body.to_text().into(),
+        ];
+        if let Some(id) = item.trace_id {
+            fields.extend(["trace_id".into(), id]);
+        }
+        if let Some(id) = item.span_id {
+            fields.extend(["span_id".into(), id]);
+        }
+        for (key, val) in item.attributes {
+            fields.extend([key, val]);
+        }
+        for (key, val) in item.resource {
+            fields.extend([format!("resource.{key}"), val]);
+        }
+        stats::count_queued(self.batcher.push(fields, level));
+    }
+
+    /// Sends the queued items; fails if a batch was dropped since the last flush.
+    fn flush(&self) -> io::Result<()> {
+        self.batcher.flush()
+    }
+}
+
+/// Creates a logger which appends items to a Redis stream in batches.
+///
+/// Each item becomes an entry with an automatic id and the fields
+/// `timestamp`(RFC 3339), `severity`, `body`, `trace_id`/`span_id`(if any),
+/// the attributes, and the resource fields prefixed with `resource.`.
+///
+/// Batches are sent from a background thread as pipelined `XADD` commands
+/// over plain TCP. After a connection error the connection is made again and
+/// the batch is sent again, waiting with [`RetryPolicy::default`]; entries of
+/// a batch may be appended twice.
+///
+/// # Arguments
+/// - addr: The address of the server(e.g. `"127.0.0.1:6379"`).
+/// - key: The key of the stream.
+/// - batch_size: The maximum number of items sent at a time.
+/// - flush_interval: The maximum time an item waits before being sent.
+pub fn redis_stream_logger_new<A>(
+    addr: A,
+    key: &str,
+    batch_size: usize,
+    flush_interval: Duration,
+) -> io::Result<RedisStreamLogger>
+where
+    A: ToSocketAddrs,
+{
+    let sender = Sender {
+        addrs: addr.to_socket_addrs()?.collect(),
+        key: key.into(),
+        trim: vec![],
+        auth: vec![],
+        retry: RetryPolicy::default(),
+        conn: None,
+    };
+    Ok(RedisStreamLogger {
+        batcher: Batcher::new(sender, batch_size, flush_interval)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::{read_reply, write_command, Reply};
+
+    #[test]
+    fn command_golden_bytes() {
+        let mut buf: Vec<u8> = Vec::new();
+        write_command(&["XADD", "logs", "*", "body", "a\r\nb", ""], &mut buf);
+        let expected: &[u8] = b"*6\r\n$4\r\nXADD\r\n$4\r\nlogs\r\n$1\r\n*\r\n\
+            $4\r\nbody\r\n$4\r\na\r\nb\r\n$0\r\n\r\n";
+        assert_eq!(expected, buf.as_slice());
+    }
+
+    #[test]
+    fn replies() {
+        // A pipeline: a status, an id, an integer, an array, a null and an error.
+        let mut r: &[u8] = b"+OK\r\n$15\r\n1700000000000-0\r\n:3\r\n\
+            *2\r\n$1\r\na\r\n*1\r\n:1\r\n$-1\r\n-ERR wrong type\r\n";
+        for _ in 0..5 {
+            assert!(matches!(read_reply(&mut r), Ok(Reply::Ok)));
+        }
+        assert!(matches!(read_reply(&mut r), Ok(Reply::Error(e)) if e == "ERR wrong type"));
+        assert!(r.is_empty());
+
+        let e: io::Error = read_reply(&mut &b""[..]).err().unwrap();
+        assert_eq!(io::ErrorKind::UnexpectedEof, e.kind());
+        let e: io::Error = read_reply(&mut &b"?\r\n"[..]).err().unwrap();
+        assert_eq!(io::ErrorKind::InvalidData, e.kind());
+    }
+}
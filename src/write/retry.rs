@@ -0,0 +1,113 @@
+//! A log writer which retries failed writes.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::time::Duration;
+
+use crate::{
+    write::{stats, LogWrite, TryLogWrite},
+    Severity,
+};
+
+/// A retry policy: exponential backoff with jitter.
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    /// The number of attempts including the first one.
+    pub max_attempts: u32,
+    /// The wait before the second attempt.
+    pub initial_backoff: Duration,
+    /// The upper bound of the wait between attempts.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    /// 3 attempts, 100ms backoff doubled up to 5s.
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Gets the backoff before the attempt after `failures` failures(without jitter).
+    pub(crate) fn backoff(&self, failures: u32) -> Duration {
+        let factor: u32 = 1u32
+            .checked_shl(failures.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        self.initial_backoff
+            .checked_mul(factor)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff)
+    }
+}
+
+/// Picks a wait between the half of the backoff and the backoff.
+pub(crate) fn jitter(backoff: Duration) -> Duration {
+    let r: u64 = RandomState::new().build_hasher().finish();
+    let half: Duration = backoff / 2;
+    let nanos: u64 = half.as_nanos().min(u128::from(u64::MAX)) as u64;
+    match nanos {
+        0 => backoff,
+        n => half + Duration::from_nanos(r % n),
+    }
+}
+
+struct RetryWrite<W> {
+    inner: W,
+    policy: RetryPolicy,
+}
+
+impl<W> TryLogWrite for RetryWrite<W>
+where
+    W: TryLogWrite,
+{
+    fn try_write(&self, serialized: &str, level: Severity) -> io::Result<()> {
+        let mut failures: u32 = 0;
+        loop {
+            match self.inner.try_write(serialized, level) {
+                Ok(_) => return Ok(()),
+                Err(e) => {
+                    failures += 1;
+                    if self.policy.max_attempts <= failures {
+                        return Err(e);
+                    }
+                    std::thread::sleep(jitter(self.policy.backoff(failures)));
+                }
+            }
+        }
+    }
+}
+
+impl<W> LogWrite for RetryWrite<W>
+where
+    W: TryLogWrite,
+{
+    fn write(&self, serialized: &str, level: Severity) {
+        stats::count_write(self.try_write(serialized, level), serialized, level);
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Creates a log writer which retries failed writes with backoff.
+///
+/// The caller sleeps between attempts, so wrap slow sinks with a background
+/// writer. The last error is returned after the attempts are exhausted; use
+/// [`fallback_writer_new`](crate::write::fallback_writer_new) to hand the
+/// item to another writer.
+///
+/// # Arguments
+/// - inner: The fallible log writer(e.g. a network sink).
+/// - policy: The attempts and backoff.
+pub fn retry_writer_new<W>(inner: W, policy: RetryPolicy) -> impl TryLogWrite
+where
+    W: TryLogWrite,
+{
+    RetryWrite { inner, policy }
+}
@@ -0,0 +1,103 @@
+//! A log writer which keeps recent items in memory for crash context.
+
+use std::collections::VecDeque;
+use std::io;
+use std::sync::Mutex;
+
+use crate::{write::LogWrite, Severity};
+
+struct RingWrite<W> {
+    inner: W,
+    capacity: usize,
+    trigger: Severity,
+    lines: Mutex<VecDeque<(String, Severity)>>,
+}
+
+impl<W> LogWrite for RingWrite<W>
+where
+    W: LogWrite,
+{
+    fn write(&self, serialized: &str, level: Severity) {
+        match self.lines.lock() {
+            Err(_) => {}
+            Ok(mut guard) => match self.trigger <= level {
+                false => {
+                    if self.capacity <= guard.len() {
+                        guard.pop_front();
+                    }
+                    guard.push_back((serialized.into(), level));
+                }
+                true => {
+                    for (line, l) in guard.drain(..) {
+                        self.inner.write(&line, l)
+                    }
+                    self.inner.write(serialized, level)
+                }
+            },
+        }
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Creates a log writer which dumps recent items when a severe item arrives.
+///
+/// The last `capacity` items(any severity) are kept in memory. An item at or
+/// above `trigger` is written to the inner writer after the kept items, which
+/// are then cleared. Combine it with [`fanout_writer_new`](crate::write::fanout_writer_new)
+/// to keep the regular output.
+///
+/// # Arguments
+/// - inner: Writes the dumped items.
+/// - capacity: The number of items to keep.
+/// - trigger: The lowest severity which dumps the items(e.g. Fatal).
+pub fn ring_buffer_writer_new<W>(inner: W, capacity: usize, trigger: Severity) -> impl LogWrite
+where
+    W: LogWrite,
+{
+    RingWrite {
+        inner,
+        capacity: capacity.max(1),
+        trigger,
+        lines: Mutex::new(VecDeque::with_capacity(capacity)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::ring_buffer_writer_new;
+    use crate::{
+        write::{log_writer_new_from_fn, LogWrite},
+        Severity,
+    };
+
+    #[test]
+    fn recent_items_are_dumped_before_the_trigger() {
+        let lines: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+        let sink = lines.clone();
+        let inner = log_writer_new_from_fn(
+            move |serialized: &str, level: Severity| {
+                sink.lock()
+                    .unwrap()
+                    .push(format!("{} {serialized}", level.as_str()))
+            },
+            |_| true,
+        );
+        let w = ring_buffer_writer_new(inner, 2, Severity::Error);
+        w.write("1", Severity::Debug);
+        w.write("2", Severity::Info);
+        w.write("3", Severity::Warn);
+        assert!(lines.lock().unwrap().is_empty());
+        w.write("4", Severity::Error);
+        // Cleared after a dump.
+        w.write("5", Severity::Fatal);
+        assert_eq!(
+            vec!["info 2", "warn 3", "error 4", "fatal 5"],
+            *lines.lock().unwrap()
+        );
+    }
+}
@@ -0,0 +1,867 @@
+//! Rotating file log writers.
+
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use crate::{
+    serialize::time::{compile_strftime, TimeToken, TimeZoneMode, TimestampFormatter},
+    write::{
+        file::{open_append, write_line, SyncPolicy, Syncer},
+        stats, LogWrite, TryLogWrite,
+    },
+    Severity,
+};
+
+/// Appends a suffix to a path(e.g. `app.log` -> `app.log.gz`).
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut s: OsString = path.as_os_str().into();
+    s.push(suffix);
+    s.into()
+}
+
+/// Gets the path of a rotated file(e.g. `app.log.1`).
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    with_suffix(path, &format!(".{index}"))
+}
+
+/// A retention policy for rotated files.
+///
+/// Rotated files are removed when either limit is exceeded. Retention(and
+/// compression) runs on a background thread owned by the writer, so live
+/// writes only pay for a rename. Files left by earlier runs are compressed
+/// and pruned when a writer is created. Errors are reported by the next
+/// [`LogWrite::flush`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RetentionPolicy {
+    /// The number of rotated files to keep(unlimited if None).
+    pub max_files: Option<usize>,
+    /// The maximum age of rotated files, by modification time(unlimited if None).
+    pub max_age: Option<Duration>,
+    /// Compresses rotated files with gzip(`app.log.1` -> `app.log.1.gz`).
+    ///
+    /// Requires the `gzip` feature; the writers fail with
+    /// [`io::ErrorKind::Unsupported`] without it.
+    pub compress: bool,
+}
+
+impl RetentionPolicy {
+    /// Checks if the policy is supported by the enabled features.
+    fn check(&self) -> io::Result<()> {
+        match self.compress && cfg!(not(feature = "gzip")) {
+            true => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "compression requires the gzip feature",
+            )),
+            false => Ok(()),
+        }
+    }
+
+    fn compress(&self, path: &Path) -> io::Result<()> {
+        self.check()?;
+        #[cfg(feature = "gzip")]
+        if self.compress {
+            return gzip(path);
+        }
+        let _ = path;
+        Ok(())
+    }
+
+    /// Removes files beyond the limits; `rotated` must be sorted from newest to oldest.
+    fn apply(&self, rotated: &[PathBuf]) -> io::Result<()> {
+        let keep: usize = self.max_files.unwrap_or(usize::MAX);
+        let oldest: Option<SystemTime> = self
+            .max_age
+            .and_then(|age: Duration| SystemTime::now().checked_sub(age));
+        for (i, path) in rotated.iter().enumerate() {
+            let expired: bool = match oldest {
+                None => false,
+                Some(oldest) => path.metadata()?.modified()? < oldest,
+            };
+            if keep <= i || expired {
+                std::fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compresses a file into `{path}.gz` keeping its modification time.
+#[cfg(feature = "gzip")]
+fn gzip(path: &Path) -> io::Result<()> {
+    use flate2::{write::GzEncoder, Compression};
+
+    let mut src: File = File::open(path)?;
+    let modified: SystemTime = src.metadata()?.modified()?;
+    let tmp: PathBuf = with_suffix(path, ".gz.tmp");
+    let mut enc = GzEncoder::new(File::create(&tmp)?, Compression::default());
+    io::copy(&mut src, &mut enc)?;
+    let f: File = enc.finish()?;
+    f.sync_all()?;
+    f.set_modified(modified)?;
+    std::fs::rename(&tmp, with_suffix(path, ".gz"))?;
+    std::fs::remove_file(path)
+}
+
+type Job = Box<dyn FnOnce() -> io::Result<()> + Send>;
+
+/// A thread which runs retention jobs one by one until it is dropped.
+struct Worker {
+    jobs: Sender<Job>,
+    /// The first error of a job since the last wait.
+    error: Arc<Mutex<Option<io::Error>>>,
+}
+
+impl Worker {
+    fn new() -> io::Result<Self> {
+        let (tx, rx) = mpsc::channel::<Job>();
+        let error: Arc<Mutex<Option<io::Error>>> = Arc::new(Mutex::new(None));
+        let first = error.clone();
+        std::thread::Builder::new()
+            .name("log-retention".into())
+            .spawn(move || {
+                for job in rx {
+                    if let (Err(e), Ok(mut g)) = (job(), first.lock()) {
+                        g.get_or_insert(e);
+                    }
+                }
+            })?;
+        Ok(Self { jobs: tx, error })
+    }
+
+    fn send(&self, job: Job) -> io::Result<()> {
+        self.jobs
+            .send(job)
+            .map_err(|_| io::Error::other("retention worker stopped"))
+    }
+
+    /// Waits for the pending jobs; gets the first error since the last call.
+    fn wait(&self) -> io::Result<()> {
+        let (tx, rx) = mpsc::channel::<()>();
+        self.send(Box::new(move || {
+            tx.send(()).ok();
+            Ok(())
+        }))?;
+        rx.recv()
+            .map_err(|_| io::Error::other("retention worker stopped"))?;
+        match self.error.lock() {
+            Err(_) => Err(io::Error::other("lock poisoned")),
+            Ok(mut g) => g.take().map_or(Ok(()), Err),
+        }
+    }
+}
+
+/// Gets the directory of a file(`.` if none).
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(d) if !d.as_os_str().is_empty() => d,
+        _ => Path::new("."),
+    }
+}
+
+fn is_gz(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == "gz")
+}
+
+/// Lists `app.log.{n}` and `app.log.{n}.gz` sorted by the index(newest first).
+fn list_rotated(path: &Path) -> io::Result<Vec<(usize, PathBuf)>> {
+    let dir: &Path = parent_dir(path);
+    let prefix: String = match path.file_name().and_then(|n| n.to_str()) {
+        None => return Ok(vec![]),
+        Some(n) => format!("{n}."),
+    };
+    let mut found: Vec<(usize, PathBuf)> = vec![];
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name: OsString = entry.file_name();
+        let index: Option<usize> = name
+            .to_str()
+            .and_then(|n: &str| n.strip_prefix(&prefix))
+            .map(|n: &str| n.strip_suffix(".gz").unwrap_or(n))
+            .filter(|n: &&str| n.bytes().all(|b: u8| b.is_ascii_digit()))
+            .and_then(|n: &str| n.parse().ok());
+        if let Some(i) = index {
+            found.push((i, entry.path()));
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+/// Moves a file renamed away by a size based writer into `app.log.1`.
+fn retire_indexed(path: &Path, renamed: &Path, policy: &RetentionPolicy) -> io::Result<()> {
+    for (i, from) in list_rotated(path)?.into_iter().rev() {
+        let gz: &str = match is_gz(&from) {
+            true => ".gz",
+            false => "",
+        };
+        std::fs::rename(&from, with_suffix(&rotated_path(path, i + 1), gz))?;
+    }
+    let first: PathBuf = rotated_path(path, 1);
+    std::fs::rename(renamed, &first)?;
+    policy.compress(&first)?;
+    let rotated: Vec<PathBuf> = list_rotated(path)?.into_iter().map(|(_, p)| p).collect();
+    policy.apply(&rotated)
+}
+
+/// Compresses and prunes the rotated files left by earlier runs.
+fn retire_existing_indexed(path: &Path, policy: &RetentionPolicy) -> io::Result<()> {
+    if policy.compress {
+        for (_, p) in list_rotated(path)? {
+            if !is_gz(&p) {
+                policy.compress(&p)?;
+            }
+        }
+    }
+    let rotated: Vec<PathBuf> = list_rotated(path)?.into_iter().map(|(_, p)| p).collect();
+    policy.apply(&rotated)
+}
+
+struct SizeState {
+    file: BufWriter<File>,
+    syncer: Syncer,
+    size: u64,
+    rotations: u64,
+}
+
+/// A log writer which rotates a file when it gets larger than a threshold.
+pub struct RotatingWriter {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    retention: Option<(RetentionPolicy, Worker)>,
+    state: Mutex<SizeState>,
+}
+
+impl RotatingWriter {
+    fn rotate(&self, state: &mut SizeState) -> io::Result<()> {
+        state.syncer.closing(&mut state.file)?;
+        match (&self.retention, self.max_files) {
+            (Some((policy, worker)), _) => {
+                state.rotations += 1;
+                let suffix: String =
+                    format!(".rotating-{}-{}", std::process::id(), state.rotations);
+                let renamed: PathBuf = with_suffix(&self.path, &suffix);
+                std::fs::rename(&self.path, &renamed)?;
+                let (path, policy) = (self.path.clone(), *policy);
+                worker.send(Box::new(move || retire_indexed(&path, &renamed, &policy)))?;
+            }
+            (None, 0) => std::fs::remove_file(&self.path)?,
+            (None, n) => {
+                for i in (1..n).rev() {
+                    let from: PathBuf = rotated_path(&self.path, i);
+                    if from.exists() {
+                        std::fs::rename(&from, rotated_path(&self.path, i + 1))?;
+                    }
+                }
+                std::fs::rename(&self.path, rotated_path(&self.path, 1))?;
+            }
+        }
+        state.file = BufWriter::new(open_append(&self.path)?);
+        state.size = 0;
+        Ok(())
+    }
+
+    fn write_locked(
+        &self,
+        state: &mut SizeState,
+        serialized: &str,
+        level: Severity,
+    ) -> io::Result<()> {
+        let len: u64 = serialized.len() as u64 + u64::from(!serialized.ends_with('\n'));
+        if 0 < state.size && self.max_bytes < state.size + len {
+            self.rotate(state)?;
+        }
+        write_line(&mut state.file, serialized)?;
+        state.size += len;
+        state.syncer.wrote(&mut state.file, level)
+    }
+
+    /// Sets the durability policy(default: [`SyncPolicy::Never`]).
+    pub fn with_sync_policy(self, policy: SyncPolicy) -> Self {
+        let mut state: SizeState = self.state.into_inner().unwrap_or_else(|e| e.into_inner());
+        state.syncer = Syncer::new(policy);
+        Self {
+            state: Mutex::new(state),
+            ..self
+        }
+    }
+}
+
+impl TryLogWrite for RotatingWriter {
+    fn try_write(&self, serialized: &str, level: Severity) -> io::Result<()> {
+        match self.state.lock() {
+            Err(_) => Err(io::Error::other("lock poisoned")),
+            Ok(mut guard) => self.write_locked(&mut guard, serialized, level),
+        }
+    }
+}
+
+impl LogWrite for RotatingWriter {
+    fn write(&self, serialized: &str, level: Severity) {
+        stats::count_write(self.try_write(serialized, level), serialized, level);
+    }
+
+    /// Flushes the file and waits for the retention jobs; fails if a job failed.
+    fn flush(&self) -> io::Result<()> {
+        let flushed: io::Result<()> = match self.state.lock() {
+            Err(_) => Err(io::Error::other("lock poisoned")),
+            Ok(mut guard) => guard.file.flush(),
+        };
+        match &self.retention {
+            None => flushed,
+            Some((_, worker)) => flushed.and(worker.wait()),
+        }
+    }
+}
+
+/// Creates a log writer which rotates a file by size.
+///
+/// When a line would make the file larger than `max_bytes`, `app.log.{n}` is
+/// renamed to `app.log.{n+1}`, `app.log` is renamed to `app.log.1` and a new
+/// `app.log` is created. Files older than `app.log.{max_files}` are removed.
+/// Rotation happens under the writer lock.
+///
+/// # Arguments
+/// - path: The log file(e.g. `app.log`).
+/// - max_bytes: The size threshold.
+/// - max_files: The number of rotated files to keep(0: truncate instead).
+pub fn rotating_writer_new<P>(
+    path: P,
+    max_bytes: u64,
+    max_files: usize,
+) -> io::Result<RotatingWriter>
+where
+    P: AsRef<Path>,
+{
+    let path: PathBuf = path.as_ref().into();
+    let f: File = open_append(&path)?;
+    let size: u64 = f.metadata()?.len();
+    Ok(RotatingWriter {
+        path,
+        max_bytes,
+        max_files,
+        retention: None,
+        state: Mutex::new(SizeState {
+            file: BufWriter::new(f),
+            syncer: Syncer::new(SyncPolicy::Never),
+            size,
+            rotations: 0,
+        }),
+    })
+}
+
+/// Creates a log writer which rotates a file by size and applies a retention policy.
+///
+/// The live file is renamed away under the writer lock; shifting
+/// `app.log.{n}`, compression and removal run on a background thread.
+/// Pending jobs may be lost if the process exits right after a rotation;
+/// the rotated files left are compressed and pruned by the next writer.
+///
+/// # Arguments
+/// - path: The log file(e.g. `app.log`).
+/// - max_bytes: The size threshold.
+/// - retention: Limits for rotated files.
+pub fn rotating_writer_new_with_retention<P>(
+    path: P,
+    max_bytes: u64,
+    retention: RetentionPolicy,
+) -> io::Result<RotatingWriter>
+where
+    P: AsRef<Path>,
+{
+    retention.check()?;
+    let mut w: RotatingWriter = rotating_writer_new(path, max_bytes, 0)?;
+    let worker: Worker = Worker::new()?;
+    let path: PathBuf = w.path.clone();
+    worker.send(Box::new(move || retire_existing_indexed(&path, &retention)))?;
+    w.retention = Some((retention, worker));
+    Ok(w)
+}
+
+/// A rotation period.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum RotationPeriod {
+    Hourly,
+    Daily,
+}
+
+impl RotationPeriod {
+    fn pattern(&self) -> &'static str {
+        match self {
+            Self::Hourly => "%Y%m%d%H",
+            Self::Daily => "%Y%m%d",
+        }
+    }
+}
+
+/// Checks if a file name matches a template(directives match any non-empty text).
+fn matches_template(tokens: &[TimeToken], name: &str) -> bool {
+    match tokens.split_first() {
+        None => name.is_empty(),
+        Some((TimeToken::Literal(l), rest)) => name
+            .strip_prefix(l.as_str())
+            .is_some_and(|n: &str| matches_template(rest, n)),
+        Some((_, rest)) => name
+            .char_indices()
+            .skip(1)
+            .map(|(i, _)| i)
+            .chain(std::iter::once(name.len()))
+            .filter(|i: &usize| 0 < *i)
+            .any(|i: usize| matches_template(rest, &name[i..])),
+    }
+}
+
+/// Compresses a closed file and applies the policy to files matching the template.
+fn retire_templated(
+    closed: &Path,
+    current: &Path,
+    tokens: &[TimeToken],
+    policy: &RetentionPolicy,
+) -> io::Result<()> {
+    policy.compress(closed)?;
+    let found: Vec<(SystemTime, PathBuf)> = list_templated(parent_dir(closed), current, tokens)?;
+    let rotated: Vec<PathBuf> = found.into_iter().map(|(_, p)| p).collect();
+    policy.apply(&rotated)
+}
+
+/// Lists the files matching the template except the current one(newest first).
+fn list_templated(
+    dir: &Path,
+    current: &Path,
+    tokens: &[TimeToken],
+) -> io::Result<Vec<(SystemTime, PathBuf)>> {
+    let mut found: Vec<(SystemTime, PathBuf)> = vec![];
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path: PathBuf = entry.path();
+        let matched: bool = entry
+            .file_name()
+            .to_str()
+            .map(|n: &str| n.strip_suffix(".gz").unwrap_or(n))
+            .is_some_and(|n: &str| matches_template(tokens, n));
+        if matched && path.file_name() != current.file_name() {
+            found.push((entry.metadata()?.modified()?, path));
+        }
+    }
+    found.sort_by(|a, b| b.cmp(a));
+    Ok(found)
+}
+
+/// Compresses and prunes the closed files left by earlier runs.
+fn retire_existing_templated(
+    current: &Path,
+    tokens: &[TimeToken],
+    policy: &RetentionPolicy,
+) -> io::Result<()> {
+    let dir: &Path = parent_dir(current);
+    if !dir.exists() {
+        return Ok(());
+    }
+    if policy.compress {
+        for (_, p) in list_templated(dir, current, tokens)? {
+            if !is_gz(&p) {
+                policy.compress(&p)?;
+            }
+        }
+    }
+    let found: Vec<(SystemTime, PathBuf)> = list_templated(dir, current, tokens)?;
+    let rotated: Vec<PathBuf> = found.into_iter().map(|(_, p)| p).collect();
+    policy.apply(&rotated)
+}
+
+struct TimeState {
+    file: Option<BufWriter<File>>,
+    syncer: Syncer,
+    path: PathBuf,
+    period: String,
+}
+
+struct TimeRetention {
+    policy: RetentionPolicy,
+    name: Arc<Vec<TimeToken>>,
+    worker: Worker,
+}
+
+/// A log writer which switches files at period boundaries.
+pub struct TimeRotatingWriter {
+    template: TimestampFormatter,
+    period: TimestampFormatter,
+    retention: Option<TimeRetention>,
+    state: Mutex<TimeState>,
+}
+
+impl TimeRotatingWriter {
+    fn write_locked(
+        &self,
+        state: &mut TimeState,
+        serialized: &str,
+        level: Severity,
+        now: SystemTime,
+    ) -> io::Result<()> {
+        let mut period: String = String::new();
+        self.period.format(now, &mut period);
+        if state.file.is_none() || state.period != period {
+            let closed: Option<BufWriter<File>> = state.file.take();
+            if let Some(mut f) = closed {
+                state.syncer.closing(&mut f)?;
+            }
+            let mut path: String = String::new();
+            self.template.format(now, &mut path);
+            let path: PathBuf = path.into();
+            if let Some(dir) = path.parent().filter(|d: &&Path| !d.as_os_str().is_empty()) {
+                std::fs::create_dir_all(dir)?;
+            }
+            state.file = Some(BufWriter::new(open_append(&path)?));
+            state.period = period;
+            let closed: PathBuf = std::mem::replace(&mut state.path, path);
+            match &self.retention {
+                Some(r) if !closed.as_os_str().is_empty() && closed != state.path => {
+                    let (current, policy) = (state.path.clone(), r.policy);
+                    let name = r.name.clone();
+                    r.worker.send(Box::new(move || {
+                        retire_templated(&closed, &current, &name, &policy)
+                    }))?;
+                }
+                _ => {}
+            }
+        }
+        match &mut state.file {
+            None => Ok(()),
+            Some(f) => {
+                write_line(f, serialized)?;
+                state.syncer.wrote(f, level)
+            }
+        }
+    }
+
+    /// Sets the durability policy(default: [`SyncPolicy::Never`]).
+    pub fn with_sync_policy(self, policy: SyncPolicy) -> Self {
+        let mut state: TimeState = self.state.into_inner().unwrap_or_else(|e| e.into_inner());
+        state.syncer = Syncer::new(policy);
+        Self {
+            state: Mutex::new(state),
+            ..self
+        }
+    }
+}
+
+impl TryLogWrite for TimeRotatingWriter {
+    fn try_write(&self, serialized: &str, level: Severity) -> io::Result<()> {
+        match self.state.lock() {
+            Err(_) => Err(io::Error::other("lock poisoned")),
+            Ok(mut guard) => self.write_locked(&mut guard, serialized, level, SystemTime::now()),
+        }
+    }
+}
+
+impl LogWrite for TimeRotatingWriter {
+    fn write(&self, serialized: &str, level: Severity) {
+        stats::count_write(self.try_write(serialized, level), serialized, level);
+    }
+
+    /// Flushes the file and waits for the retention jobs; fails if a job failed.
+    fn flush(&self) -> io::Result<()> {
+        let flushed: io::Result<()> = match self.state.lock() {
+            Err(_) => Err(io::Error::other("lock poisoned")),
+            Ok(mut guard) => match &mut guard.file {
+                None => Ok(()),
+                Some(f) => f.flush(),
+            },
+        };
+        match &self.retention {
+            None => flushed,
+            Some(r) => flushed.and(r.worker.wait()),
+        }
+    }
+}
+
+/// Creates a log writer which switches files at period boundaries.
+///
+/// The file name is rendered from a strftime-like template when the first
+/// line of a period is written(missing directories are created).
+///
+/// # Arguments
+/// - template: The file name template(e.g. `logs/app-%Y-%m-%d.log`).
+/// - period: The rotation period.
+/// - zone: The time zone used for the template and the period boundaries.
+pub fn time_rotating_writer_new(
+    template: &str,
+    period: RotationPeriod,
+    zone: TimeZoneMode,
+) -> io::Result<TimeRotatingWriter> {
+    let template = TimestampFormatter::strftime(template)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?
+        .with_time_zone(zone);
+    let period = TimestampFormatter::strftime(period.pattern())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?
+        .with_time_zone(zone);
+    Ok(TimeRotatingWriter {
+        template,
+        period,
+        retention: None,
+        state: Mutex::new(TimeState {
+            file: None,
+            syncer: Syncer::new(SyncPolicy::Never),
+            path: PathBuf::new(),
+            period: String::new(),
+        }),
+    })
+}
+
+/// Creates a log writer which switches files at period boundaries and applies a retention policy.
+///
+/// When a period ends, the closed file is compressed(if enabled) and files in
+/// its directory whose names match the file name part of the template are
+/// removed beyond the limits, newest kept first. This runs on a background
+/// thread; directory parts of the template are not searched. Files of earlier
+/// periods in the directory of the current file are compressed and pruned
+/// when the writer is created.
+///
+/// # Arguments
+/// - template: The file name template(e.g. `logs/app-%Y-%m-%d.log`).
+/// - period: The rotation period.
+/// - zone: The time zone used for the template and the period boundaries.
+/// - retention: Limits for closed files.
+pub fn time_rotating_writer_new_with_retention(
+    template: &str,
+    period: RotationPeriod,
+    zone: TimeZoneMode,
+    retention: RetentionPolicy,
+) -> io::Result<TimeRotatingWriter> {
+    retention.check()?;
+    let name: &str = Path::new(template)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(template);
+    let name: Vec<TimeToken> =
+        compile_strftime(name).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut w: TimeRotatingWriter = time_rotating_writer_new(template, period, zone)?;
+    let name: Arc<Vec<TimeToken>> = Arc::new(name);
+    let mut current: String = String::new();
+    w.template.format(SystemTime::now(), &mut current);
+    let worker: Worker = Worker::new()?;
+    let tokens = name.clone();
+    worker.send(Box::new(move || {
+        retire_existing_templated(Path::new(&current), &tokens, &retention)
+    }))?;
+    w.retention = Some(TimeRetention {
+        policy: retention,
+        name,
+        worker,
+    });
+    Ok(w)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, SystemTime};
+
+    use super::{
+        rotating_writer_new, rotating_writer_new_with_retention, time_rotating_writer_new,
+        time_rotating_writer_new_with_retention, RetentionPolicy, RotationPeriod,
+    };
+    use crate::{
+        serialize::time::TimeZoneMode,
+        write::{LogWrite, TryLogWrite},
+        Severity,
+    };
+
+    /// Creates an empty directory for a test.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir: PathBuf =
+            std::env::temp_dir().join(format!("rotate-{name}-{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn read(path: &Path) -> String {
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    fn names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn touch(path: &Path, age: Duration) {
+        let f: File = File::create(path).unwrap();
+        f.set_modified(SystemTime::now() - age).unwrap();
+    }
+
+    #[test]
+    fn size_rotation_keeps_max_files() {
+        let dir: PathBuf = temp_dir("size");
+        let path: PathBuf = dir.join("app.log");
+        let w = rotating_writer_new(&path, 10, 2).unwrap();
+        for line in ["line-1", "line-2", "line-3", "line-4"] {
+            w.try_write(line, Severity::Info).unwrap();
+        }
+        w.flush().unwrap();
+        assert_eq!(vec!["app.log", "app.log.1", "app.log.2"], names(&dir));
+        assert_eq!("line-4\n", read(&path));
+        assert_eq!("line-3\n", read(&dir.join("app.log.1")));
+        assert_eq!("line-2\n", read(&dir.join("app.log.2")));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn size_rotation_without_files_truncates() {
+        let dir: PathBuf = temp_dir("truncate");
+        let path: PathBuf = dir.join("app.log");
+        let w = rotating_writer_new(&path, 10, 0).unwrap();
+        for line in ["line-1", "line-2", "line-3"] {
+            w.try_write(line, Severity::Info).unwrap();
+        }
+        w.flush().unwrap();
+        assert_eq!(vec!["app.log"], names(&dir));
+        assert_eq!("line-3\n", read(&path));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn retention_prunes_by_count_and_age() {
+        let dir: PathBuf = temp_dir("retention");
+        let path: PathBuf = dir.join("app.log");
+        // Left by an earlier run.
+        touch(&dir.join("app.log.1"), Duration::ZERO);
+        touch(&dir.join("app.log.2"), Duration::from_secs(7200));
+        touch(&dir.join("app.log.3"), Duration::ZERO);
+        let policy = RetentionPolicy {
+            max_files: Some(2),
+            max_age: Some(Duration::from_secs(3600)),
+            compress: false,
+        };
+        let w = rotating_writer_new_with_retention(&path, 10, policy).unwrap();
+        w.flush().unwrap();
+        assert_eq!(vec!["app.log", "app.log.1"], names(&dir));
+
+        for line in ["line-1", "line-2", "line-3"] {
+            w.try_write(line, Severity::Info).unwrap();
+        }
+        w.flush().unwrap();
+        assert_eq!(vec!["app.log", "app.log.1", "app.log.2"], names(&dir));
+        assert_eq!("line-2\n", read(&dir.join("app.log.1")));
+        assert_eq!("line-1\n", read(&dir.join("app.log.2")));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn retention_errors_are_reported_by_flush() {
+        let dir: PathBuf = temp_dir("errors");
+        let path: PathBuf = dir.join("app.log");
+        touch(&dir.join("app.log.1"), Duration::ZERO);
+        // A directory cannot be removed as a file.
+        std::fs::create_dir(dir.join("app.log.2")).unwrap();
+        let policy = RetentionPolicy {
+            max_files: Some(1),
+            ..Default::default()
+        };
+        let w = rotating_writer_new_with_retention(&path, 10, policy).unwrap();
+        assert!(w.flush().is_err());
+        // Reported once.
+        assert!(w.flush().is_ok());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn time_rotation_switches_files_and_prunes() {
+        let dir: PathBuf = temp_dir("time");
+        let template: String = format!("{}/app-%Y%m%d%H.log", dir.display());
+        let policy = RetentionPolicy {
+            max_files: Some(1),
+            ..Default::default()
+        };
+        let w = time_rotating_writer_new_with_retention(
+            &template,
+            RotationPeriod::Hourly,
+            TimeZoneMode::Utc,
+            policy,
+        )
+        .unwrap();
+        // The startup scan has nothing to do.
+        w.flush().unwrap();
+        let t0: SystemTime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let hour = Duration::from_secs(3600);
+        for (i, t) in [t0, t0 + Duration::from_secs(60), t0 + hour, t0 + hour * 2]
+            .into_iter()
+            .enumerate()
+        {
+            let mut state = w.state.lock().unwrap();
+            w.write_locked(&mut state, &format!("line-{i}"), Severity::Info, t)
+                .unwrap();
+        }
+        w.flush().unwrap();
+        // 2023-11-14T22:13:20Z; the file of 22h is pruned.
+        assert_eq!(
+            vec!["app-2023111423.log", "app-2023111500.log"],
+            names(&dir)
+        );
+        assert_eq!("line-2\n", read(&dir.join("app-2023111423.log")));
+        assert_eq!("line-3\n", read(&dir.join("app-2023111500.log")));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn time_rotation_creates_directories() {
+        let dir: PathBuf = temp_dir("dirs");
+        let template: String = format!("{}/%Y/%m/app-%d.log", dir.display());
+        let w =
+            time_rotating_writer_new(&template, RotationPeriod::Daily, TimeZoneMode::Utc).unwrap();
+        let t0: SystemTime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let day = Duration::from_secs(86400);
+        for (i, t) in [t0, t0 + day * 17].into_iter().enumerate() {
+            let mut state = w.state.lock().unwrap();
+            w.write_locked(&mut state, &format!("line-{i}"), Severity::Info, t)
+                .unwrap();
+        }
+        w.flush().unwrap();
+        assert_eq!("line-0\n", read(&dir.join("2023/11/app-14.log")));
+        assert_eq!("line-1\n", read(&dir.join("2023/12/app-01.log")));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn rotated_files_are_compressed() {
+        use std::io::Read;
+
+        let gunzip = |path: &Path| {
+            let mut s: String = String::new();
+            flate2::read::GzDecoder::new(File::open(path).unwrap())
+                .read_to_string(&mut s)
+                .unwrap();
+            s
+        };
+        let dir: PathBuf = temp_dir("gzip");
+        let path: PathBuf = dir.join("app.log");
+        // Rotated before a restart but not compressed.
+        std::fs::write(dir.join("app.log.1"), "old\n").unwrap();
+        let policy = RetentionPolicy {
+            compress: true,
+            ..Default::default()
+        };
+        let w = rotating_writer_new_with_retention(&path, 10, policy).unwrap();
+        w.flush().unwrap();
+        assert_eq!(vec!["app.log", "app.log.1.gz"], names(&dir));
+
+        w.try_write("line-1", Severity::Info).unwrap();
+        w.try_write("line-2", Severity::Info).unwrap();
+        w.flush().unwrap();
+        assert_eq!(vec!["app.log", "app.log.1.gz", "app.log.2.gz"], names(&dir));
+        assert_eq!("line-1\n", gunzip(&dir.join("app.log.1.gz")));
+        assert_eq!("old\n", gunzip(&dir.join("app.log.2.gz")));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
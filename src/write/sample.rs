@@ -0,0 +1,431 @@
+//! Log writers and loggers which keep a fraction of items.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::{copy::Logger, write::LogWrite, Item, Severity};
+
+/// A lock-free pseudo random number generator(SplitMix64).
+struct Rng {
+    state: AtomicU64,
+}
+
+impl Rng {
+    fn new() -> Self {
+        Self {
+            state: AtomicU64::new(RandomState::new().build_hasher().finish()),
+        }
+    }
+
+    fn next_u64(&self) -> u64 {
+        let z: u64 = self
+            .state
+            .fetch_add(0x9e37_79b9_7f4a_7c15, Ordering::Relaxed)
+            .wrapping_add(0x9e37_79b9_7f4a_7c15);
+        mix(z)
+    }
+}
+
+/// Mixes the bits of a value(the SplitMix64 finalizer).
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Hashes a trace id to a uniform value which is the same in every process.
+fn trace_hash(trace_id: &str) -> u64 {
+    // FNV-1a of the lowercase id.
+    let h: u64 = trace_id
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |h: u64, b: u8| {
+            (h ^ u64::from(b.to_ascii_lowercase())).wrapping_mul(0x0000_0100_0000_01b3)
+        });
+    mix(h)
+}
+
+/// Checks if a 64-bit value falls in the kept fraction.
+fn keeps(rate: f64, value: u64) -> bool {
+    match rate {
+        r if 1.0 <= r => true,
+        r if r <= 0.0 || r.is_nan() => false,
+        r => (value as f64) < r * (u64::MAX as f64),
+    }
+}
+
+struct SamplingWriter<W, R> {
+    inner: W,
+    rate: R,
+    rng: Rng,
+}
+
+impl<W, R> LogWrite for SamplingWriter<W, R>
+where
+    W: LogWrite,
+    R: Fn(Severity) -> f64 + Sync + Send,
+{
+    fn write(&self, serialized: &str, level: Severity) {
+        if keeps((self.rate)(level), self.rng.next_u64()) {
+            self.inner.write(serialized, level)
+        }
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Creates a log writer which keeps a random fraction of lines per severity.
+///
+/// Each line is kept independently with the probability given by `rate`, e.g.
+/// 0.01 for Trace, 0.1 for Debug and 1.0 for Warn and higher.
+///
+/// # Arguments
+/// - inner: The writer of kept lines.
+/// - rate: Gets the fraction to keep(0.0 to 1.0) of a severity.
+pub fn sampling_writer_new<W, R>(inner: W, rate: R) -> impl LogWrite
+where
+    W: LogWrite,
+    R: Fn(Severity) -> f64 + Sync + Send,
+{
+    SamplingWriter {
+        inner,
+        rate,
+        rng: Rng::new(),
+    }
+}
+
+struct TraceSamplingLogger<L, R> {
+    inner: L,
+    rate: R,
+    rng: Rng,
+}
+
+impl<L, R> Logger for TraceSamplingLogger<L, R>
+where
+    L: Logger,
+    R: Fn(Severity) -> f64 + Sync + Send,
+{
+    fn log(&self, item: Item) {
+        let value: u64 = match &item.trace_id {
+            Some(id) if !id.is_empty() => trace_hash(id),
+            _ => self.rng.next_u64(),
+        };
+        if keeps((self.rate)(item.severity), value) {
+            self.inner.log(item)
+        }
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Creates a logger which keeps or drops whole traces.
+///
+/// An item with a trace id is kept if the hash of the id falls in the
+/// fraction of its severity, so every process using this logger keeps all the
+/// items of a sampled trace. With higher fractions for higher severities(e.g.
+/// 0.1 for Info and 1.0 for Warn) the sampled traces stay complete while
+/// warnings of other traces are still kept. Items without a trace id are
+/// sampled one by one.
+///
+/// # Arguments
+/// - inner: The logger of kept items.
+/// - rate: Gets the fraction to keep(0.0 to 1.0) of a severity.
+pub fn trace_sampling_logger_new<L, R>(inner: L, rate: R) -> impl Logger
+where
+    L: Logger,
+    R: Fn(Severity) -> f64 + Sync + Send,
+{
+    TraceSamplingLogger {
+        inner,
+        rate,
+        rng: Rng::new(),
+    }
+}
+
+/// The rate measurement of an adaptive sampler.
+struct Window {
+    start: Instant,
+    /// Lines below the kept severity.
+    low: u64,
+    /// Lines of the kept severity or higher.
+    high: u64,
+    /// The fraction of low severity lines to keep.
+    rate: f64,
+}
+
+/// The length of a measurement window.
+const WINDOW: Duration = Duration::from_secs(1);
+
+impl Window {
+    /// Counts a line; starts the next window(and gets its rate) if elapsed.
+    fn count(&mut self, high: bool, target: f64, now: Instant) -> f64 {
+        let elapsed: Duration = now.saturating_duration_since(self.start);
+        if WINDOW <= elapsed {
+            let secs: f64 = elapsed.as_secs_f64();
+            let low: f64 = self.low as f64 / secs;
+            // The records/sec left for low severity lines.
+            let budget: f64 = (target - self.high as f64 / secs).max(0.0);
+            self.rate = match low <= budget {
+                true => 1.0,
+                false => budget / low,
+            };
+            self.start = now;
+            self.low = 0;
+            self.high = 0;
+        }
+        match high {
+            true => self.high += 1,
+            false => self.low += 1,
+        }
+        self.rate
+    }
+}
+
+struct AdaptiveSamplingWriter<W> {
+    inner: W,
+    target: f64,
+    keep: u8,
+    window: Mutex<Window>,
+    rng: Rng,
+}
+
+impl<W> LogWrite for AdaptiveSamplingWriter<W>
+where
+    W: LogWrite,
+{
+    fn write(&self, serialized: &str, level: Severity) {
+        let high: bool = self.keep <= u8::from(level);
+        let rate: f64 = match self.window.lock() {
+            Ok(mut w) => w.count(high, self.target, Instant::now()),
+            Err(_) => 1.0,
+        };
+        if high || keeps(rate, self.rng.next_u64()) {
+            self.inner.write(serialized, level)
+        }
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Creates a log writer which samples low severity lines when too many are written.
+///
+/// The rate of lines is measured every second. If it exceeded the target, the
+/// lines below `keep` are kept with the fraction which would have met the
+/// target during the next second; the fraction returns to 1.0 when the rate
+/// drops. Lines of `keep` or higher are always kept(and use up the target
+/// first).
+///
+/// # Arguments
+/// - inner: The writer of kept lines.
+/// - target: The records/sec to aim at.
+/// - keep: The lowest severity never sampled(e.g. Warn).
+pub fn adaptive_sampling_writer_new<W>(inner: W, target: f64, keep: Severity) -> impl LogWrite
+where
+    W: LogWrite,
+{
+    AdaptiveSamplingWriter {
+        inner,
+        target,
+        keep: keep.into(),
+        window: Mutex::new(Window {
+            start: Instant::now(),
+            low: 0,
+            high: 0,
+            rate: 1.0,
+        }),
+        rng: Rng::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+    use std::io;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    use super::{
+        adaptive_sampling_writer_new, sampling_writer_new, trace_sampling_logger_new, Window,
+    };
+    use crate::{
+        copy::Logger,
+        write::{log_writer_new_from_fn, LogWrite},
+        Item, Severity,
+    };
+
+    type Lines = Arc<Mutex<Vec<(String, Severity)>>>;
+
+    fn recorder(lines: &Lines) -> impl LogWrite {
+        let sink: Lines = lines.clone();
+        log_writer_new_from_fn(
+            move |serialized: &str, level: Severity| {
+                sink.lock().unwrap().push((serialized.into(), level))
+            },
+            |_| true,
+        )
+    }
+
+    fn count(lines: &Lines, level: Severity) -> usize {
+        lines
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, l)| *l == level)
+            .count()
+    }
+
+    #[test]
+    fn keeps_the_fraction_of_each_severity() {
+        let lines: Lines = Arc::new(Mutex::new(vec![]));
+        let w = sampling_writer_new(recorder(&lines), |level: Severity| match level {
+            Severity::Trace => 0.0,
+            Severity::Debug => 0.1,
+            _ => 1.0,
+        });
+        for _ in 0..10_000 {
+            w.write("t", Severity::Trace);
+            w.write("d", Severity::Debug);
+            w.write("w", Severity::Warn);
+        }
+        assert_eq!(0, count(&lines, Severity::Trace));
+        let debug: usize = count(&lines, Severity::Debug);
+        // 1000 expected; the standard deviation is 30.
+        assert!((800..1200).contains(&debug), "{debug}");
+        assert_eq!(10_000, count(&lines, Severity::Warn));
+    }
+
+    #[test]
+    fn invalid_rates_are_clamped() {
+        let lines: Lines = Arc::new(Mutex::new(vec![]));
+        let w = sampling_writer_new(recorder(&lines), |level: Severity| match level {
+            Severity::Info => f64::NAN,
+            Severity::Warn => -1.0,
+            _ => 2.0,
+        });
+        for _ in 0..100 {
+            w.write("i", Severity::Info);
+            w.write("w", Severity::Warn);
+            w.write("e", Severity::Error);
+        }
+        assert_eq!(0, count(&lines, Severity::Info));
+        assert_eq!(0, count(&lines, Severity::Warn));
+        assert_eq!(100, count(&lines, Severity::Error));
+    }
+
+    /// Records the trace ids of logged items.
+    struct Traces(Arc<Mutex<Vec<String>>>);
+
+    impl Logger for Traces {
+        fn log(&self, item: Item) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(item.trace_id.unwrap_or_default())
+        }
+
+        fn flush(&self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn traced(trace_id: &str, level: Severity) -> Item {
+        let mut item: Item = Item::new("x", BTreeMap::new());
+        item.trace_id = Some(trace_id.into());
+        item.severity = level;
+        item
+    }
+
+    #[test]
+    fn whole_traces_are_kept_by_every_logger() {
+        let rate = |level: Severity| match level {
+            Severity::Warn | Severity::Error | Severity::Fatal => 1.0,
+            _ => 0.5,
+        };
+        // Two loggers stand for two processes.
+        let kept: Vec<Arc<Mutex<Vec<String>>>> = (0..2)
+            .map(|_| {
+                let ids: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+                let l = trace_sampling_logger_new(Traces(ids.clone()), rate);
+                for i in 0..200 {
+                    let id: String = format!("{i:032x}");
+                    for _ in 0..3 {
+                        l.log(traced(&id, Severity::Info));
+                    }
+                    l.log(traced(&id.to_ascii_uppercase(), Severity::Info));
+                    l.log(traced(&id, Severity::Warn));
+                }
+                ids
+            })
+            .collect();
+
+        let a: Vec<String> = kept[0].lock().unwrap().clone();
+        assert_eq!(a, *kept[1].lock().unwrap());
+        let sampled: BTreeSet<String> = a
+            .iter()
+            .map(|id: &String| id.to_ascii_lowercase())
+            .collect();
+        assert_eq!(200, sampled.len());
+        // Each sampled trace: 3 items, the uppercase id and the warning.
+        let info: usize = a.len() - 200;
+        assert_eq!(0, info % 4);
+        // 100 traces expected; the standard deviation is 7.
+        assert!((60..140).contains(&(info / 4)), "{}", info / 4);
+        for id in a
+            .iter()
+            .filter(|id| id.bytes().any(|b| b.is_ascii_uppercase()))
+        {
+            let lower: String = id.to_ascii_lowercase();
+            assert_eq!(4, a.iter().filter(|i| **i == lower).count(), "{id}");
+        }
+    }
+
+    #[test]
+    fn adaptive_rate_follows_the_measured_rate() {
+        let t0: Instant = Instant::now();
+        let mut w = Window {
+            start: t0,
+            low: 0,
+            high: 0,
+            rate: 1.0,
+        };
+        for _ in 0..900 {
+            assert_eq!(1.0, w.count(false, 500.0, t0));
+        }
+        for _ in 0..100 {
+            w.count(true, 500.0, t0);
+        }
+        // 400 records/sec left for 900 low severity records/sec.
+        let t1: Instant = t0 + Duration::from_secs(1);
+        let rate: f64 = w.count(false, 500.0, t1);
+        assert!((rate - 400.0 / 900.0).abs() < 1e-9, "{rate}");
+        // High severity lines alone exceed the target.
+        for _ in 0..600 {
+            w.count(true, 500.0, t1);
+        }
+        assert_eq!(0.0, w.count(false, 500.0, t1 + Duration::from_secs(1)));
+        // The rate drops.
+        assert_eq!(1.0, w.count(false, 500.0, t1 + Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn adaptive_writer_keeps_high_severities() {
+        let lines: Lines = Arc::new(Mutex::new(vec![]));
+        let w = adaptive_sampling_writer_new(recorder(&lines), 10.0, Severity::Warn);
+        for _ in 0..1000 {
+            w.write("w", Severity::Warn);
+            // Kept during the first window.
+            w.write("d", Severity::Debug);
+        }
+        assert_eq!(1000, count(&lines, Severity::Warn));
+        assert_eq!(1000, count(&lines, Severity::Debug));
+    }
+}
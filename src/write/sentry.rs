@@ -0,0 +1,253 @@
+//! A logger which sends error items to Sentry as events.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+#[cfg(feature = "tls")]
+use crate::write::net::tls::TlsConfig;
+use crate::{
+    copy::Logger,
+    serialize::{json::ObjectWriter, time},
+    write::{
+        http::{Batcher, HttpClient, Target},
+        retry::RetryPolicy,
+        stats,
+    },
+    Item, Severity,
+};
+
+fn invalid_dsn(dsn: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("invalid dsn: {dsn}"))
+}
+
+/// Gets the envelope URL and the public key of a DSN(`https://KEY@HOST/PROJECT_ID`).
+fn parse_dsn(dsn: &str) -> io::Result<(String, String)> {
+    let (scheme, rest) = dsn.split_once("://").ok_or_else(|| invalid_dsn(dsn))?;
+    let (userinfo, rest) = rest.split_once('@').ok_or_else(|| invalid_dsn(dsn))?;
+    let key: &str = userinfo.split(':').next().unwrap_or("");
+    let (prefix, project) = rest
+        .trim_end_matches('/')
+        .rsplit_once('/')
+        .ok_or_else(|| invalid_dsn(dsn))?;
+    if key.is_empty() || project.is_empty() {
+        return Err(invalid_dsn(dsn));
+    }
+    Ok((
+        format!("{scheme}://{prefix}/api/{project}/envelope/"),
+        key.into(),
+    ))
+}
+
+/// Maps a severity to a Sentry level(only errors are sent).
+fn level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Fatal => "fatal",
+        _ => "error",
+    }
+}
+
+/// Gets a new random event id(32 hex digits).
+fn event_id() -> String {
+    let state = RandomState::new();
+    let mut h = state.build_hasher();
+    h.write_u64(0);
+    let mut l = state.build_hasher();
+    l.write_u64(1);
+    format!("{:016x}{:016x}", h.finish(), l.finish())
+}
+
+/// Allows at most `max_events` events in each window of `period`.
+struct RateLimit {
+    max_events: u32,
+    period: Duration,
+    /// The start of the current window and the events sent in it.
+    window: Mutex<(Instant, u32)>,
+}
+
+impl RateLimit {
+    fn allow(&self) -> bool {
+        let Ok(mut guard) = self.window.lock() else {
+            return false;
+        };
+        if self.period <= guard.0.elapsed() {
+            *guard = (Instant::now(), 0);
+        }
+        match guard.1 < self.max_events {
+            true => {
+                guard.1 += 1;
+                true
+            }
+            false => false,
+        }
+    }
+}
+
+/// A logger which sends `Error` and `Fatal` items to Sentry.
+pub struct SentryLogger {
+    dsn: String,
+    limit: RateLimit,
+    batcher: Batcher<String>,
+}
+
+impl SentryLogger {
+    /// Sets the retries of a failed event(connection errors, 429 and 5xx).
+    pub fn with_retry_policy(self, policy: RetryPolicy) -> Self {
+        self.batcher
+            .configure(|t: &mut Target<String>| {
+                t.retry = policy;
+                Ok(())
+            })
+            .ok();
+        self
+    }
+
+    /// Sets the maximum number of queued events(default: 64).
+    pub fn with_max_pending(self, max_pending: usize) -> Self {
+        self.batcher.set_max_pending(max_pending);
+        self
+    }
+
+    /// Sends the queued items at once after an item of this severity or higher
+    /// is logged(default: Error; None disables it).
+    pub fn with_flush_severity(self, level: Option<Severity>) -> Self {
+        self.batcher.set_flush_severity(level);
+        self
+    }
+
+    /// Sets the trusted roots and the client certificate for `https` endpoints.
+    #[cfg(feature = "tls")]
+    pub fn with_tls_config(self, config: TlsConfig) -> io::Result<Self> {
+        self.batcher
+            .configure(|t: &mut Target<String>| t.client.set_tls_config(config))?;
+        Ok(self)
+    }
+
+    fn write_event(&self, item: &Item, id: &str, buf: &mut String) {
+        let mut event = ObjectWriter::begin(buf);
+        event.str("event_id", id);
+        let t = time::since_epoch(item.timestamp);
+        event.raw(
+            "timestamp",
+            &format!("{}.{:03}", t.as_secs(), t.subsec_millis()),
+        );
+        event.str("platform", "other");
+        event.str("level", level(item.severity));
+        event.str("message", &item.body.to_text());
+
+        let mut tags: Vec<(&str, &str)> = vec![];
+        for (key, val) in &item.resource {
+            match key.as_str() {
+                "host.name" => event.str("server_name", val),
+                "service.version" => event.str("release", val),
+                "deployment.environment" => event.str("environment", val),
+                _ => tags.push((key, val)),
+            }
+        }
+        if let Some(trace_id) = &item.trace_id {
+            tags.push(("trace_id", trace_id));
+        }
+        if let Some(span_id) = &item.span_id {
+            tags.push(("span_id", span_id));
+        }
+        if !tags.is_empty() {
+            let mut obj = ObjectWriter::begin(event.key("tags"));
+            for (key, val) in tags {
+                obj.str(key, val);
+            }
+            obj.end();
+        }
+        if !item.attributes.is_empty() {
+            let mut extra = ObjectWriter::begin(event.key("extra"));
+            for (key, val) in &item.attributes {
+                extra.str(key, val);
+            }
+            extra.end();
+        }
+        event.end();
+    }
+}
+
+impl Logger for SentryLogger {
+    /// Queues an `Error` or `Fatal` item unless the rate limit is reached.
+    fn log(&self, item: Item) {
+        let level: Severity = item.severity;
+        if item.severity < Severity::Error || !self.limit.allow() {
+            return;
+        }
+        let id: String = event_id();
+        let mut event: String = String::new();
+        self.write_event(&item, &id, &mut event);
+
+        let mut envelope: String = String::new();
+        let mut header = ObjectWriter::begin(&mut envelope);
+        header.str("event_id", &id);
+        header.str("dsn", &self.dsn);
+        let sent_at = header.key("sent_at");
+        sent_at.push('"');
+        time::write_rfc3339(SystemTime::now(), 3, sent_at);
+        sent_at.push('"');
+        header.end();
+        envelope.push('\n');
+        envelope.push_str(&format!(
+            "{{\"type\":\"event\",\"length\":{}}}\n",
+            event.len()
+        ));
+        envelope.push_str(&event);
+        stats::count_queued(self.batcher.push(envelope, level));
+    }
+
+    /// Sends the queued events; fails if an event was dropped since the last flush.
+    fn flush(&self) -> io::Result<()> {
+        self.batcher.flush()
+    }
+}
+
+/// Creates a logger which sends `Error` and `Fatal` items to Sentry as events.
+///
+/// Other items are ignored. The body becomes the message, attributes become
+/// `extra` and trace/span ids become tags. The `host.name`, `service.version`
+/// and `deployment.environment` resource fields become `server_name`,
+/// `release` and `environment`; other resource fields become tags.
+///
+/// At most `max_events` events are sent in each `period`; the rest are
+/// dropped so that an error storm does not exhaust the quota. Each event is
+/// sent in its own envelope from a background thread, like
+/// [`http_batch_writer_new`](crate::write::http::http_batch_writer_new).
+///
+/// # Arguments
+/// - dsn: The client key(e.g. `"https://KEY@o0.ingest.sentry.io/0"`).
+/// - max_events: The maximum number of events in a period.
+/// - period: The length of a rate limit window(e.g. 1 minute).
+pub fn sentry_logger_new(dsn: &str, max_events: u32, period: Duration) -> io::Result<SentryLogger> {
+    let (url, key) = parse_dsn(dsn)?;
+    let auth: String = format!(
+        "Sentry sentry_version=7, sentry_key={key}, sentry_client=rs-simple-logging/{}",
+        env!("CARGO_PKG_VERSION")
+    );
+    let target: Target<String> = Target {
+        client: HttpClient::new(&url, &[("X-Sentry-Auth", &auth)])?,
+        content_type: "application/x-sentry-envelope".into(),
+        content_encoding: None,
+        encode: Box::new(|batch: &[String], buf: &mut Vec<u8>| {
+            for envelope in batch {
+                buf.extend_from_slice(envelope.as_bytes());
+            }
+        }),
+        retry: RetryPolicy::default(),
+        check: None,
+    };
+    let batcher: Batcher<String> = Batcher::new(target, 1, Duration::ZERO)?;
+    batcher.set_max_pending(64);
+    Ok(SentryLogger {
+        dsn: dsn.into(),
+        limit: RateLimit {
+            max_events,
+            period,
+            window: Mutex::new((Instant::now(), 0)),
+        },
+        batcher,
+    })
+}
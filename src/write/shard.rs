@@ -0,0 +1,121 @@
+//! A log writer and a logger which spread items over several inner sinks.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{copy::Logger, write::LogWrite, Item, Severity};
+
+/// The slot of the next thread which writes.
+static NEXT_SLOT: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    /// The slot of the current thread(assigned round robin).
+    static THREAD_SLOT: u64 = NEXT_SLOT.fetch_add(1, Ordering::Relaxed);
+}
+
+fn hash_of<K>(key: &K) -> u64
+where
+    K: Hash + ?Sized,
+{
+    let mut h = DefaultHasher::new();
+    key.hash(&mut h);
+    h.finish()
+}
+
+/// Flushes every shard; returns the first error.
+fn flush_all<T, F>(shards: &[T], flush: F) -> io::Result<()>
+where
+    F: Fn(&T) -> io::Result<()>,
+{
+    let mut first: io::Result<()> = Ok(());
+    for s in shards {
+        let res: io::Result<()> = flush(s);
+        first = first.and(res);
+    }
+    first
+}
+
+struct ShardedWriter<W> {
+    shards: Vec<W>,
+}
+
+impl<W> LogWrite for ShardedWriter<W>
+where
+    W: LogWrite,
+{
+    fn write(&self, serialized: &str, level: Severity) {
+        let n: u64 = self.shards.len() as u64;
+        if 0 < n {
+            let i: u64 = THREAD_SLOT.with(|s: &u64| *s) % n;
+            self.shards[i as usize].write(serialized, level)
+        }
+    }
+
+    /// Flushes every shard; returns the first error.
+    fn flush(&self) -> io::Result<()> {
+        flush_all(&self.shards, W::flush)
+    }
+}
+
+/// Creates a log writer which writes the lines of a thread to one of several writers.
+///
+/// Threads get shards round robin when they first write and always use the
+/// same shard, so the lines of a thread stay in order while threads on
+/// different shards do not contend for the same lock or queue(e.g. a
+/// [`crate::write::nonblocking_writer_new`] per shard, each writing to its own
+/// file or to the same downstream sink). Lines are dropped if there are no
+/// shards.
+///
+/// # Arguments
+/// - shards: The inner writers.
+pub fn sharded_writer_new<W>(shards: Vec<W>) -> impl LogWrite
+where
+    W: LogWrite,
+{
+    ShardedWriter { shards }
+}
+
+struct ShardedLogger<L, K> {
+    shards: Vec<L>,
+    key: K,
+}
+
+impl<L, K, H> Logger for ShardedLogger<L, K>
+where
+    L: Logger,
+    K: Fn(&Item) -> H + Sync + Send,
+    H: Hash,
+{
+    fn log(&self, item: Item) {
+        let n: u64 = self.shards.len() as u64;
+        if 0 < n {
+            let i: u64 = hash_of(&(self.key)(&item)) % n;
+            self.shards[i as usize].log(item)
+        }
+    }
+
+    /// Flushes every shard; returns the first error.
+    fn flush(&self) -> io::Result<()> {
+        flush_all(&self.shards, L::flush)
+    }
+}
+
+/// Creates a logger which logs an item with one of several loggers chosen by a key.
+///
+/// Items with the same key(e.g. `attributes["tenant"]` or the trace id) always
+/// use the same shard and stay in order. Items are dropped if there are no
+/// shards.
+///
+/// # Arguments
+/// - shards: The inner loggers.
+/// - key: Gets the key of an item.
+pub fn sharded_logger_new<L, K, H>(shards: Vec<L>, key: K) -> impl Logger
+where
+    L: Logger,
+    K: Fn(&Item) -> H + Sync + Send,
+    H: Hash,
+{
+    ShardedLogger { shards, key }
+}
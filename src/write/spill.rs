@@ -0,0 +1,268 @@
+//! A log writer which spills items to a local file while a sink fails.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::{
+    write::{stats, LogWrite, TryLogWrite},
+    Severity,
+};
+
+struct SpillState {
+    file: File,
+    /// The length of the spill file.
+    size: u64,
+    /// The number of bytes already replayed.
+    offset: u64,
+}
+
+/// A log writer which keeps failed items in a spill file and replays them.
+pub struct SpillWriter<W> {
+    inner: W,
+    max_bytes: u64,
+    state: Mutex<SpillState>,
+}
+
+/// Reads a spilled record(`{severity} {length}\n{line}\n`); None at the end.
+fn read_record<R>(r: &mut R) -> io::Result<Option<(Severity, String, u64)>>
+where
+    R: BufRead,
+{
+    let mut header: String = String::new();
+    let header_len: usize = r.read_line(&mut header)?;
+    if header_len == 0 {
+        return Ok(None);
+    }
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "broken spill record");
+    let (level, len) = header.trim_end().split_once(' ').ok_or_else(invalid)?;
+    let level: u8 = level.parse().map_err(|_| invalid())?;
+    let len: usize = len.parse().map_err(|_| invalid())?;
+    let mut line: Vec<u8> = vec![0; len + 1];
+    r.read_exact(&mut line)?;
+    line.pop();
+    let line: String = String::from_utf8(line).map_err(|_| invalid())?;
+    Ok(Some((level.into(), line, (header_len + len + 1) as u64)))
+}
+
+impl<W> SpillWriter<W>
+where
+    W: TryLogWrite,
+{
+    /// Writes spilled records to the inner writer until it fails.
+    fn replay_locked(&self, state: &mut SpillState) -> io::Result<()> {
+        if state.size <= state.offset {
+            return Ok(());
+        }
+        let mut reader = BufReader::new(state.file.try_clone()?);
+        reader.seek(SeekFrom::Start(state.offset))?;
+        while state.offset < state.size {
+            match read_record(&mut reader) {
+                Ok(Some((level, line, len))) => {
+                    self.inner.try_write(&line, level)?;
+                    state.offset += len;
+                }
+                // A broken tail(e.g. a crash while spilling) is discarded.
+                Ok(None) | Err(_) => state.offset = state.size,
+            }
+        }
+        state.file.set_len(0)?;
+        state.size = 0;
+        state.offset = 0;
+        Ok(())
+    }
+
+    fn spill_locked(
+        &self,
+        state: &mut SpillState,
+        serialized: &str,
+        level: Severity,
+    ) -> io::Result<()> {
+        let record: String = format!("{} {}\n{serialized}\n", u8::from(level), serialized.len());
+        let len: u64 = record.len() as u64;
+        if self.max_bytes < state.size + len {
+            return Err(io::Error::new(
+                io::ErrorKind::StorageFull,
+                "spill file full",
+            ));
+        }
+        state.file.write_all(record.as_bytes())?;
+        state.size += len;
+        Ok(())
+    }
+
+    /// Replays spilled records(e.g. on a timer while the application is idle).
+    pub fn replay(&self) -> io::Result<()> {
+        match self.state.lock() {
+            Err(_) => Err(io::Error::other("lock poisoned")),
+            Ok(mut guard) => self.replay_locked(&mut guard),
+        }
+    }
+}
+
+impl<W> TryLogWrite for SpillWriter<W>
+where
+    W: TryLogWrite,
+{
+    /// Fails only if the item could not be spilled either.
+    fn try_write(&self, serialized: &str, level: Severity) -> io::Result<()> {
+        let mut guard = self
+            .state
+            .lock()
+            .map_err(|_| io::Error::other("lock poisoned"))?;
+        let state: &mut SpillState = &mut guard;
+        let replayed: bool = self.replay_locked(state).is_ok();
+        let written: bool = replayed && self.inner.try_write(serialized, level).is_ok();
+        match written {
+            true => Ok(()),
+            false => self.spill_locked(state, serialized, level),
+        }
+    }
+}
+
+impl<W> LogWrite for SpillWriter<W>
+where
+    W: TryLogWrite,
+{
+    fn write(&self, serialized: &str, level: Severity) {
+        stats::count_write(self.try_write(serialized, level), serialized, level);
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.replay()?;
+        self.inner.flush()
+    }
+}
+
+/// Creates a log writer which spills items to a file while the inner writer fails.
+///
+/// Items the inner writer rejects(e.g. a full queue or a dead endpoint) are
+/// appended to the spill file. Spilled items are replayed in order before the
+/// next item, so the inner writer sees the original order. The file is
+/// truncated once everything is replayed; items are dropped while it would
+/// grow beyond `max_bytes`.
+///
+/// A spill file left by a previous process is replayed too; items replayed
+/// right before a crash may be written twice.
+///
+/// # Arguments
+/// - inner: The fallible log writer(e.g. a non-blocking network sink).
+/// - path: The spill file(created if missing).
+/// - max_bytes: The maximum size of the spill file.
+pub fn spill_writer_new<W, P>(inner: W, path: P, max_bytes: u64) -> io::Result<SpillWriter<W>>
+where
+    W: TryLogWrite,
+    P: AsRef<Path>,
+{
+    let file: File = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(path)?;
+    let size: u64 = file.metadata()?.len();
+    Ok(SpillWriter {
+        inner,
+        max_bytes,
+        state: Mutex::new(SpillState {
+            file,
+            size,
+            offset: 0,
+        }),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use super::spill_writer_new;
+    use crate::{
+        write::{try_log_writer_new_from_fn, LogWrite, TryLogWrite},
+        Severity,
+    };
+
+    type Lines = Arc<Mutex<Vec<String>>>;
+
+    /// A writer which accepts `budget` lines, then fails until the budget is raised.
+    fn flaky(lines: &Lines, budget: &Arc<AtomicUsize>) -> impl TryLogWrite {
+        let (sink, budget) = (lines.clone(), budget.clone());
+        try_log_writer_new_from_fn(move |serialized: &str, level: Severity| {
+            budget
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |b| b.checked_sub(1))
+                .map_err(|_| io::Error::from(io::ErrorKind::ConnectionRefused))?;
+            let line: String = format!("{} {serialized}", level.as_str());
+            sink.lock().unwrap().push(line);
+            Ok(())
+        })
+    }
+
+    fn spill_path(name: &str) -> PathBuf {
+        let path: PathBuf =
+            std::env::temp_dir().join(format!("spill-{name}-{}.log", std::process::id()));
+        std::fs::remove_file(&path).ok();
+        path
+    }
+
+    #[test]
+    fn spilled_items_are_replayed_in_order() {
+        let path: PathBuf = spill_path("order");
+        let lines: Lines = Arc::new(Mutex::new(vec![]));
+        let budget: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(1));
+        let w = spill_writer_new(flaky(&lines, &budget), &path, 1 << 20).unwrap();
+        w.try_write("a", Severity::Info).unwrap();
+        // The sink is down: spilled.
+        w.try_write("b1\nb2", Severity::Warn).unwrap();
+        w.try_write("c", Severity::Error).unwrap();
+        w.try_write("d", Severity::Debug).unwrap();
+        assert_eq!(vec!["info a"], *lines.lock().unwrap());
+
+        // Only one record gets through; the rest stays spilled.
+        budget.store(1, Ordering::SeqCst);
+        assert!(w.replay().is_err());
+        budget.store(usize::MAX, Ordering::SeqCst);
+        w.try_write("e", Severity::Info).unwrap();
+        assert_eq!(
+            vec!["info a", "warn b1\nb2", "error c", "debug d", "info e"],
+            *lines.lock().unwrap()
+        );
+        assert_eq!(0, std::fs::metadata(&path).unwrap().len());
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn a_full_spill_file_drops_items() {
+        let path: PathBuf = spill_path("full");
+        let lines: Lines = Arc::new(Mutex::new(vec![]));
+        let budget: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        // Fits one record(`9 3\nabc\n`).
+        let w = spill_writer_new(flaky(&lines, &budget), &path, 10).unwrap();
+        w.try_write("abc", Severity::Info).unwrap();
+        let e: io::Error = w.try_write("def", Severity::Info).unwrap_err();
+        assert_eq!(io::ErrorKind::StorageFull, e.kind());
+
+        budget.store(usize::MAX, Ordering::SeqCst);
+        w.flush().unwrap();
+        assert_eq!(vec!["info abc"], *lines.lock().unwrap());
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn a_spill_file_of_an_earlier_run_is_replayed() {
+        let path: PathBuf = spill_path("restart");
+        let lines: Lines = Arc::new(Mutex::new(vec![]));
+        let budget: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        let w = spill_writer_new(flaky(&lines, &budget), &path, 1 << 20).unwrap();
+        w.try_write("kept", Severity::Warn).unwrap();
+        drop(w);
+
+        budget.store(usize::MAX, Ordering::SeqCst);
+        let w = spill_writer_new(flaky(&lines, &budget), &path, 1 << 20).unwrap();
+        w.flush().unwrap();
+        assert_eq!(vec!["warn kept"], *lines.lock().unwrap());
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,183 @@
+//! A logger which sends items to the Splunk HTTP Event Collector(HEC).
+
+use std::io;
+use std::time::Duration;
+
+#[cfg(feature = "tls")]
+use crate::write::net::tls::TlsConfig;
+use crate::{
+    copy::Logger,
+    serialize::{json::ObjectWriter, time, Serialize},
+    write::{
+        http::{BatchFormat, Batcher, HttpClient, Target},
+        retry::RetryPolicy,
+        stats,
+        syslog::hostname,
+    },
+    Item, Severity,
+};
+
+/// A logger which wraps serialized items in HEC event envelopes.
+pub struct SplunkHecLogger<S> {
+    serialize: S,
+    host: String,
+    sourcetype: String,
+    source: Option<String>,
+    index: Option<String>,
+    json_event: bool,
+    batcher: Batcher<String>,
+}
+
+impl<S> SplunkHecLogger<S> {
+    /// Sets the `source` field.
+    pub fn with_source(mut self, source: &str) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// Sets the `index` field(default: the default index of the token).
+    pub fn with_index(mut self, index: &str) -> Self {
+        self.index = Some(index.into());
+        self
+    }
+
+    /// Overrides the `host` field(default: the host name of this machine).
+    ///
+    /// The `host.name` resource field still takes precedence.
+    pub fn with_host(mut self, host: &str) -> Self {
+        self.host = host.into();
+        self
+    }
+
+    /// Embeds the serialized item as a JSON value instead of a string.
+    ///
+    /// The serializer must write a JSON value(e.g. ECS JSON).
+    pub fn with_json_event(mut self) -> Self {
+        self.json_event = true;
+        self
+    }
+
+    /// Sets the retries of a failed batch(default: 3 attempts on errors, 429 and 5xx such as 503).
+    pub fn with_retry_policy(self, policy: RetryPolicy) -> Self {
+        self.batcher
+            .configure(|t: &mut Target<String>| {
+                t.retry = policy;
+                Ok(())
+            })
+            .ok();
+        self
+    }
+
+    /// Sets the maximum number of queued items(default: 8 batches).
+    pub fn with_max_pending(self, max_pending: usize) -> Self {
+        self.batcher.set_max_pending(max_pending);
+        self
+    }
+
+    /// Sends the queued items at once after an item of this severity or higher
+    /// is logged(default: Error; None disables it).
+    pub fn with_flush_severity(self, level: Option<Severity>) -> Self {
+        self.batcher.set_flush_severity(level);
+        self
+    }
+
+    /// Sets the trusted roots and the client certificate for `https` endpoints.
+    #[cfg(feature = "tls")]
+    pub fn with_tls_config(self, config: TlsConfig) -> io::Result<Self> {
+        self.batcher
+            .configure(|t: &mut Target<String>| t.client.set_tls_config(config))?;
+        Ok(self)
+    }
+}
+
+impl<S> Logger for SplunkHecLogger<S>
+where
+    S: Serialize,
+{
+    fn log(&self, item: Item) {
+        let level: Severity = item.severity;
+        let mut event: String = String::new();
+        self.serialize.serialize(&item, &mut event);
+        let event: &str = event.trim_end_matches('\n');
+        let host: &str = item
+            .resource
+            .get("host.name")
+            .map(|s| s.as_str())
+            .filter(|s: &&str| !s.is_empty())
+            .unwrap_or(&self.host);
+        let d: Duration = time::since_epoch(item.timestamp);
+
+        let mut envelope: String = String::new();
+        let mut obj = ObjectWriter::begin(&mut envelope);
+        obj.raw("time", &format!("{}.{:03}", d.as_secs(), d.subsec_millis()));
+        obj.str("host", host);
+        obj.str("sourcetype", &self.sourcetype);
+        if let Some(source) = &self.source {
+            obj.str("source", source);
+        }
+        if let Some(index) = &self.index {
+            obj.str("index", index);
+        }
+        match self.json_event {
+            true => obj.raw("event", event),
+            false => obj.str("event", event),
+        }
+        obj.end();
+        stats::count_queued(self.batcher.push(envelope, level));
+    }
+
+    /// Sends the queued items; fails if a batch was dropped since the last flush.
+    fn flush(&self) -> io::Result<()> {
+        self.batcher.flush()
+    }
+}
+
+/// Creates a logger which sends items to the HEC event endpoint in batches.
+///
+/// Each item becomes an envelope with `time`(the item timestamp), `host`
+/// (the `host.name` resource field or this machine), `sourcetype` and
+/// `event`(the serialized item as a string). Batches are sent with the
+/// `Authorization: Splunk <token>` header and retried when the collector is
+/// busy(503), like [`http_batch_writer_new`](crate::write::http::http_batch_writer_new).
+///
+/// # Arguments
+/// - endpoint: The HEC URL(e.g. `"https://splunk:8088"`); `/services/collector/event` is appended.
+/// - token: The HEC token.
+/// - sourcetype: The `sourcetype` field(e.g. `"_json"`).
+/// - serialize: Serializes the event.
+/// - batch_size: The maximum number of items in a request.
+/// - flush_interval: The maximum time an item waits before being sent.
+pub fn splunk_hec_logger_new<S>(
+    endpoint: &str,
+    token: &str,
+    sourcetype: &str,
+    serialize: S,
+    batch_size: usize,
+    flush_interval: Duration,
+) -> io::Result<SplunkHecLogger<S>>
+where
+    S: Serialize,
+{
+    let url: String = format!(
+        "{}/services/collector/event",
+        endpoint.trim_end_matches('/')
+    );
+    let auth: String = format!("Splunk {token}");
+    let target: Target<String> = Target {
+        client: HttpClient::new(&url, &[("Authorization", &auth)])?,
+        content_type: "application/json".into(),
+        content_encoding: None,
+        encode: BatchFormat::Ndjson.encode(),
+        retry: RetryPolicy::default(),
+        check: None,
+    };
+    Ok(SplunkHecLogger {
+        serialize,
+        host: hostname(),
+        sourcetype: sourcetype.into(),
+        source: None,
+        index: None,
+        json_event: false,
+        batcher: Batcher::new(target, batch_size, flush_interval)?,
+    })
+}
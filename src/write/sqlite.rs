@@ -0,0 +1,198 @@
+//! A logger which inserts items into a SQLite database(requires the `sqlite` feature).
+
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use rusqlite::{params, Connection};
+
+use crate::{
+    copy::Logger,
+    serialize::{json::ObjectWriter, time},
+    write::{
+        http::{Batcher, Deliver},
+        stats,
+    },
+    Item, Severity,
+};
+
+/// A row to insert.
+struct Row {
+    timestamp: String,
+    severity: String,
+    body: String,
+    attributes: String,
+    resource: String,
+    trace_id: Option<String>,
+    span_id: Option<String>,
+}
+
+fn sql_error(e: rusqlite::Error) -> io::Error {
+    io::Error::other(format!("sqlite: {e}"))
+}
+
+/// Inserts batches from the background thread.
+struct Database {
+    conn: Connection,
+    insert: String,
+}
+
+impl Deliver<Row> for Database {
+    /// Inserts a batch in a transaction.
+    fn deliver(&mut self, batch: &[Row]) -> io::Result<()> {
+        let tx = self.conn.transaction().map_err(sql_error)?;
+        {
+            let mut stmt = tx.prepare_cached(&self.insert).map_err(sql_error)?;
+            for r in batch {
+                stmt.execute(params![
+                    r.timestamp,
+                    r.severity,
+                    r.body,
+                    r.attributes,
+                    r.resource,
+                    r.trace_id,
+                    r.span_id,
+                ])
+                .map_err(sql_error)?;
+            }
+        }
+        tx.commit().map_err(sql_error)
+    }
+}
+
+/// Creates the table and its indices if missing.
+fn create_schema(conn: &Connection, table: &str) -> rusqlite::Result<()> {
+    conn.execute_batch(&format!(
+        "CREATE TABLE IF NOT EXISTS {table} (
+            id INTEGER PRIMARY KEY,
+            timestamp TEXT NOT NULL,
+            severity TEXT NOT NULL,
+            body TEXT NOT NULL,
+            attributes TEXT NOT NULL,
+            resource TEXT NOT NULL,
+            trace_id TEXT,
+            span_id TEXT
+        );
+        CREATE INDEX IF NOT EXISTS {table}_timestamp ON {table} (timestamp);
+        CREATE INDEX IF NOT EXISTS {table}_severity ON {table} (severity);"
+    ))
+}
+
+fn write_map<'a, I>(entries: I, buf: &mut String)
+where
+    I: Iterator<Item = (&'a String, &'a String)>,
+{
+    let mut obj = ObjectWriter::begin(buf);
+    for (key, val) in entries {
+        obj.str(key, val);
+    }
+    obj.end()
+}
+
+/// A logger which inserts items into a SQLite table.
+pub struct SqliteLogger {
+    batcher: Batcher<Row, Database>,
+}
+
+impl SqliteLogger {
+    /// Sets the maximum number of queued items(default: 8 batches).
+    pub fn with_max_pending(self, max_pending: usize) -> Self {
+        self.batcher.set_max_pending(max_pending);
+        self
+    }
+
+    /// Sends the queued items at once after an item of this severity or higher
+    /// is logged(default: Error; None disables it).
+    pub fn with_flush_severity(self, level: Option<Severity>) -> Self {
+        self.batcher.set_flush_severity(level);
+        self
+    }
+}
+
+impl Logger for SqliteLogger {
+    fn log(&self, item: Item) {
+        let level: Severity = item.severity;
+        let mut timestamp: String = String::new();
+        time::write_rfc3339(item.timestamp, 6, &mut timestamp);
+        let mut attributes: String = String::new();
+        write_map(item.attributes.iter(), &mut attributes);
+        let mut resource: String = String::new();
+        write_map(item.resource.iter(), &mut resource);
+        let row = Row {
+            timestamp,
+            severity: item.severity.as_str().into(),
+            body: item.body.to_text().into(),
+            attributes,
+            resource,
+            trace_id: item.trace_id,
+            span_id: item.span_id,
+        };
+        stats::count_queued(self.batcher.push(row, level));
+    }
+
+    /// Inserts the queued items; fails if a batch was dropped since the last flush.
+    fn flush(&self) -> io::Result<()> {
+        self.batcher.flush()
+    }
+}
+
+/// Creates a logger which inserts items into a SQLite database in batches.
+///
+/// The database is opened(or created) in WAL mode and the table is created
+/// if missing:
+///
+/// | Column     | Type    | Value                              |
+/// |:----------:|:-------:|:----------------------------------:|
+/// | id         | INTEGER | primary key                        |
+/// | timestamp  | TEXT    | RFC 3339 in UTC(microseconds)      |
+/// | severity   | TEXT    | e.g. `info`                        |
+/// | body       | TEXT    | [`crate::Body::to_text`]           |
+/// | attributes | TEXT    | JSON object(e.g. `json_extract`)   |
+/// | resource   | TEXT    | JSON object                        |
+/// | trace_id   | TEXT    | NULL if missing                    |
+/// | span_id    | TEXT    | NULL if missing                    |
+///
+/// The timestamp and the severity are indexed. Each batch is inserted in a
+/// transaction from a background thread.
+///
+/// # Arguments
+/// - path: The database file.
+/// - table: The table name(letters, digits and `_`).
+/// - batch_size: The maximum number of items in a transaction.
+/// - flush_interval: The maximum time an item waits before being inserted.
+pub fn sqlite_logger_new<P>(
+    path: P,
+    table: &str,
+    batch_size: usize,
+    flush_interval: Duration,
+) -> io::Result<SqliteLogger>
+where
+    P: AsRef<Path>,
+{
+    let valid: bool = table.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && table.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid table name: {table}"),
+        ));
+    }
+    let conn: Connection = Connection::open(path).map_err(sql_error)?;
+    conn.busy_timeout(Duration::from_secs(5))
+        .map_err(sql_error)?;
+    conn.pragma_update(None, "journal_mode", "WAL")
+        .map_err(sql_error)?;
+    conn.pragma_update(None, "synchronous", "NORMAL")
+        .map_err(sql_error)?;
+    create_schema(&conn, table).map_err(sql_error)?;
+    let database = Database {
+        conn,
+        insert: format!(
+            "INSERT INTO {table} (timestamp, severity, body, attributes, resource, trace_id, span_id)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"
+        ),
+    };
+    Ok(SqliteLogger {
+        batcher: Batcher::new(database, batch_size, flush_interval)?,
+    })
+}
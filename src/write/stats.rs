@@ -0,0 +1,175 @@
+//! Counters of written and dropped records, maintained by the built-in writers.
+//!
+//! With the `metrics` feature the built-in writers also report to the
+//! [`metrics`](https://docs.rs/metrics) facade:
+//!
+//! | Name                          | Type      | Labels                                |
+//! |:-----------------------------:|:---------:|:-------------------------------------:|
+//! | log_records_written_total     | counter   |                                       |
+//! | log_records_dropped_total     | counter   | reason(level, rate_limit, full_queue) |
+//! | log_write_errors_total        | counter   |                                       |
+//! | log_records_by_severity_total | counter   | severity                              |
+//! | log_bytes_written_total       | counter   |                                       |
+//! | log_queue_depth               | gauge     | queue(nonblocking, batch)             |
+//! | log_flush_seconds             | histogram | writer(nonblocking)                   |
+//! | log_batch_delivery_seconds    | histogram |                                       |
+//!
+//! The severity and the bytes are counted by synchronous writers only(batching
+//! sinks count delivered records).
+
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::Severity;
+
+/// Labels of a metric.
+type Labels = &'static [(&'static str, &'static str)];
+
+/// A process-wide counter.
+pub(crate) struct Counter {
+    value: AtomicU64,
+    #[cfg(feature = "metrics")]
+    name: &'static str,
+    #[cfg(feature = "metrics")]
+    labels: Labels,
+}
+
+impl Counter {
+    const fn new(name: &'static str, labels: Labels) -> Self {
+        #[cfg(not(feature = "metrics"))]
+        let _ = (name, labels);
+        Self {
+            value: AtomicU64::new(0),
+            #[cfg(feature = "metrics")]
+            name,
+            #[cfg(feature = "metrics")]
+            labels,
+        }
+    }
+
+    pub(crate) fn add(&self, n: u64) {
+        self.value.fetch_add(n, Ordering::Relaxed);
+        #[cfg(feature = "metrics")]
+        metrics::counter!(self.name, self.labels).increment(n);
+    }
+
+    fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+const DROPPED: &str = "log_records_dropped_total";
+
+pub(crate) static WRITTEN: Counter = Counter::new("log_records_written_total", &[]);
+pub(crate) static DROPPED_BY_LEVEL: Counter = Counter::new(DROPPED, &[("reason", "level")]);
+pub(crate) static DROPPED_BY_RATE_LIMIT: Counter =
+    Counter::new(DROPPED, &[("reason", "rate_limit")]);
+pub(crate) static DROPPED_BY_FULL_QUEUE: Counter =
+    Counter::new(DROPPED, &[("reason", "full_queue")]);
+pub(crate) static WRITE_ERRORS: Counter = Counter::new("log_write_errors_total", &[]);
+
+/// Counts a record written by a synchronous writer.
+pub(crate) fn count_written(serialized: &str, level: Severity) {
+    WRITTEN.add(1);
+    #[cfg(feature = "metrics")]
+    {
+        let severity: &'static str = match level {
+            Severity::Trace => "trace",
+            Severity::Debug => "debug",
+            Severity::Info => "info",
+            Severity::Warn => "warn",
+            Severity::Error => "error",
+            Severity::Fatal => "fatal",
+        };
+        metrics::counter!("log_records_by_severity_total", "severity" => severity).increment(1);
+        metrics::counter!("log_bytes_written_total").increment(serialized.len() as u64);
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = (serialized, level);
+}
+
+/// Counts the result of writing a record(`WouldBlock` means a full queue).
+///
+/// Only the outermost writer counts a record, so wrappers like
+/// [`crate::write::retry::retry_writer_new`] do not count a drop again.
+pub(crate) fn count_write(result: io::Result<()>, serialized: &str, level: Severity) {
+    match result {
+        Ok(()) => count_written(serialized, level),
+        Err(e) if e.kind() == io::ErrorKind::WouldBlock => DROPPED_BY_FULL_QUEUE.add(1),
+        Err(_) => WRITE_ERRORS.add(1),
+    }
+}
+
+/// Counts a record a queue rejected; queued records are counted when written.
+pub(crate) fn count_queued(result: io::Result<()>) {
+    if let Err(e) = result {
+        match e.kind() {
+            io::ErrorKind::WouldBlock => DROPPED_BY_FULL_QUEUE.add(1),
+            _ => WRITE_ERRORS.add(1),
+        }
+    }
+}
+
+/// Reports records added to(positive) or taken from(negative) a queue.
+pub(crate) fn queue_depth(queue: &'static str, delta: i64) {
+    #[cfg(feature = "metrics")]
+    metrics::gauge!("log_queue_depth", "queue" => queue).increment(delta as f64);
+    #[cfg(not(feature = "metrics"))]
+    let _ = (queue, delta);
+}
+
+/// Runs a function and reports its duration to a histogram.
+pub(crate) fn timed<T, F>(name: &'static str, labels: Labels, f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    #[cfg(feature = "metrics")]
+    {
+        let started = std::time::Instant::now();
+        let t: T = f();
+        metrics::histogram!(name, labels).record(started.elapsed());
+        t
+    }
+    #[cfg(not(feature = "metrics"))]
+    {
+        let _ = (name, labels);
+        f()
+    }
+}
+
+/// Counters since the start of the process.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub struct Stats {
+    /// Records written by sinks(for batching sinks: delivered in batches).
+    pub written: u64,
+    /// Records skipped by level checks.
+    pub dropped_by_level: u64,
+    /// Records skipped by limited writers and loggers.
+    pub dropped_by_rate_limit: u64,
+    /// Records dropped because a background queue was full.
+    pub dropped_by_full_queue: u64,
+    /// Records which could not be written(for batching sinks: in failed batches).
+    pub write_errors: u64,
+}
+
+impl Stats {
+    /// Gets the number of records lost for any reason except level checks.
+    pub fn lost(&self) -> u64 {
+        self.dropped_by_rate_limit + self.dropped_by_full_queue + self.write_errors
+    }
+}
+
+/// Gets the current counters.
+///
+/// A record is counted by the outermost built-in writer which writes or skips
+/// it; a record queued for a background thread is counted when the thread
+/// writes it(or when the queue is full). Custom writers are not counted.
+pub fn snapshot() -> Stats {
+    Stats {
+        written: WRITTEN.get(),
+        dropped_by_level: DROPPED_BY_LEVEL.get(),
+        dropped_by_rate_limit: DROPPED_BY_RATE_LIMIT.get(),
+        dropped_by_full_queue: DROPPED_BY_FULL_QUEUE.get(),
+        write_errors: WRITE_ERRORS.get(),
+    }
+}
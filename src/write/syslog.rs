@@ -0,0 +1,154 @@
+//! A syslog sink: message header, facility and transport in one writer.
+
+use std::io;
+use std::time::SystemTime;
+
+use crate::{
+    serialize::{
+        syslog::{priority, write_rfc3164_header, write_rfc5424_header, Facility},
+        time::{TimeZoneMode, TimestampFormatter},
+    },
+    write::{
+        net::{tcp_connect, udp_writer_new, StreamWriter, DEFAULT_TIMEOUT},
+        stats, LogWrite, TryLogWrite,
+    },
+    Severity,
+};
+
+/// A syslog message format.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum SyslogFormat {
+    /// BSD syslog(`<PRI>Mmm dd hh:mm:ss HOST TAG[PID]: MSG`, local time).
+    Rfc3164,
+    /// `<PRI>1 TIMESTAMP HOST APP-NAME PROCID - - MSG`(UTC).
+    Rfc5424,
+}
+
+/// A syslog transport.
+#[derive(Clone, Debug)]
+pub enum SyslogTransport {
+    /// One message per datagram(e.g. `"127.0.0.1:514"`).
+    Udp(String),
+    /// Octet-counting framing(RFC 6587, e.g. `"127.0.0.1:601"`) with reconnects.
+    Tcp(String),
+    /// One message per unix datagram(e.g. `/dev/log`).
+    #[cfg(unix)]
+    Unix(std::path::PathBuf),
+}
+
+/// Frames a message with its length(`{len} {msg}`).
+pub(crate) fn frame_octet_counting(serialized: &str, buf: &mut Vec<u8>) {
+    let msg: &str = serialized.trim_end_matches('\n');
+    buf.extend_from_slice(format!("{} ", msg.len()).as_bytes());
+    buf.extend_from_slice(msg.as_bytes());
+}
+
+/// Gets the host name(`HOSTNAME`, then the kernel host name).
+pub(crate) fn hostname() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/proc/sys/kernel/hostname").ok())
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|h: String| h.trim().to_string())
+        .unwrap_or_default()
+}
+
+/// A log writer which sends serialized items as syslog messages.
+pub struct SyslogWriter {
+    format: SyslogFormat,
+    facility: Facility,
+    app_name: String,
+    host: String,
+    proc_id: String,
+    stamp: TimestampFormatter,
+    transport: Box<dyn TryLogWrite>,
+}
+
+impl SyslogWriter {
+    /// Overrides the HOSTNAME field(default: the host name of this machine).
+    pub fn with_hostname(mut self, host: &str) -> Self {
+        self.host = host.into();
+        self
+    }
+
+    fn message(&self, serialized: &str, level: Severity, buf: &mut String) {
+        let pri: u16 = priority(self.facility, level);
+        let now: SystemTime = SystemTime::now();
+        match self.format {
+            SyslogFormat::Rfc3164 => write_rfc3164_header(
+                pri,
+                now,
+                &self.stamp,
+                &self.host,
+                &self.app_name,
+                &self.proc_id,
+                buf,
+            ),
+            SyslogFormat::Rfc5424 => {
+                write_rfc5424_header(pri, now, &self.host, &self.app_name, &self.proc_id, buf);
+                buf.push_str("- ");
+            }
+        }
+        buf.push_str(serialized.trim_end_matches('\n'));
+    }
+}
+
+impl TryLogWrite for SyslogWriter {
+    fn try_write(&self, serialized: &str, level: Severity) -> io::Result<()> {
+        let mut buf: String = String::new();
+        self.message(serialized, level, &mut buf);
+        self.transport.try_write(&buf, level)
+    }
+}
+
+impl LogWrite for SyslogWriter {
+    fn write(&self, serialized: &str, level: Severity) {
+        stats::count_write(self.try_write(serialized, level), serialized, level);
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.transport.flush()
+    }
+}
+
+/// Creates a log writer which sends serialized items to a syslog server.
+///
+/// The serialized item(e.g. logfmt or JSON) becomes the MSG part; the PRI
+/// part is computed from the facility and the severity of the item.
+///
+/// # Arguments
+/// - transport: Where to send the messages.
+/// - format: The header format.
+/// - facility: The facility used to compute the PRI part.
+/// - app_name: The APP-NAME(RFC 5424) or TAG(RFC 3164) field.
+pub fn syslog_writer_new(
+    transport: SyslogTransport,
+    format: SyslogFormat,
+    facility: Facility,
+    app_name: &str,
+) -> io::Result<SyslogWriter> {
+    let transport: Box<dyn TryLogWrite> = match transport {
+        SyslogTransport::Udp(addr) => Box::new(udp_writer_new(addr)?),
+        SyslogTransport::Tcp(addr) => Box::new(StreamWriter::new(
+            Box::new(move || {
+                let s = tcp_connect(&addr.as_str(), DEFAULT_TIMEOUT)?;
+                Ok(Box::new(s) as Box<dyn io::Write + Send>)
+            }),
+            frame_octet_counting,
+        )),
+        #[cfg(unix)]
+        SyslogTransport::Unix(path) => Box::new(crate::write::net::unix_datagram_writer_new(path)),
+    };
+    let stamp = TimestampFormatter::strftime("%b %d %H:%M:%S")
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?
+        .with_time_zone(TimeZoneMode::Local);
+    Ok(SyslogWriter {
+        format,
+        facility,
+        app_name: app_name.into(),
+        host: hostname(),
+        proc_id: std::process::id().to_string(),
+        stamp,
+        transport,
+    })
+}
@@ -0,0 +1,31 @@
+//! A log writer for the browser console(requires the `wasm-console` feature).
+
+use web_sys::{console, wasm_bindgen::JsValue};
+
+use crate::{write::LogWrite, Severity};
+
+struct ConsoleWriter;
+
+impl LogWrite for ConsoleWriter {
+    fn write(&self, serialized: &str, level: Severity) {
+        let line = JsValue::from_str(serialized.trim_end_matches('\n'));
+        match level {
+            Severity::Trace | Severity::Debug => console::debug_1(&line),
+            Severity::Info => console::info_1(&line),
+            Severity::Warn => console::warn_1(&line),
+            Severity::Error | Severity::Fatal => console::error_1(&line),
+        }
+    }
+}
+
+/// Creates a log writer which writes lines to the browser console(`wasm32` only).
+///
+/// | Severity     | Method          |
+/// |:------------:|:---------------:|
+/// | Trace, Debug | `console.debug` |
+/// | Info         | `console.info`  |
+/// | Warn         | `console.warn`  |
+/// | Error, Fatal | `console.error` |
+pub fn console_writer_new() -> impl LogWrite {
+    ConsoleWriter
+}
@@ -0,0 +1,140 @@
+//! A log writer for the Windows Event Log(requires the `windows-eventlog` feature).
+
+use std::io;
+use std::ptr;
+
+use windows_sys::Win32::Foundation::HANDLE;
+use windows_sys::Win32::System::EventLog::{
+    DeregisterEventSource, RegisterEventSourceW, ReportEventW, EVENTLOG_ERROR_TYPE,
+    EVENTLOG_INFORMATION_TYPE, EVENTLOG_WARNING_TYPE, REPORT_EVENT_TYPE,
+};
+
+use crate::{
+    write::{stats, LogWrite, TryLogWrite},
+    Severity,
+};
+
+/// The maximum length of an event string(in UTF-16 code units).
+const MAX_MESSAGE_LEN: usize = 31839;
+
+/// Converts a string to a NUL terminated UTF-16 string(truncated to `max_len`).
+fn to_wide(s: &str, max_len: usize) -> Vec<u16> {
+    let mut wide: Vec<u16> = Vec::with_capacity(s.len().min(max_len) + 1);
+    let mut units = [0u16; 2];
+    for c in s.chars() {
+        let c: char = match c {
+            '\0' => ' ',
+            c => c,
+        };
+        let encoded: &[u16] = c.encode_utf16(&mut units);
+        if max_len < wide.len() + encoded.len() {
+            break;
+        }
+        wide.extend_from_slice(encoded);
+    }
+    wide.push(0);
+    wide
+}
+
+/// Maps a severity to an event type(Error/Fatal: error, Warn: warning, others: information).
+pub fn event_type(level: Severity) -> REPORT_EVENT_TYPE {
+    match level {
+        Severity::Trace | Severity::Debug | Severity::Info => EVENTLOG_INFORMATION_TYPE,
+        Severity::Warn => EVENTLOG_WARNING_TYPE,
+        Severity::Error | Severity::Fatal => EVENTLOG_ERROR_TYPE,
+    }
+}
+
+/// A registered event source handle.
+struct EventSource(HANDLE);
+
+// SAFETY: an event source handle can be used from any thread.
+unsafe impl Send for EventSource {}
+unsafe impl Sync for EventSource {}
+
+impl Drop for EventSource {
+    fn drop(&mut self) {
+        // SAFETY: the handle was returned by RegisterEventSourceW and is not used after this.
+        unsafe {
+            DeregisterEventSource(self.0);
+        }
+    }
+}
+
+/// A log writer which reports serialized items as events of an event source.
+pub struct EventLogWriter {
+    source: EventSource,
+    event_id: u32,
+    category: u16,
+}
+
+impl EventLogWriter {
+    /// Sets the event identifier(1 by default).
+    pub fn with_event_id(mut self, event_id: u32) -> Self {
+        self.event_id = event_id;
+        self
+    }
+
+    /// Sets the event category(0 by default).
+    pub fn with_category(mut self, category: u16) -> Self {
+        self.category = category;
+        self
+    }
+}
+
+impl TryLogWrite for EventLogWriter {
+    fn try_write(&self, serialized: &str, level: Severity) -> io::Result<()> {
+        let message: Vec<u16> = to_wide(serialized.trim_end_matches('\n'), MAX_MESSAGE_LEN);
+        let strings = [message.as_ptr()];
+        // SAFETY: the handle is valid until drop and `strings` holds one NUL
+        // terminated string which outlives the call.
+        let reported = unsafe {
+            ReportEventW(
+                self.source.0,
+                event_type(level),
+                self.category,
+                self.event_id,
+                ptr::null_mut(),
+                1,
+                0,
+                strings.as_ptr(),
+                ptr::null(),
+            )
+        };
+        match reported {
+            0 => Err(io::Error::last_os_error()),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl LogWrite for EventLogWriter {
+    fn write(&self, serialized: &str, level: Severity) {
+        stats::count_write(self.try_write(serialized, level), serialized, level);
+    }
+}
+
+/// Creates a log writer which reports items to the Windows Event Log.
+///
+/// The source should be installed under
+/// `HKLM\SYSTEM\CurrentControlSet\Services\EventLog\Application` with a
+/// message file whose message for the event id is `%1`(e.g. by an installer or
+/// `New-EventLog`); otherwise the Event Viewer shows the text with a
+/// "description not found" notice. The severity maps to the event type via
+/// [`event_type`].
+///
+/// # Arguments
+/// - source: The event source name(e.g. the service name).
+pub fn event_log_writer_new(source: &str) -> io::Result<EventLogWriter> {
+    let name: Vec<u16> = to_wide(source, 256);
+    // SAFETY: `name` is a NUL terminated string; a null server means the local computer.
+    let handle: HANDLE = unsafe { RegisterEventSourceW(ptr::null(), name.as_ptr()) };
+    match handle.is_null() {
+        true => Err(io::Error::last_os_error()),
+        false => Ok(EventLogWriter {
+            source: EventSource(handle),
+            event_id: 1,
+            category: 0,
+        }),
+    }
+}